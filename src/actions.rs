@@ -0,0 +1,551 @@
+//! Lifecycle actions on the selected container, run in the background so a
+//! slow `docker stop` does not freeze the UI.
+
+use crate::backend::{ContainerBackend, ContainerStats, ResourceLimits, RunSpec};
+use crate::config::{Guard, GuardedAction, Template};
+use crate::templates;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local, NaiveDateTime, NaiveTime};
+use futures::future::join_all;
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContainerAction {
+    Start,
+    Stop,
+    Restart,
+    Pause,
+    Unpause,
+    /// Sends the signal, e.g. `SIGKILL`.
+    Kill(String),
+    Remove,
+    /// Removes it even while it runs, with its anonymous volumes.
+    ForceRemove,
+    /// Gives it the new name.
+    Rename(String),
+    /// Changes its CPU and memory limits in place.
+    Update(ResourceLimits),
+    /// Snapshots it as an image.
+    Commit { repository: String, tag: String },
+    /// Replaces it with one run from the changed configuration.
+    Recreate(Box<RunSpec>),
+    /// Writes its logs since `since` (Unix seconds, 0 for all) to a file,
+    /// with timestamps.
+    ExportLogs { path: PathBuf, since: i64 },
+}
+
+impl ContainerAction {
+    /// Stop for running containers, start for the others.
+    pub fn toggle(container: &ContainerStats) -> Self {
+        if container.is_running() {
+            ContainerAction::Stop
+        } else {
+            ContainerAction::Start
+        }
+    }
+
+    /// Unpause for paused containers, pause for the others.
+    pub fn pause_toggle(container: &ContainerStats) -> Self {
+        if container.is_paused() {
+            ContainerAction::Unpause
+        } else {
+            ContainerAction::Pause
+        }
+    }
+
+    async fn perform(&self, backend: &dyn ContainerBackend, id: &str) -> Result<()> {
+        match self {
+            ContainerAction::Start => backend.start_container(id).await,
+            ContainerAction::Stop => backend.stop_container(id).await,
+            ContainerAction::Restart => backend.restart_container(id).await,
+            ContainerAction::Pause => backend.pause_container(id).await,
+            ContainerAction::Unpause => backend.unpause_container(id).await,
+            ContainerAction::Kill(signal) => backend.kill_container(id, signal).await,
+            ContainerAction::Remove => backend.remove_container(id).await,
+            ContainerAction::ForceRemove => backend.force_remove_container(id).await,
+            ContainerAction::Rename(new_name) => backend.rename_container(id, new_name).await,
+            ContainerAction::Update(limits) => backend.update_container(id, limits).await,
+            ContainerAction::Commit { repository, tag } => backend.commit_container(id, repository, tag).await,
+            ContainerAction::Recreate(spec) => {
+                backend.recreate_container(id, spec).await?;
+                backend.remove_container(id).await.context("Recreated, but the old container is still there")
+            }
+            ContainerAction::ExportLogs { path, since } => {
+                let lines = backend.logs_since(id, *since).await?;
+                let text: String = lines.iter().map(|line| format!("{}\n", line.text)).collect();
+                tokio::fs::write(path, text).await.context("Failed to write the file")
+            }
+        }
+    }
+
+    /// How guards in the config refer to it.
+    pub fn kind(&self) -> GuardedAction {
+        match self {
+            ContainerAction::Start => GuardedAction::Start,
+            ContainerAction::Stop => GuardedAction::Stop,
+            ContainerAction::Restart => GuardedAction::Restart,
+            ContainerAction::Pause => GuardedAction::Pause,
+            ContainerAction::Unpause => GuardedAction::Unpause,
+            ContainerAction::Kill(_) => GuardedAction::Kill,
+            ContainerAction::Remove => GuardedAction::Remove,
+            ContainerAction::ForceRemove => GuardedAction::ForceRemove,
+            ContainerAction::Rename(_) => GuardedAction::Rename,
+            ContainerAction::Update(_) => GuardedAction::Update,
+            ContainerAction::Commit { .. } => GuardedAction::Commit,
+            ContainerAction::Recreate(_) => GuardedAction::Recreate,
+            ContainerAction::ExportLogs { .. } => GuardedAction::Export,
+        }
+    }
+
+    /// Plain, progressive and past forms, e.g. "stop", "stopping" and "stopped".
+    pub fn verbs(&self) -> (&'static str, &'static str, &'static str) {
+        match self {
+            ContainerAction::Start => ("start", "starting", "started"),
+            ContainerAction::Stop => ("stop", "stopping", "stopped"),
+            ContainerAction::Restart => ("restart", "restarting", "restarted"),
+            ContainerAction::Pause => ("pause", "pausing", "paused"),
+            ContainerAction::Unpause => ("unpause", "unpausing", "unpaused"),
+            ContainerAction::Kill(_) => ("kill", "killing", "killed"),
+            ContainerAction::Remove => ("remove", "removing", "removed"),
+            ContainerAction::ForceRemove => ("force-remove", "force-removing", "force-removed"),
+            ContainerAction::Rename(_) => ("rename", "renaming", "renamed"),
+            ContainerAction::Update(_) => ("update", "updating", "updated"),
+            ContainerAction::Commit { .. } => ("commit", "committing", "committed"),
+            ContainerAction::Recreate(_) => ("recreate", "recreating", "recreated"),
+            ContainerAction::ExportLogs { .. } => ("export", "exporting", "exported"),
+        }
+    }
+}
+
+/// What has to be typed before `action` on `containers`, if a guard covers
+/// it for any of them; the first such guard counts.
+pub fn guard_phrase(guards: &[Guard], containers: &[ContainerStats], action: &ContainerAction) -> Option<String> {
+    let guard = guards
+        .iter()
+        .find(|g| g.actions.contains(&action.kind()) && containers.iter().any(|c| g.containers.matches(c)))?;
+    let name = match containers {
+        [container] => container.name.clone(),
+        _ => format!("{} containers", containers.len()),
+    };
+    Some(guard.phrase.replace("{{name}}", &name))
+}
+
+/// Signals offered by the kill picker, with what they are for. The picker's
+/// last entry asks for any other signal.
+pub const SIGNALS: [(&str, &str); 3] = [
+    ("SIGTERM", "ask the process to exit"),
+    ("SIGKILL", "end it at once, e.g. when a stop hangs"),
+    ("SIGHUP", "many daemons reload their config"),
+];
+
+/// The signal picker opened by `K`.
+pub struct KillPicker {
+    pub container: ContainerStats,
+    /// Index into [`SIGNALS`], or its length for a custom signal.
+    pub cursor: usize,
+}
+
+/// Checks a new container name against Docker's rules, so a typo is caught
+/// before the daemon rejects it: a letter or digit, then at least one more
+/// letter, digit, `_`, `.` or `-`.
+pub fn check_name(name: &str) -> Result<()> {
+    let mut chars = name.chars();
+    if !chars.next().is_some_and(|c| c.is_ascii_alphanumeric()) {
+        bail!("a name must start with a letter or digit");
+    }
+    if name.len() < 2 {
+        bail!("a name needs at least two characters");
+    }
+    if let Some(c) = chars.find(|c| !c.is_ascii_alphanumeric() && !"_.-".contains(*c)) {
+        bail!("{:?} is not allowed; use letters, digits, _, . or -", c);
+    }
+    Ok(())
+}
+
+/// The start of a log export as Unix seconds: empty for all of the logs, an
+/// age such as `30m`, `2h` or `1d`, a time today such as `14:05`, or a local
+/// date and time such as `2024-05-01 14:05`.
+pub fn parse_since(input: &str, now: DateTime<Local>) -> Result<i64> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Ok(0);
+    }
+    if let Ok(age) = parse_age(input) {
+        return now.timestamp().checked_sub(age).context("that age is too long");
+    }
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S"] {
+        if let Ok(at) = NaiveDateTime::parse_from_str(input, format) {
+            let at = at.and_local_timezone(Local).earliest().context("that time does not exist here")?;
+            return Ok(at.timestamp());
+        }
+    }
+    for format in ["%H:%M:%S", "%H:%M"] {
+        if let Ok(time) = NaiveTime::parse_from_str(input, format) {
+            // A time later than now means yesterday's.
+            let mut day = now.date_naive();
+            if time > now.time() {
+                day = day.pred_opt().context("no day before today")?;
+            }
+            let at = day.and_time(time).and_local_timezone(Local).earliest().context("that time does not exist here")?;
+            return Ok(at.timestamp());
+        }
+    }
+    bail!("expected an age such as 30m, a time such as 14:05 or a date and time such as 2024-05-01 14:05")
+}
+
+/// An age such as `45s`, `30m`, `2h` or `1d` in seconds.
+pub fn parse_age(input: &str) -> Result<i64> {
+    let input = input.trim();
+    let unit = match input.chars().last() {
+        Some('s') => 1,
+        Some('m') => 60,
+        Some('h') => 3600,
+        Some('d') => 86_400,
+        _ => bail!("expected an age such as 30m, 2h or 1d"),
+    };
+    match input[..input.len() - 1].parse::<i64>() {
+        Ok(count) if count >= 0 => count.checked_mul(unit).context("that age is too long"),
+        _ => bail!("expected an age such as 30m, 2h or 1d"),
+    }
+}
+
+/// An age as [`parse_age`] reads it, in the largest unit it is a whole
+/// number of; empty for 0.
+pub fn format_age(seconds: i64) -> String {
+    match seconds {
+        0 => String::new(),
+        s if s % 86_400 == 0 => format!("{}d", s / 86_400),
+        s if s % 3600 == 0 => format!("{}h", s / 3600),
+        s if s % 60 == 0 => format!("{}m", s / 60),
+        s => format!("{}s", s),
+    }
+}
+
+/// Splits an image reference such as `registry:5000/debug/api:before-fix`
+/// into repository and tag, checking both against Docker's rules. The tag
+/// defaults to `latest`.
+pub fn parse_reference(reference: &str) -> Result<(String, String)> {
+    let reference = reference.trim();
+    let (repository, tag) = match reference.rsplit_once(':') {
+        Some((repository, tag)) if !tag.contains('/') => (repository, tag),
+        _ => (reference, "latest"),
+    };
+    if repository.is_empty() {
+        bail!("the repository is missing");
+    }
+    if let Some(c) = repository
+        .chars()
+        .find(|c| !c.is_ascii_lowercase() && !c.is_ascii_digit() && !"._-/:".contains(*c))
+    {
+        bail!("{:?} is not allowed in a repository; use lowercase letters, digits, ., _, - or /", c);
+    }
+    if tag.is_empty() || tag.len() > 128 || tag.starts_with(['.', '-']) {
+        bail!("a tag has 1 to 128 characters and starts with a letter, digit or _");
+    }
+    if let Some(c) = tag.chars().find(|c| !c.is_ascii_alphanumeric() && !"_.-".contains(*c)) {
+        bail!("{:?} is not allowed in a tag; use letters, digits, _, . or -", c);
+    }
+    Ok((repository.to_string(), tag.to_string()))
+}
+
+/// Like [`parse_reference`], but also takes an image pinned by digest, such
+/// as `registry:5000/api@sha256:…`. The digest comes back in place of the
+/// tag, which is how the daemon's pull takes it; a tag written alongside a
+/// digest is dropped, as the digest decides what is pulled.
+pub fn parse_image(reference: &str) -> Result<(String, String)> {
+    let reference = reference.trim();
+    let Some((name, digest)) = reference.split_once('@') else {
+        return parse_reference(reference);
+    };
+    let (repository, _) = parse_reference(name)?;
+    let valid = digest
+        .split_once(':')
+        .is_some_and(|(algorithm, hex)| !algorithm.is_empty() && !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit()));
+    if !valid {
+        bail!("a digest is written algorithm:hex, e.g. sha256:0123…");
+    }
+    Ok((repository, digest.to_string()))
+}
+
+/// Puts the halves [`parse_image`] returns back together; tags never
+/// contain `:`, digests always do.
+pub fn join_image(repository: &str, tag: &str) -> String {
+    if tag.contains(':') {
+        format!("{}@{}", repository, tag)
+    } else {
+        format!("{}:{}", repository, tag)
+    }
+}
+
+/// A finished run: its number, the IDs of the containers acted on, and the
+/// outcome.
+type Outcome = (u64, Vec<String>, String);
+
+/// Actions in flight and their outcomes.
+pub struct Actions {
+    /// By container ID, oldest first, with the number of the run each is
+    /// part of, so an action finishing leaves the others on the container
+    /// pending.
+    in_flight: HashMap<String, Vec<(u64, ContainerAction)>>,
+    next_run: u64,
+    tx: UnboundedSender<Outcome>,
+    rx: UnboundedReceiver<Outcome>,
+}
+
+impl Default for Actions {
+    fn default() -> Self {
+        let (tx, rx) = unbounded_channel();
+        Self {
+            in_flight: HashMap::new(),
+            next_run: 0,
+            tx,
+            rx,
+        }
+    }
+}
+
+impl Actions {
+    /// Starts the action and returns a message saying so.
+    pub fn run(&mut self, backend: &Arc<dyn ContainerBackend>, container: &ContainerStats, action: ContainerAction) -> String {
+        let (verb, doing, done) = action.verbs();
+        let name = match &action {
+            ContainerAction::Kill(signal) => format!("{} with {}", container.name, signal),
+            ContainerAction::Rename(new_name) => format!("{} to {}", container.name, new_name),
+            ContainerAction::Update(limits) => format!("{} with {}", container.name, limits),
+            ContainerAction::Commit { repository, tag } => format!("{} to {}:{}", container.name, repository, tag),
+            ContainerAction::Recreate(spec) => format!("{} from {}", container.name, spec.image),
+            ContainerAction::ExportLogs { path, .. } => format!("the logs of {} to {}", container.name, path.display()),
+            _ => container.name.clone(),
+        };
+        let run = self.start(std::slice::from_ref(container), &action);
+        let (backend, tx, id) = (backend.clone(), self.tx.clone(), container.id.clone());
+        let target = name.clone();
+        tokio::spawn(async move {
+            let message = match action.perform(backend.as_ref(), &id).await {
+                Ok(()) => format!("{} {}", capitalize(done), target),
+                Err(e) => format!("Could not {} {}: {:#}", verb, target, e),
+            };
+            let _ = tx.send((run, vec![id], message));
+        });
+        format!("{} {}…", capitalize(doing), name)
+    }
+
+    /// Starts the action on all `containers` at once and returns a message
+    /// saying so; a single message reports the outcomes once all are done.
+    pub fn run_batch(
+        &mut self,
+        backend: &Arc<dyn ContainerBackend>,
+        containers: &[ContainerStats],
+        action: ContainerAction,
+    ) -> String {
+        let (verb, doing, done) = action.verbs();
+        let run = self.start(containers, &action);
+        let targets: Vec<(String, String)> = containers.iter().map(|c| (c.id.clone(), c.name.clone())).collect();
+        let names = containers.iter().map(|c| c.name.as_str()).collect::<Vec<_>>().join(", ");
+        let (backend, tx) = (backend.clone(), self.tx.clone());
+        tokio::spawn(async move {
+            let results = join_all(targets.iter().map(|(id, _)| action.perform(backend.as_ref(), id))).await;
+            let mut succeeded = Vec::new();
+            let mut outcomes = Vec::new();
+            for ((_, name), result) in targets.iter().zip(results) {
+                match result {
+                    Ok(()) => succeeded.push(name.as_str()),
+                    Err(e) => outcomes.push(format!("could not {} {}: {:#}", verb, name, e)),
+                }
+            }
+            if !succeeded.is_empty() {
+                outcomes.insert(0, format!("{} {}", done, succeeded.join(", ")));
+            }
+            let ids = targets.into_iter().map(|(id, _)| id).collect();
+            let _ = tx.send((run, ids, capitalize(&outcomes.join("; "))));
+        });
+        format!("{} {}…", capitalize(doing), names)
+    }
+
+    /// Runs a container from the template and returns a message saying so.
+    pub fn run_template(&mut self, backend: &Arc<dyn ContainerBackend>, template: Template) -> String {
+        let (backend, tx) = (backend.clone(), self.tx.clone());
+        let message = format!("Running a container from {}…", template.name);
+        tokio::spawn(async move {
+            let message = match templates::run(backend.as_ref(), &template).await {
+                Ok(spec) => format!("Started {} from {}", spec.name, template.name),
+                Err(e) => format!("Could not run {}: {:#}", template.name, e),
+            };
+            let _ = tx.send((0, Vec::new(), message));
+        });
+        message
+    }
+
+    /// Marks the action pending on the containers and returns the number
+    /// of its run.
+    fn start(&mut self, containers: &[ContainerStats], action: &ContainerAction) -> u64 {
+        self.next_run += 1;
+        for container in containers {
+            self.in_flight.entry(container.id.clone()).or_default().push((self.next_run, action.clone()));
+        }
+        self.next_run
+    }
+
+    /// What is being done to the container, e.g. "restarting", while an
+    /// action on it runs; the latest one if there are several.
+    pub fn pending(&self, container_id: &str) -> Option<&'static str> {
+        let (_, action) = self.in_flight.get(container_id)?.last()?;
+        Some(action.verbs().1)
+    }
+
+    /// Outcomes of finished actions, oldest first.
+    pub fn collect(&mut self) -> Vec<String> {
+        let mut messages = Vec::new();
+        while let Ok((run, ids, message)) = self.rx.try_recv() {
+            for id in ids {
+                if let Some(actions) = self.in_flight.get_mut(&id) {
+                    actions.retain(|(r, _)| *r != run);
+                    if actions.is_empty() {
+                        self.in_flight.remove(&id);
+                    }
+                }
+            }
+            messages.push(message);
+        }
+        messages
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn container_names_follow_dockers_rules() {
+        for name in ["db", "shop-web-1", "api_v2.blue", "9lives"] {
+            assert!(check_name(name).is_ok(), "{}", name);
+        }
+        for (name, error) in [
+            ("", "must start with a letter or digit"),
+            ("-db", "must start with a letter or digit"),
+            ("a", "at least two characters"),
+            ("web/1", "'/' is not allowed"),
+            ("web 1", "' ' is not allowed"),
+            ("wéb", "'é' is not allowed"),
+        ] {
+            let message = check_name(name).unwrap_err().to_string();
+            assert!(message.contains(error), "{}: {}", name, message);
+        }
+    }
+
+    #[test]
+    fn ages_are_read_in_seconds_and_too_long_ones_rejected() {
+        for (input, seconds) in [("45s", 45), ("30m", 1800), (" 2h ", 7200), ("1d", 86_400), ("0m", 0)] {
+            assert_eq!(parse_age(input).unwrap(), seconds, "{}", input);
+            assert_eq!(parse_age(&format_age(seconds)).unwrap_or(0), seconds);
+        }
+        for input in ["", "30", "m", "-5m", "1.5h", "2w", "99999999999999999d"] {
+            assert!(parse_age(input).is_err(), "{}", input);
+        }
+        let now = Local::now();
+        assert_eq!(parse_since("2h", now).unwrap(), now.timestamp() - 7200);
+        assert!(parse_since("99999999999999999d", now).is_err());
+    }
+
+    #[test]
+    fn export_starts_are_ages_times_or_dates() {
+        use chrono::TimeZone;
+        let at = |day, hour, minute, second| Local.with_ymd_and_hms(2024, 5, day, hour, minute, second).unwrap().timestamp();
+        let now = Local.with_ymd_and_hms(2024, 5, 1, 14, 0, 0).unwrap();
+        for (input, since) in [
+            ("", 0),
+            ("30m", at(1, 13, 30, 0)),
+            ("13:30", at(1, 13, 30, 0)),
+            ("13:30:15", at(1, 13, 30, 15)),
+            // Later than now, so yesterday's.
+            ("15:00", Local.with_ymd_and_hms(2024, 4, 30, 15, 0, 0).unwrap().timestamp()),
+            ("2024-05-01 09:15", at(1, 9, 15, 0)),
+            ("2024-05-01 09:15:30", at(1, 9, 15, 30)),
+            ("2024-05-01T09:15:30", at(1, 9, 15, 30)),
+        ] {
+            assert_eq!(parse_since(input, now).unwrap(), since, "{}", input);
+        }
+        for input in ["yesterday", "25:00", "2024-13-01 09:15", "1w"] {
+            assert!(parse_since(input, now).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn commit_references_are_checked_against_dockers_rules() {
+        for (reference, repository, tag) in [
+            ("debug/api", "debug/api", "latest"),
+            (" debug/api:before-fix ", "debug/api", "before-fix"),
+            ("localhost:5000/api", "localhost:5000/api", "latest"),
+            ("api:_1.2-rc", "api", "_1.2-rc"),
+        ] {
+            assert_eq!(parse_reference(reference).unwrap(), (repository.to_string(), tag.to_string()), "{}", reference);
+        }
+        let long_tag = format!("api:{}", "a".repeat(129));
+        for (reference, error) in [
+            ("", "repository is missing"),
+            (":1", "repository is missing"),
+            ("Debug/api", "'D' is not allowed in a repository"),
+            ("api:", "1 to 128 characters"),
+            ("api:.hidden", "starts with a letter, digit or _"),
+            ("api:-rc", "starts with a letter, digit or _"),
+            (long_tag.as_str(), "1 to 128 characters"),
+            ("api:v1+build", "'+' is not allowed in a tag"),
+        ] {
+            let message = parse_reference(reference).unwrap_err().to_string();
+            assert!(message.contains(error), "{}: {}", reference, message);
+        }
+    }
+
+    #[test]
+    fn images_split_into_repository_and_tag_or_digest() {
+        for (image, repository, tag, joined) in [
+            ("redis", "redis", "latest", "redis:latest"),
+            ("redis:7", "redis", "7", "redis:7"),
+            ("registry:5000/app", "registry:5000/app", "latest", "registry:5000/app:latest"),
+            ("registry:5000/debug/api:before-fix", "registry:5000/debug/api", "before-fix", "registry:5000/debug/api:before-fix"),
+            ("redis@sha256:0123abcd", "redis", "sha256:0123abcd", "redis@sha256:0123abcd"),
+            ("registry:5000/app:1@sha256:0123abcd", "registry:5000/app", "sha256:0123abcd", "registry:5000/app@sha256:0123abcd"),
+        ] {
+            let (parsed_repository, parsed_tag) = parse_image(image).unwrap();
+            assert_eq!((parsed_repository.as_str(), parsed_tag.as_str()), (repository, tag), "{}", image);
+            assert_eq!(join_image(repository, tag), joined);
+        }
+        for invalid in ["redis@", "redis@sha256:", "redis@sha256:xyz", "@sha256:0123", "Redis:7"] {
+            assert!(parse_image(invalid).is_err(), "{}", invalid);
+        }
+        // Commits take a tag, never a digest.
+        assert!(parse_reference("redis@sha256:0123").is_err());
+    }
+
+    #[tokio::test]
+    async fn an_action_finishing_leaves_the_others_on_the_container_pending() {
+        use crate::backend::mock::{container, MockBackend};
+
+        let backend: Arc<dyn ContainerBackend> = Arc::new(MockBackend::new([]));
+        let web = container("web");
+        let mut actions = Actions::default();
+        actions.run(&backend, &web, ContainerAction::Restart);
+        actions.run_batch(&backend, std::slice::from_ref(&web), ContainerAction::Stop);
+        assert_eq!(actions.pending("web-id"), Some("stopping"));
+
+        // The restart, run 1, finishes first.
+        actions.tx.send((1, vec!["web-id".to_string()], "Restarted web".to_string())).unwrap();
+        assert_eq!(actions.collect(), ["Restarted web"]);
+        assert_eq!(actions.pending("web-id"), Some("stopping"));
+
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        actions.collect();
+        assert_eq!(actions.pending("web-id"), None);
+    }
+}
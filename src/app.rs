@@ -0,0 +1,3921 @@
+//! Application state and the key-driven state machine on top of it.
+
+use crate::actions::{check_name, format_age, guard_phrase, parse_age, parse_reference, parse_since, Actions, ContainerAction, KillPicker, SIGNALS};
+use crate::auto_actions::AutoActions;
+use crate::backend::{AuditLog, ContainerBackend, ContainerStats, RegistryLogin, ResourceLimits};
+use crate::bandwidth::Bandwidth;
+use crate::budgets::Budgets;
+use crate::commands::{self, ColumnRunner};
+use crate::compare::Compare;
+use crate::cores::CoreMeter;
+use crate::cost::CostMeter;
+use crate::cpu_history::CpuHistory;
+use crate::deploy::{self, Deployment};
+use crate::diagnostics;
+use crate::config::{AutoPruneConfig, BuiltinColumn, ChartStyle, CommandMode, Config, ConfigWatch, CustomCommand, DiagnosticsConfig, FormatConfig, Guard, HighlightRule, Template, with_builtin_highlights};
+use crate::disk::DiskTrend;
+use crate::error_rate::ErrorMeter;
+use crate::files::FileBrowser;
+use crate::host::HostMonitor;
+use crate::idle::IdleTracker;
+use crate::io_rate::IoMeter;
+use crate::journal::{Journal, Snapshot};
+use crate::json_log::FieldFilter;
+use crate::limits::LimitsForm;
+use crate::log_rate::LogMeter;
+use crate::log_search::LogSearch;
+use crate::log_view::LogView;
+use crate::orphans::{self, Orphan, OrphanKind};
+use crate::pods;
+use crate::probes::ProbeRunner;
+use crate::query::Query;
+use crate::recap::Recap;
+use crate::recreate::RecreateForm;
+use crate::registries::{self, PullLogins};
+use crate::report::UsageHistory;
+use crate::self_usage::SelfMonitor;
+use crate::sessions::{Session, SessionKind, Sessions};
+use crate::stats_diff::StatsSnapshot;
+use crate::status_line::StatusLine;
+use crate::templates::{run_spec, TemplatePicker};
+use crate::timeline::{self, Timeline};
+use crate::trend::TrendMeter;
+use crate::units;
+use crate::watch::Watch;
+use bollard::models::{ImageSummary, Network, ServiceUpdateStatusStateEnum, Volume};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use regex::Regex;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+/// What the text prompt submits to once the user presses Enter.
+pub enum PromptAction {
+    ServiceImage { service: String },
+    /// Name for a new checkpoint of the container.
+    Checkpoint { id: String, name: String },
+    /// Checkpoint to restore the container from.
+    Restore { id: String, name: String },
+    /// Regex to search the recent logs of the listed containers for.
+    SearchLogs,
+    /// `field=value` filter for the JSON lines of the shown log session.
+    LogFilter,
+    /// File to save a snapshot of the screen to.
+    ExportScreen,
+    /// File to save the lines of the output pane to.
+    SaveOutput,
+    /// Filter expression for the container list, empty for none.
+    Filter,
+    /// Signal to kill the container with, from the picker's "Custom…" entry.
+    KillSignal { container: Box<ContainerStats> },
+    /// `phrase`, the container's name unless a guard says otherwise, typed
+    /// to confirm force-removing it.
+    ForceRemove { container: Box<ContainerStats>, phrase: String },
+    /// A guard's phrase, typed to confirm the action.
+    Guarded { containers: Vec<ContainerStats>, action: ContainerAction, phrase: String },
+    /// The container's new name, prefilled with the current one.
+    Rename { container: Box<ContainerStats> },
+    /// `repository:tag` to commit the container to.
+    CommitImage { container: Box<ContainerStats> },
+    /// File to export the container's logs to.
+    ExportLogs { container: Box<ContainerStats> },
+    /// A command to run once in the container, showing its output.
+    RunCommand { container: Box<ContainerStats> },
+    /// How far back the export of the container's logs to `path` goes.
+    ExportLogsSince { container: Box<ContainerStats>, path: PathBuf },
+    /// Name for a new snapshot of every container's stats.
+    TakeSnapshot,
+    /// Name of the snapshot to compare the current stats with.
+    DiffSnapshot,
+    /// Regex to search the log view for, empty to clear the search.
+    SearchLogView,
+    /// Lines the log view fetches from the end of the logs.
+    LogViewTail,
+    /// How far back the log view goes, fetching `tail` lines at most.
+    LogViewAge { tail: usize },
+    /// Registry to log in to, e.g. `ghcr.io`.
+    RegistryServer,
+    RegistryUsername { server: String },
+    /// Shown masked.
+    RegistryPassword { server: String, username: String },
+}
+
+pub struct Prompt {
+    pub title: String,
+    pub input: String,
+    pub action: PromptAction,
+}
+
+pub enum InputMode {
+    Normal,
+    Prompt(Prompt),
+    /// A yes/no question; `y` confirms and any other key cancels.
+    Confirm(Confirm),
+}
+
+pub struct Confirm {
+    /// The question, e.g. "Remove container foo?".
+    pub question: String,
+    pub action: ConfirmAction,
+}
+
+pub enum ConfirmAction {
+    RemoveContainers(Vec<ContainerStats>),
+    /// Every stopped container, listed or not.
+    PruneContainers,
+    /// A soft redeploy of the project, in stages.
+    Redeploy(String, Vec<Vec<ContainerStats>>),
+    /// Pulling the container's image and recreating it if there is a newer one.
+    Update(Box<ContainerStats>),
+    /// Removing the login stored for the server.
+    RegistryLogout(String),
+    /// Removing the orphans in the selected categories.
+    CleanupOrphans,
+    /// A blue/green switch from the first container to the second.
+    BlueGreen(Box<ContainerStats>, Box<ContainerStats>),
+}
+
+/// A scrollable overlay showing the output of a command.
+pub struct OutputPane {
+    pub title: String,
+    pub lines: Vec<String>,
+    pub scroll: u16,
+}
+
+/// Progress of a rolling service update started from dockertop.
+pub struct ServiceUpdate {
+    pub service: String,
+    pub image: String,
+    pub state: String,
+    pub message: String,
+    pub updated_tasks: usize,
+    pub total_tasks: usize,
+    pub finished: bool,
+}
+
+/// Top-level screens, switched with the number keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Screen {
+    Containers,
+    Dependencies,
+    Networks,
+    Volumes,
+    Images,
+    Orphans,
+    Disk,
+    /// Containers idle long enough to be worth shutting down.
+    Idle,
+    /// Logins stored by the Docker client.
+    Registries,
+    /// Single-container dashboard, only reachable through `dockertop watch`.
+    Watch,
+}
+
+/// Tabs of the details pane beside the container list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DetailsTab {
+    Info,
+    Events,
+}
+
+/// How the container list is sectioned, cycled with `g`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Grouping {
+    Off,
+    Status,
+    /// By compose project, offered only when some container is in one.
+    Project,
+    /// By Kubernetes pod, offered only when some container is in one.
+    Pod,
+}
+
+impl Grouping {
+    fn next(self, has_projects: bool, has_pods: bool) -> Self {
+        match self {
+            Grouping::Off => Grouping::Status,
+            Grouping::Status if has_projects => Grouping::Project,
+            Grouping::Status | Grouping::Project if has_pods => Grouping::Pod,
+            Grouping::Status | Grouping::Project | Grouping::Pod => Grouping::Off,
+        }
+    }
+}
+
+/// The column the container list is sorted by, highest first, cycled with
+/// `>`. Within a grouping, each section is sorted on its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    /// The daemon's order.
+    Off,
+    Cpu,
+    Memory,
+    /// Bytes read and written since the container started.
+    BlockIo,
+}
+
+impl SortKey {
+    fn next(self) -> Self {
+        match self {
+            SortKey::Off => SortKey::Cpu,
+            SortKey::Cpu => SortKey::Memory,
+            SortKey::Memory => SortKey::BlockIo,
+            SortKey::BlockIo => SortKey::Off,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortKey::Off => "unsorted",
+            SortKey::Cpu => "CPU",
+            SortKey::Memory => "memory",
+            SortKey::BlockIo => "block I/O",
+        }
+    }
+}
+
+/// Sections of the container list when grouping by status.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum StatusGroup {
+    Running,
+    Paused,
+    /// Everything not up, including created and dead containers.
+    Exited,
+}
+
+impl StatusGroup {
+    pub fn of(container: &ContainerStats) -> Self {
+        if container.is_paused() {
+            StatusGroup::Paused
+        } else if container.is_running() {
+            StatusGroup::Running
+        } else {
+            StatusGroup::Exited
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            StatusGroup::Running => "Running",
+            StatusGroup::Paused => "Paused",
+            StatusGroup::Exited => "Exited",
+        }
+    }
+}
+
+/// A container that just exited or was removed, kept on screen dimmed with
+/// its last running stats until the fade-out period is over.
+pub struct Fading {
+    pub since: Instant,
+    /// The last sample taken while it was running.
+    pub last: ContainerStats,
+    pub removed: bool,
+}
+
+/// Work a key press asks of the main loop, which owns the terminal.
+#[derive(Debug, PartialEq)]
+pub enum Effect {
+    /// Suspend the UI and run the command in the terminal.
+    RunInTerminal(String),
+    /// Save what is on screen to the file, as HTML if it ends in `.html`.
+    ExportScreen(PathBuf),
+}
+
+pub struct App {
+    pub backend: Arc<dyn ContainerBackend>,
+    pub containers: Vec<ContainerStats>,
+    pub selected_index: usize,
+    pub should_quit: bool,
+    pub input_mode: InputMode,
+    pub status_message: Option<String>,
+    pub service_update: Option<ServiceUpdate>,
+    /// Blue/green switch in progress or just finished.
+    pub deployment: Option<Deployment>,
+    pub columns: ColumnRunner,
+    pub probes: ProbeRunner,
+    /// Set when the log rate column is enabled.
+    pub log_meter: Option<LogMeter>,
+    /// Counts error lines, for the column or for auto-actions.
+    pub error_meter: Option<ErrorMeter>,
+    /// The `error_rate` column is shown.
+    pub error_column: bool,
+    pub trends: TrendMeter,
+    /// The `memory_growth` column is shown.
+    pub memory_growth_column: bool,
+    /// The `restart_rate` column is shown.
+    pub restart_column: bool,
+    /// The `net_io` column is shown.
+    pub net_io_column: bool,
+    /// The `block_io` column is shown.
+    pub block_io_column: bool,
+    pub commands: Vec<CustomCommand>,
+    guards: Vec<Guard>,
+    pub templates: Vec<Template>,
+    diagnostics: DiagnosticsConfig,
+    pub output: Option<OutputPane>,
+    /// The `O` file browser, over the list while open.
+    pub files: Option<FileBrowser>,
+    output_tx: UnboundedSender<(String, Vec<String>)>,
+    output_rx: UnboundedReceiver<(String, Vec<String>)>,
+    /// Set with `--filter` or `/`.
+    pub filter: Option<Query>,
+    /// Hidden panel with API call statistics, toggled with F12.
+    pub show_debug: bool,
+    pub self_monitor: SelfMonitor,
+    pub host_monitor: HostMonitor,
+    pub screen: Screen,
+    /// Scroll offset of full-screen views other than the container list.
+    pub screen_scroll: u16,
+    /// Legacy links per container ID, fetched while the dependency graph is shown.
+    pub links: HashMap<String, Vec<String>>,
+    /// Fetched while the network screen is shown.
+    pub networks: Vec<Network>,
+    /// Fetched while the volume screen is shown.
+    pub volumes: Vec<Volume>,
+    /// Fetched while the image screen is shown.
+    pub images: Vec<ImageSummary>,
+    pub stopped_days: u64,
+    /// Recomputed while the orphans screen is shown.
+    pub orphans: Vec<Orphan>,
+    /// Highlighted category on the orphans screen, an index into [`OrphanKind::ALL`].
+    pub orphan_cursor: usize,
+    /// Categories the cleanup key removes.
+    pub orphan_selection: Vec<OrphanKind>,
+    /// Fetched when the registries screen is shown.
+    pub registries: Vec<RegistryLogin>,
+    /// Highlighted login on the registries screen.
+    pub registry_cursor: usize,
+    pub disk: DiskTrend,
+    /// Start/stop history of the selected container.
+    pub timeline: Option<Timeline>,
+    pub details_tab: DetailsTab,
+    /// Also handed to the backend; used here for custom commands.
+    pub audit_log: Option<Arc<AuditLog>>,
+    pub io: IoMeter,
+    pub cores: CoreMeter,
+    pub cpu_history: CpuHistory,
+    pub recap: Recap,
+    pub idle: IdleTracker,
+    pub status_line: StatusLine,
+    pub budgets: Budgets,
+    pub bandwidth: Bandwidth,
+    /// Sampled for `dockertop report`.
+    pub usage: UsageHistory,
+    /// Two-line rows with image, ports and I/O rates, toggled with `w`.
+    pub extended_rows: bool,
+    pub grouping: Grouping,
+    pub sort: SortKey,
+    /// Whether the selection is on the header of the selected container's
+    /// project rather than on the container; see [`App::selected_project`].
+    pub header_selected: bool,
+    /// Rows keep their position between refreshes, toggled with `o`.
+    pub stable_order: bool,
+    /// Containers that just exited or were removed, by ID.
+    pub fading: HashMap<String, Fading>,
+    fade_out: Duration,
+    auto_prune: AutoPruneConfig,
+    last_auto_prune: Option<Instant>,
+    auto_actions: AutoActions,
+    pub actions: Actions,
+    /// IDs of the containers marked with space, so marks stay on the same
+    /// containers however the list is reordered.
+    pub marked: HashSet<String>,
+    pub kill_picker: Option<KillPicker>,
+    pub template_picker: Option<TemplatePicker>,
+    pub limits_form: Option<LimitsForm>,
+    pub recreate_form: Option<RecreateForm>,
+    /// A second host shown beside the list, see `--compare`.
+    pub compare: Option<Compare>,
+    pub cost: Option<CostMeter>,
+    pub watch: Option<Watch>,
+    /// Exec shells and log follows, alive in the background until closed.
+    pub sessions: Sessions,
+    /// Results of the global log search, shown until closed with Esc.
+    pub log_search: Option<LogSearch>,
+    /// The `l` pane with the selected container's recent logs.
+    pub log_view: Option<LogView>,
+    /// What the log view fetches: from `--tail` and `--since`, changed for
+    /// the rest of the session with `l` in the view.
+    pub log_tail: usize,
+    pub log_age: i64,
+    /// Taken with `t` and compared against with `v`, oldest first.
+    pub snapshots: Vec<StatsSnapshot>,
+    /// Every container of the latest refresh, whatever the filter, which
+    /// snapshots are taken of and compared with.
+    unfiltered: Vec<ContainerStats>,
+    /// Colors for matching log lines.
+    pub highlights: Vec<HighlightRule>,
+    /// Set when the config file should be reloaded on changes.
+    pub config_watch: Option<ConfigWatch>,
+    /// Why the edited config file was rejected, shown until it is fixed.
+    pub config_error: Option<String>,
+    /// Time between ticks.
+    pub refresh: Duration,
+    /// `j`/`k` navigate.
+    vim_keys: bool,
+    pub chart_style: ChartStyle,
+    /// How sizes and percentages are written.
+    pub format: FormatConfig,
+    /// Set when the session's history should survive a crash.
+    pub journal: Option<Journal>,
+}
+
+impl App {
+    pub fn new(config: Config, backend: Arc<dyn ContainerBackend>) -> Self {
+        let (output_tx, output_rx) = unbounded_channel();
+        let error_meter = config.counts_errors().then(|| ErrorMeter::new(config.error_rate.pattern.clone()));
+        Self {
+            backend,
+            containers: Vec::new(),
+            selected_index: 0,
+            should_quit: false,
+            input_mode: InputMode::Normal,
+            status_message: None,
+            service_update: None,
+            deployment: None,
+            columns: ColumnRunner::new(config.columns),
+            probes: ProbeRunner::new(config.probes),
+            log_meter: config
+                .builtin_columns
+                .contains(&BuiltinColumn::LogRate)
+                .then(LogMeter::default),
+            error_meter,
+            error_column: config.builtin_columns.contains(&BuiltinColumn::ErrorRate),
+            trends: TrendMeter::default(),
+            memory_growth_column: config.builtin_columns.contains(&BuiltinColumn::MemoryGrowth),
+            restart_column: config.builtin_columns.contains(&BuiltinColumn::RestartRate),
+            net_io_column: config.builtin_columns.contains(&BuiltinColumn::NetIo),
+            block_io_column: config.builtin_columns.contains(&BuiltinColumn::BlockIo),
+            commands: config.commands,
+            guards: config.guards,
+            templates: config.templates,
+            diagnostics: config.diagnostics,
+            output: None,
+            files: None,
+            output_tx,
+            output_rx,
+            filter: None,
+            show_debug: false,
+            self_monitor: SelfMonitor::default(),
+            host_monitor: HostMonitor::default(),
+            screen: Screen::Containers,
+            screen_scroll: 0,
+            links: HashMap::new(),
+            networks: Vec::new(),
+            volumes: Vec::new(),
+            images: Vec::new(),
+            stopped_days: config.orphans.stopped_days,
+            orphans: Vec::new(),
+            orphan_cursor: 0,
+            registries: Vec::new(),
+            registry_cursor: 0,
+            orphan_selection: Vec::new(),
+            disk: DiskTrend::default(),
+            timeline: None,
+            details_tab: DetailsTab::Info,
+            audit_log: None,
+            io: IoMeter::default(),
+            cores: CoreMeter::default(),
+            cpu_history: CpuHistory::default(),
+            recap: Recap::default(),
+            idle: IdleTracker::new(config.idle),
+            status_line: StatusLine::new(config.status_line),
+            budgets: Budgets::new(config.budgets),
+            bandwidth: Bandwidth::default(),
+            usage: UsageHistory::default(),
+            extended_rows: false,
+            grouping: Grouping::Off,
+            sort: SortKey::Off,
+            header_selected: false,
+            stable_order: false,
+            fading: HashMap::new(),
+            fade_out: Duration::from_secs(config.list.fade_out),
+            auto_prune: config.auto_prune,
+            last_auto_prune: None,
+            auto_actions: AutoActions::new(config.auto_actions),
+            actions: Actions::default(),
+            marked: HashSet::new(),
+            kill_picker: None,
+            template_picker: None,
+            limits_form: None,
+            recreate_form: None,
+            compare: None,
+            cost: CostMeter::new(config.cost),
+            watch: None,
+            sessions: Sessions::default(),
+            log_search: None,
+            log_view: None,
+            log_tail: crate::log_view::TAIL,
+            log_age: 0,
+            snapshots: Vec::new(),
+            unfiltered: Vec::new(),
+            highlights: with_builtin_highlights(config.highlights),
+            config_watch: None,
+            config_error: None,
+            refresh: Duration::from_secs(config.list.refresh_seconds.max(1)),
+            vim_keys: config.keys.vim,
+            chart_style: config.charts.style,
+            format: config.format,
+            journal: None,
+        }
+    }
+
+    /// Picks up the history of the previous run from the journal.
+    pub fn restore_journal(&mut self) {
+        let Some(snapshot) = self.journal.as_ref().and_then(Journal::load) else {
+            return;
+        };
+        self.disk.restore(snapshot.disk);
+        if let (Some(watch), Some(history)) = (&mut self.watch, snapshot.watch) {
+            watch.restore(history);
+        }
+        self.auto_actions.restore(snapshot.auto_actions);
+        self.usage.restore(snapshot.usage);
+        let saved_at = chrono::DateTime::from_timestamp(snapshot.saved_at, 0)
+            .map(|t| t.with_timezone(&chrono::Local).format("%H:%M:%S").to_string())
+            .unwrap_or_default();
+        self.status_message = Some(format!("Restored history saved at {}", saved_at));
+    }
+
+    /// Writes the journal, when it is due or `now` is set (on quitting).
+    pub fn save_journal(&mut self, now: bool) {
+        if !self.journal.as_ref().is_some_and(|j| now || j.due()) {
+            return;
+        }
+        let snapshot = Snapshot {
+            saved_at: chrono::Utc::now().timestamp(),
+            disk: self.disk.history(),
+            watch: self.watch.as_ref().map(Watch::history),
+            auto_actions: self.auto_actions.timers(),
+            usage: self.usage.history(),
+        };
+        if let Some(Err(e)) = self.journal.as_mut().map(|j| j.save(&snapshot)) {
+            self.status_message = Some(format!("{:#}", e));
+        }
+    }
+
+    /// Applies a reloaded config. The `api` and `audit` sections are wired
+    /// into the backend at startup and keep their old values.
+    fn apply_config(&mut self, config: Config) {
+        let counts_errors = config.counts_errors();
+        self.format = config.format;
+        self.columns = ColumnRunner::new(config.columns);
+        self.probes = ProbeRunner::new(config.probes);
+        let log_rate = config.builtin_columns.contains(&BuiltinColumn::LogRate);
+        if log_rate != self.log_meter.is_some() {
+            self.log_meter = log_rate.then(LogMeter::default);
+        }
+        let pattern = config.error_rate.pattern.as_str();
+        if !counts_errors {
+            self.error_meter = None;
+        } else if self.error_meter.as_ref().map(|m| m.pattern().as_str()) != Some(pattern) {
+            self.error_meter = Some(ErrorMeter::new(config.error_rate.pattern.clone()));
+        }
+        self.error_column = config.builtin_columns.contains(&BuiltinColumn::ErrorRate);
+        self.memory_growth_column = config.builtin_columns.contains(&BuiltinColumn::MemoryGrowth);
+        self.restart_column = config.builtin_columns.contains(&BuiltinColumn::RestartRate);
+        self.net_io_column = config.builtin_columns.contains(&BuiltinColumn::NetIo);
+        self.block_io_column = config.builtin_columns.contains(&BuiltinColumn::BlockIo);
+        self.commands = config.commands;
+        self.guards = config.guards;
+        self.templates = config.templates;
+        self.diagnostics = config.diagnostics;
+        self.stopped_days = config.orphans.stopped_days;
+        self.fade_out = Duration::from_secs(config.list.fade_out);
+        self.refresh = Duration::from_secs(config.list.refresh_seconds.max(1));
+        self.vim_keys = config.keys.vim;
+        self.chart_style = config.charts.style;
+        self.auto_prune = config.auto_prune;
+        self.auto_actions.reconfigure(config.auto_actions);
+        self.highlights = with_builtin_highlights(config.highlights);
+        self.idle.reconfigure(config.idle);
+        self.status_line.reconfigure(config.status_line);
+        self.budgets.reconfigure(config.budgets);
+        self.cost = CostMeter::new(config.cost).map(|meter| meter.keeping(self.cost.take()));
+    }
+
+    /// Reloads the config file if it changed, keeping the current settings
+    /// if it no longer parses.
+    fn reload_config(&mut self) {
+        if !self.config_watch.as_mut().is_some_and(|w| w.changed()) {
+            return;
+        }
+        match Config::load() {
+            Ok(config) => {
+                self.apply_config(config);
+                self.config_error = None;
+                self.status_message = Some("Config reloaded".to_string());
+            }
+            Err(e) => {
+                // TOML errors go on to quote the offending line, too long for the status bar.
+                let error = format!("{:#}", e);
+                let error = error.lines().next().unwrap_or_default();
+                self.config_error = Some(format!("Config not reloaded: {}", error));
+            }
+        }
+    }
+
+    pub fn selected(&self) -> Option<&ContainerStats> {
+        self.containers.get(self.selected_index)
+    }
+
+    /// The marked containers in list order.
+    fn marked_containers(&self) -> Vec<ContainerStats> {
+        self.containers.iter().filter(|c| self.marked.contains(&c.id)).cloned().collect()
+    }
+
+    /// The project whose header is selected. The selected container is
+    /// then the project's first, right below the header.
+    pub fn selected_project(&self) -> Option<&str> {
+        if !self.header_selected || self.grouping != Grouping::Project {
+            return None;
+        }
+        let project = self.selected()?.project()?;
+        let first = self.selected_index == 0 || self.containers[self.selected_index - 1].project() != Some(project);
+        first.then_some(project)
+    }
+
+    /// What `s`, `r` and `d` act on together: the marked containers, or
+    /// all listed containers of the selected project header.
+    fn batch(&self) -> Option<Vec<ContainerStats>> {
+        if !self.marked.is_empty() {
+            return Some(self.marked_containers());
+        }
+        let project = self.selected_project()?;
+        Some(self.containers.iter().filter(|c| c.project() == Some(project)).cloned().collect())
+    }
+
+    /// Refreshes everything that is polled once per tick. Errors end up in
+    /// the status bar rather than tearing down the UI.
+    pub async fn tick(&mut self) {
+        self.reload_config();
+        if let Err(e) = self.update_stats().await {
+            self.status_message = Some(format!("{:#}", e));
+        }
+        self.update_compare().await;
+        if let Some(cost) = &mut self.cost {
+            let live: Vec<_> = self.containers.iter().filter(|c| !self.fading.contains_key(&c.id)).cloned().collect();
+            cost.update(&live);
+        }
+        self.columns.schedule(&self.containers);
+        self.probes.schedule(&self.containers);
+        if let Some(meter) = &mut self.log_meter {
+            meter.sync(&self.backend, &self.containers);
+        }
+        if let Some(meter) = &mut self.error_meter {
+            meter.sync(&self.backend, &self.containers);
+        }
+        if let Err(e) = self.update_service_progress().await {
+            self.status_message = Some(format!("{:#}", e));
+        }
+        self.self_monitor.sample();
+        self.host_monitor.sample();
+        self.sample_disk().await;
+        self.run_auto_prune().await;
+        let containers = self.existing();
+        if let Err(e) = self.status_line.update(&containers, &self.format) {
+            self.status_message = Some(format!("{:#}", e));
+        }
+        self.auto_actions
+            .check(&self.backend, &containers, self.error_meter.as_ref(), self.audit_log.as_ref())
+            .await;
+        self.save_journal(false);
+        self.refresh_screen().await;
+    }
+
+    /// Picks up results of background work; cheap enough to run every frame.
+    pub fn collect(&mut self) {
+        self.columns.collect();
+        self.probes.collect();
+        if let Some(deployment) = &mut self.deployment {
+            deployment.poll();
+        }
+        if let Some(search) = &mut self.log_search {
+            search.poll();
+        }
+        if let Some(view) = &mut self.log_view {
+            view.poll();
+        }
+        let mut messages = self.auto_actions.collect();
+        for message in &messages {
+            self.recap.alert(message);
+        }
+        if let Some(message) = messages.pop() {
+            self.status_message = Some(message);
+        }
+        if let Some(message) = self.actions.collect().pop() {
+            self.status_message = Some(message);
+        }
+        self.collect_output();
+    }
+
+    pub async fn handle_key(&mut self, key: KeyEvent) -> Option<Effect> {
+        if let InputMode::Prompt(prompt) = &mut self.input_mode {
+            match key.code {
+                KeyCode::Enter => {
+                    if let InputMode::Prompt(prompt) = std::mem::replace(&mut self.input_mode, InputMode::Normal) {
+                        return self.submit_prompt(prompt).await;
+                    }
+                }
+                KeyCode::Esc => self.input_mode = InputMode::Normal,
+                KeyCode::Backspace => {
+                    prompt.input.pop();
+                }
+                KeyCode::Char(c) => prompt.input.push(c),
+                _ => {}
+            }
+            return None;
+        }
+
+        if matches!(self.input_mode, InputMode::Confirm(_)) {
+            if let InputMode::Confirm(confirm) = std::mem::replace(&mut self.input_mode, InputMode::Normal) {
+                if matches!(key.code, KeyCode::Char('y' | 'Y')) {
+                    self.confirmed(confirm.action).await;
+                }
+            }
+            return None;
+        }
+
+        if let Some(pane) = &mut self.output {
+            match key.code {
+                KeyCode::Esc | KeyCode::Char('q') => self.output = None,
+                KeyCode::Char('s') => {
+                    self.input_mode = InputMode::Prompt(Prompt {
+                        title: "Save the output to".to_string(),
+                        input: chrono::Local::now().format("dockertop-output-%Y%m%d-%H%M%S.txt").to_string(),
+                        action: PromptAction::SaveOutput,
+                    });
+                }
+                KeyCode::Up => pane.scroll = pane.scroll.saturating_sub(1),
+                KeyCode::Down => pane.scroll = pane.scroll.saturating_add(1),
+                KeyCode::PageUp => pane.scroll = pane.scroll.saturating_sub(10),
+                KeyCode::PageDown => pane.scroll = pane.scroll.saturating_add(10),
+                _ => {}
+            }
+            return None;
+        }
+
+        if let Some(browser) = &mut self.files {
+            let backend = self.backend.clone();
+            if let Some(view) = &mut browser.viewing {
+                match key.code {
+                    KeyCode::Esc | KeyCode::Char('q') | KeyCode::Left | KeyCode::Backspace => browser.viewing = None,
+                    KeyCode::Up => view.scroll = view.scroll.saturating_sub(1),
+                    KeyCode::Down => view.scroll = view.scroll.saturating_add(1),
+                    KeyCode::PageUp => view.scroll = view.scroll.saturating_sub(10),
+                    KeyCode::PageDown => view.scroll = view.scroll.saturating_add(10),
+                    _ => {}
+                }
+                return None;
+            }
+            match key.code {
+                KeyCode::Esc | KeyCode::Char('q') => self.files = None,
+                KeyCode::Up => browser.move_cursor(false),
+                KeyCode::Down => browser.move_cursor(true),
+                KeyCode::Enter | KeyCode::Right => browser.enter(backend.as_ref()).await,
+                KeyCode::Left | KeyCode::Backspace => browser.up(backend.as_ref()).await,
+                _ => {}
+            }
+            return None;
+        }
+        if let Some(view) = &mut self.log_view {
+            match key.code {
+                KeyCode::Esc if view.search.is_some() => {
+                    view.search(None);
+                }
+                KeyCode::Esc | KeyCode::Char('q') => self.log_view = None,
+                KeyCode::Char('r') => view.reload(self.backend.clone()),
+                KeyCode::Char('f') => view.toggle_follow(self.backend.clone()),
+                KeyCode::Char('t') => view.toggle_timestamps(self.backend.clone()),
+                KeyCode::Char('l') => {
+                    self.input_mode = InputMode::Prompt(Prompt {
+                        title: "Lines to fetch from the end of the logs".to_string(),
+                        input: view.tail.to_string(),
+                        action: PromptAction::LogViewTail,
+                    });
+                }
+                KeyCode::Char('/') => {
+                    self.input_mode = InputMode::Prompt(Prompt {
+                        title: "Search the logs for (regex, empty clears)".to_string(),
+                        input: view.search.as_ref().map(|r| r.to_string()).unwrap_or_default(),
+                        action: PromptAction::SearchLogView,
+                    });
+                }
+                KeyCode::Char(c @ ('n' | 'N')) if !view.jump(c == 'n') => {
+                    self.status_message = Some(match (&view.search, c) {
+                        (None, _) => "Search with / first".to_string(),
+                        (Some(_), 'n') => "No older matches".to_string(),
+                        (Some(_), _) => "No newer matches".to_string(),
+                    });
+                }
+                KeyCode::Char('&') if !view.toggle_filter() => {
+                    self.status_message = Some("Search with / first".to_string());
+                }
+                KeyCode::Up => view.scroll_by(1),
+                KeyCode::Down => view.scroll_by(-1),
+                KeyCode::PageUp => view.scroll_by(20),
+                KeyCode::PageDown => view.scroll_by(-20),
+                KeyCode::Home => view.scroll = view.lines.len(),
+                KeyCode::End => view.scroll = 0,
+                _ => {}
+            }
+            return None;
+        }
+        if let Some(search) = &mut self.log_search {
+            match key.code {
+                KeyCode::Esc if search.context => search.context = false,
+                KeyCode::Esc | KeyCode::Char('q') => self.log_search = None,
+                KeyCode::Up => search.move_cursor(false),
+                KeyCode::Down => search.move_cursor(true),
+                KeyCode::Enter => search.context = !search.context && search.selected().is_some(),
+                _ => {}
+            }
+            return None;
+        }
+        if self.sessions.switcher.is_some() {
+            self.handle_switcher_key(key);
+            return None;
+        }
+        if self.sessions.active.is_some() {
+            self.handle_session_key(key);
+            return None;
+        }
+        if self.kill_picker.is_some() {
+            self.handle_kill_picker_key(key.code);
+            return None;
+        }
+        if self.template_picker.is_some() {
+            self.handle_template_picker_key(key.code);
+            return None;
+        }
+        if self.limits_form.is_some() {
+            self.handle_limits_form_key(key.code);
+            return None;
+        }
+        if self.recreate_form.is_some() {
+            self.handle_recreate_form_key(key.code);
+            return None;
+        }
+
+        self.status_message = None;
+        if self.service_update.as_ref().is_some_and(|u| u.finished) {
+            self.service_update = None;
+        }
+        if self.deployment.as_ref().is_some_and(|d| d.finished) {
+            self.deployment = None;
+        }
+        let code = match key.code {
+            KeyCode::Char('j') if self.vim_keys => KeyCode::Down,
+            KeyCode::Char('k') if self.vim_keys => KeyCode::Up,
+            code => code,
+        };
+        match code {
+            KeyCode::Char('q') => self.should_quit = true,
+            KeyCode::Char('U') => self.prompt_service_image(),
+            KeyCode::Char('B') => self.prompt_blue_green(),
+            KeyCode::Char('P') => self.prompt_redeploy(),
+            KeyCode::Char('W') => self.prompt_update(),
+            KeyCode::Char('C') => self.prompt_checkpoint(),
+            KeyCode::Char('R') => self.prompt_restore().await,
+            KeyCode::Char('E') => self.open_session(SessionKind::Exec),
+            KeyCode::Char('L') => self.open_session(SessionKind::Logs),
+            KeyCode::Char('T') => self.open_session(SessionKind::Tail),
+            KeyCode::Char('A') => self.open_session(SessionKind::Attach { stdin: false }),
+            KeyCode::Char('I') => self.open_session(SessionKind::Attach { stdin: true }),
+            KeyCode::Char('F') => {
+                self.input_mode = InputMode::Prompt(Prompt {
+                    title: "Search logs of listed containers (regex)".to_string(),
+                    input: String::new(),
+                    action: PromptAction::SearchLogs,
+                });
+            }
+            KeyCode::Char('/') => {
+                self.input_mode = InputMode::Prompt(Prompt {
+                    title: "Filter (e.g. cpu > 50 && label(\"env\") == \"prod\")".to_string(),
+                    input: self.filter.as_ref().map(|f| f.to_string()).unwrap_or_default(),
+                    action: PromptAction::Filter,
+                });
+            }
+            KeyCode::Char('X') => {
+                self.input_mode = InputMode::Prompt(Prompt {
+                    title: "Export screen to (.html keeps colors)".to_string(),
+                    input: chrono::Local::now().format("dockertop-%Y%m%d-%H%M%S.txt").to_string(),
+                    action: PromptAction::ExportScreen,
+                });
+            }
+            KeyCode::Char('S') if self.sessions.list.is_empty() => {
+                self.status_message = Some("No sessions open (E starts a shell, L follows logs)".to_string());
+            }
+            KeyCode::Char('S') => self.sessions.switcher = Some(self.sessions.active.unwrap_or(0)),
+            KeyCode::F(12) => self.show_debug = !self.show_debug,
+            KeyCode::Char('1') => self.screen = Screen::Containers,
+            KeyCode::Char('2') => self.switch_screen(Screen::Dependencies).await,
+            KeyCode::Char('3') => self.switch_screen(Screen::Networks).await,
+            KeyCode::Char('4') => self.switch_screen(Screen::Volumes).await,
+            KeyCode::Char('5') => self.switch_screen(Screen::Images).await,
+            KeyCode::Char('6') => self.switch_screen(Screen::Orphans).await,
+            KeyCode::Char('7') => self.switch_screen(Screen::Disk).await,
+            KeyCode::Char('8') => self.switch_screen(Screen::Idle).await,
+            KeyCode::Char('9') => self.switch_screen(Screen::Registries).await,
+            KeyCode::Char('w') if self.screen == Screen::Containers => self.extended_rows = !self.extended_rows,
+            KeyCode::Char('g') if self.screen == Screen::Containers => {
+                let has_projects = self.containers.iter().any(|c| c.project().is_some());
+                self.grouping = self.grouping.next(has_projects, pods::any(&self.containers));
+                self.header_selected = false;
+                let selected = self.selected().map(|c| c.id.clone());
+                self.order_containers();
+                if let Some(i) = selected.and_then(|id| self.containers.iter().position(|c| c.id == id)) {
+                    self.selected_index = i;
+                }
+            }
+            KeyCode::Char('o') if self.screen == Screen::Containers => self.stable_order = !self.stable_order,
+            KeyCode::Char('>') if self.screen == Screen::Containers => {
+                self.sort = self.sort.next();
+                self.header_selected = false;
+                let selected = self.selected().map(|c| c.id.clone());
+                self.order_containers();
+                if let Some(i) = selected.and_then(|id| self.containers.iter().position(|c| c.id == id)) {
+                    self.selected_index = i;
+                }
+            }
+            KeyCode::Char(' ') if self.screen == Screen::Containers => {
+                if let Some(id) = self.selected().map(|c| c.id.clone()) {
+                    if !self.marked.remove(&id) {
+                        self.marked.insert(id);
+                    }
+                    self.selected_index = (self.selected_index + 1).min(self.containers.len() - 1);
+                }
+            }
+            KeyCode::Esc if self.screen == Screen::Containers => self.marked.clear(),
+            // With containers marked or a project header selected, stop them
+            // if any runs, start them otherwise.
+            KeyCode::Char('s') if self.screen == Screen::Containers && self.batch().is_some() => {
+                let batch = self.batch().unwrap_or_default();
+                let stop = batch.iter().any(|c| c.is_running());
+                let targets: Vec<_> = batch.into_iter().filter(|c| c.is_running() == stop).collect();
+                let action = if stop { ContainerAction::Stop } else { ContainerAction::Start };
+                self.act(targets, action);
+            }
+            KeyCode::Char('r') if self.screen == Screen::Containers && self.batch().is_some() => {
+                let batch = self.batch().unwrap_or_default();
+                self.act(batch, ContainerAction::Restart);
+            }
+            KeyCode::Char('d') if self.screen == Screen::Containers && self.batch().is_some() => {
+                let batch = self.batch().unwrap_or_default();
+                let names: Vec<_> = batch.iter().map(|c| c.name.as_str()).collect();
+                let question = format!("Remove {} containers ({})?", batch.len(), names.join(", "));
+                self.confirm_remove(batch, question);
+            }
+            KeyCode::Char('s') if self.screen == Screen::Containers => {
+                if let Some(container) = self.selected().cloned() {
+                    let action = ContainerAction::toggle(&container);
+                    self.act(vec![container], action);
+                }
+            }
+            KeyCode::Char('r') if self.screen == Screen::Containers => {
+                if let Some(container) = self.selected().cloned() {
+                    self.act(vec![container], ContainerAction::Restart);
+                }
+            }
+            KeyCode::Char('p') if self.screen == Screen::Containers => {
+                if let Some(container) = self.selected().cloned() {
+                    let action = ContainerAction::pause_toggle(&container);
+                    self.act(vec![container], action);
+                }
+            }
+            KeyCode::Char('d') if self.screen == Screen::Containers => {
+                if let Some(container) = self.selected().cloned() {
+                    let question = format!("Remove container {}?", container.name);
+                    self.confirm_remove(vec![container], question);
+                }
+            }
+            KeyCode::Char('D') if self.screen == Screen::Containers => {
+                if let Some(container) = self.selected().cloned() {
+                    let containers = std::slice::from_ref(&container);
+                    let phrase = guard_phrase(&self.guards, containers, &ContainerAction::ForceRemove)
+                        .unwrap_or_else(|| container.name.clone());
+                    self.input_mode = InputMode::Prompt(Prompt {
+                        title: format!("Type {} to force-remove {} and its anonymous volumes", phrase, container.name),
+                        input: String::new(),
+                        action: PromptAction::ForceRemove {
+                            container: Box::new(container),
+                            phrase,
+                        },
+                    });
+                }
+            }
+            KeyCode::F(2) | KeyCode::Char('c') if self.screen == Screen::Containers => {
+                if let Some(container) = self.selected().cloned() {
+                    self.input_mode = InputMode::Prompt(Prompt {
+                        title: format!("New name for {}", container.name),
+                        input: container.name.clone(),
+                        action: PromptAction::Rename {
+                            container: Box::new(container),
+                        },
+                    });
+                }
+            }
+            KeyCode::Char('b') if self.screen == Screen::Containers => self.run_diagnostics(),
+            KeyCode::Char('O') if self.screen == Screen::Containers => {
+                if let Some(container) = self.selected().cloned() {
+                    if container.is_running() {
+                        self.files = Some(FileBrowser::open(self.backend.as_ref(), container).await);
+                    } else {
+                        self.status_message = Some(format!("{} is not running", container.name));
+                    }
+                }
+            }
+            KeyCode::Char('H') if self.screen == Screen::Containers => self.show_recap(),
+            KeyCode::Char('!') if self.screen == Screen::Containers => {
+                if let Some(container) = self.selected().cloned() {
+                    if !container.is_running() {
+                        self.status_message = Some(format!("{} is not running", container.name));
+                    } else {
+                        self.input_mode = InputMode::Prompt(Prompt {
+                            title: format!("Run in {} (with sh -c)", container.name),
+                            input: String::new(),
+                            action: PromptAction::RunCommand {
+                                container: Box::new(container),
+                            },
+                        });
+                    }
+                }
+            }
+            KeyCode::Char('t') if self.screen == Screen::Containers => {
+                self.input_mode = InputMode::Prompt(Prompt {
+                    title: "Name this snapshot of all containers' stats".to_string(),
+                    input: chrono::Local::now().format("%H:%M:%S").to_string(),
+                    action: PromptAction::TakeSnapshot,
+                });
+            }
+            KeyCode::Char('v') if self.screen == Screen::Containers => match self.snapshots.last() {
+                Some(latest) => {
+                    let names: Vec<_> = self.snapshots.iter().map(|s| s.name.as_str()).collect();
+                    self.input_mode = InputMode::Prompt(Prompt {
+                        title: format!("Compare with snapshot ({})", names.join(", ")),
+                        input: latest.name.clone(),
+                        action: PromptAction::DiffSnapshot,
+                    });
+                }
+                None => self.status_message = Some("No snapshots yet (t takes one)".to_string()),
+            },
+            KeyCode::Char('l') if self.screen == Screen::Containers => {
+                // Marked containers or a project's are merged, as with
+                // `docker compose logs`.
+                let containers = self.batch().or_else(|| Some(vec![self.selected()?.clone()]));
+                if let Some(containers) = containers.filter(|c| !c.is_empty()) {
+                    self.log_view = Some(LogView::open(self.backend.clone(), containers, self.log_tail, self.log_age));
+                }
+            }
+            KeyCode::Char('i') if self.screen == Screen::Containers => {
+                if let Some(container) = self.selected().cloned() {
+                    let tag = chrono::Local::now().format("snapshot-%Y%m%d-%H%M");
+                    self.input_mode = InputMode::Prompt(Prompt {
+                        title: format!("Commit {} to image (repository:tag)", container.name),
+                        input: format!("{}:{}", container.name.to_lowercase(), tag),
+                        action: PromptAction::CommitImage {
+                            container: Box::new(container),
+                        },
+                    });
+                }
+            }
+            KeyCode::Char('e') if self.screen == Screen::Containers => {
+                if let Some(container) = self.selected().cloned() {
+                    self.input_mode = InputMode::Prompt(Prompt {
+                        title: format!("Export the logs of {} to", container.name),
+                        input: format!("{}-{}.log", container.name, chrono::Local::now().format("%Y%m%d-%H%M%S")),
+                        action: PromptAction::ExportLogs {
+                            container: Box::new(container),
+                        },
+                    });
+                }
+            }
+            KeyCode::Char('u') if self.screen == Screen::Containers => {
+                if let Some(container) = self.selected().cloned() {
+                    match self.backend.inspect_container(&container.id).await {
+                        Ok(inspect) => {
+                            let host_config = inspect.host_config.unwrap_or_default();
+                            self.limits_form = Some(LimitsForm::new(container, &host_config));
+                        }
+                        Err(e) => self.status_message = Some(format!("Could not read the limits: {:#}", e)),
+                    }
+                }
+            }
+            KeyCode::Char('M') if self.screen == Screen::Containers => {
+                if let Some(container) = self.selected().cloned() {
+                    match self.backend.inspect_container(&container.id).await {
+                        Ok(inspect) => self.recreate_form = Some(RecreateForm::new(container, &inspect)),
+                        Err(e) => self.status_message = Some(format!("Could not read the configuration: {:#}", e)),
+                    }
+                }
+            }
+            KeyCode::Char('K') if self.screen == Screen::Containers => {
+                if let Some(container) = self.selected().cloned() {
+                    self.kill_picker = Some(KillPicker { container, cursor: 0 });
+                }
+            }
+            KeyCode::Char('a') if self.screen == Screen::Containers => {
+                if self.templates.is_empty() {
+                    self.status_message = Some("No templates configured ([[templates]] in the config)".to_string());
+                } else {
+                    self.template_picker = Some(TemplatePicker { cursor: 0 });
+                }
+            }
+            KeyCode::Tab if self.screen == Screen::Containers => {
+                self.details_tab = match self.details_tab {
+                    DetailsTab::Info => DetailsTab::Events,
+                    DetailsTab::Events => DetailsTab::Info,
+                };
+            }
+            KeyCode::Up if self.screen == Screen::Orphans => {
+                self.orphan_cursor = self.orphan_cursor.saturating_sub(1);
+            }
+            KeyCode::Down if self.screen == Screen::Orphans => {
+                self.orphan_cursor = (self.orphan_cursor + 1).min(OrphanKind::ALL.len() - 1);
+            }
+            KeyCode::Char(' ') if self.screen == Screen::Orphans => {
+                let kind = OrphanKind::ALL[self.orphan_cursor];
+                if let Some(i) = self.orphan_selection.iter().position(|&k| k == kind) {
+                    self.orphan_selection.remove(i);
+                } else {
+                    self.orphan_selection.push(kind);
+                }
+            }
+            KeyCode::Char('x') if self.screen == Screen::Orphans => self.prompt_orphan_cleanup(),
+            KeyCode::Up if self.screen == Screen::Registries => {
+                self.registry_cursor = self.registry_cursor.saturating_sub(1);
+            }
+            KeyCode::Down if self.screen == Screen::Registries => {
+                self.registry_cursor = (self.registry_cursor + 1).min(self.registries.len().saturating_sub(1));
+            }
+            KeyCode::Enter if self.screen == Screen::Registries => {
+                if let Some(login) = self.registries.get(self.registry_cursor) {
+                    self.status_message = Some(match self.backend.pull_logins() {
+                        Some(pull_logins) => {
+                            pull_logins.choose(login);
+                            format!(
+                                "Pulls from {} use {} ({})",
+                                registries::registry_host(&login.server),
+                                login.username,
+                                login.server
+                            )
+                        }
+                        None => "Pulls here do not use the stored logins".to_string(),
+                    });
+                }
+            }
+            KeyCode::Char('i') if self.screen == Screen::Registries => {
+                self.input_mode = InputMode::Prompt(Prompt {
+                    title: "Registry to log in to".to_string(),
+                    input: "docker.io".to_string(),
+                    action: PromptAction::RegistryServer,
+                });
+            }
+            KeyCode::Char('o') if self.screen == Screen::Registries => {
+                if let Some(login) = self.registries.get(self.registry_cursor) {
+                    self.input_mode = InputMode::Confirm(Confirm {
+                        question: format!("Log out of {} and remove the stored login of {}?", login.server, login.username),
+                        action: ConfirmAction::RegistryLogout(login.server.clone()),
+                    });
+                }
+            }
+            KeyCode::Char('x') if self.screen == Screen::Containers => {
+                // The prune takes every stopped container, not just the ones
+                // the filter shows.
+                let stopped: Vec<_> =
+                    self.unfiltered.iter().filter(|c| !c.is_running()).map(|c| c.name.as_str()).collect();
+                let question = match stopped.len() {
+                    0 => "Remove all stopped containers? There are none".to_string(),
+                    n => format!("Remove all {} stopped containers: {}?", n, stopped.join(", ")),
+                };
+                self.input_mode = InputMode::Confirm(Confirm {
+                    question,
+                    action: ConfirmAction::PruneContainers,
+                });
+            }
+            KeyCode::Up if self.screen != Screen::Containers => {
+                self.screen_scroll = self.screen_scroll.saturating_sub(1);
+            }
+            KeyCode::Down if self.screen != Screen::Containers => {
+                self.screen_scroll = self.screen_scroll.saturating_add(1);
+            }
+            KeyCode::Left | KeyCode::Right if self.compare.is_some() => {
+                if let Some(compare) = &mut self.compare {
+                    compare.focused = code == KeyCode::Right;
+                }
+            }
+            KeyCode::Up | KeyCode::Down if self.compare.as_ref().is_some_and(|c| c.focused) => {
+                if let Some(compare) = &mut self.compare {
+                    compare.move_selection(code == KeyCode::Down);
+                }
+            }
+            // In project grouping, a project's header is a stop between its
+            // first container and the row above.
+            KeyCode::Up if self.grouping == Grouping::Project && self.selected_project().is_none() => {
+                self.header_selected = true;
+                if self.selected_project().is_none() {
+                    self.header_selected = false;
+                    self.selected_index = self.selected_index.saturating_sub(1);
+                }
+            }
+            KeyCode::Down if self.selected_project().is_some() => self.header_selected = false,
+            KeyCode::Up if self.selected_index > 0 => {
+                self.header_selected = false;
+                self.selected_index -= 1;
+            }
+            KeyCode::Down if self.selected_index < self.containers.len().saturating_sub(1) => {
+                self.header_selected = false;
+                self.selected_index += 1;
+            }
+            KeyCode::Char(c) => return self.run_custom_command(c),
+            _ => {}
+        }
+        None
+    }
+
+    fn open_session(&mut self, kind: SessionKind) {
+        let Some(container) = self.selected() else {
+            return;
+        };
+        let session = match kind {
+            SessionKind::Tail => return self.open_tail(),
+            _ if !container.is_running() => {
+                self.status_message = Some(format!("{} is not running", container.name));
+                return;
+            }
+            SessionKind::Exec => Session::exec(&self.backend, container),
+            SessionKind::Logs => Session::logs(&self.backend, container),
+            SessionKind::Attach { stdin } => Session::attach(&self.backend, container, stdin),
+        };
+        self.sessions.open(session);
+    }
+
+    /// Follows the running containers of the selected container's compose
+    /// project in one session, or all listed running containers if it has none.
+    fn open_tail(&mut self) {
+        let project = self
+            .selected()
+            .and_then(|c| c.labels.get("com.docker.compose.project"))
+            .cloned();
+        let containers: Vec<ContainerStats> = self
+            .existing()
+            .into_iter()
+            .filter(|c| c.is_running())
+            .filter(|c| project.is_none() || c.labels.get("com.docker.compose.project") == project.as_ref())
+            .collect();
+        if containers.is_empty() {
+            self.status_message = Some("No running containers to tail".to_string());
+            return;
+        }
+        let label = match project {
+            Some(project) => format!("{} ({} containers)", project, containers.len()),
+            None => format!("{} containers", containers.len()),
+        };
+        self.sessions.open(Session::tail(&self.backend, label, &containers));
+    }
+
+    /// Runs the action in the background, or first asks for the phrase of
+    /// the guard covering it.
+    fn act(&mut self, containers: Vec<ContainerStats>, action: ContainerAction) {
+        let Some(phrase) = guard_phrase(&self.guards, &containers, &action) else {
+            self.status_message = Some(self.run_action(&containers, action));
+            return;
+        };
+        let target = match containers.as_slice() {
+            [container] => container.name.clone(),
+            _ => format!("{} containers", containers.len()),
+        };
+        self.input_mode = InputMode::Prompt(Prompt {
+            title: format!("Type {} to {} {}", phrase, action.verbs().0, target),
+            input: String::new(),
+            action: PromptAction::Guarded {
+                containers,
+                action,
+                phrase,
+            },
+        });
+    }
+
+    fn run_action(&mut self, containers: &[ContainerStats], action: ContainerAction) -> String {
+        match containers {
+            [container] => self.actions.run(&self.backend, container, action),
+            _ => self.actions.run_batch(&self.backend, containers, action),
+        }
+    }
+
+    /// A guarded removal asks for the guard's phrase instead of y/n.
+    fn confirm_remove(&mut self, containers: Vec<ContainerStats>, question: String) {
+        if guard_phrase(&self.guards, &containers, &ContainerAction::Remove).is_some() {
+            self.act(containers, ContainerAction::Remove);
+        } else {
+            self.input_mode = InputMode::Confirm(Confirm {
+                question,
+                action: ConfirmAction::RemoveContainers(containers),
+            });
+        }
+    }
+
+    async fn confirmed(&mut self, action: ConfirmAction) {
+        match action {
+            ConfirmAction::RemoveContainers(containers) => {
+                self.status_message = Some(self.actions.run_batch(&self.backend, &containers, ContainerAction::Remove));
+            }
+            ConfirmAction::PruneContainers => {
+                self.status_message = Some(match self.backend.prune_containers().await {
+                    Ok(report) => format!(
+                        "Pruned {} stopped containers, reclaiming {}",
+                        report.containers.len(),
+                        units::format_bytes(&self.format, report.space_reclaimed)
+                    ),
+                    Err(e) => format!("Prune failed: {:#}", e),
+                });
+            }
+            ConfirmAction::Redeploy(project, stages) => {
+                self.deployment = Some(Deployment::redeploy(self.backend.clone(), project, stages));
+            }
+            ConfirmAction::Update(container) => {
+                self.deployment = Some(Deployment::update(self.backend.clone(), *container));
+            }
+            ConfirmAction::RegistryLogout(server) => {
+                self.status_message = Some(match self.backend.registry_logout(&server).await {
+                    Ok(()) => format!("Logged out of {}", server),
+                    Err(e) => format!("Could not log out of {}: {:#}", server, e),
+                });
+                self.update_registries().await;
+            }
+            ConfirmAction::CleanupOrphans => self.cleanup_orphans().await,
+            ConfirmAction::BlueGreen(old, new) => {
+                self.deployment = Some(Deployment::start(self.backend.clone(), *old, *new));
+            }
+        }
+    }
+
+    fn handle_kill_picker_key(&mut self, code: KeyCode) {
+        let Some(picker) = &mut self.kill_picker else {
+            return;
+        };
+        match code {
+            KeyCode::Esc | KeyCode::Char('q') => self.kill_picker = None,
+            KeyCode::Up => picker.cursor = picker.cursor.saturating_sub(1),
+            KeyCode::Down => picker.cursor = (picker.cursor + 1).min(SIGNALS.len()),
+            KeyCode::Enter => {
+                let Some(KillPicker { container, cursor }) = self.kill_picker.take() else {
+                    return;
+                };
+                match SIGNALS.get(cursor) {
+                    Some((signal, _)) => self.act(vec![container], ContainerAction::Kill(signal.to_string())),
+                    None => {
+                        self.input_mode = InputMode::Prompt(Prompt {
+                            title: format!("Signal to send to {} (e.g. SIGUSR1 or 10)", container.name),
+                            input: String::new(),
+                            action: PromptAction::KillSignal {
+                                container: Box::new(container),
+                            },
+                        });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_template_picker_key(&mut self, code: KeyCode) {
+        let Some(picker) = &mut self.template_picker else {
+            return;
+        };
+        match code {
+            KeyCode::Esc | KeyCode::Char('q') => self.template_picker = None,
+            KeyCode::Up => picker.cursor = picker.cursor.saturating_sub(1),
+            KeyCode::Down => picker.cursor = (picker.cursor + 1).min(self.templates.len().saturating_sub(1)),
+            KeyCode::Enter => {
+                let Some(TemplatePicker { cursor }) = self.template_picker.take() else {
+                    return;
+                };
+                let Some(template) = self.templates.get(cursor).cloned() else {
+                    return;
+                };
+                // Checked here so a typo shows at once; the name is picked
+                // once the current containers are listed.
+                self.status_message = Some(match run_spec(&template, &[]) {
+                    Ok(_) => self.actions.run_template(&self.backend, template),
+                    Err(e) => format!("Template {} is invalid: {:#}", template.name, e),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_limits_form_key(&mut self, code: KeyCode) {
+        let Some(form) = &mut self.limits_form else {
+            return;
+        };
+        match code {
+            KeyCode::Esc => self.limits_form = None,
+            KeyCode::Up | KeyCode::BackTab => form.move_cursor(false),
+            KeyCode::Down | KeyCode::Tab => form.move_cursor(true),
+            KeyCode::Backspace => {
+                form.input().pop();
+            }
+            KeyCode::Char(c) => form.input().push(c),
+            KeyCode::Enter => match form.limits() {
+                Ok(limits) if limits == ResourceLimits::default() => self.limits_form = None,
+                Ok(limits) => {
+                    let container = form.container.clone();
+                    self.limits_form = None;
+                    self.act(vec![container], ContainerAction::Update(limits));
+                }
+                Err(e) => form.error = Some(format!("{:#}", e)),
+            },
+            _ => {}
+        }
+    }
+
+    fn handle_recreate_form_key(&mut self, code: KeyCode) {
+        let Some(form) = &mut self.recreate_form else {
+            return;
+        };
+        match code {
+            KeyCode::Esc => self.recreate_form = None,
+            KeyCode::Up | KeyCode::BackTab => form.move_cursor(false),
+            KeyCode::Down | KeyCode::Tab => form.move_cursor(true),
+            KeyCode::Backspace => form.pop(),
+            KeyCode::Char(c) => form.push(c),
+            KeyCode::Enter if !form.changed() => self.recreate_form = None,
+            KeyCode::Enter => match form.spec() {
+                Ok(spec) => {
+                    let container = form.container.clone();
+                    self.recreate_form = None;
+                    self.act(vec![container], ContainerAction::Recreate(Box::new(spec)));
+                }
+                Err(e) => form.error = Some(format!("{:#}", e)),
+            },
+            _ => {}
+        }
+    }
+
+    fn handle_switcher_key(&mut self, key: KeyEvent) {
+        let sessions = &mut self.sessions;
+        let Some(cursor) = sessions.switcher else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc => sessions.switcher = None,
+            KeyCode::Up => sessions.switcher = Some(cursor.saturating_sub(1)),
+            KeyCode::Down => sessions.switcher = Some((cursor + 1).min(sessions.list.len().saturating_sub(1))),
+            KeyCode::Enter => {
+                sessions.switcher = None;
+                sessions.active = (cursor < sessions.list.len()).then_some(cursor);
+            }
+            KeyCode::Char('x') | KeyCode::Delete => {
+                sessions.close(cursor);
+                if sessions.list.is_empty() {
+                    sessions.switcher = None;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Keys go to the shown session; only Esc and Ctrl combinations are
+    /// kept for dockertop so shells get every printable key.
+    fn handle_session_key(&mut self, key: KeyEvent) {
+        let sessions = &mut self.sessions;
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => sessions.active = None,
+            KeyCode::Char('n') if ctrl => sessions.cycle(true),
+            KeyCode::Char('p') if ctrl => sessions.cycle(false),
+            KeyCode::Char('s') if ctrl => sessions.switcher = sessions.active,
+            // Detaches, never stopping the container: Ctrl-C is not passed on.
+            KeyCode::Char('q') if ctrl && matches!(sessions.active().map(|s| s.kind), Some(SessionKind::Attach { .. })) => {
+                if let Some(active) = sessions.active {
+                    sessions.close(active);
+                }
+            }
+            _ => {
+                let Some(session) = sessions.active_mut() else {
+                    return;
+                };
+                let interactive = matches!(session.kind, SessionKind::Exec | SessionKind::Attach { stdin: true });
+                let logs = session.kind == SessionKind::Logs;
+                match key.code {
+                    KeyCode::Char('j') if logs => {
+                        session.json_view = session.json_view.next();
+                        session.scroll = 0;
+                    }
+                    KeyCode::Char('f') if logs => {
+                        self.input_mode = InputMode::Prompt(Prompt {
+                            title: "Show JSON lines with field=value (empty shows all)".to_string(),
+                            input: session.filter.as_ref().map(|f| f.to_string()).unwrap_or_default(),
+                            action: PromptAction::LogFilter,
+                        });
+                    }
+                    KeyCode::PageUp => session.scroll_by(10),
+                    KeyCode::PageDown => session.scroll_by(-10),
+                    KeyCode::Up if interactive => session.recall(true),
+                    KeyCode::Down if interactive => session.recall(false),
+                    KeyCode::Up => session.scroll_by(1),
+                    KeyCode::Down => session.scroll_by(-1),
+                    KeyCode::Enter if interactive => session.submit(),
+                    KeyCode::Backspace if interactive => {
+                        session.input.pop();
+                    }
+                    KeyCode::Char(c) if interactive && !ctrl => session.input.push(c),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    async fn switch_screen(&mut self, screen: Screen) {
+        if self.screen == screen {
+            return;
+        }
+        self.screen = screen;
+        self.screen_scroll = 0;
+        self.refresh_screen().await;
+    }
+
+    /// Fetches the extra data the current screen needs beyond the container list.
+    async fn refresh_screen(&mut self) {
+        match self.screen {
+            Screen::Containers => {
+                if let Err(e) = self.update_timeline().await {
+                    self.status_message = Some(format!("{:#}", e));
+                }
+            }
+            Screen::Disk | Screen::Idle => {}
+            Screen::Registries => self.update_registries().await,
+            Screen::Watch => {
+                if let Err(e) = self.update_watch().await {
+                    self.status_message = Some(format!("{:#}", e));
+                }
+            }
+            Screen::Dependencies => self.update_links().await,
+            Screen::Networks => match self.backend.networks().await {
+                Ok(networks) => self.networks = networks,
+                Err(e) => self.status_message = Some(format!("{:#}", e)),
+            },
+            Screen::Volumes => match self.backend.volumes().await {
+                Ok(volumes) => self.volumes = volumes,
+                Err(e) => self.status_message = Some(format!("{:#}", e)),
+            },
+            Screen::Images => match self.backend.images().await {
+                Ok(images) => self.images = images,
+                Err(e) => self.status_message = Some(format!("{:#}", e)),
+            },
+            Screen::Orphans => {
+                if let Err(e) = self.update_orphans().await {
+                    self.status_message = Some(format!("{:#}", e));
+                }
+            }
+        }
+    }
+
+    async fn update_registries(&mut self) {
+        match self.backend.registry_logins().await {
+            Ok(logins) => self.registries = logins,
+            Err(e) => self.status_message = Some(format!("{:#}", e)),
+        }
+        self.registry_cursor = self.registry_cursor.min(self.registries.len().saturating_sub(1));
+    }
+
+    /// Disk usage is sampled in the background of every screen so the trend
+    /// covers the whole session; failures only show up on the disk screen.
+    async fn sample_disk(&mut self) {
+        if !self.disk.due() {
+            return;
+        }
+        self.disk.attempted();
+        match self.backend.disk_usage().await {
+            Ok(usage) => self.disk.push(usage),
+            Err(e) if self.screen == Screen::Disk => self.status_message = Some(format!("{:#}", e)),
+            Err(_) => {}
+        }
+    }
+
+    /// The configured auto-prune policy, run on its own schedule. It only
+    /// runs with the audit log open, and records every removal there.
+    async fn run_auto_prune(&mut self) {
+        let policy = &self.auto_prune;
+        let interval = Duration::from_secs(policy.interval);
+        if !policy.enabled || self.last_auto_prune.is_some_and(|t| t.elapsed() < interval) {
+            return;
+        }
+        self.last_auto_prune = Some(Instant::now());
+        let Some(log) = self.audit_log.clone() else {
+            self.status_message = Some("Auto-prune is off: it needs the audit log ([audit] enabled)".to_string());
+            return;
+        };
+
+        let images = if policy.dangling_images {
+            match self.backend.images().await {
+                Ok(images) => images,
+                Err(e) => {
+                    self.status_message = Some(format!("Auto-prune: {:#}", e));
+                    return;
+                }
+            }
+        } else {
+            Vec::new()
+        };
+        // Every container, whatever the filter: an image a hidden one runs
+        // is not dangling.
+        let containers = match self.backend.containers().await {
+            Ok(containers) => containers,
+            Err(e) => {
+                self.status_message = Some(format!("Auto-prune: {:#}", e));
+                return;
+            }
+        };
+        let now = chrono::Utc::now().timestamp();
+        let candidates: Vec<Orphan> = orphans::find(&containers, &[], &[], &images, policy.exited_days, now, &self.format)
+            .into_iter()
+            .filter(|o| o.kind != OrphanKind::StoppedContainer || policy.exited_days > 0)
+            .collect();
+        if candidates.is_empty() {
+            return;
+        }
+
+        let mut removed = 0;
+        let mut failures = Vec::new();
+        for orphan in &candidates {
+            let result = orphans::remove_one(self.backend.as_ref(), orphan).await;
+            let target = format!("{} ({})", orphan.name, orphan.detail);
+            if let Err(e) = log.record("auto_prune", &target, &result) {
+                failures.push(format!("{:#}", e));
+            }
+            match result {
+                Ok(()) => removed += 1,
+                Err(e) => failures.push(format!("{}: {:#}", orphan.name, e)),
+            }
+        }
+        self.status_message = Some(match failures.first() {
+            None => format!("Auto-prune removed {} containers and images", removed),
+            Some(first) => format!("Auto-prune removed {}, {} failed ({})", removed, failures.len(), first),
+        });
+    }
+
+    async fn update_orphans(&mut self) -> anyhow::Result<()> {
+        self.volumes = self.backend.volumes().await?;
+        self.networks = self.backend.networks().await?;
+        self.images = self.backend.images().await?;
+        // Every container, not the filtered list: what hidden ones use is
+        // still in use.
+        let containers = self.backend.containers().await?;
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        self.orphans = orphans::find(
+            &containers,
+            &self.volumes,
+            &self.networks,
+            &self.images,
+            self.stopped_days,
+            now,
+            &self.format,
+        );
+        Ok(())
+    }
+
+    fn prompt_orphan_cleanup(&mut self) {
+        let count = self
+            .orphans
+            .iter()
+            .filter(|o| self.orphan_selection.contains(&o.kind))
+            .count();
+        if count == 0 {
+            self.status_message = Some("Nothing selected to clean up (Space selects a category)".to_string());
+            return;
+        }
+        self.input_mode = InputMode::Confirm(Confirm {
+            question: format!("Remove {} orphans?", count),
+            action: ConfirmAction::CleanupOrphans,
+        });
+    }
+
+    /// Removes the orphans of the selected categories, in [`OrphanKind::ALL`] order.
+    async fn cleanup_orphans(&mut self) {
+        let selected: Vec<Orphan> = OrphanKind::ALL
+            .into_iter()
+            .filter(|k| self.orphan_selection.contains(k))
+            .flat_map(|kind| self.orphans.iter().filter(move |o| o.kind == kind).cloned())
+            .collect();
+        let (removed, failures) = orphans::remove(self.backend.as_ref(), &selected).await;
+        self.status_message = Some(match failures.first() {
+            None => format!("Removed {} orphans", removed),
+            Some(first) => format!("Removed {} orphans, {} failed ({})", removed, failures.len(), first),
+        });
+        if let Err(e) = self.update_stats().await {
+            self.status_message = Some(format!("{:#}", e));
+        }
+        self.refresh_screen().await;
+    }
+
+    async fn update_watch(&mut self) -> anyhow::Result<()> {
+        let Some(watch) = &mut self.watch else {
+            return Ok(());
+        };
+        watch.update(&self.backend, &self.containers);
+        watch.processes = match watch.container.as_ref().filter(|c| c.is_running()) {
+            Some(container) => self.backend.processes(&container.id).await?,
+            None => Default::default(),
+        };
+        Ok(())
+    }
+
+    async fn update_timeline(&mut self) -> anyhow::Result<()> {
+        let Some(id) = self.selected().map(|c| c.id.clone()) else {
+            self.timeline = None;
+            return Ok(());
+        };
+        let since = chrono::Utc::now().timestamp() - timeline::WINDOW;
+        let events = self.backend.container_events(&id, since).await?;
+        // Inspect fails for containers removed meanwhile; events alone will do.
+        let state = self.backend.inspect_container(&id).await.ok().and_then(|i| i.state);
+        self.timeline = Some(Timeline {
+            marks: timeline::marks(&events, state.as_ref()),
+            startups: timeline::startups(&events),
+            container_id: id,
+            events,
+        });
+        Ok(())
+    }
+
+    /// Legacy links are only visible through inspect; containers that fail
+    /// to inspect (e.g. removed meanwhile) are simply left out.
+    async fn update_links(&mut self) {
+        let backend = self.backend.clone();
+        let inspections = futures::future::join_all(self.containers.iter().map(|c| {
+            let backend = backend.clone();
+            async move { (c.id.clone(), backend.inspect_container(&c.id).await) }
+        }))
+        .await;
+        self.links = inspections
+            .into_iter()
+            .filter_map(|(id, result)| Some((id, result.ok()?.host_config?.links?)))
+            .collect();
+    }
+
+    fn run_custom_command(&mut self, key: char) -> Option<Effect> {
+        let custom = self.commands.iter().find(|cmd| cmd.key == key)?;
+        let container = self.selected()?;
+        let command = commands::expand_placeholders(&custom.command, container);
+        // Commands may do anything, so they are logged when started.
+        if let Some(log) = &self.audit_log {
+            let target = format!("{}: {}", container.name, command);
+            if let Err(e) = log.record("command", &target, &Ok(())) {
+                self.status_message = Some(format!("{:#}", e));
+                return None;
+            }
+        }
+        match custom.mode {
+            CommandMode::Terminal => return Some(Effect::RunInTerminal(command)),
+            CommandMode::Capture => self.run_captured(command),
+        }
+        None
+    }
+
+    /// Opens the image prompt for the swarm service owning the selected container.
+    fn prompt_service_image(&mut self) {
+        let Some(container) = self.selected() else {
+            return;
+        };
+        let Some(service) = container.service_name().map(str::to_string) else {
+            self.status_message = Some(format!("{} is not part of a swarm service", container.name));
+            return;
+        };
+        // Swarm pins images by digest; drop it so the user edits the tag.
+        let image = container.image.split('@').next().unwrap_or_default().to_string();
+        self.input_mode = InputMode::Prompt(Prompt {
+            title: format!(" New image for service {} ", service),
+            input: image,
+            action: PromptAction::ServiceImage { service },
+        });
+    }
+
+    /// Asks before switching the selected container's service over to
+    /// its stopped sibling.
+    fn prompt_blue_green(&mut self) {
+        let Some(selected) = self.selected() else {
+            return;
+        };
+        if let Some(deployment) = self.deployment.as_ref().filter(|d| !d.finished) {
+            self.status_message = Some(format!("{} is still running", deployment.title));
+            return;
+        }
+        match deploy::find_pair(&self.containers, selected) {
+            Ok((old, new)) => {
+                self.input_mode = InputMode::Confirm(Confirm {
+                    question: format!(
+                        "Start {} ({}), then stop {} ({}) once healthy?",
+                        new.name, new.image, old.name, old.image
+                    ),
+                    action: ConfirmAction::BlueGreen(Box::new(old), Box::new(new)),
+                });
+            }
+            Err(e) => self.status_message = Some(format!("{:#}", e)),
+        }
+    }
+
+    /// Asks before restarting the running containers of the selected
+    /// project, or of the selected container's, in dependency order.
+    fn prompt_redeploy(&mut self) {
+        let Some(project) = self.selected_project().or_else(|| self.selected()?.project()) else {
+            self.status_message = Some("Select a container of a compose project to redeploy it".to_string());
+            return;
+        };
+        if let Some(deployment) = self.deployment.as_ref().filter(|d| !d.finished) {
+            self.status_message = Some(format!("{} is still running", deployment.title));
+            return;
+        }
+        let members: Vec<_> = self
+            .containers
+            .iter()
+            .filter(|c| c.is_running() && c.project() == Some(project))
+            .cloned()
+            .collect();
+        if members.is_empty() {
+            self.status_message = Some(format!("Nothing of {} is running", project));
+            return;
+        }
+        let stages = deploy::stages(&members);
+        self.input_mode = InputMode::Confirm(Confirm {
+            question: format!("Stop {} and start it again in order {}?", project, deploy::describe(&stages)),
+            action: ConfirmAction::Redeploy(project.to_string(), stages),
+        });
+    }
+
+    /// Asks before pulling the selected container's image and recreating
+    /// the container from it.
+    fn prompt_update(&mut self) {
+        let Some(container) = self.selected() else {
+            return;
+        };
+        if let Some(deployment) = self.deployment.as_ref().filter(|d| !d.finished) {
+            self.status_message = Some(format!("{} is still running", deployment.title));
+            return;
+        }
+        if !container.is_running() {
+            self.status_message = Some(format!("{} is not running", container.name));
+            return;
+        }
+        self.input_mode = InputMode::Confirm(Confirm {
+            question: format!(
+                "Pull {} and recreate {} if there is a newer image? The old container is kept until the new one is healthy",
+                container.image, container.name
+            ),
+            action: ConfirmAction::Update(Box::new(container.clone())),
+        });
+    }
+
+    fn prompt_checkpoint(&mut self) {
+        let Some(container) = self.selected() else {
+            return;
+        };
+        if !container.is_running() {
+            self.status_message = Some(format!("{} is not running", container.name));
+            return;
+        }
+        self.input_mode = InputMode::Prompt(Prompt {
+            title: format!(" Checkpoint and stop {} as ", container.name),
+            input: chrono::Local::now().format("checkpoint-%Y%m%d-%H%M%S").to_string(),
+            action: PromptAction::Checkpoint {
+                id: container.id.clone(),
+                name: container.name.clone(),
+            },
+        });
+    }
+
+    /// Offers the newest checkpoint of the selected stopped container.
+    async fn prompt_restore(&mut self) {
+        let Some(container) = self.selected() else {
+            return;
+        };
+        if container.is_running() {
+            self.status_message = Some(format!(
+                "{} is running; only stopped containers can be restored",
+                container.name
+            ));
+            return;
+        }
+        let (id, name) = (container.id.clone(), container.name.clone());
+        match self.backend.checkpoints(&id).await {
+            Ok(checkpoints) if checkpoints.is_empty() => {
+                self.status_message = Some(format!("{} has no checkpoints", name));
+            }
+            Ok(checkpoints) => {
+                self.input_mode = InputMode::Prompt(Prompt {
+                    title: format!(" Restore {} from checkpoint ({}) ", name, checkpoints.join(", ")),
+                    input: checkpoints.last().cloned().unwrap_or_default(),
+                    action: PromptAction::Restore { id, name },
+                });
+            }
+            Err(e) => self.status_message = Some(format!("{:#}", e)),
+        }
+    }
+
+    /// Starts a captured custom command; its output replaces the pane once done.
+    fn run_captured(&mut self, command: String) {
+        self.output = Some(OutputPane {
+            title: command.clone(),
+            lines: vec!["Running…".to_string()],
+            scroll: 0,
+        });
+        let tx = self.output_tx.clone();
+        tokio::spawn(async move {
+            let lines = commands::capture_output(&command).await;
+            let _ = tx.send((command, lines));
+        });
+    }
+
+    /// Runs the configured diagnostics in the selected container; the
+    /// report replaces the pane once every command is done.
+    fn run_diagnostics(&mut self) {
+        let Some(container) = self.selected().cloned() else {
+            return;
+        };
+        if !container.is_running() {
+            self.status_message = Some(format!("{} is not running", container.name));
+            return;
+        }
+        let title = format!("Diagnostics of {}", container.name);
+        self.output = Some(OutputPane {
+            title: title.clone(),
+            lines: vec![format!("Running {} commands…", self.diagnostics.commands.len())],
+            scroll: 0,
+        });
+        let (backend, tx, commands) = (self.backend.clone(), self.output_tx.clone(), self.diagnostics.commands.clone());
+        tokio::spawn(async move {
+            let lines = diagnostics::collect(backend, container, commands).await;
+            let _ = tx.send((title, lines));
+        });
+    }
+
+    /// Runs `command` in the container; its output replaces the pane once
+    /// done.
+    fn run_one_off(&mut self, container: ContainerStats, command: String) {
+        let title = format!("{} in {}", command, container.name);
+        self.output = Some(OutputPane {
+            title: title.clone(),
+            lines: vec!["Running…".to_string()],
+            scroll: 0,
+        });
+        let (backend, tx) = (self.backend.clone(), self.output_tx.clone());
+        tokio::spawn(async move {
+            let lines = match diagnostics::run(backend.as_ref(), &container.id, &command, diagnostics::ONE_OFF_TIMEOUT).await {
+                Ok(output) if output.trim().is_empty() => vec!["(no output)".to_string()],
+                Ok(output) => output.lines().map(str::to_string).collect(),
+                Err(e) => vec![format!("(failed: {:#})", e)],
+            };
+            let _ = tx.send((title, lines));
+        });
+    }
+
+    /// Shows the session recap in the output pane; reading OOM kills takes
+    /// a request per container seen, so it is filled in when done.
+    fn show_recap(&mut self) {
+        let title = "Session recap".to_string();
+        self.output = Some(OutputPane {
+            title: title.clone(),
+            lines: vec!["Reading events…".to_string()],
+            scroll: 0,
+        });
+        let (backend, tx, recap, format) = (self.backend.clone(), self.output_tx.clone(), self.recap.clone(), self.format);
+        tokio::spawn(async move {
+            let _ = tx.send((title, recap.lines(backend.as_ref(), &format).await));
+        });
+    }
+
+    /// Picks up finished captured commands for the pane that is still open.
+    fn collect_output(&mut self) {
+        while let Ok((title, lines)) = self.output_rx.try_recv() {
+            if let Some(pane) = self.output.as_mut().filter(|p| p.title == title) {
+                pane.lines = lines;
+            }
+        }
+    }
+
+    async fn submit_prompt(&mut self, prompt: Prompt) -> Option<Effect> {
+        match prompt.action {
+            PromptAction::ServiceImage { service } => {
+                let image = prompt.input.trim().to_string();
+                if image.is_empty() {
+                    return None;
+                }
+                match self.backend.update_service_image(&service, &image).await {
+                    Ok(()) => {
+                        self.status_message = Some(format!("Rolling update of {} to {} started", service, image));
+                        self.service_update = Some(ServiceUpdate {
+                            service,
+                            image,
+                            state: "updating".to_string(),
+                            message: String::new(),
+                            updated_tasks: 0,
+                            total_tasks: 0,
+                            finished: false,
+                        });
+                    }
+                    Err(e) => self.status_message = Some(format!("Service update failed: {:#}", e)),
+                }
+            }
+            PromptAction::Checkpoint { id, name } => {
+                let checkpoint = prompt.input.trim();
+                if checkpoint.is_empty() {
+                    return None;
+                }
+                self.status_message = Some(match self.backend.checkpoint_container(&id, checkpoint).await {
+                    Ok(()) => format!("Checkpointed {} as {}", name, checkpoint),
+                    Err(e) => format!("Checkpoint failed: {:#}", e),
+                });
+            }
+            PromptAction::Restore { id, name } => {
+                let checkpoint = prompt.input.trim();
+                if checkpoint.is_empty() {
+                    return None;
+                }
+                self.status_message = Some(match self.backend.restore_container(&id, checkpoint).await {
+                    Ok(()) => format!("Restored {} from {}", name, checkpoint),
+                    Err(e) => format!("Restore failed: {:#}", e),
+                });
+            }
+            PromptAction::LogFilter => {
+                let input = prompt.input.trim();
+                let filter = match FieldFilter::parse(input) {
+                    Some(filter) => Some(filter),
+                    None if input.is_empty() => None,
+                    None => {
+                        self.status_message = Some(format!("Expected field=value, e.g. level=error, not {}", input));
+                        return None;
+                    }
+                };
+                if let Some(session) = self.sessions.active_mut() {
+                    session.filter = filter;
+                    session.scroll = 0;
+                }
+            }
+            PromptAction::Filter => {
+                if prompt.input.trim().is_empty() {
+                    self.filter = None;
+                    return None;
+                }
+                match Query::parse(&prompt.input) {
+                    Ok(query) => {
+                        // Hide what no longer matches now; the rest shows up on the next refresh.
+                        self.containers.retain(|c| query.matches(c));
+                        self.selected_index = self.selected_index.min(self.containers.len().saturating_sub(1));
+                        self.filter = Some(query);
+                    }
+                    Err(e) => self.status_message = Some(format!("Invalid filter: {:#}", e)),
+                }
+            }
+            PromptAction::KillSignal { container } => {
+                let signal = prompt.input.trim();
+                if !signal.is_empty() {
+                    self.act(vec![*container], ContainerAction::Kill(signal.to_string()));
+                }
+            }
+            PromptAction::ForceRemove { container, phrase } => {
+                self.status_message = Some(if prompt.input.trim() == phrase {
+                    self.actions.run(&self.backend, &container, ContainerAction::ForceRemove)
+                } else {
+                    format!("Not removed: {:?} is not {}", prompt.input.trim(), phrase)
+                });
+            }
+            PromptAction::Guarded { containers, action, phrase } => {
+                self.status_message = Some(if prompt.input.trim() == phrase {
+                    self.run_action(&containers, action)
+                } else {
+                    format!("Not {}: {:?} is not {}", action.verbs().2, prompt.input.trim(), phrase)
+                });
+            }
+            PromptAction::CommitImage { container } => {
+                if prompt.input.trim().is_empty() {
+                    return None;
+                }
+                self.status_message = Some(match parse_reference(&prompt.input) {
+                    Ok((repository, tag)) => {
+                        self.act(vec![*container], ContainerAction::Commit { repository, tag });
+                        return None;
+                    }
+                    Err(e) => format!("Invalid image name: {:#}", e),
+                });
+            }
+            PromptAction::RunCommand { container } => {
+                let command = prompt.input.trim();
+                if !command.is_empty() {
+                    self.run_one_off(*container, command.to_string());
+                }
+            }
+            PromptAction::ExportLogs { container } => {
+                let path = prompt.input.trim();
+                if !path.is_empty() {
+                    self.input_mode = InputMode::Prompt(Prompt {
+                        title: "Since (e.g. 30m, 14:05 or 2024-05-01 14:05; empty for all)".to_string(),
+                        input: String::new(),
+                        action: PromptAction::ExportLogsSince {
+                            container,
+                            path: PathBuf::from(path),
+                        },
+                    });
+                }
+            }
+            PromptAction::ExportLogsSince { container, path } => {
+                self.status_message = Some(match parse_since(&prompt.input, chrono::Local::now()) {
+                    Ok(since) => {
+                        self.act(vec![*container], ContainerAction::ExportLogs { path, since });
+                        return None;
+                    }
+                    Err(e) => format!("Invalid start: {:#}", e),
+                });
+            }
+            PromptAction::Rename { container } => {
+                let name = prompt.input.trim();
+                if name != container.name {
+                    self.status_message = Some(match check_name(name) {
+                        Ok(()) => {
+                            self.act(vec![*container], ContainerAction::Rename(name.to_string()));
+                            return None;
+                        }
+                        Err(e) => format!("Invalid name: {:#}", e),
+                    });
+                }
+            }
+            PromptAction::SaveOutput => {
+                let (path, pane) = (prompt.input.trim(), self.output.as_ref()?);
+                if !path.is_empty() {
+                    let text = pane.lines.iter().map(|line| format!("{}\n", line)).collect::<String>();
+                    self.status_message = Some(match std::fs::write(path, text) {
+                        Ok(()) => format!("Saved to {}", path),
+                        Err(e) => format!("Could not save to {}: {}", path, e),
+                    });
+                }
+            }
+            PromptAction::TakeSnapshot => {
+                let name = prompt.input.trim().to_string();
+                if name.is_empty() {
+                    return None;
+                }
+                let containers = self.unfiltered.clone();
+                self.status_message = Some(format!("Snapshot \"{}\" taken of {} containers", name, containers.len()));
+                self.snapshots.retain(|s| s.name != name);
+                self.snapshots.push(StatsSnapshot::take(name, containers));
+            }
+            PromptAction::DiffSnapshot => {
+                let name = prompt.input.trim();
+                let Some(snapshot) = self.snapshots.iter().find(|s| s.name == name) else {
+                    self.status_message = Some(format!("No snapshot named \"{}\"", name));
+                    return None;
+                };
+                self.output = Some(OutputPane {
+                    title: format!("Changes since {}", snapshot.name),
+                    lines: snapshot.diff(&self.unfiltered, &self.format),
+                    scroll: 0,
+                });
+            }
+            PromptAction::ExportScreen => {
+                let path = prompt.input.trim();
+                if !path.is_empty() {
+                    return Some(Effect::ExportScreen(PathBuf::from(path)));
+                }
+            }
+            PromptAction::SearchLogView => {
+                let Some(view) = &mut self.log_view else {
+                    return None;
+                };
+                if prompt.input.is_empty() {
+                    view.search(None);
+                    return None;
+                }
+                match Regex::new(&prompt.input) {
+                    Ok(pattern) => {
+                        if !view.search(Some(pattern)) {
+                            self.status_message = Some(format!("No lines match {}", prompt.input));
+                        }
+                    }
+                    Err(e) => self.status_message = Some(format!("Invalid pattern: {}", e)),
+                }
+            }
+            PromptAction::LogViewTail => match prompt.input.trim().parse::<usize>() {
+                Ok(tail) if tail > 0 => {
+                    self.input_mode = InputMode::Prompt(Prompt {
+                        title: "How far back (e.g. 30m, 2h or 1d; empty for any age)".to_string(),
+                        input: self.log_view.as_ref().map(|v| format_age(v.age)).unwrap_or_default(),
+                        action: PromptAction::LogViewAge { tail },
+                    });
+                }
+                _ => self.status_message = Some(format!("Invalid number of lines: {:?}", prompt.input.trim())),
+            },
+            PromptAction::LogViewAge { tail } => {
+                let input = prompt.input.trim();
+                let age = if input.is_empty() { Ok(0) } else { parse_age(input) };
+                match age {
+                    Ok(age) => {
+                        (self.log_tail, self.log_age) = (tail, age);
+                        if let Some(view) = &mut self.log_view {
+                            (view.tail, view.age) = (tail, age);
+                            view.reload(self.backend.clone());
+                        }
+                    }
+                    Err(e) => self.status_message = Some(format!("Invalid age: {:#}", e)),
+                }
+            }
+            PromptAction::RegistryServer => {
+                let server = prompt.input.trim();
+                if !server.is_empty() {
+                    // Prefilled with the login stored for it, if any.
+                    let host = registries::registry_host(server);
+                    let username =
+                        registries::pull_login(&host, &self.registries, &self.chosen_logins()).map(|l| l.username.clone());
+                    self.input_mode = InputMode::Prompt(Prompt {
+                        title: format!("Username for {}", server),
+                        input: username.unwrap_or_default(),
+                        action: PromptAction::RegistryUsername { server: server.to_string() },
+                    });
+                }
+            }
+            PromptAction::RegistryUsername { server } => {
+                let username = prompt.input.trim();
+                if !username.is_empty() {
+                    self.input_mode = InputMode::Prompt(Prompt {
+                        title: format!("Password or token of {} for {}", username, server),
+                        input: String::new(),
+                        action: PromptAction::RegistryPassword {
+                            server,
+                            username: username.to_string(),
+                        },
+                    });
+                }
+            }
+            PromptAction::RegistryPassword { server, username } => {
+                self.status_message = Some(match self.backend.registry_login(&server, &username, &prompt.input).await {
+                    Ok(()) => format!("Logged in to {} as {}", server, username),
+                    Err(e) => format!("Could not log in to {}: {:#}", server, e),
+                });
+                self.update_registries().await;
+            }
+            PromptAction::SearchLogs => {
+                if prompt.input.is_empty() {
+                    return None;
+                }
+                match Regex::new(&prompt.input) {
+                    Ok(pattern) => {
+                        self.log_search = Some(LogSearch::start(self.backend.clone(), &self.existing(), pattern));
+                    }
+                    Err(e) => self.status_message = Some(format!("Invalid pattern: {}", e)),
+                }
+            }
+        }
+        None
+    }
+
+    /// Refreshes the progress of the rolling update, if one is being tracked.
+    async fn update_service_progress(&mut self) -> anyhow::Result<()> {
+        let Some(update) = self.service_update.as_mut().filter(|u| !u.finished) else {
+            return Ok(());
+        };
+
+        if let Some(status) = self.backend.service_update_status(&update.service).await? {
+            if let Some(state) = status.state {
+                update.finished = matches!(
+                    state,
+                    ServiceUpdateStatusStateEnum::COMPLETED
+                        | ServiceUpdateStatusStateEnum::PAUSED
+                        | ServiceUpdateStatusStateEnum::ROLLBACK_COMPLETED
+                        | ServiceUpdateStatusStateEnum::ROLLBACK_PAUSED
+                );
+                update.state = state.to_string();
+            }
+            update.message = status.message.unwrap_or_default();
+        }
+
+        // Task progress is taken from the task containers on this node.
+        let tasks: Vec<&ContainerStats> = self
+            .containers
+            .iter()
+            .filter(|c| c.service_name() == Some(update.service.as_str()) && c.status.starts_with("Up"))
+            .collect();
+        update.total_tasks = tasks.len();
+        update.updated_tasks = tasks
+            .iter()
+            .filter(|c| c.image.split('@').next() == Some(update.image.as_str()))
+            .count();
+
+        Ok(())
+    }
+
+    /// The listed containers minus removed ones still fading out.
+    /// Registry host to the server of the login pulls from it use, if
+    /// one was chosen.
+    pub fn chosen_logins(&self) -> BTreeMap<String, String> {
+        self.backend.pull_logins().map(PullLogins::chosen).unwrap_or_default()
+    }
+
+    fn existing(&self) -> Vec<ContainerStats> {
+        self.containers
+            .iter()
+            .filter(|c| !self.fading.get(&c.id).is_some_and(|f| f.removed))
+            .cloned()
+            .collect()
+    }
+
+    /// Keeps containers that just exited showing their last running stats,
+    /// and removed ones listed at their old row, until the fade-out period
+    /// is over.
+    fn fade_out(&mut self, mut fresh: Vec<ContainerStats>) -> Vec<ContainerStats> {
+        if self.fade_out.is_zero() {
+            self.fading.clear();
+            return fresh;
+        }
+        let now = Instant::now();
+        for container in &mut fresh {
+            if container.is_running() {
+                self.fading.remove(&container.id);
+                continue;
+            }
+            let previous = self.containers.iter().find(|p| p.id == container.id);
+            if let Some(previous) = previous.filter(|p| p.is_running()) {
+                let fading = Fading {
+                    since: now,
+                    last: previous.clone(),
+                    removed: false,
+                };
+                self.fading.insert(container.id.clone(), fading);
+            }
+            if let Some(fading) = self.fading.get(&container.id) {
+                container.cpu_usage = fading.last.cpu_usage;
+                container.memory_usage = fading.last.memory_usage;
+            }
+        }
+
+        for (i, previous) in self.containers.iter().enumerate() {
+            if fresh.iter().any(|c| c.id == previous.id) {
+                continue;
+            }
+            let fading = self.fading.entry(previous.id.clone()).or_insert_with(|| Fading {
+                since: now,
+                last: previous.clone(),
+                removed: false,
+            });
+            if !fading.removed {
+                fading.removed = true;
+                fading.since = now;
+            }
+            if now.duration_since(fading.since) < self.fade_out {
+                fresh.insert(i.min(fresh.len()), previous.clone());
+            }
+        }
+        let fade_out = self.fade_out;
+        self.fading
+            .retain(|id, f| now.duration_since(f.since) < fade_out && fresh.iter().any(|c| c.id == *id));
+        fresh
+    }
+
+    /// Both lists are ordered alike so a side-by-side comparison lines up.
+    fn order_containers(&mut self) {
+        order(self.grouping, self.sort, &mut self.containers);
+        if let Some(compare) = &mut self.compare {
+            order(self.grouping, self.sort, &mut compare.containers);
+        }
+    }
+
+    async fn update_stats(&mut self) -> anyhow::Result<()> {
+        let mut containers = self.backend.containers().await?;
+        self.unfiltered = containers.clone();
+        self.marked.retain(|id| containers.iter().any(|c| &c.id == id));
+        self.io.update(&containers);
+        self.cores.update(&containers);
+        self.cpu_history.update(&containers);
+        self.usage.sample(&containers, &self.io);
+        let restarted = self.trends.update(&containers);
+        self.recap.sample(&containers, &restarted);
+        self.idle.update(&containers, &self.io);
+        let mut warnings = self.budgets.update(&containers, &self.format);
+        warnings.extend(self.bandwidth.update(self.backend.as_ref(), &containers, &self.io, &self.format).await);
+        for warning in &warnings {
+            self.recap.alert(warning);
+        }
+        if !warnings.is_empty() {
+            self.status_message = Some(warnings.join("; "));
+        }
+        containers = self.fade_out(containers);
+        // After fading, so containers whose stats stop matching are not
+        // mistaken for removed ones.
+        if let Some(filter) = &self.filter {
+            containers.retain(|c| filter.matches(c));
+        }
+        if self.stable_order {
+            containers = keep_order(&self.containers, containers);
+        }
+        // A sort can reorder the rows, so the selection follows the
+        // container rather than the row.
+        let selected = self.selected().map(|c| c.id.clone());
+        self.containers = containers;
+        order(self.grouping, self.sort, &mut self.containers);
+        match selected.and_then(|id| self.containers.iter().position(|c| c.id == id)) {
+            Some(i) => self.selected_index = i,
+            None => self.selected_index = self.selected_index.min(self.containers.len().saturating_sub(1)),
+        }
+        Ok(())
+    }
+
+    /// Refreshes the second host's list with the main list's filter and
+    /// order.
+    async fn update_compare(&mut self) {
+        let Some(compare) = &mut self.compare else {
+            return;
+        };
+        let Some(mut containers) = compare.fetch().await else {
+            return;
+        };
+        if let Some(filter) = &self.filter {
+            containers.retain(|c| filter.matches(c));
+        }
+        if self.stable_order {
+            containers = keep_order(&compare.containers, containers);
+        }
+        order(self.grouping, self.sort, &mut containers);
+        compare.containers = containers;
+        compare.selected_index = compare.selected_index.min(compare.containers.len().saturating_sub(1));
+    }
+}
+
+/// Lays `fresh` out in the order of `previous`: known containers keep their
+/// row and new ones are appended.
+fn keep_order(previous: &[ContainerStats], mut fresh: Vec<ContainerStats>) -> Vec<ContainerStats> {
+    let mut ordered = Vec::with_capacity(fresh.len());
+    for previous in previous {
+        if let Some(i) = fresh.iter().position(|c| c.id == previous.id) {
+            ordered.push(fresh.remove(i));
+        }
+    }
+    ordered.extend(fresh);
+    ordered
+}
+
+/// Sorting is stable, so within a section the sort order, or else the
+/// backend's order, is kept.
+fn order(grouping: Grouping, sort: SortKey, containers: &mut [ContainerStats]) {
+    match sort {
+        SortKey::Off => {}
+        SortKey::Cpu => containers.sort_by(|a, b| b.cpu_usage.total_cmp(&a.cpu_usage)),
+        SortKey::Memory => containers.sort_by_key(|c| std::cmp::Reverse(c.memory_usage)),
+        SortKey::BlockIo => containers.sort_by_key(|c| std::cmp::Reverse(c.io.block_read + c.io.block_write)),
+    }
+    match grouping {
+        Grouping::Off => {}
+        Grouping::Status => containers.sort_by_key(StatusGroup::of),
+        // Containers outside any project go last.
+        Grouping::Project => containers.sort_by(|a, b| (a.project().is_none(), a.project()).cmp(&(b.project().is_none(), b.project()))),
+        // Containers outside any pod go last.
+        Grouping::Pod => containers.sort_by(|a, b| {
+            let (a, b) = (pods::pod_of(a), pods::pod_of(b));
+            (a.is_none(), a).cmp(&(b.is_none(), b))
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::mock::{container, MockBackend, Snapshot};
+    use bollard::models::{ImageSummary, Network, ServiceUpdateStatus};
+    use crossterm::event::KeyModifiers;
+
+    fn app_with(backend: MockBackend) -> (App, Arc<MockBackend>) {
+        let backend = Arc::new(backend);
+        (App::new(Config::default(), backend.clone()), backend)
+    }
+
+    async fn press(app: &mut App, code: KeyCode) -> Option<Effect> {
+        app.handle_key(KeyEvent::new(code, KeyModifiers::NONE)).await
+    }
+
+    async fn type_text(app: &mut App, text: &str) {
+        for c in text.chars() {
+            press(app, KeyCode::Char(c)).await;
+        }
+    }
+
+    fn service_task(name: &str, service: &str, image: &str) -> ContainerStats {
+        let mut c = container(name);
+        c.image = image.to_string();
+        c.labels
+            .insert("com.docker.swarm.service.name".to_string(), service.to_string());
+        c
+    }
+
+    #[tokio::test]
+    async fn navigation_stays_within_the_list() {
+        let (mut app, _) = app_with(MockBackend::new([Snapshot::Containers(vec![
+            container("a"),
+            container("b"),
+        ])]));
+        app.tick().await;
+
+        press(&mut app, KeyCode::Up).await;
+        assert_eq!(app.selected_index, 0);
+        press(&mut app, KeyCode::Down).await;
+        press(&mut app, KeyCode::Down).await;
+        assert_eq!(app.selected().unwrap().name, "b");
+    }
+
+    #[tokio::test]
+    async fn selection_is_clamped_when_containers_disappear() {
+        let (mut app, _) = app_with(MockBackend::new([
+            Snapshot::Containers(vec![container("a"), container("b"), container("c")]),
+            Snapshot::Containers(vec![container("a")]),
+            Snapshot::Containers(vec![]),
+        ]));
+        // Removed containers would otherwise linger for the fade-out period.
+        app.fade_out = Duration::ZERO;
+        app.tick().await;
+        app.selected_index = 2;
+
+        app.tick().await;
+        assert_eq!(app.selected().unwrap().name, "a");
+        app.tick().await;
+        assert!(app.selected().is_none());
+        assert_eq!(app.selected_index, 0);
+    }
+
+    #[tokio::test]
+    async fn api_errors_keep_the_last_containers_and_show_a_message() {
+        let (mut app, _) = app_with(MockBackend::new([
+            Snapshot::Containers(vec![container("a")]),
+            Snapshot::Error("daemon went away"),
+            Snapshot::Containers(vec![container("a"), container("b")]),
+        ]));
+        app.tick().await;
+        app.tick().await;
+        assert_eq!(app.containers.len(), 1);
+        assert_eq!(app.status_message.as_deref(), Some("daemon went away"));
+
+        app.tick().await;
+        assert_eq!(app.containers.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn filter_hides_non_matching_containers() {
+        let (mut app, _) = app_with(MockBackend::new([Snapshot::Containers(vec![
+            container("shop-web"),
+            container("metrics"),
+        ])]));
+        app.filter = Query::parse("shop").ok();
+        app.tick().await;
+        assert_eq!(app.containers.len(), 1);
+        assert_eq!(app.containers[0].name, "shop-web");
+    }
+
+    #[tokio::test]
+    async fn filter_expressions_match_live_stats_and_labels() {
+        let mut busy_prod = container("api");
+        busy_prod.cpu_usage = 80.0;
+        busy_prod.labels.insert("env".to_string(), "prod".to_string());
+        let mut busy_dev = busy_prod.clone();
+        busy_dev.name = "api-dev".to_string();
+        busy_dev.id = "api-dev-id".to_string();
+        busy_dev.labels.insert("env".to_string(), "dev".to_string());
+        let mut stopped = busy_prod.clone();
+        stopped.name = "api-old".to_string();
+        stopped.id = "api-old-id".to_string();
+        stopped.status = "Exited (0) 1 hour ago".to_string();
+        let idle = container("idle");
+        let (mut app, _) = app_with(MockBackend::new([Snapshot::Containers(vec![
+            busy_prod, busy_dev, stopped, idle,
+        ])]));
+
+        press(&mut app, KeyCode::Char('/')).await;
+        for c in r#"cpu > 50 && status == running && label("env") == "prod""#.chars() {
+            press(&mut app, KeyCode::Char(c)).await;
+        }
+        press(&mut app, KeyCode::Enter).await;
+        app.tick().await;
+        let names: Vec<&str> = app.containers.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["api"]);
+
+        assert!(Query::parse("cpu >").is_err());
+        assert!(Query::parse("(idle || api").is_err());
+        assert!(Query::parse("!(idle || api)").unwrap().matches(&container("metrics")));
+    }
+
+    #[test]
+    fn ip_filters_match_either_address_family_and_subnets() {
+        use crate::backend::NetworkAttachment;
+        let mut web = container("web");
+        web.networks.push(NetworkAttachment {
+            network: "front".to_string(),
+            ip_address: "172.20.0.3".to_string(),
+            ipv6_address: "fd00:20::3".to_string(),
+        });
+        let matches = |query: &str| Query::parse(query).unwrap().matches(&web);
+        assert!(matches("ip == 172.20.0.3"));
+        assert!(matches("ip == fd00:20:0:0::3"));
+        assert!(matches("ip ~ 172.20.0.0/16 && ip ~ fd00::/8"));
+        assert!(!matches("ip ~ 172.21.0.0/16"));
+        assert!(!matches("ip != fd00:20::3"));
+        assert!(!Query::parse("ip ~ fd00::/8").unwrap().matches(&container("isolated")));
+    }
+
+    #[tokio::test]
+    async fn service_image_update_goes_through_the_prompt() {
+        let (mut app, backend) = app_with(MockBackend::new([
+            Snapshot::Containers(vec![service_task("web.1", "web", "nginx:1.24@sha256:abc")]),
+            Snapshot::Containers(vec![service_task("web.1", "web", "nginx:1.25@sha256:def")]),
+        ]));
+        app.tick().await;
+
+        press(&mut app, KeyCode::Char('U')).await;
+        match &app.input_mode {
+            InputMode::Prompt(prompt) => assert_eq!(prompt.input, "nginx:1.24"),
+            _ => panic!("expected the image prompt"),
+        }
+        for _ in 0..4 {
+            press(&mut app, KeyCode::Backspace).await;
+        }
+        type_text(&mut app, "1.25").await;
+        press(&mut app, KeyCode::Enter).await;
+
+        assert!(matches!(app.input_mode, InputMode::Normal));
+        assert_eq!(
+            *backend.service_updates.lock().unwrap(),
+            vec![("web".to_string(), "nginx:1.25".to_string())]
+        );
+
+        *backend.service_status.lock().unwrap() = Some(ServiceUpdateStatus {
+            state: Some(ServiceUpdateStatusStateEnum::COMPLETED),
+            ..Default::default()
+        });
+        app.tick().await;
+        let update = app.service_update.as_ref().unwrap();
+        assert!(update.finished);
+        assert_eq!((update.updated_tasks, update.total_tasks), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn service_image_prompt_requires_a_swarm_task() {
+        let (mut app, _) = app_with(MockBackend::new([Snapshot::Containers(vec![container("a")])]));
+        app.tick().await;
+
+        press(&mut app, KeyCode::Char('U')).await;
+        assert!(matches!(app.input_mode, InputMode::Normal));
+        assert!(app.status_message.is_some());
+    }
+
+    #[tokio::test]
+    async fn escape_cancels_the_prompt() {
+        let (mut app, backend) = app_with(MockBackend::new([Snapshot::Containers(vec![service_task(
+            "web.1", "web", "nginx",
+        )])]));
+        app.tick().await;
+
+        press(&mut app, KeyCode::Char('U')).await;
+        press(&mut app, KeyCode::Esc).await;
+        assert!(matches!(app.input_mode, InputMode::Normal));
+        assert!(backend.service_updates.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn stable_order_keeps_rows_and_lingers_removed_containers() {
+        let (mut app, _) = app_with(MockBackend::new([
+            Snapshot::Containers(vec![container("a"), container("b")]),
+            Snapshot::Containers(vec![container("c"), container("b")]),
+        ]));
+        press(&mut app, KeyCode::Char('o')).await;
+        app.tick().await;
+        app.tick().await;
+
+        let names: Vec<&str> = app.containers.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["a", "b", "c"]);
+        assert!(app.fading["a-id"].removed);
+    }
+
+    #[tokio::test]
+    async fn greater_than_sorts_by_cpu_memory_and_block_io_keeping_the_selection() {
+        let mut reader = container("reader");
+        reader.io.block_read = 5 << 30;
+        let mut busy = container("busy");
+        busy.cpu_usage = 90.0;
+        let mut big = container("big");
+        big.memory_usage = 1 << 30;
+        big.io.block_write = 1 << 20;
+        let (mut app, _) = app_with(MockBackend::new([Snapshot::Containers(vec![reader, busy, big])]));
+        app.tick().await;
+        let names = |app: &App| app.containers.iter().map(|c| c.name.clone()).collect::<Vec<_>>();
+
+        press(&mut app, KeyCode::Char('>')).await;
+        assert_eq!(names(&app), ["busy", "reader", "big"]);
+        assert_eq!(app.selected().unwrap().name, "reader");
+        press(&mut app, KeyCode::Char('>')).await;
+        assert_eq!(names(&app), ["big", "busy", "reader"]);
+        press(&mut app, KeyCode::Char('>')).await;
+        assert_eq!(names(&app), ["reader", "big", "busy"]);
+        // The next refresh keeps the order.
+        app.tick().await;
+        assert_eq!(names(&app), ["reader", "big", "busy"]);
+        press(&mut app, KeyCode::Char('>')).await;
+        assert_eq!(app.sort, SortKey::Off);
+    }
+
+    #[tokio::test]
+    async fn orphans_are_found_among_all_containers_not_just_the_filtered_ones() {
+        let mut db = container("db");
+        db.status = "Exited (0) 1 hour ago".to_string();
+        db.networks.push(crate::backend::NetworkAttachment {
+            network: "backend".to_string(),
+            ip_address: String::new(),
+            ipv6_address: String::new(),
+        });
+        let backend = MockBackend::new([Snapshot::Containers(vec![container("api"), db])]);
+        *backend.networks.lock().unwrap() = vec![Network {
+            name: Some("backend".to_string()),
+            id: Some("backend-net".to_string()),
+            ..Default::default()
+        }];
+        let (mut app, _) = app_with(backend);
+        app.filter = Query::parse("api").ok();
+        app.tick().await;
+        assert_eq!(app.containers.len(), 1);
+
+        press(&mut app, KeyCode::Char('6')).await;
+
+        assert!(app.orphans.iter().all(|o| o.kind != OrphanKind::UnusedNetwork));
+    }
+
+    #[tokio::test]
+    async fn x_on_the_orphans_screen_removes_the_selected_categories_once_confirmed() {
+        let backend = MockBackend::new([Snapshot::Containers(vec![container("api")])]);
+        *backend.networks.lock().unwrap() = vec![Network {
+            name: Some("backend".to_string()),
+            id: Some("backend-net".to_string()),
+            ..Default::default()
+        }];
+        let (mut app, backend) = app_with(backend);
+        app.tick().await;
+
+        press(&mut app, KeyCode::Char('6')).await;
+        press(&mut app, KeyCode::Down).await;
+        press(&mut app, KeyCode::Down).await;
+        press(&mut app, KeyCode::Char(' ')).await;
+        press(&mut app, KeyCode::Char('x')).await;
+        assert!(matches!(&app.input_mode, InputMode::Confirm(c) if c.question == "Remove 1 orphans?"));
+        press(&mut app, KeyCode::Char('y')).await;
+
+        assert_eq!(*backend.removed.lock().unwrap(), ["backend-net"]);
+        assert_eq!(app.status_message.as_deref(), Some("Removed 1 orphans"));
+    }
+
+    #[tokio::test]
+    async fn auto_prune_needs_the_audit_log() {
+        let mut old = container("old");
+        old.status = "Exited (0) 30 days ago".to_string();
+        old.created = "0".to_string();
+        let (mut app, backend) = app_with(MockBackend::new([Snapshot::Containers(vec![old])]));
+        app.auto_prune.enabled = true;
+
+        app.tick().await;
+
+        assert!(backend.removed.lock().unwrap().is_empty());
+        assert!(app.status_message.as_deref().unwrap_or_default().contains("needs the audit log"));
+    }
+
+    #[tokio::test]
+    async fn auto_prune_ignores_the_filter_and_audits_each_removal_with_its_result() {
+        let mut old = container("old");
+        old.status = "Exited (0) 30 days ago".to_string();
+        old.created = "0".to_string();
+        let mut worker = container("worker");
+        worker.image_id = "sha256:worker".to_string();
+        let untagged = |id: &str| ImageSummary {
+            id: id.to_string(),
+            repo_tags: vec!["<none>:<none>".to_string()],
+            ..Default::default()
+        };
+        let backend = MockBackend::new([Snapshot::Containers(vec![container("web"), old, worker])]);
+        *backend.images.lock().unwrap() = vec![untagged("sha256:worker"), untagged("sha256:gone")];
+        *backend.failing_removals.lock().unwrap() = vec!["sha256:gone".to_string()];
+        let (mut app, backend) = app_with(backend);
+        let path = std::env::temp_dir().join(format!("dockertop-prune-{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        app.audit_log = Some(Arc::new(AuditLog::open(&path).unwrap()));
+        app.auto_prune.enabled = true;
+        app.filter = Query::parse("web").ok();
+
+        app.tick().await;
+
+        // The worker is hidden by the filter, but its image is in use.
+        assert_eq!(*backend.removed.lock().unwrap(), ["old-id", "sha256:gone"]);
+        let log = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let entries: Vec<serde_json::Value> = log.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(entries.len(), 2);
+        assert!(entries.iter().all(|e| e["action"] == "auto_prune"));
+        assert_eq!(entries[0]["result"], "ok");
+        assert_eq!(entries[1]["result"], "sha256:gone is in use");
+        assert!(app.status_message.as_deref().unwrap_or_default().contains("1 failed"));
+    }
+
+    #[tokio::test]
+    async fn sorted_refreshes_keep_the_selected_container_as_rows_move() {
+        let with_cpu = |name: &str, cpu_usage: f64| ContainerStats { cpu_usage, ..container(name) };
+        let (mut app, _) = app_with(MockBackend::new([
+            Snapshot::Containers(vec![with_cpu("api", 50.0), with_cpu("db", 10.0)]),
+            Snapshot::Containers(vec![with_cpu("api", 5.0), with_cpu("db", 80.0)]),
+        ]));
+        app.tick().await;
+        press(&mut app, KeyCode::Char('>')).await;
+        assert_eq!(app.selected().unwrap().id, "api-id");
+
+        app.tick().await;
+
+        assert_eq!(app.containers[0].id, "db-id");
+        assert_eq!(app.selected().unwrap().id, "api-id");
+    }
+
+    #[tokio::test]
+    async fn per_core_usage_comes_from_percpu_counters_between_refreshes() {
+        let mut pinned = container("worker");
+        pinned.cpu_cores = vec![0, 0];
+        let mut busier = pinned.clone();
+        busier.cpu_cores = vec![5_000_000_000, 0];
+        let (mut app, _) = app_with(MockBackend::new([
+            Snapshot::Containers(vec![pinned, container("v2")]),
+            Snapshot::Containers(vec![busier, container("v2")]),
+        ]));
+        app.tick().await;
+        assert!(app.cores.usage("worker-id").is_empty());
+        app.tick().await;
+
+        assert_eq!(app.cores.usage("worker-id"), [100.0, 0.0]);
+        // Not reported on cgroup v2.
+        assert!(app.cores.usage("v2-id").is_empty());
+    }
+
+    #[tokio::test]
+    async fn cpu_history_keeps_recent_samples_of_listed_containers() {
+        let with_cpu = |name: &str, cpu_usage: f64| ContainerStats { cpu_usage, ..container(name) };
+        let (mut app, _) = app_with(MockBackend::new([
+            Snapshot::Containers(vec![with_cpu("api", 10.0), with_cpu("db", 5.0)]),
+            Snapshot::Containers(vec![with_cpu("api", 90.0), with_cpu("db", 6.0)]),
+            Snapshot::Containers(vec![with_cpu("api", 20.0)]),
+        ]));
+        for _ in 0..3 {
+            app.tick().await;
+        }
+
+        assert_eq!(app.cpu_history.of("api-id"), [(1.0, 10.0), (2.0, 90.0), (3.0, 20.0)]);
+        // Removed containers are forgotten.
+        assert!(app.cpu_history.of("db-id").is_empty());
+    }
+
+    #[tokio::test]
+    async fn shift_h_recaps_the_session_with_peaks_restarts_and_alerts() {
+        let mut busy = container("api");
+        busy.cpu_usage = 80.0;
+        let mut crashed = container("api");
+        crashed.status = "Exited (137) 1 second ago".to_string();
+        let (mut app, _) = app_with(MockBackend::new([
+            Snapshot::Containers(vec![busy]),
+            Snapshot::Containers(vec![crashed]),
+            Snapshot::Containers(vec![container("api")]),
+        ]));
+        for _ in 0..3 {
+            app.tick().await;
+        }
+        app.recap.alert("api is over budget");
+
+        press(&mut app, KeyCode::Char('H')).await;
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        app.collect();
+        let pane = app.output.as_ref().unwrap();
+        assert_eq!(pane.title, "Session recap");
+        assert!(pane.lines[1].starts_with("Peak CPU: 80"), "{:?}", pane.lines);
+        assert_eq!(pane.lines[3], "Restarts: api ×1");
+        assert_eq!(pane.lines[4], "OOM kills: none");
+        assert_eq!(pane.lines[5], "Alerts (1):");
+        assert!(pane.lines[6].ends_with("api is over budget"));
+    }
+
+    #[tokio::test]
+    async fn exited_containers_keep_their_last_stats_while_fading() {
+        let mut running = container("a");
+        running.cpu_usage = 42.0;
+        let mut exited = container("a");
+        exited.status = "Exited (137) 1 second ago".to_string();
+        let (mut app, _) = app_with(MockBackend::new([
+            Snapshot::Containers(vec![running]),
+            Snapshot::Containers(vec![exited]),
+        ]));
+        app.tick().await;
+        app.tick().await;
+
+        assert_eq!(app.containers[0].cpu_usage, 42.0);
+        assert!(!app.fading["a-id"].removed);
+    }
+
+    #[tokio::test]
+    async fn s_stops_running_and_starts_stopped_containers() {
+        let mut stopped = container("b");
+        stopped.status = "Exited (0) 1 minute ago".to_string();
+        let (mut app, backend) = app_with(MockBackend::new([Snapshot::Containers(vec![container("a"), stopped])]));
+        app.tick().await;
+
+        press(&mut app, KeyCode::Char('s')).await;
+        assert_eq!(app.status_message.as_deref(), Some("Stopping a…"));
+        press(&mut app, KeyCode::Down).await;
+        press(&mut app, KeyCode::Char('s')).await;
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        app.collect();
+
+        let lifecycle = backend.lifecycle.lock().unwrap().clone();
+        assert_eq!(lifecycle, [("stop", "a-id".to_string()), ("start", "b-id".to_string())]);
+        assert_eq!(app.status_message.as_deref(), Some("Started b"));
+    }
+
+    #[tokio::test]
+    async fn k_kills_with_the_picked_or_a_custom_signal() {
+        let (mut app, backend) = app_with(MockBackend::new([Snapshot::Containers(vec![container("a")])]));
+        app.tick().await;
+
+        press(&mut app, KeyCode::Char('K')).await;
+        press(&mut app, KeyCode::Down).await;
+        press(&mut app, KeyCode::Enter).await;
+        assert!(app.kill_picker.is_none());
+        assert_eq!(app.status_message.as_deref(), Some("Killing a with SIGKILL…"));
+
+        press(&mut app, KeyCode::Char('K')).await;
+        for _ in 0..=SIGNALS.len() {
+            press(&mut app, KeyCode::Down).await;
+        }
+        press(&mut app, KeyCode::Enter).await;
+        for c in "USR1".chars() {
+            press(&mut app, KeyCode::Char(c)).await;
+        }
+        press(&mut app, KeyCode::Enter).await;
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        app.collect();
+
+        let lifecycle = backend.lifecycle.lock().unwrap().clone();
+        assert_eq!(lifecycle, [("kill", "a-id".to_string()), ("kill", "a-id".to_string())]);
+        assert_eq!(app.status_message.as_deref(), Some("Killed a with USR1"));
+    }
+
+    #[tokio::test]
+    async fn c_renames_only_to_valid_names() {
+        let (mut app, backend) = app_with(MockBackend::new([Snapshot::Containers(vec![container("a")])]));
+        app.tick().await;
+
+        for input in ["a b", "a-2"] {
+            press(&mut app, KeyCode::Char('c')).await;
+            press(&mut app, KeyCode::Backspace).await;
+            for c in input.chars() {
+                press(&mut app, KeyCode::Char(c)).await;
+            }
+            press(&mut app, KeyCode::Enter).await;
+            if input == "a b" {
+                assert!(app.status_message.as_deref().unwrap().starts_with("Invalid name: ' ' is not allowed"));
+            }
+        }
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        app.collect();
+        assert_eq!(*backend.lifecycle.lock().unwrap(), [("rename", "a-id".to_string())]);
+        assert_eq!(app.status_message.as_deref(), Some("Renamed a to a-2"));
+    }
+
+    #[tokio::test]
+    async fn i_commits_to_a_valid_image_name() {
+        let (mut app, backend) = app_with(MockBackend::new([Snapshot::Containers(vec![container("a")])]));
+        app.tick().await;
+
+        press(&mut app, KeyCode::Char('i')).await;
+        press(&mut app, KeyCode::Char('!')).await;
+        press(&mut app, KeyCode::Enter).await;
+        assert!(app.status_message.as_deref().unwrap().starts_with("Invalid image name:"));
+
+        press(&mut app, KeyCode::Char('i')).await;
+        press(&mut app, KeyCode::Enter).await;
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        app.collect();
+        assert_eq!(*backend.lifecycle.lock().unwrap(), [("commit", "a-id".to_string())]);
+        assert!(app.status_message.as_deref().unwrap().starts_with("Committed a to a:snapshot-"));
+    }
+
+    #[tokio::test]
+    async fn u_sends_only_the_changed_limits() {
+        let (mut app, backend) = app_with(MockBackend::new([Snapshot::Containers(vec![container("a")])]));
+        app.tick().await;
+
+        press(&mut app, KeyCode::Char('u')).await;
+        press(&mut app, KeyCode::Up).await;
+        for c in "12x".chars() {
+            press(&mut app, KeyCode::Char(c)).await;
+        }
+        press(&mut app, KeyCode::Enter).await;
+        let form = app.limits_form.as_ref().unwrap();
+        assert!(form.error.as_deref().unwrap().starts_with("Memory: unknown unit"));
+
+        press(&mut app, KeyCode::Backspace).await;
+        press(&mut app, KeyCode::Char('m')).await;
+        press(&mut app, KeyCode::Enter).await;
+        assert!(app.limits_form.is_none());
+        assert_eq!(app.status_message.as_deref(), Some("Updating a with --memory 12m…"));
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        app.collect();
+        assert_eq!(*backend.lifecycle.lock().unwrap(), [("update", "a-id".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn marks_follow_containers_and_batch_actions_use_them() {
+        let (mut app, backend) = app_with(MockBackend::new([
+            Snapshot::Containers(vec![container("a"), container("b"), container("c")]),
+            Snapshot::Containers(vec![container("c"), container("b"), container("a")]),
+            Snapshot::Containers(vec![container("b")]),
+        ]));
+        app.tick().await;
+
+        press(&mut app, KeyCode::Char(' ')).await;
+        press(&mut app, KeyCode::Down).await;
+        press(&mut app, KeyCode::Char(' ')).await;
+        app.tick().await;
+        assert_eq!(app.marked_containers().iter().map(|c| c.name.as_str()).collect::<Vec<_>>(), ["c", "a"]);
+
+        press(&mut app, KeyCode::Char('r')).await;
+        assert_eq!(app.status_message.as_deref(), Some("Restarting c, a…"));
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        app.collect();
+        let lifecycle = backend.lifecycle.lock().unwrap().clone();
+        assert_eq!(lifecycle, [("restart", "c-id".to_string()), ("restart", "a-id".to_string())]);
+        assert_eq!(app.status_message.as_deref(), Some("Restarted c, a"));
+
+        app.tick().await;
+        assert!(app.marked.is_empty());
+    }
+
+    #[tokio::test]
+    async fn a_selected_project_header_restarts_the_whole_project() {
+        let member = |name: &str| {
+            let mut c = container(name);
+            c.labels.insert("com.docker.compose.project".to_string(), "shop".to_string());
+            c
+        };
+        let (mut app, backend) = app_with(MockBackend::new([Snapshot::Containers(vec![
+            container("other"),
+            member("web"),
+            member("db"),
+        ])]));
+        app.tick().await;
+        press(&mut app, KeyCode::Down).await;
+        press(&mut app, KeyCode::Char('g')).await;
+        press(&mut app, KeyCode::Char('g')).await;
+        assert_eq!(app.grouping, Grouping::Project);
+
+        // The project sorts first, the selection stays on web and ↑ from
+        // there selects the header.
+        press(&mut app, KeyCode::Up).await;
+        assert_eq!(app.selected_project(), Some("shop"));
+        press(&mut app, KeyCode::Char('r')).await;
+        assert_eq!(app.status_message.as_deref(), Some("Restarting web, db…"));
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        app.collect();
+        let lifecycle = backend.lifecycle.lock().unwrap().clone();
+        assert_eq!(lifecycle, [("restart", "web-id".to_string()), ("restart", "db-id".to_string())]);
+
+        press(&mut app, KeyCode::Down).await;
+        assert_eq!((app.selected_project(), app.selected().unwrap().name.as_str()), (None, "web"));
+    }
+
+    #[tokio::test]
+    async fn shift_p_redeploys_running_project_members_in_dependency_order() {
+        let member = |name: &str, depends_on: &str| {
+            let mut c = container(name);
+            c.labels.insert("com.docker.compose.project".to_string(), "shop".to_string());
+            c.labels.insert("com.docker.compose.service".to_string(), name.to_string());
+            c.labels.insert("com.docker.compose.depends_on".to_string(), depends_on.to_string());
+            c
+        };
+        let mut stopped = member("cache", "");
+        stopped.status = "Exited (0) 1 hour ago".to_string();
+        let (mut app, _) = app_with(MockBackend::new([Snapshot::Containers(vec![
+            member("web", "api:service_started:false"),
+            member("api", "db:service_healthy:false,cache:service_started:false"),
+            member("db", ""),
+            stopped,
+        ])]));
+        app.tick().await;
+
+        press(&mut app, KeyCode::Char('P')).await;
+        let InputMode::Confirm(confirm) = &app.input_mode else {
+            panic!("no confirmation");
+        };
+        assert_eq!(confirm.question, "Stop shop and start it again in order db → api → web?");
+    }
+
+    #[tokio::test]
+    async fn shift_i_attaches_to_the_container_and_ctrl_q_detaches_leaving_it_running() {
+        let backend = MockBackend::new([Snapshot::Containers(vec![container("web")])]);
+        *backend.followed.lock().unwrap() = vec!["ready\n".to_string()];
+        let (mut app, backend) = app_with(backend);
+        app.tick().await;
+
+        press(&mut app, KeyCode::Char('I')).await;
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        type_text(&mut app, "hello").await;
+        press(&mut app, KeyCode::Enter).await;
+        let ctrl = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL);
+        app.handle_key(ctrl('c')).await;
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        let session = app.sessions.active().expect("Ctrl-C closed the session");
+        assert_eq!(session.title(), "attach: web");
+        assert_eq!(session.visible(2), ["ready", "$ hello"]);
+
+        app.handle_key(ctrl('q')).await;
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        assert!(app.sessions.list.is_empty());
+        assert_eq!(*backend.attached.lock().unwrap(), [("web-id".to_string(), true)]);
+        assert_eq!(*backend.attach_input.lock().unwrap(), b"hello\n\x10\x11");
+        assert!(backend.lifecycle.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn bang_runs_a_command_in_the_container_and_shows_its_output() {
+        let (mut app, backend) = app_with(MockBackend::new([Snapshot::Containers(vec![container("web")])]));
+        app.tick().await;
+
+        press(&mut app, KeyCode::Char('!')).await;
+        type_text(&mut app, "cat /etc/hosts").await;
+        press(&mut app, KeyCode::Enter).await;
+        assert_eq!(app.output.as_ref().unwrap().lines, ["Running…"]);
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        app.collect();
+
+        let pane = app.output.as_ref().unwrap();
+        assert_eq!(pane.title, "cat /etc/hosts in web");
+        assert_eq!(pane.lines, ["(no output)"]);
+        let execs = backend.execs.lock().unwrap().clone();
+        assert_eq!(execs, [("web-id".to_string(), vec!["sh".to_string(), "-c".to_string(), "cat /etc/hosts".to_string()])]);
+    }
+
+    #[tokio::test]
+    async fn shift_o_browses_the_container_filesystem_and_shows_small_files() {
+        let backend = MockBackend::new([Snapshot::Containers(vec![container("web")])]);
+        *backend.exec_output.lock().unwrap() = [
+            "regular file|12|1714572300|notes.txt\ndirectory|4096|1714572300|etc\n".to_string(),
+            "regular file|70000|1714572300|big.log\nregular file|9|1714572300|it's.txt\n".to_string(),
+            "hello\tthere\n".to_string(),
+        ]
+        .into();
+        let (mut app, backend) = app_with(backend);
+        app.tick().await;
+
+        press(&mut app, KeyCode::Char('O')).await;
+        let browser = app.files.as_ref().unwrap();
+        assert_eq!(browser.entries.iter().map(|e| e.name.as_str()).collect::<Vec<_>>(), ["etc", "notes.txt"]);
+        press(&mut app, KeyCode::Enter).await;
+        press(&mut app, KeyCode::Enter).await;
+        let browser = app.files.as_ref().unwrap();
+        assert_eq!(browser.path, "/etc");
+        assert_eq!(browser.error.as_deref(), Some("big.log is over 64 KB; only smaller files are shown"));
+        press(&mut app, KeyCode::Down).await;
+        press(&mut app, KeyCode::Enter).await;
+
+        let view = app.files.as_ref().unwrap().viewing.as_ref().unwrap();
+        assert_eq!(view.path, "/etc/it's.txt");
+        assert_eq!(view.lines, ["hello    there"]);
+        let commands: Vec<String> = backend.execs.lock().unwrap().iter().map(|(_, command)| command[2].clone()).collect();
+        assert!(commands[1].starts_with("cd -- '/etc' || exit 1; stat "));
+        assert_eq!(commands[2], r"head -c 65536 -- '/etc/it'\''s.txt'");
+    }
+
+    #[tokio::test]
+    async fn shift_w_pulls_the_image_and_recreates_the_container_from_it() {
+        let (mut app, backend) = app_with(MockBackend::new([Snapshot::Containers(vec![container("web")])]));
+        app.tick().await;
+
+        press(&mut app, KeyCode::Char('W')).await;
+        let InputMode::Confirm(confirm) = &app.input_mode else {
+            panic!("no confirmation");
+        };
+        assert!(confirm.question.starts_with("Pull web:latest and recreate web"));
+        press(&mut app, KeyCode::Char('y')).await;
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        app.collect();
+
+        assert_eq!(*backend.pulls.lock().unwrap(), ["web:latest"]);
+        let recreated = backend.recreated.lock().unwrap().clone();
+        assert_eq!(recreated[0].0, "web-id");
+        assert_eq!(recreated[0].1.image, "web:latest");
+        // The old container stays until the new one has been checked.
+        assert!(backend.removed.lock().unwrap().is_empty());
+        assert_eq!(app.deployment.as_ref().unwrap().title, "Update web");
+    }
+
+    #[tokio::test]
+    async fn registries_screen_picks_the_login_for_pulls_and_logs_in_and_out() {
+        let login = |server: &str, username: &str| RegistryLogin {
+            server: server.to_string(),
+            username: username.to_string(),
+            store: "config.json".to_string(),
+        };
+        let (mut app, backend) = app_with(MockBackend::new([]));
+        *backend.logins.lock().unwrap() = vec![login("https://index.docker.io/v1/", "me"), login("docker.io", "ci")];
+        press(&mut app, KeyCode::Char('9')).await;
+        assert_eq!(registries::pull_login("docker.io", &app.registries, &app.chosen_logins()).unwrap().username, "me");
+        press(&mut app, KeyCode::Down).await;
+        press(&mut app, KeyCode::Enter).await;
+        assert_eq!(registries::pull_login("docker.io", &app.registries, &app.chosen_logins()).unwrap().username, "ci");
+
+        press(&mut app, KeyCode::Char('i')).await;
+        for _ in 0.."docker.io".len() {
+            press(&mut app, KeyCode::Backspace).await;
+        }
+        type_text(&mut app, "ghcr.io").await;
+        press(&mut app, KeyCode::Enter).await;
+        type_text(&mut app, "octocat").await;
+        press(&mut app, KeyCode::Enter).await;
+        type_text(&mut app, "token").await;
+        press(&mut app, KeyCode::Enter).await;
+        assert_eq!(app.status_message.as_deref(), Some("Logged in to ghcr.io as octocat"));
+        assert_eq!(app.registries.len(), 3);
+
+        press(&mut app, KeyCode::Char('o')).await;
+        press(&mut app, KeyCode::Char('y')).await;
+        assert_eq!(app.status_message.as_deref(), Some("Logged out of docker.io"));
+        assert_eq!(app.registries.iter().map(|l| l.username.as_str()).collect::<Vec<_>>(), ["me", "octocat"]);
+    }
+
+    #[tokio::test]
+    async fn x_prunes_stopped_containers_once_confirmed() {
+        let mut exited = container("old");
+        exited.status = "Exited (0) 3 days ago".to_string();
+        let (mut app, backend) = app_with(MockBackend::new([Snapshot::Containers(vec![container("web"), exited])]));
+        app.tick().await;
+
+        press(&mut app, KeyCode::Char('x')).await;
+        assert!(matches!(&app.input_mode, InputMode::Confirm(c) if c.question == "Remove all 1 stopped containers: old?"));
+        press(&mut app, KeyCode::Char('y')).await;
+
+        assert_eq!(*backend.removed.lock().unwrap(), ["old-id"]);
+        assert_eq!(app.status_message.as_deref(), Some("Pruned 1 stopped containers, reclaiming 1.00 KB"));
+    }
+
+    #[tokio::test]
+    async fn status_messages_use_the_configured_number_format() {
+        let mut exited = container("old");
+        exited.status = "Exited (0) 3 days ago".to_string();
+        let (mut app, _) = app_with(MockBackend::new([Snapshot::Containers(vec![exited])]));
+        app.format = FormatConfig {
+            units: crate::config::Units::Si,
+            decimal_separator: ',',
+            precision: 1,
+            percent_precision: 0,
+        };
+        app.tick().await;
+
+        press(&mut app, KeyCode::Char('x')).await;
+        press(&mut app, KeyCode::Char('y')).await;
+
+        assert_eq!(app.status_message.as_deref(), Some("Pruned 1 stopped containers, reclaiming 1,0 kB"));
+    }
+
+    #[tokio::test]
+    async fn x_names_the_stopped_containers_a_filter_hides() {
+        let stopped = |name| {
+            let mut c = container(name);
+            c.status = "Exited (0) 3 days ago".to_string();
+            c
+        };
+        let containers = vec![container("web"), stopped("web-old"), stopped("db-old")];
+        let (mut app, backend) = app_with(MockBackend::new([Snapshot::Containers(containers)]));
+        app.filter = Query::parse("web").ok();
+        app.tick().await;
+        assert_eq!(app.containers.len(), 2);
+
+        press(&mut app, KeyCode::Char('x')).await;
+        assert!(
+            matches!(&app.input_mode, InputMode::Confirm(c) if c.question == "Remove all 2 stopped containers: web-old, db-old?")
+        );
+        press(&mut app, KeyCode::Char('y')).await;
+
+        assert_eq!(*backend.removed.lock().unwrap(), ["web-old-id", "db-old-id"]);
+    }
+
+    #[tokio::test]
+    async fn l_shows_the_recent_logs_until_closed() {
+        let (mut app, backend) = app_with(MockBackend::new([Snapshot::Containers(vec![container("web")])]));
+        *backend.logs.lock().unwrap() = (1..=800).map(|i| format!("line {}", i)).collect();
+        app.tick().await;
+
+        press(&mut app, KeyCode::Char('l')).await;
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        app.collect();
+        let view = app.log_view.as_mut().unwrap();
+        assert_eq!(view.lines.len(), crate::log_view::TAIL);
+        assert_eq!(view.visible(2), ["line 799", "line 800"]);
+        view.scroll_by(3);
+        assert_eq!(view.visible(2), ["line 796", "line 797"]);
+
+        // The pane has the keys until it is closed.
+        press(&mut app, KeyCode::Char('s')).await;
+        press(&mut app, KeyCode::Char('q')).await;
+        assert!(app.log_view.is_none() && !app.should_quit);
+        assert!(backend.lifecycle.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn l_merges_the_logs_of_marked_containers() {
+        let (mut app, backend) = app_with(MockBackend::new([Snapshot::Containers(vec![container("web"), container("db")])]));
+        *backend.logs.lock().unwrap() = vec!["started".to_string(), "ready".to_string()];
+        app.tick().await;
+        press(&mut app, KeyCode::Char(' ')).await;
+        press(&mut app, KeyCode::Char(' ')).await;
+
+        press(&mut app, KeyCode::Char('l')).await;
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        app.collect();
+        let view = app.log_view.as_ref().unwrap();
+        assert!(view.merged());
+        // Fetched with timestamps to merge by, which are then dropped.
+        assert_eq!(view.visible(4), ["started", "ready", "started", "ready"]);
+        let sources: Vec<_> = view.sources.iter().map(|&i| view.containers[i].name.as_str()).collect();
+        assert_eq!(sources, ["web", "web", "db", "db"]);
+    }
+
+    #[tokio::test]
+    async fn t_fetches_the_logs_again_with_or_without_timestamps() {
+        let (mut app, backend) = app_with(MockBackend::new([Snapshot::Containers(vec![container("web")])]));
+        *backend.logs.lock().unwrap() = vec!["started".to_string()];
+        app.tick().await;
+        press(&mut app, KeyCode::Char('l')).await;
+
+        let mut shown = Vec::new();
+        for _ in 0..3 {
+            for _ in 0..10 {
+                tokio::task::yield_now().await;
+            }
+            app.collect();
+            shown.push(app.log_view.as_ref().unwrap().visible(1)[0].to_string());
+            press(&mut app, KeyCode::Char('t')).await;
+        }
+        assert_eq!(shown, ["started", "2024-01-01T00:00:00Z started", "started"]);
+    }
+
+    #[tokio::test]
+    async fn l_in_the_log_view_changes_the_lines_fetched_for_the_session() {
+        let (mut app, backend) = app_with(MockBackend::new([Snapshot::Containers(vec![container("web")])]));
+        app.tick().await;
+        press(&mut app, KeyCode::Char('l')).await;
+        press(&mut app, KeyCode::Char('l')).await;
+        press(&mut app, KeyCode::Backspace).await;
+        press(&mut app, KeyCode::Backspace).await;
+        press(&mut app, KeyCode::Enter).await;
+        type_text(&mut app, "2h").await;
+        press(&mut app, KeyCode::Enter).await;
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+
+        let requests = backend.log_requests.lock().unwrap().clone();
+        let now = chrono::Utc::now().timestamp();
+        assert_eq!(requests[0], (crate::log_view::TAIL, 0));
+        assert_eq!(requests[1].0, 5);
+        assert!((now - 7200 - 5..=now - 7200).contains(&requests[1].1));
+        assert_eq!((app.log_tail, app.log_age), (5, 7200));
+    }
+
+    #[tokio::test]
+    async fn f_follows_the_logs_and_keeps_a_scrolled_view_in_place() {
+        let (mut app, backend) = app_with(MockBackend::new([Snapshot::Containers(vec![container("web")])]));
+        *backend.logs.lock().unwrap() = (1..=5).map(|i| format!("line {}", i)).collect();
+        *backend.followed.lock().unwrap() = vec!["line 6".to_string(), "line 7".to_string()];
+        app.tick().await;
+        press(&mut app, KeyCode::Char('l')).await;
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        app.collect();
+
+        press(&mut app, KeyCode::Up).await;
+        assert_eq!(app.log_view.as_ref().unwrap().visible(2), ["line 3", "line 4"]);
+        press(&mut app, KeyCode::Char('f')).await;
+        assert!(app.log_view.as_ref().unwrap().following());
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        app.collect();
+        let view = app.log_view.as_mut().unwrap();
+        assert_eq!(view.lines.len(), 7);
+        assert_eq!(view.visible(2), ["line 3", "line 4"]);
+        // The mock's stream ends after its lines, as when the container stops.
+        assert!(!view.following());
+
+        press(&mut app, KeyCode::End).await;
+        assert_eq!(app.log_view.as_ref().unwrap().visible(2), ["line 6", "line 7"]);
+    }
+
+    #[tokio::test]
+    async fn slash_searches_the_log_view_and_ampersand_shows_only_matches() {
+        let (mut app, backend) = app_with(MockBackend::new([Snapshot::Containers(vec![container("web")])]));
+        *backend.logs.lock().unwrap() = (1..=30)
+            .map(|i| if i % 10 == 0 { format!("error {}", i) } else { format!("line {}", i) })
+            .collect();
+        app.tick().await;
+        press(&mut app, KeyCode::Char('l')).await;
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        app.collect();
+
+        press(&mut app, KeyCode::Char('/')).await;
+        for c in "error".chars() {
+            press(&mut app, KeyCode::Char(c)).await;
+        }
+        press(&mut app, KeyCode::Enter).await;
+        let view = app.log_view.as_ref().unwrap();
+        assert_eq!((view.match_count(), view.current), (3, Some(29)));
+
+        // n goes to older matches, N back to newer ones.
+        press(&mut app, KeyCode::Char('n')).await;
+        press(&mut app, KeyCode::Char('n')).await;
+        assert_eq!(app.log_view.as_ref().unwrap().visible(2), ["line 9", "error 10"]);
+        press(&mut app, KeyCode::Char('n')).await;
+        assert_eq!(app.status_message.take().as_deref(), Some("No older matches"));
+        press(&mut app, KeyCode::Char('N')).await;
+        assert_eq!(app.log_view.as_ref().unwrap().current, Some(19));
+
+        press(&mut app, KeyCode::Char('&')).await;
+        assert_eq!(app.log_view.as_ref().unwrap().visible(5), ["error 10", "error 20", "error 30"]);
+
+        // Esc clears the search first, with every line back.
+        press(&mut app, KeyCode::Esc).await;
+        let view = app.log_view.as_ref().unwrap();
+        assert!(view.search.is_none() && !view.filtering);
+        assert_eq!(view.visible(2), ["line 29", "error 30"]);
+        press(&mut app, KeyCode::Esc).await;
+        assert!(app.log_view.is_none());
+    }
+
+    #[tokio::test]
+    async fn e_exports_the_selected_containers_logs_to_a_file() {
+        let (mut app, backend) = app_with(MockBackend::new([Snapshot::Containers(vec![container("web")])]));
+        *backend.logs.lock().unwrap() = vec!["started".to_string(), "ready".to_string()];
+        app.tick().await;
+        let path = std::env::temp_dir().join(format!("dockertop-export-{}.log", std::process::id()));
+
+        press(&mut app, KeyCode::Char('e')).await;
+        if let InputMode::Prompt(prompt) = &mut app.input_mode {
+            prompt.input = path.display().to_string();
+        }
+        press(&mut app, KeyCode::Enter).await;
+        for c in "2h".chars() {
+            press(&mut app, KeyCode::Char(c)).await;
+        }
+        press(&mut app, KeyCode::Enter).await;
+        // The file is written on the blocking pool.
+        for _ in 0..100 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            app.collect();
+            if !app.status_message.as_deref().unwrap_or_default().starts_with("Exporting") {
+                break;
+            }
+        }
+
+        let expected = format!("Exported the logs of web to {}", path.display());
+        assert_eq!(app.status_message.as_deref(), Some(expected.as_str()));
+        let exported = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(exported, "2024-01-01T00:00:00Z started\n2024-01-01T00:00:00Z ready\n");
+    }
+
+    #[tokio::test]
+    async fn v_reports_changes_since_a_snapshot_taken_with_t() {
+        let with_memory = |name: &str, memory_usage: u64| ContainerStats {
+            memory_usage,
+            ..container(name)
+        };
+        let before = vec![with_memory("web", 1024), with_memory("db", 4096), container("old")];
+        let after = vec![with_memory("web", 3072), with_memory("db", 2048), container("new")];
+        let (mut app, _) = app_with(MockBackend::new([Snapshot::Containers(before), Snapshot::Containers(after)]));
+        // Snapshots cover every container, whatever the filter.
+        app.filter = Query::parse("web").ok();
+        app.tick().await;
+
+        press(&mut app, KeyCode::Char('t')).await;
+        app.input_mode = InputMode::Normal;
+        press(&mut app, KeyCode::Char('v')).await;
+        assert_eq!(app.status_message.as_deref(), Some("No snapshots yet (t takes one)"));
+
+        press(&mut app, KeyCode::Char('t')).await;
+        if let InputMode::Prompt(prompt) = &mut app.input_mode {
+            prompt.input = "baseline".to_string();
+        }
+        press(&mut app, KeyCode::Enter).await;
+        assert_eq!(app.status_message.as_deref(), Some("Snapshot \"baseline\" taken of 3 containers"));
+        app.tick().await;
+        press(&mut app, KeyCode::Char('v')).await;
+        press(&mut app, KeyCode::Enter).await;
+
+        let report = &app.output.as_ref().unwrap().lines;
+        let section = |heading: &str| {
+            let start = report.iter().position(|l| l.starts_with(heading)).unwrap();
+            report[start..].iter().take_while(|l| !l.is_empty()).cloned().collect::<Vec<_>>()
+        };
+        assert_eq!(section("Appeared"), ["Appeared (1):", "  new  new:latest  Up 1 minute"]);
+        assert_eq!(section("Disappeared"), ["Disappeared (1):", "  old  old:latest  Up 1 minute"]);
+        assert_eq!(
+            section("Memory growth"),
+            ["Memory growth (largest 1 of 1):", "  web  1.00 KB → 3.00 KB  +2.00 KB (+200%)"]
+        );
+    }
+
+    #[tokio::test]
+    async fn d_and_shift_d_remove_the_container_only_once_confirmed() {
+        let (mut app, backend) = app_with(MockBackend::new([Snapshot::Containers(vec![container("a")])]));
+        app.tick().await;
+
+        press(&mut app, KeyCode::Char('d')).await;
+        assert!(matches!(&app.input_mode, InputMode::Confirm(c) if c.question == "Remove container a?"));
+        press(&mut app, KeyCode::Enter).await;
+        assert!(matches!(app.input_mode, InputMode::Normal));
+
+        press(&mut app, KeyCode::Char('d')).await;
+        press(&mut app, KeyCode::Char('y')).await;
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        app.collect();
+
+        assert_eq!(*backend.removed.lock().unwrap(), ["a-id"]);
+        assert_eq!(app.status_message.as_deref(), Some("Removed a"));
+
+        // Force-removing takes the container's name.
+        for input in ["b", "a"] {
+            press(&mut app, KeyCode::Char('D')).await;
+            press(&mut app, KeyCode::Char(input.chars().next().unwrap())).await;
+            press(&mut app, KeyCode::Enter).await;
+        }
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        app.collect();
+        assert_eq!(*backend.lifecycle.lock().unwrap(), [("force remove", "a-id".to_string())]);
+        assert_eq!(app.status_message.as_deref(), Some("Force-removed a"));
+    }
+
+    #[test]
+    fn configured_highlights_take_precedence_over_the_builtin_ones() {
+        use crate::config::HighlightColor;
+        let config = Config {
+            highlights: vec![HighlightRule {
+                pattern: Regex::new("ERROR: disk").unwrap(),
+                color: HighlightColor::Magenta,
+            }],
+            ..Config::default()
+        };
+        let app = App::new(config, Arc::new(MockBackend::new([])));
+        let color = |line: &str| app.highlights.iter().find(|r| r.pattern.is_match(line)).map(|r| r.color);
+        assert_eq!(color("ERROR: disk full"), Some(HighlightColor::Magenta));
+        assert_eq!(color("ERROR: timed out"), Some(HighlightColor::Red));
+        assert_eq!(color(r#"{"level":"warn","msg":"slow query"}"#), Some(HighlightColor::Yellow));
+        assert_eq!(color("GET /api/errors 200"), None);
+    }
+
+    #[tokio::test]
+    async fn guarded_actions_wait_for_the_guard_phrase() {
+        let mut prod = container("db");
+        prod.labels.insert("env".to_string(), "prod".to_string());
+        let config = Config {
+            guards: vec![crate::config::Guard {
+                containers: Query::parse(r#"label("env") == "prod""#).unwrap(),
+                actions: vec![crate::config::GuardedAction::Remove, crate::config::GuardedAction::ForceRemove],
+                phrase: "{{name}}".to_string(),
+            }],
+            ..Config::default()
+        };
+        let backend = Arc::new(MockBackend::new([Snapshot::Containers(vec![prod])]));
+        let mut app = App::new(config, backend.clone());
+        app.tick().await;
+
+        press(&mut app, KeyCode::Char('d')).await;
+        assert!(matches!(&app.input_mode, InputMode::Prompt(p) if p.title == "Type db to remove db"));
+        press(&mut app, KeyCode::Char('y')).await;
+        press(&mut app, KeyCode::Enter).await;
+        assert_eq!(app.status_message.as_deref(), Some("Not removed: \"y\" is not db"));
+
+        press(&mut app, KeyCode::Char('d')).await;
+        type_text(&mut app, "db").await;
+        press(&mut app, KeyCode::Enter).await;
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        app.collect();
+        assert_eq!(*backend.removed.lock().unwrap(), ["db-id"]);
+
+        // Stopping is not guarded.
+        press(&mut app, KeyCode::Char('s')).await;
+        assert!(matches!(app.input_mode, InputMode::Normal));
+    }
+
+    #[tokio::test]
+    async fn a_runs_a_numbered_container_from_the_picked_template() {
+        let template = |name: &str, image: &str| crate::config::Template {
+            name: name.to_string(),
+            image: image.to_string(),
+            env: [("REDIS_ARGS".to_string(), "--save ''".to_string())].into(),
+            ports: vec!["127.0.0.1:6379:6379".to_string()],
+            volumes: Vec::new(),
+            container_name: "{{template}}-{{n}}".to_string(),
+        };
+        let config = Config {
+            templates: vec![template("postgres", "postgres:16"), template("redis", "redis:7")],
+            ..Config::default()
+        };
+        let backend = Arc::new(MockBackend::new([Snapshot::Containers(vec![container("redis-1")])]));
+        let mut app = App::new(config, backend.clone());
+        app.tick().await;
+
+        press(&mut app, KeyCode::Char('a')).await;
+        press(&mut app, KeyCode::Down).await;
+        press(&mut app, KeyCode::Enter).await;
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        app.collect();
+
+        let runs = backend.runs.lock().unwrap().clone();
+        assert_eq!(runs.len(), 1);
+        assert_eq!((runs[0].name.as_str(), runs[0].image.as_str()), ("redis-2", "redis:7"));
+        assert_eq!(runs[0].env, ["REDIS_ARGS=--save ''"]);
+        assert_eq!(runs[0].ports[0].host_ip.as_deref(), Some("127.0.0.1"));
+        assert_eq!(app.status_message.as_deref(), Some("Started redis-2 from redis"));
+    }
+
+    #[tokio::test]
+    async fn shift_m_recreates_the_container_with_the_edited_configuration() {
+        let mut web = container("web");
+        web.mounts.push(crate::backend::Mount {
+            kind: "volume".to_string(),
+            name: "webdata".to_string(),
+            source: "/var/lib/docker/volumes/webdata/_data".to_string(),
+            destination: "/data".to_string(),
+            read_write: true,
+        });
+        let (mut app, backend) = app_with(MockBackend::new([Snapshot::Containers(vec![web])]));
+        app.tick().await;
+
+        // Unchanged, Enter only closes the form.
+        press(&mut app, KeyCode::Char('M')).await;
+        press(&mut app, KeyCode::Enter).await;
+        assert!(app.recreate_form.is_none());
+
+        press(&mut app, KeyCode::Char('M')).await;
+        press(&mut app, KeyCode::Down).await;
+        type_text(&mut app, "DEBUG").await;
+        press(&mut app, KeyCode::Enter).await;
+        assert_eq!(
+            app.recreate_form.as_ref().unwrap().error.as_deref(),
+            Some("Environment: \"DEBUG\" is not KEY=value")
+        );
+        type_text(&mut app, "=1").await;
+        press(&mut app, KeyCode::Enter).await;
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        app.collect();
+
+        let recreated = backend.recreated.lock().unwrap().clone();
+        assert_eq!(recreated.len(), 1);
+        let (id, spec) = &recreated[0];
+        assert_eq!((id.as_str(), spec.image.as_str()), ("web-id", "web:latest"));
+        assert_eq!(spec.env, ["DEBUG=1"]);
+        assert_eq!(spec.volumes, ["webdata:/data"]);
+        assert_eq!(app.status_message.as_deref(), Some("Recreated web from web:latest"));
+    }
+
+    #[tokio::test]
+    async fn journal_restores_the_disk_trend_after_a_restart() {
+        let path = std::env::temp_dir().join(format!("dockertop-journal-{}.json", std::process::id()));
+        let config = crate::config::JournalConfig {
+            path: Some(path.clone()),
+            ..Default::default()
+        };
+
+        let (mut app, _) = app_with(MockBackend::new([Snapshot::Containers(vec![container("a")])]));
+        app.journal = Journal::open(&config);
+        app.tick().await;
+        assert_eq!(app.disk.samples.len(), 1);
+        app.save_journal(true);
+
+        let (mut restarted, _) = app_with(MockBackend::new([Snapshot::Containers(vec![container("a")])]));
+        restarted.journal = Journal::open(&config);
+        restarted.restore_journal();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(restarted.disk.samples.len(), 1);
+        assert!(restarted.status_message.as_deref().unwrap_or_default().starts_with("Restored history"));
+    }
+
+    #[tokio::test]
+    async fn the_status_line_summary_goes_to_the_title_and_the_file() {
+        let path = std::env::temp_dir().join(format!("dockertop-status-{}", std::process::id()));
+        let mut sick = container("api");
+        sick.status = "Up 5 minutes (unhealthy)".to_string();
+        let mut stopped = container("old");
+        stopped.status = "Exited (0) 1 hour ago".to_string();
+        let (mut app, _) = app_with(MockBackend::new([Snapshot::Containers(vec![container("web"), sick, stopped])]));
+        app.status_line = StatusLine::new(crate::config::StatusLineConfig {
+            terminal_title: true,
+            file: Some(path.clone()),
+        });
+        app.tick().await;
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert!(written.starts_with("2 running, 1 unhealthy, CPU "), "{}", written);
+        assert_eq!(app.status_line.take_title(), Some(format!("dockertop: {}", written.trim_end())));
+        // Unchanged on the next refresh, so the title is left alone.
+        app.tick().await;
+        assert_eq!(app.status_line.take_title(), None);
+        app.status_line.clear();
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn auto_actions_need_opt_in_and_wait_out_their_cool_down() {
+        let mut hungry = container("worker-1");
+        hungry.memory_usage = 95;
+        hungry.memory_limit = 100;
+        let config = |enabled| Config {
+            auto_actions: crate::config::AutoActionsConfig {
+                enabled,
+                rules: vec![crate::config::AutoActionRule {
+                    containers: "worker-*".to_string(),
+                    action: crate::config::AutoAction::Restart,
+                    unhealthy_checks: None,
+                    memory_percent: Some(90.0),
+                    for_minutes: 0,
+                    cooldown_minutes: 15,
+                    errors_per_minute: None,
+                }],
+            },
+            ..Config::default()
+        };
+
+        for enabled in [false, true] {
+            let backend = Arc::new(MockBackend::new([Snapshot::Containers(vec![hungry.clone()])]));
+            let mut app = App::new(config(enabled), backend.clone());
+            app.tick().await;
+            for _ in 0..10 {
+                tokio::task::yield_now().await;
+            }
+            app.collect();
+            app.tick().await;
+
+            let restarts = backend.lifecycle.lock().unwrap().len();
+            assert_eq!(restarts, usize::from(enabled));
+        }
+    }
+
+    #[tokio::test]
+    async fn containers_at_their_docker_tc_limit_warn_once() {
+        let shaped = |sent: u64| {
+            let mut c = container("web");
+            c.labels.insert(crate::bandwidth::TC_LIMIT_LABEL.to_string(), "8kbit".to_string());
+            c.io.net_tx = sent;
+            c
+        };
+        let (mut app, _) = app_with(MockBackend::new([
+            Snapshot::Containers(vec![shaped(0)]),
+            Snapshot::Containers(vec![shaped(10_000_000)]),
+            Snapshot::Containers(vec![shaped(20_000_000)]),
+            Snapshot::Containers(vec![shaped(20_000_000)]),
+        ]));
+
+        let mut warnings = Vec::new();
+        for _ in 0..4 {
+            app.tick().await;
+            warnings.push(app.status_message.take());
+        }
+        assert_eq!(warnings[0], None);
+        assert!(warnings[1].as_deref().unwrap().starts_with("web is at its bandwidth cap: network out "));
+        assert_eq!(warnings[2..], [None, None]);
+        assert!(app.bandwidth.saturated().is_empty());
+        let caps = app.bandwidth.usage(&app.containers[0], &app.io);
+        assert_eq!(caps.iter().map(|c| (c.what, c.cap)).collect::<Vec<_>>(), [("network in", 1000.0), ("network out", 1000.0)]);
+    }
+
+    #[tokio::test]
+    async fn the_report_ranks_running_containers_by_their_sampled_usage() {
+        let with_cpu = |name: &str, cpu_usage: f64| {
+            let mut c = container(name);
+            c.cpu_usage = cpu_usage;
+            c
+        };
+        let mut stopped = with_cpu("old", 90.0);
+        stopped.status = "Exited (0) 1 hour ago".to_string();
+        let (mut app, _) = app_with(MockBackend::new([Snapshot::Containers(vec![
+            with_cpu("web", 10.0),
+            with_cpu("worker", 42.5),
+            stopped,
+        ])]));
+        app.tick().await;
+        app.tick().await;
+
+        let history = app.usage.history();
+        assert_eq!(history.len(), 2, "sampled once a minute, running containers only");
+        let table = crate::report::table(&history, 0, 1, crate::report::Metric::Cpu, &FormatConfig::DEFAULT);
+        let lines: Vec<&str> = table.lines().collect();
+        assert!(lines[0].starts_with("Top 1 by CPU, "));
+        assert_eq!(lines[1..], ["#  CONTAINER  AVERAGE  PEAK   SAMPLES", "1  worker     42.5%    42.5%  1"]);
+    }
+
+    #[tokio::test]
+    async fn project_budgets_warn_once_each_time_they_are_exceeded() {
+        let member = |name: &str, cpu_usage: f64| {
+            let mut c = container(name);
+            c.cpu_usage = cpu_usage;
+            c.labels.insert("com.docker.compose.project".to_string(), "shop".to_string());
+            c
+        };
+        let over = vec![member("web", 30.0), member("db", 25.0), container("other")];
+        let under = vec![member("web", 30.0), member("db", 5.0)];
+        let config = Config {
+            budgets: vec![crate::config::Budget {
+                project: "shop".to_string(),
+                cpu_percent: Some(50.0),
+                memory: None,
+            }],
+            ..Config::default()
+        };
+        let backend = MockBackend::new([
+            Snapshot::Containers(over.clone()),
+            Snapshot::Containers(over.clone()),
+            Snapshot::Containers(under),
+            Snapshot::Containers(over),
+        ]);
+        let mut app = App::new(config, Arc::new(backend));
+
+        let mut warnings = Vec::new();
+        for _ in 0..4 {
+            app.tick().await;
+            warnings.push(app.status_message.take());
+        }
+        let warned = Some("Project shop is over budget: CPU 55.0% of 50%".to_string());
+        assert_eq!(warnings, [warned.clone(), None, None, warned]);
+        assert_eq!(app.budgets.exceeded(), ["shop"]);
+    }
+
+    #[tokio::test]
+    async fn restarts_are_counted_from_status_changes_and_dropping_uptime() {
+        let with_status = |status: &str| {
+            let mut c = container("web");
+            c.status = status.to_string();
+            vec![c]
+        };
+        let backend = MockBackend::new([
+            Snapshot::Containers(with_status("Up 5 minutes")),
+            Snapshot::Containers(with_status("Up 6 minutes")),
+            Snapshot::Containers(with_status("Up 3 seconds")),
+            Snapshot::Containers(with_status("Exited (1) 2 seconds ago")),
+            Snapshot::Containers(with_status("Up Less than a second")),
+        ]);
+        let mut app = App::new(Config::default(), Arc::new(backend));
+
+        let mut restarts = Vec::new();
+        for _ in 0..5 {
+            app.tick().await;
+            restarts.push(app.trends.restarts_per_hour("web-id"));
+        }
+        assert_eq!(restarts, [0, 0, 1, 1, 2]);
+    }
+}
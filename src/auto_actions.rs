@@ -0,0 +1,175 @@
+//! Restarting or stopping containers automatically when a configured rule
+//! fires, e.g. after repeated failed healthchecks.
+
+use crate::backend::{AuditLog, ContainerBackend, ContainerStats};
+use crate::config::{AutoAction, AutoActionRule, AutoActionsConfig};
+use crate::error_rate::ErrorMeter;
+use crate::journal::{self, AutoActionTimers};
+use crate::probes;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+/// Rule index and container ID.
+type Key = (usize, String);
+
+pub struct AutoActions {
+    enabled: bool,
+    rules: Vec<AutoActionRule>,
+    /// Since when a container's memory has been over a rule's threshold.
+    over_since: HashMap<Key, Instant>,
+    last_fired: HashMap<Key, Instant>,
+    /// Containers with an action still running, by ID.
+    in_flight: HashSet<String>,
+    tx: UnboundedSender<(String, String)>,
+    rx: UnboundedReceiver<(String, String)>,
+}
+
+impl AutoActions {
+    pub fn new(config: AutoActionsConfig) -> Self {
+        let (tx, rx) = unbounded_channel();
+        Self {
+            enabled: config.enabled,
+            rules: config.rules,
+            over_since: HashMap::new(),
+            last_fired: HashMap::new(),
+            in_flight: HashSet::new(),
+            tx,
+            rx,
+        }
+    }
+
+    /// Takes new rules from a reloaded config. Cool-downs carry over, so a
+    /// reload does not fire actions again.
+    pub fn reconfigure(&mut self, config: AutoActionsConfig) {
+        self.enabled = config.enabled;
+        self.rules = config.rules;
+        self.over_since.clear();
+    }
+
+    pub fn timers(&self) -> AutoActionTimers {
+        let unix = |timers: &HashMap<Key, Instant>| {
+            timers
+                .iter()
+                .map(|((rule, id), at)| (*rule, id.clone(), journal::unix_at(*at)))
+                .collect()
+        };
+        AutoActionTimers {
+            last_fired: unix(&self.last_fired),
+            over_since: unix(&self.over_since),
+        }
+    }
+
+    /// Picks up cool-downs and thresholds crossed during an earlier run.
+    pub fn restore(&mut self, timers: AutoActionTimers) {
+        for (into, from) in [(&mut self.last_fired, timers.last_fired), (&mut self.over_since, timers.over_since)] {
+            for (rule, id, unix) in from {
+                if let Some(at) = journal::instant_at(unix) {
+                    into.insert((rule, id), at);
+                }
+            }
+        }
+    }
+
+    /// Evaluates every rule against the running containers and starts the
+    /// actions of those that fire. Each action is written to `audit_log`
+    /// with the reason it was taken.
+    pub async fn check(
+        &mut self,
+        backend: &Arc<dyn ContainerBackend>,
+        containers: &[ContainerStats],
+        errors: Option<&ErrorMeter>,
+        audit_log: Option<&Arc<AuditLog>>,
+    ) {
+        if !self.enabled {
+            return;
+        }
+        for (index, rule) in self.rules.iter().enumerate() {
+            for container in containers.iter().filter(|c| c.is_running()) {
+                if !probes::matches(&rule.containers, &container.name) || self.in_flight.contains(&container.id) {
+                    continue;
+                }
+                let key = (index, container.id.clone());
+                let cooldown = Duration::from_secs(rule.cooldown_minutes * 60);
+                if self.last_fired.get(&key).is_some_and(|t| t.elapsed() < cooldown) {
+                    continue;
+                }
+                let Some(reason) = reason(rule, &key, container, errors, backend, &mut self.over_since).await else {
+                    continue;
+                };
+                self.last_fired.insert(key.clone(), Instant::now());
+                self.over_since.remove(&key);
+                self.in_flight.insert(container.id.clone());
+
+                let (backend, tx, audit_log) = (backend.clone(), self.tx.clone(), audit_log.cloned());
+                let (id, name, action) = (container.id.clone(), container.name.clone(), rule.action);
+                tokio::spawn(async move {
+                    let (verb, result) = match action {
+                        AutoAction::Restart => ("restarted", backend.restart_container(&id).await),
+                        AutoAction::Stop => ("stopped", backend.stop_container(&id).await),
+                    };
+                    let target = format!("{} ({})", name, reason);
+                    if let Some(log) = audit_log {
+                        let _ = log.record(&format!("auto_{}", verb), &target, &result);
+                    }
+                    let message = match result {
+                        Ok(()) => format!("Auto-action {} {}", verb, target),
+                        Err(e) => format!("Auto-action on {} failed: {:#}", target, e),
+                    };
+                    let _ = tx.send((id, message));
+                });
+            }
+        }
+        // Forget containers that are gone or stopped.
+        self.over_since
+            .retain(|(_, id), _| containers.iter().any(|c| c.id == *id && c.is_running()));
+    }
+
+    /// Messages of finished actions, oldest first.
+    pub fn collect(&mut self) -> Vec<String> {
+        let mut messages = Vec::new();
+        while let Ok((id, message)) = self.rx.try_recv() {
+            self.in_flight.remove(&id);
+            messages.push(message);
+        }
+        messages
+    }
+}
+
+/// Why the rule fires for the container now, if it does.
+async fn reason(
+    rule: &AutoActionRule,
+    key: &Key,
+    container: &ContainerStats,
+    errors: Option<&ErrorMeter>,
+    backend: &Arc<dyn ContainerBackend>,
+    over_since: &mut HashMap<Key, Instant>,
+) -> Option<String> {
+    if let Some(threshold) = rule.memory_percent {
+        if container.memory_percent() > threshold {
+            let since = *over_since.entry(key.clone()).or_insert_with(Instant::now);
+            if since.elapsed() >= Duration::from_secs(rule.for_minutes * 60) {
+                return Some(format!("memory above {}% for {} min", threshold, rule.for_minutes));
+            }
+        } else {
+            over_since.remove(key);
+        }
+    }
+    if let Some(threshold) = rule.errors_per_minute {
+        let rate = errors.and_then(|e| e.per_minute(&container.id)).unwrap_or(0.0);
+        if rate > threshold {
+            return Some(format!("{:.0} error lines per minute", rate));
+        }
+    }
+    // The status only says unhealthy; the streak needs an inspect.
+    let checks = rule.unhealthy_checks?;
+    if !container.status.contains("(unhealthy)") {
+        return None;
+    }
+    let health = backend.inspect_container(&container.id).await.ok()?.state?.health?;
+    let streak = health.failing_streak.unwrap_or(0);
+    (streak >= checks as i64).then(|| format!("{} failed healthchecks in a row", streak))
+}
@@ -0,0 +1,357 @@
+//! Append-only log of every mutating action taken through dockertop, so
+//! operators of shared servers can tell who restarted or removed what.
+//!
+//! The log is a JSON-lines file with one [`AuditEntry`] per action.
+
+use super::{
+    ApiMetrics, ContainerBackend, ContainerEvent, ContainerStats, DiskUsage, ExecStreams, LogLine, PruneReport, RegistryLogin, ResourceLimits,
+    RunSpec,
+};
+use crate::registries::PullLogins;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use bollard::models::{ContainerInspectResponse, ContainerTopResponse, ImageSummary, Network, ServiceUpdateStatus, Volume};
+use futures::stream::BoxStream;
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    env,
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+#[derive(Debug, Serialize)]
+struct AuditEntry<'a> {
+    time: String,
+    user: &'a str,
+    action: &'a str,
+    target: &'a str,
+    /// `ok`, or the error message.
+    result: String,
+}
+
+pub struct AuditLog {
+    file: Mutex<File>,
+    user: String,
+}
+
+impl AuditLog {
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open audit log {}", path.display()))?;
+        // Under sudo, the invoking user is the interesting one.
+        let user = match (env::var("SUDO_USER"), env::var("USER")) {
+            (Ok(sudo_user), _) => format!("{} (sudo)", sudo_user),
+            (Err(_), Ok(user)) => user,
+            _ => "unknown".to_string(),
+        };
+        Ok(Self {
+            file: Mutex::new(file),
+            user,
+        })
+    }
+
+    pub fn record<T>(&self, action: &str, target: &str, result: &Result<T>) -> Result<()> {
+        let entry = AuditEntry {
+            time: chrono::Local::now().to_rfc3339(),
+            user: &self.user,
+            action,
+            target,
+            result: match result {
+                Ok(_) => "ok".to_string(),
+                Err(e) => format!("{:#}", e),
+            },
+        };
+        let mut line = serde_json::to_string(&entry)?;
+        line.push('\n');
+        self.file
+            .lock()
+            .unwrap()
+            .write_all(line.as_bytes())
+            .context("Failed to write audit log")
+    }
+}
+
+/// Wraps another backend and writes every mutating call to an [`AuditLog`].
+/// Container IDs are logged with the name they had in the last refresh.
+pub struct AuditBackend {
+    inner: Box<dyn ContainerBackend>,
+    log: Arc<AuditLog>,
+    names: Mutex<HashMap<String, String>>,
+}
+
+impl AuditBackend {
+    pub fn new(inner: Box<dyn ContainerBackend>, log: Arc<AuditLog>) -> Self {
+        Self {
+            inner,
+            log,
+            names: Mutex::default(),
+        }
+    }
+
+    fn container(&self, id: &str) -> String {
+        match self.names.lock().unwrap().get(id) {
+            Some(name) => format!("{} ({})", name, &id[..id.len().min(12)]),
+            None => id.to_string(),
+        }
+    }
+
+    /// Logs the outcome; a failure to log fails the action's result too, so
+    /// nothing goes unrecorded silently.
+    fn audited<T>(&self, action: &str, target: &str, result: Result<T>) -> Result<T> {
+        self.log.record(action, target, &result)?;
+        result
+    }
+}
+
+#[async_trait]
+impl ContainerBackend for AuditBackend {
+    async fn containers(&self) -> Result<Vec<ContainerStats>> {
+        let containers = self.inner.containers().await?;
+        *self.names.lock().unwrap() = containers.iter().map(|c| (c.id.clone(), c.name.clone())).collect();
+        Ok(containers)
+    }
+
+    async fn inspect_container(&self, id: &str) -> Result<ContainerInspectResponse> {
+        self.inner.inspect_container(id).await
+    }
+
+    async fn networks(&self) -> Result<Vec<Network>> {
+        self.inner.networks().await
+    }
+
+    async fn volumes(&self) -> Result<Vec<Volume>> {
+        self.inner.volumes().await
+    }
+
+    async fn images(&self) -> Result<Vec<ImageSummary>> {
+        self.inner.images().await
+    }
+
+    async fn disk_usage(&self) -> Result<DiskUsage> {
+        self.inner.disk_usage().await
+    }
+
+    async fn container_events(&self, id: &str, since: i64) -> Result<Vec<ContainerEvent>> {
+        self.inner.container_events(id, since).await
+    }
+
+    fn follow_logs(&self, id: &str, tail: usize, timestamps: bool) -> BoxStream<'static, Result<LogLine>> {
+        self.inner.follow_logs(id, tail, timestamps)
+    }
+
+    async fn recent_logs(&self, id: &str, tail: usize, since: i64, timestamps: bool) -> Result<Vec<LogLine>> {
+        self.inner.recent_logs(id, tail, since, timestamps).await
+    }
+
+    async fn logs_since(&self, id: &str, since: i64) -> Result<Vec<LogLine>> {
+        self.inner.logs_since(id, since).await
+    }
+
+    async fn processes(&self, id: &str) -> Result<ContainerTopResponse> {
+        self.inner.processes(id).await
+    }
+
+    async fn start_container(&self, id: &str) -> Result<()> {
+        let result = self.inner.start_container(id).await;
+        self.audited("start_container", &self.container(id), result)
+    }
+
+    async fn stop_container(&self, id: &str) -> Result<()> {
+        let result = self.inner.stop_container(id).await;
+        self.audited("stop_container", &self.container(id), result)
+    }
+
+    async fn restart_container(&self, id: &str) -> Result<()> {
+        let result = self.inner.restart_container(id).await;
+        self.audited("restart_container", &self.container(id), result)
+    }
+
+    async fn pause_container(&self, id: &str) -> Result<()> {
+        let result = self.inner.pause_container(id).await;
+        self.audited("pause_container", &self.container(id), result)
+    }
+
+    async fn unpause_container(&self, id: &str) -> Result<()> {
+        let result = self.inner.unpause_container(id).await;
+        self.audited("unpause_container", &self.container(id), result)
+    }
+
+    async fn kill_container(&self, id: &str, signal: &str) -> Result<()> {
+        let result = self.inner.kill_container(id, signal).await;
+        self.audited("kill_container", &format!("{} with {}", self.container(id), signal), result)
+    }
+
+    async fn rename_container(&self, id: &str, name: &str) -> Result<()> {
+        let result = self.inner.rename_container(id, name).await;
+        self.audited("rename_container", &format!("{} -> {}", self.container(id), name), result)
+    }
+
+    async fn update_container(&self, id: &str, limits: &ResourceLimits) -> Result<()> {
+        let result = self.inner.update_container(id, limits).await;
+        self.audited("update_container", &format!("{} with {}", self.container(id), limits), result)
+    }
+
+    async fn commit_container(&self, id: &str, repository: &str, tag: &str) -> Result<()> {
+        let result = self.inner.commit_container(id, repository, tag).await;
+        self.audited("commit_container", &format!("{} to {}:{}", self.container(id), repository, tag), result)
+    }
+
+    async fn run_container(&self, spec: &RunSpec) -> Result<String> {
+        let result = self.inner.run_container(spec).await;
+        self.audited("run_container", &format!("{} from {}", spec.name, spec.image), result)
+    }
+
+    async fn recreate_container(&self, id: &str, spec: &RunSpec) -> Result<String> {
+        let result = self.inner.recreate_container(id, spec).await;
+        self.audited("recreate_container", &format!("{} from {}", self.container(id), spec.image), result)
+    }
+
+    async fn pull_image(&self, image: &str) -> Result<String> {
+        let result = self.inner.pull_image(image).await;
+        self.audited("pull_image", image, result)
+    }
+
+    async fn registry_logins(&self) -> Result<Vec<RegistryLogin>> {
+        self.inner.registry_logins().await
+    }
+
+    async fn registry_login(&self, server: &str, username: &str, password: &str) -> Result<()> {
+        let result = self.inner.registry_login(server, username, password).await;
+        self.audited("registry_login", &format!("{} at {}", username, server), result)
+    }
+
+    async fn registry_logout(&self, server: &str) -> Result<()> {
+        let result = self.inner.registry_logout(server).await;
+        self.audited("registry_logout", server, result)
+    }
+
+    async fn checkpoint_container(&self, id: &str, checkpoint: &str) -> Result<()> {
+        let result = self.inner.checkpoint_container(id, checkpoint).await;
+        self.audited("checkpoint_container", &format!("{} as {}", self.container(id), checkpoint), result)
+    }
+
+    async fn restore_container(&self, id: &str, checkpoint: &str) -> Result<()> {
+        let result = self.inner.restore_container(id, checkpoint).await;
+        self.audited("restore_container", &format!("{} from {}", self.container(id), checkpoint), result)
+    }
+
+    async fn checkpoints(&self, id: &str) -> Result<Vec<String>> {
+        self.inner.checkpoints(id).await
+    }
+
+    /// Only the start is logged; what is typed into a shell is not.
+    async fn exec(&self, id: &str, command: &[&str]) -> Result<ExecStreams> {
+        let result = self.inner.exec(id, command).await;
+        self.audited("exec", &format!("{}: {}", self.container(id), command.join(" ")), result)
+    }
+
+    async fn attach(&self, id: &str, stdin: bool) -> Result<ExecStreams> {
+        // Only typing into the main process can change anything.
+        if !stdin {
+            return self.inner.attach(id, stdin).await;
+        }
+        let result = self.inner.attach(id, stdin).await;
+        self.audited("attach", &self.container(id), result)
+    }
+
+    async fn remove_container(&self, id: &str) -> Result<()> {
+        let result = self.inner.remove_container(id).await;
+        self.audited("remove_container", &self.container(id), result)
+    }
+
+    async fn force_remove_container(&self, id: &str) -> Result<()> {
+        let result = self.inner.force_remove_container(id).await;
+        self.audited("force_remove_container", &self.container(id), result)
+    }
+
+    async fn prune_containers(&self) -> Result<PruneReport> {
+        let result = self.inner.prune_containers().await;
+        let target = match &result {
+            Ok(report) => format!("stopped containers ({} removed)", report.containers.len()),
+            Err(_) => "stopped containers".to_string(),
+        };
+        self.audited("prune_containers", &target, result)
+    }
+
+    async fn remove_volume(&self, name: &str) -> Result<()> {
+        let result = self.inner.remove_volume(name).await;
+        self.audited("remove_volume", name, result)
+    }
+
+    async fn remove_network(&self, id: &str) -> Result<()> {
+        let result = self.inner.remove_network(id).await;
+        self.audited("remove_network", id, result)
+    }
+
+    async fn remove_image(&self, id: &str) -> Result<()> {
+        let result = self.inner.remove_image(id).await;
+        self.audited("remove_image", id, result)
+    }
+
+    async fn update_service_image(&self, service: &str, image: &str) -> Result<()> {
+        let result = self.inner.update_service_image(service, image).await;
+        self.audited("update_service_image", &format!("{} -> {}", service, image), result)
+    }
+
+    async fn service_update_status(&self, service: &str) -> Result<Option<ServiceUpdateStatus>> {
+        self.inner.service_update_status(service).await
+    }
+
+    fn api_metrics(&self) -> Option<&ApiMetrics> {
+        self.inner.api_metrics()
+    }
+
+    fn pull_logins(&self) -> Option<&PullLogins> {
+        self.inner.pull_logins()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::mock::{container, MockBackend, Snapshot};
+
+    #[tokio::test]
+    async fn mutating_calls_are_logged_with_names_and_results() {
+        let path = env::temp_dir().join(format!("dockertop-audit-{}.log", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mock = MockBackend::new([Snapshot::Containers(vec![container("web")])]);
+        *mock.failing_removals.lock().unwrap() = vec!["web-id".to_string()];
+        let backend = AuditBackend::new(Box::new(mock), Arc::new(AuditLog::open(&path).unwrap()));
+
+        backend.stop_container("web-id").await.unwrap();
+        backend.containers().await.unwrap();
+        backend.inspect_container("web-id").await.unwrap();
+        backend.kill_container("web-id", "SIGHUP").await.unwrap();
+        assert!(backend.remove_container("web-id").await.is_err());
+
+        let log = fs::read_to_string(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        let entries: Vec<(String, String, String)> = log
+            .lines()
+            .map(|line| {
+                let entry: serde_json::Value = serde_json::from_str(line).unwrap();
+                let field = |key: &str| entry[key].as_str().unwrap().to_string();
+                (field("action"), field("target"), field("result"))
+            })
+            .collect();
+        let expected = [
+            ("stop_container", "web-id", "ok"),
+            ("kill_container", "web (web-id) with SIGHUP", "ok"),
+            ("remove_container", "web (web-id)", "web-id is in use"),
+        ];
+        assert_eq!(entries.len(), expected.len(), "{}", log);
+        for (entry, (action, target, result)) in entries.iter().zip(expected) {
+            assert_eq!((entry.0.as_str(), entry.1.as_str(), entry.2.as_str()), (action, target, result));
+        }
+    }
+}
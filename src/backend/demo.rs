@@ -0,0 +1,1430 @@
+//! Simulated containers for `--demo`, used for screenshots, UI work and
+//! trying dockertop on machines without Docker.
+
+use super::{
+    ContainerBackend, ContainerEvent, ContainerStats, DiskUsage, ExecStreams, InterfaceCounters, IoCounters, LogLine,
+    MemoryBreakdown, Mount, NetworkAttachment, PruneReport, RegistryLogin, ResourceLimits, RunSpec, CPU_PERIOD,
+};
+use crate::registries::PullLogins;
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use bollard::models::{
+    ContainerConfig, ContainerInspectResponse, ContainerState, ContainerTopResponse, Health, HealthStatusEnum, HostConfig, ImageSummary, Ipam, IpamConfig, Network, Port, PortTypeEnum, ServiceUpdateStatus, ThrottleDevice, Volume,
+};
+use futures::stream::{self, BoxStream, StreamExt};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+const MB: u64 = 1024 * 1024;
+const HOST_MEMORY: u64 = 16 * 1024 * MB;
+const HOST_CORES: usize = 4;
+
+/// Small xorshift generator; the demo only needs plausible noise.
+struct Rng(u64);
+
+impl Rng {
+    fn seeded() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+        Self(nanos | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// A float uniformly distributed in `[0, 1)`.
+    fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+struct DemoContainer {
+    /// The name the simulation knows the container by.
+    name: &'static str,
+    /// Set by `docker rename`; the simulation keeps going by `name`.
+    renamed: Option<String>,
+    image: &'static str,
+    /// The image `image` referred to when the container was created.
+    image_id: String,
+    port: Option<i64>,
+    /// Typical CPU usage the random walk is pulled towards.
+    base_cpu: f64,
+    base_memory: u64,
+    /// 0 for no limit.
+    memory_limit: u64,
+    /// Set by `docker update`; 0 for the default.
+    cpu_shares: i64,
+    /// Microseconds per [`CPU_PERIOD`], 0 for none. Caps `cpu`, where 100%
+    /// is one CPU.
+    cpu_quota: i64,
+    running: bool,
+    /// Frozen by `docker pause`: still up, but using no CPU.
+    paused: bool,
+    cpu: f64,
+    memory: u64,
+    uptime_ticks: u64,
+    io: IoCounters,
+    /// Nanoseconds of CPU time per core.
+    cpu_cores: [u64; HOST_CORES],
+    /// Unix milliseconds of the latest sample.
+    sampled_at: i64,
+    latency_ms: u64,
+}
+
+impl DemoContainer {
+    fn display_name(&self) -> &str {
+        self.renamed.as_deref().unwrap_or(self.name)
+    }
+}
+
+struct Simulation {
+    rng: Rng,
+    containers: Vec<DemoContainer>,
+    created: i64,
+    /// IDs and names of everything removed during the session.
+    removed: HashSet<String>,
+    /// `system df` calls so far; the database volume grows with each.
+    disk_samples: u64,
+    /// Container index and event, oldest first.
+    events: Vec<(usize, ContainerEvent)>,
+    /// Container index and checkpoint name.
+    checkpoints: Vec<(usize, String)>,
+    /// `repository:tag` of the images committed from containers.
+    committed: Vec<String>,
+    /// `repository:tag` of the images pulled, each of which then refers to
+    /// a newer image than the containers were created from.
+    pulled: HashSet<String>,
+    logins: Vec<RegistryLogin>,
+}
+
+pub struct DemoBackend {
+    simulation: Mutex<Simulation>,
+    pull_logins: PullLogins,
+}
+
+impl DemoBackend {
+    pub fn new() -> Self {
+        Self {
+            simulation: Mutex::new(Simulation::new()),
+            pull_logins: PullLogins::default(),
+        }
+    }
+
+    fn remove(&self, id: &str) -> Result<()> {
+        if !self.simulation.lock().unwrap().removed.insert(id.to_string()) {
+            bail!("No such object: {}", id);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ContainerBackend for DemoBackend {
+    async fn containers(&self) -> Result<Vec<ContainerStats>> {
+        Ok(self.simulation.lock().unwrap().tick())
+    }
+
+    async fn inspect_container(&self, id: &str) -> Result<ContainerInspectResponse> {
+        let simulation = self.simulation.lock().unwrap();
+        let index = (0..simulation.containers.len())
+            .find(|&i| demo_id(i) == id)
+            .context("No such container")?;
+        let container = &simulation.containers[index];
+        // Every demo container has a healthcheck that passes after a few seconds.
+        let health = container.running.then(|| Health {
+            status: Some(if container.uptime_ticks < 3 {
+                HealthStatusEnum::STARTING
+            } else {
+                HealthStatusEnum::HEALTHY
+            }),
+            ..Default::default()
+        });
+        Ok(ContainerInspectResponse {
+            id: Some(id.to_string()),
+            name: Some(format!("/{}", container.display_name())),
+            image: Some(container.image.to_string()),
+            config: Some(ContainerConfig {
+                image: Some(container.image.to_string()),
+                env: Some(demo_env(container.name)),
+                ..Default::default()
+            }),
+            state: Some(ContainerState {
+                running: Some(container.running),
+                health,
+                ..Default::default()
+            }),
+            host_config: Some(HostConfig {
+                cpu_shares: Some(container.cpu_shares),
+                cpu_period: Some(if container.cpu_quota > 0 { CPU_PERIOD } else { 0 }),
+                cpu_quota: Some(container.cpu_quota),
+                memory: Some(container.memory_limit as i64),
+                blkio_device_read_bps: match demo_bandwidth_caps(container.name).0 {
+                    0 => None,
+                    rate => Some(vec![ThrottleDevice {
+                        path: Some("/dev/sda".to_string()),
+                        rate: Some(rate as i64),
+                    }]),
+                },
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+    }
+
+    async fn networks(&self) -> Result<Vec<Network>> {
+        let network = |name: &str, driver: &str, subnets: &[&str]| Network {
+            name: Some(name.to_string()),
+            driver: Some(driver.to_string()),
+            scope: Some("local".to_string()),
+            enable_ipv6: Some(subnets.iter().any(|s| s.contains(':'))),
+            ipam: (!subnets.is_empty()).then(|| Ipam {
+                config: Some(
+                    subnets
+                        .iter()
+                        .map(|subnet| IpamConfig {
+                            subnet: Some(subnet.to_string()),
+                            ..Default::default()
+                        })
+                        .collect(),
+                ),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let removed = &self.simulation.lock().unwrap().removed;
+        Ok([
+            network("bridge", "bridge", &["172.17.0.0/16"]),
+            network("host", "host", &[]),
+            network("none", "null", &[]),
+            network("shop_default", "bridge", &["172.20.0.0/16", "fd00:20::/64"]),
+            network("shop_backend", "bridge", &["172.21.0.0/16"]),
+            network("legacy_frontend", "bridge", &["172.22.0.0/16"]),
+        ]
+        .into_iter()
+        .filter(|n| !removed.contains(n.name.as_deref().unwrap_or_default()))
+        .collect())
+    }
+
+    async fn volumes(&self) -> Result<Vec<Volume>> {
+        let removed = &self.simulation.lock().unwrap().removed;
+        Ok(["shop_pgdata", "shop_redis", "prometheus_data", "old_uploads"]
+            .into_iter()
+            .filter(|name| !removed.contains(*name))
+            .map(|name| Volume {
+                name: name.to_string(),
+                driver: "local".to_string(),
+                mountpoint: format!("/var/lib/docker/volumes/{}/_data", name),
+                ..Default::default()
+            })
+            .collect())
+    }
+
+    /// The images in use plus a superseded tag and a dangling layer.
+    async fn images(&self) -> Result<Vec<ImageSummary>> {
+        let simulation = self.simulation.lock().unwrap();
+        let mut tags: Vec<&str> = simulation.containers.iter().map(|c| c.image).collect();
+        tags.extend(["node:18-alpine", "<none>:<none>"]);
+        tags.extend(simulation.committed.iter().map(String::as_str));
+        tags.dedup();
+        Ok(tags
+            .into_iter()
+            .filter(|tag| !simulation.removed.contains(&demo_image_id(tag)))
+            .enumerate()
+            .map(|(i, tag)| ImageSummary {
+                id: demo_image_id(tag),
+                repo_tags: if tag == "<none>:<none>" { Vec::new() } else { vec![tag.to_string()] },
+                size: (40 + (i as i64 * 73) % 400) * MB as i64,
+                ..Default::default()
+            })
+            .collect())
+    }
+
+    /// Steady growth with some jitter, like a database nobody vacuums.
+    async fn disk_usage(&self) -> Result<DiskUsage> {
+        let mut simulation = self.simulation.lock().unwrap();
+        simulation.disk_samples += 1;
+        let samples = simulation.disk_samples;
+        let jitter = simulation.rng.next() % (2 * MB);
+        Ok(DiskUsage {
+            images: 1900 * MB,
+            containers: 350 * MB + samples * MB + jitter,
+            volumes: 1200 * MB + samples * 4 * MB,
+            build_cache: 600 * MB,
+        })
+    }
+
+    async fn container_events(&self, id: &str, since: i64) -> Result<Vec<ContainerEvent>> {
+        let simulation = self.simulation.lock().unwrap();
+        Ok(simulation
+            .events
+            .iter()
+            .filter(|(index, event)| demo_id(*index) == id && event.time >= since)
+            .map(|(_, event)| event.clone())
+            .collect())
+    }
+
+    /// Access-log style lines at a per-container rate; the worker is chatty.
+    fn follow_logs(&self, id: &str, tail: usize, timestamps: bool) -> BoxStream<'static, Result<LogLine>> {
+        let simulation = self.simulation.lock().unwrap();
+        let Some(container) = (0..simulation.containers.len())
+            .find(|&i| demo_id(i) == id)
+            .map(|i| &simulation.containers[i])
+        else {
+            return stream::empty().boxed();
+        };
+        let name = container.name;
+        let lines_per_sec = match name {
+            "shop-worker-1" => 40,
+            "shop-web-1" => 12,
+            "shop-api-1" | "shop-api-2" => 5,
+            _ => 1,
+        };
+        let interval = std::time::Duration::from_millis(1000 / lines_per_sec);
+        let history = tail.min(50) as u64;
+        // The backlog is dated as if it had been written at the live pace.
+        let line = move |n: u64, at: chrono::DateTime<chrono::Utc>| {
+            let mut line = demo_log_line(name, n, timestamps.then_some(at));
+            line.text.push('\n');
+            line
+        };
+        let now = chrono::Utc::now();
+        let backlog = stream::iter((0..history).map(move |n| Ok(line(n, now - interval * (history - n) as u32))));
+        let live = stream::unfold(history, move |n| async move {
+            tokio::time::sleep(interval).await;
+            Some((Ok(line(n, chrono::Utc::now())), n + 1))
+        });
+        backlog.chain(live).boxed()
+    }
+
+    async fn recent_logs(&self, id: &str, tail: usize, since: i64, timestamps: bool) -> Result<Vec<LogLine>> {
+        let simulation = self.simulation.lock().unwrap();
+        let index = simulation.index(id)?;
+        let name = simulation.containers[index].name;
+        let count = tail.min(5000) as u64;
+        // Dated a second apart, ending now.
+        let now = chrono::Utc::now();
+        Ok((0..count)
+            .map(|n| (n, now - chrono::Duration::seconds((count - n) as i64)))
+            .filter(|(_, at)| at.timestamp() >= since)
+            .map(|(n, at)| demo_log_line(name, n, timestamps.then_some(at)))
+            .collect())
+    }
+
+    /// A day's worth of lines, a minute apart.
+    async fn logs_since(&self, id: &str, since: i64) -> Result<Vec<LogLine>> {
+        let simulation = self.simulation.lock().unwrap();
+        let index = simulation.index(id)?;
+        let name = simulation.containers[index].name;
+        let now = chrono::Utc::now();
+        Ok((0..24 * 60)
+            .map(|n| (n, now - chrono::Duration::minutes(24 * 60 - n as i64)))
+            .filter(|(_, at)| at.timestamp() >= since)
+            .map(|(n, at)| demo_log_line(name, n, Some(at)))
+            .collect())
+    }
+
+    async fn processes(&self, id: &str) -> Result<ContainerTopResponse> {
+        let simulation = self.simulation.lock().unwrap();
+        let container = (0..simulation.containers.len())
+            .find(|&i| demo_id(i) == id)
+            .map(|i| &simulation.containers[i])
+            .context("No such container")?;
+        let commands: &[&str] = match container.name {
+            "shop-web-1" => &["nginx: master process nginx -g daemon off;", "nginx: worker process", "nginx: worker process"],
+            "shop-api-1" | "shop-api-2" => &["node server.js"],
+            "shop-db-1" => &["postgres", "postgres: checkpointer", "postgres: walwriter", "postgres: autovacuum launcher"],
+            "shop-cache-1" => &["redis-server *:6379"],
+            "shop-worker-1" => &["python -m worker", "python -m worker --child", "python -m worker --child"],
+            "metrics" => &["/bin/prometheus --config.file=/etc/prometheus/prometheus.yml"],
+            _ => &[],
+        };
+        let titles = ["UID", "PID", "PPID", "C", "STIME", "TTY", "TIME", "CMD"];
+        Ok(ContainerTopResponse {
+            titles: Some(titles.iter().map(|t| t.to_string()).collect()),
+            processes: Some(
+                commands
+                    .iter()
+                    .enumerate()
+                    .map(|(i, cmd)| {
+                        let pid = 4200 + i;
+                        let ppid = if i == 0 { "4100".to_string() } else { "4200".to_string() };
+                        ["root", &pid.to_string(), &ppid, "0", "09:12", "?", "00:00:03", cmd]
+                            .iter()
+                            .map(|f| f.to_string())
+                            .collect()
+                    })
+                    .collect(),
+            ),
+        })
+    }
+
+    /// Whatever the command, the demo runs a pretend shell that knows a
+    /// handful of commands.
+    async fn start_container(&self, id: &str) -> Result<()> {
+        self.simulation.lock().unwrap().set_running(id, true)
+    }
+
+    async fn stop_container(&self, id: &str) -> Result<()> {
+        self.simulation.lock().unwrap().set_running(id, false)
+    }
+
+    async fn restart_container(&self, id: &str) -> Result<()> {
+        let mut simulation = self.simulation.lock().unwrap();
+        let index = simulation.index(id)?;
+        if simulation.containers[index].running {
+            simulation.set_running(id, false)?;
+        }
+        simulation.set_running(id, true)?;
+        let container = &mut simulation.containers[index];
+        container.memory = container.base_memory;
+        container.cpu = container.base_cpu;
+        Ok(())
+    }
+
+    async fn pause_container(&self, id: &str) -> Result<()> {
+        self.simulation.lock().unwrap().set_paused(id, true)
+    }
+
+    async fn unpause_container(&self, id: &str) -> Result<()> {
+        self.simulation.lock().unwrap().set_paused(id, false)
+    }
+
+    async fn kill_container(&self, id: &str, signal: &str) -> Result<()> {
+        self.simulation.lock().unwrap().kill(id, signal)
+    }
+
+    async fn rename_container(&self, id: &str, name: &str) -> Result<()> {
+        self.simulation.lock().unwrap().rename(id, name)
+    }
+
+    async fn update_container(&self, id: &str, limits: &ResourceLimits) -> Result<()> {
+        self.simulation.lock().unwrap().update(id, limits)
+    }
+
+    async fn commit_container(&self, id: &str, repository: &str, tag: &str) -> Result<()> {
+        let mut simulation = self.simulation.lock().unwrap();
+        let index = simulation.index(id)?;
+        let image = format!("{}:{}", repository, tag);
+        if !simulation.committed.contains(&image) {
+            simulation.committed.push(image);
+        }
+        simulation.push_event(index, "commit", None);
+        Ok(())
+    }
+
+    async fn run_container(&self, spec: &RunSpec) -> Result<String> {
+        self.simulation.lock().unwrap().run(spec)
+    }
+
+    async fn recreate_container(&self, id: &str, spec: &RunSpec) -> Result<String> {
+        self.simulation.lock().unwrap().recreate(id, spec)
+    }
+
+    /// Every image has a newer version to pull, once.
+    async fn pull_image(&self, image: &str) -> Result<String> {
+        let mut simulation = self.simulation.lock().unwrap();
+        simulation.pulled.insert(image.to_string());
+        Ok(simulation.image_id(image))
+    }
+
+    async fn registry_logins(&self) -> Result<Vec<RegistryLogin>> {
+        Ok(self.simulation.lock().unwrap().logins.clone())
+    }
+
+    /// Any password will do but `wrong`.
+    async fn registry_login(&self, server: &str, username: &str, password: &str) -> Result<()> {
+        if password == "wrong" {
+            bail!("Error response from daemon: Get \"https://{}/v2/\": unauthorized: incorrect username or password", server);
+        }
+        let mut simulation = self.simulation.lock().unwrap();
+        simulation.logins.retain(|l| l.server != server);
+        simulation.logins.push(RegistryLogin {
+            server: server.to_string(),
+            username: username.to_string(),
+            store: "desktop".to_string(),
+        });
+        Ok(())
+    }
+
+    async fn registry_logout(&self, server: &str) -> Result<()> {
+        let mut simulation = self.simulation.lock().unwrap();
+        if !simulation.logins.iter().any(|l| l.server == server) {
+            bail!("Not logged in to {}", server);
+        }
+        simulation.logins.retain(|l| l.server != server);
+        Ok(())
+    }
+
+    async fn checkpoint_container(&self, id: &str, checkpoint: &str) -> Result<()> {
+        self.simulation.lock().unwrap().checkpoint(id, checkpoint)
+    }
+
+    async fn restore_container(&self, id: &str, checkpoint: &str) -> Result<()> {
+        self.simulation.lock().unwrap().restore(id, checkpoint)
+    }
+
+    async fn checkpoints(&self, id: &str) -> Result<Vec<String>> {
+        let simulation = self.simulation.lock().unwrap();
+        let index = simulation.index(id)?;
+        Ok(simulation
+            .checkpoints
+            .iter()
+            .filter(|(i, _)| *i == index)
+            .map(|(_, name)| name.clone())
+            .collect())
+    }
+
+    /// An interactive shell, or a one-off `sh -c` script of the same
+    /// commands. Pipes and `||` are ignored: the first command answers.
+    async fn exec(&self, id: &str, command: &[&str]) -> Result<ExecStreams> {
+        let simulation = self.simulation.lock().unwrap();
+        let container = (0..simulation.containers.len())
+            .find(|&i| demo_id(i) == id)
+            .map(|i| &simulation.containers[i])
+            .context("No such container")?;
+        if !container.running {
+            bail!("Container {} is not running", container.name);
+        }
+        let hostname = id[..12].to_string();
+        let (input, shell) = tokio::io::duplex(4096);
+        if let ["sh", "-c", script] = command {
+            let output = script.lines().filter_map(|line| demo_shell(&hostname, line.trim())).collect::<String>();
+            return Ok(ExecStreams {
+                output: stream::once(async move { Ok(output) }).boxed(),
+                input: Box::pin(input),
+            });
+        }
+        let output = stream::unfold(BufReader::new(shell).lines(), move |mut lines| {
+            let hostname = hostname.clone();
+            async move {
+                let line = lines.next_line().await.ok()??;
+                let reply = demo_shell(&hostname, line.trim())?;
+                Some((Ok(reply), lines))
+            }
+        });
+        Ok(ExecStreams {
+            output: output.boxed(),
+            input: Box::pin(input),
+        })
+    }
+
+    async fn attach(&self, id: &str, stdin: bool) -> Result<ExecStreams> {
+        let name = {
+            let simulation = self.simulation.lock().unwrap();
+            let container = (0..simulation.containers.len())
+                .find(|&i| demo_id(i) == id)
+                .map(|i| &simulation.containers[i])
+                .context("No such container")?;
+            if !container.running {
+                bail!("Container {} is not running", container.name);
+            }
+            container.name
+        };
+        let logs = self.follow_logs(id, 0, false).map(|line| line.map(|line| line.text));
+        if !stdin {
+            return Ok(ExecStreams {
+                output: logs.boxed(),
+                input: Box::pin(tokio::io::sink()),
+            });
+        }
+        // The main process reads its stdin and says what it got.
+        let (input, process) = tokio::io::duplex(4096);
+        let replies = stream::unfold(BufReader::new(process).lines(), move |mut lines| async move {
+            let line = lines.next_line().await.ok()??;
+            Some((Ok(format!("{}: read {:?}\n", name, line)), lines))
+        });
+        Ok(ExecStreams {
+            output: stream::select(logs, replies).boxed(),
+            input: Box::pin(input),
+        })
+    }
+
+    async fn remove_container(&self, id: &str) -> Result<()> {
+        {
+            let simulation = self.simulation.lock().unwrap();
+            let container = &simulation.containers[simulation.index(id)?];
+            if container.running {
+                bail!("Cannot remove running container {}: stop it first", container.name);
+            }
+        }
+        self.remove(id)
+    }
+
+    async fn force_remove_container(&self, id: &str) -> Result<()> {
+        {
+            let mut simulation = self.simulation.lock().unwrap();
+            let index = simulation.index(id)?;
+            if simulation.containers[index].running {
+                simulation.kill(id, "SIGKILL")?;
+            }
+        }
+        self.remove(id)
+    }
+
+    async fn remove_volume(&self, name: &str) -> Result<()> {
+        self.remove(name)
+    }
+
+    async fn remove_network(&self, id: &str) -> Result<()> {
+        self.remove(id)
+    }
+
+    async fn remove_image(&self, id: &str) -> Result<()> {
+        self.remove(id)
+    }
+
+    async fn prune_containers(&self) -> Result<PruneReport> {
+        let mut simulation = self.simulation.lock().unwrap();
+        let stopped: Vec<String> = (0..simulation.containers.len())
+            .filter(|&i| !simulation.containers[i].running)
+            .map(demo_id)
+            .filter(|id| !simulation.removed.contains(id))
+            .collect();
+        simulation.removed.extend(stopped.iter().cloned());
+        Ok(PruneReport {
+            // Writable layers of a few dozen megabytes each.
+            space_reclaimed: stopped.len() as u64 * 38 * MB,
+            containers: stopped,
+        })
+    }
+
+    async fn update_service_image(&self, _service: &str, _image: &str) -> Result<()> {
+        bail!("Service updates are not available in demo mode")
+    }
+
+    async fn service_update_status(&self, _service: &str) -> Result<Option<ServiceUpdateStatus>> {
+        Ok(None)
+    }
+
+    fn pull_logins(&self) -> Option<&PullLogins> {
+        Some(&self.pull_logins)
+    }
+}
+
+impl Simulation {
+    fn new() -> Self {
+        let specs = [
+            ("shop-web-1", "nginx:1.25", Some(8080), 2.0, 24 * MB, 256 * MB, true),
+            ("shop-api-1", "node:20-alpine", Some(3000), 18.0, 310 * MB, 1024 * MB, true),
+            ("shop-api-2", "node:20-alpine", Some(3001), 15.0, 295 * MB, 1024 * MB, true),
+            ("shop-db-1", "postgres:16", Some(5432), 6.0, 520 * MB, 2048 * MB, true),
+            ("shop-cache-1", "redis:7", Some(6379), 1.5, 48 * MB, 512 * MB, true),
+            ("shop-worker-1", "python:3.12-slim", None, 35.0, 410 * MB, 768 * MB, true),
+            // Started without `--memory`, as many are.
+            ("metrics", "prom/prometheus:v2.51.0", Some(9090), 4.0, 180 * MB, 0, true),
+            ("migrate-once", "shop/migrations:latest", None, 0.0, 0, 256 * MB, false),
+            // Staged next version of the web front end, for trying blue/green switches.
+            ("shop-web-green", "nginx:1.27", Some(8081), 2.0, 22 * MB, 256 * MB, false),
+            // A single-node cluster using Docker as its runtime: a pod's
+            // pause container and its workload.
+            ("k8s_POD_coredns-5d78c9869d-x2x8k_kube-system_0", "registry.k8s.io/pause:3.9", None, 0.0, MB, 0, true),
+            (
+                "k8s_coredns_coredns-5d78c9869d-x2x8k_kube-system_0",
+                "registry.k8s.io/coredns/coredns:v1.11.1",
+                Some(9153),
+                1.0,
+                22 * MB,
+                170 * MB,
+                true,
+            ),
+        ];
+        let mut rng = Rng::seeded();
+        let mut containers: Vec<DemoContainer> = specs
+            .into_iter()
+            .map(|(name, image, port, base_cpu, base_memory, memory_limit, running)| DemoContainer {
+                name,
+                image,
+                image_id: demo_image_id(image),
+                port,
+                base_cpu,
+                base_memory,
+                memory_limit,
+                cpu_shares: 0,
+                cpu_quota: 0,
+                renamed: None,
+                running,
+                paused: false,
+                cpu: base_cpu,
+                memory: base_memory,
+                uptime_ticks: rng.next() % 20_000,
+                io: IoCounters::default(),
+                cpu_cores: [0; HOST_CORES],
+                sampled_at: chrono::Utc::now().timestamp_millis(),
+                latency_ms: 10,
+            })
+            .collect();
+        let now = unix_now();
+        let mut events = Vec::new();
+        let mut event = |index: usize, time: i64, action: &str, attributes: &[(&str, &str)]| {
+            let event = ContainerEvent {
+                time,
+                action: action.to_string(),
+                attributes: attributes.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            };
+            events.push((index, event));
+        };
+        let ago = |minutes: i64| now - minutes * 60;
+
+        // The worker has been flapping all afternoon, more often lately, and
+        // takes longer to become healthy since its last image update.
+        let worker = containers.iter().position(|c| c.name == "shop-worker-1").unwrap_or(0);
+        for (minutes_ago, healthy_after, image) in [
+            (290, 6, "python:3.11-slim"),
+            (205, 7, "python:3.11-slim"),
+            (130, 6, "python:3.11-slim"),
+            (75, 19, "python:3.12-slim"),
+            (42, 18, "python:3.12-slim"),
+            (16, 21, "python:3.12-slim"),
+        ] {
+            let image = ("image", image);
+            event(worker, ago(minutes_ago), "oom", &[image]);
+            event(worker, ago(minutes_ago), "die", &[image, ("exitCode", "137")]);
+            event(worker, ago(minutes_ago - 1), "start", &[image]);
+            event(worker, ago(minutes_ago - 1) + healthy_after, "health_status: healthy", &[image]);
+        }
+        containers[worker].uptime_ticks = 15 * 30;
+
+        let migrate = containers.iter().position(|c| c.name == "migrate-once").unwrap_or(0);
+        event(migrate, ago(125) - 2, "create", &[]);
+        event(migrate, ago(125), "start", &[]);
+        event(migrate, ago(120), "die", &[("exitCode", "0")]);
+
+        let db = containers.iter().position(|c| c.name == "shop-db-1").unwrap_or(0);
+        event(db, ago(180), "exec_create: pg_dump -U shop shop", &[]);
+        event(db, ago(180), "exec_start: pg_dump -U shop shop", &[]);
+        event(db, ago(179), "exec_die", &[("exitCode", "0")]);
+
+        for api in ["shop-api-1", "shop-api-2"] {
+            let api = containers.iter().position(|c| c.name == api).unwrap_or(0);
+            event(api, ago(95), "health_status: unhealthy", &[]);
+            event(api, ago(93), "health_status: healthy", &[]);
+        }
+        events.sort_by_key(|(_, e)| e.time);
+
+        Self {
+            rng,
+            containers,
+            created: now - 86_400,
+            removed: HashSet::new(),
+            disk_samples: 0,
+            events,
+            checkpoints: Vec::new(),
+            committed: Vec::new(),
+            pulled: HashSet::new(),
+            logins: demo_logins(),
+        }
+    }
+
+    /// The ID of the image `tag` refers to now.
+    fn image_id(&self, tag: &str) -> String {
+        if self.pulled.contains(tag) {
+            demo_image_id(&format!("{} (pulled)", tag))
+        } else {
+            demo_image_id(tag)
+        }
+    }
+
+    fn index(&self, id: &str) -> Result<usize> {
+        (0..self.containers.len())
+            .find(|&i| demo_id(i) == id && !self.removed.contains(id))
+            .context("No such container")
+    }
+
+    fn push_event(&mut self, index: usize, action: &str, exit_code: Option<&str>) {
+        let mut attributes = HashMap::from([("image".to_string(), self.containers[index].image.to_string())]);
+        if let Some(code) = exit_code {
+            attributes.insert("exitCode".to_string(), code.to_string());
+        }
+        let event = ContainerEvent {
+            time: unix_now(),
+            action: action.to_string(),
+            attributes,
+        };
+        self.events.push((index, event));
+    }
+
+    fn set_running(&mut self, id: &str, running: bool) -> Result<()> {
+        let index = self.index(id)?;
+        let container = &mut self.containers[index];
+        if container.running == running {
+            bail!("Container {} is already {}", container.name, if running { "running" } else { "stopped" });
+        }
+        container.running = running;
+        container.paused = false;
+        if running {
+            container.uptime_ticks = 0;
+            container.io = IoCounters::default();
+            self.push_event(index, "start", None);
+        } else {
+            self.push_event(index, "die", Some("0"));
+            self.push_event(index, "stop", None);
+        }
+        Ok(())
+    }
+
+    fn set_paused(&mut self, id: &str, paused: bool) -> Result<()> {
+        let index = self.index(id)?;
+        let container = &mut self.containers[index];
+        if !container.running {
+            bail!("Container {} is not running", container.name);
+        }
+        if container.paused == paused {
+            bail!("Container {} is {}", container.name, if paused { "already paused" } else { "not paused" });
+        }
+        container.paused = paused;
+        self.push_event(index, if paused { "pause" } else { "unpause" }, None);
+        Ok(())
+    }
+
+    /// Adds a running container. The simulation knows containers by static
+    /// names, so the few a session runs are leaked.
+    fn run(&mut self, spec: &RunSpec) -> Result<String> {
+        if self.containers.iter().enumerate().any(|(i, c)| c.display_name() == spec.name && !self.removed.contains(&demo_id(i))) {
+            bail!("Conflict: the container name \"/{}\" is already in use", spec.name);
+        }
+        self.containers.push(DemoContainer {
+            name: Box::leak(spec.name.clone().into_boxed_str()),
+            renamed: None,
+            image: Box::leak(spec.image.clone().into_boxed_str()),
+            image_id: self.image_id(&spec.image),
+            port: spec.ports.first().map(|p| i64::from(p.container_port)),
+            base_cpu: 1.0,
+            base_memory: 40 * MB,
+            memory_limit: 0,
+            cpu_shares: 0,
+            cpu_quota: 0,
+            running: true,
+            paused: false,
+            cpu: 1.0,
+            memory: 40 * MB,
+            uptime_ticks: 0,
+            io: IoCounters::default(),
+            cpu_cores: [0; HOST_CORES],
+            sampled_at: chrono::Utc::now().timestamp_millis(),
+            latency_ms: 10,
+        });
+        let index = self.containers.len() - 1;
+        self.push_event(index, "create", None);
+        self.push_event(index, "start", None);
+        Ok(demo_id(index))
+    }
+
+    /// Sets the old container aside, stopped, and adds a fresh copy of it
+    /// with the image and published port of `spec`. The copy goes by the
+    /// same simulated name, so it logs and behaves as the old one did.
+    fn recreate(&mut self, id: &str, spec: &RunSpec) -> Result<String> {
+        let index = self.index(id)?;
+        self.rename(id, &format!("{}-replaced-{}", spec.name, &id[..12]))?;
+        let running = self.containers[index].running;
+        if running {
+            self.set_running(id, false)?;
+        }
+        let old = &self.containers[index];
+        let copy = DemoContainer {
+            name: old.name,
+            renamed: (spec.name != old.name).then(|| spec.name.clone()),
+            image: Box::leak(spec.image.clone().into_boxed_str()),
+            image_id: self.image_id(&spec.image),
+            port: spec.ports.first().map(|p| i64::from(p.container_port)),
+            base_cpu: old.base_cpu,
+            base_memory: old.base_memory,
+            memory_limit: old.memory_limit,
+            cpu_shares: old.cpu_shares,
+            cpu_quota: old.cpu_quota,
+            running,
+            paused: false,
+            cpu: old.base_cpu,
+            memory: old.base_memory,
+            uptime_ticks: 0,
+            io: IoCounters::default(),
+            cpu_cores: [0; HOST_CORES],
+            sampled_at: chrono::Utc::now().timestamp_millis(),
+            latency_ms: old.latency_ms,
+        };
+        self.containers.push(copy);
+        let index = self.containers.len() - 1;
+        self.push_event(index, "create", None);
+        if running {
+            self.push_event(index, "start", None);
+        }
+        Ok(demo_id(index))
+    }
+
+    fn rename(&mut self, id: &str, name: &str) -> Result<()> {
+        let index = self.index(id)?;
+        let taken = self
+            .containers
+            .iter()
+            .any(|c| c.display_name() == name);
+        if taken {
+            bail!("Conflict: the container name \"/{}\" is already in use", name);
+        }
+        self.containers[index].renamed = Some(name.to_string());
+        self.push_event(index, "rename", None);
+        Ok(())
+    }
+
+    fn update(&mut self, id: &str, limits: &ResourceLimits) -> Result<()> {
+        let index = self.index(id)?;
+        let container = &mut self.containers[index];
+        if let Some(memory) = limits.memory {
+            if memory < 6 * MB as i64 {
+                bail!("Minimum memory limit allowed is 6MB");
+            }
+            container.memory_limit = memory as u64;
+        }
+        if let Some(shares) = limits.cpu_shares {
+            container.cpu_shares = shares;
+        }
+        if let Some(quota) = limits.cpu_quota {
+            container.cpu_quota = quota.max(0);
+        }
+        self.push_event(index, "update", None);
+        Ok(())
+    }
+
+    /// The pretend services reload on SIGHUP and SIGUSR1/2 and exit on
+    /// anything else.
+    fn kill(&mut self, id: &str, signal: &str) -> Result<()> {
+        let index = self.index(id)?;
+        let Some(number) = signal_number(signal) else {
+            bail!("Invalid signal: {}", signal);
+        };
+        let container = &mut self.containers[index];
+        if !container.running {
+            bail!("Container {} is not running", container.name);
+        }
+        self.events.push((
+            index,
+            ContainerEvent {
+                time: unix_now(),
+                action: "kill".to_string(),
+                attributes: HashMap::from([("signal".to_string(), number.to_string())]),
+            },
+        ));
+        if ![1, 10, 12].contains(&number) {
+            let container = &mut self.containers[index];
+            container.running = false;
+            container.paused = false;
+            self.push_event(index, "die", Some(&(128 + number).to_string()));
+        }
+        Ok(())
+    }
+
+    fn checkpoint(&mut self, id: &str, checkpoint: &str) -> Result<()> {
+        let index = self.index(id)?;
+        if self.checkpoints.iter().any(|(i, name)| *i == index && name == checkpoint) {
+            bail!("Checkpoint {} already exists", checkpoint);
+        }
+        self.set_running(id, false)?;
+        self.push_event(index, &format!("checkpoint: {}", checkpoint), None);
+        self.checkpoints.push((index, checkpoint.to_string()));
+        Ok(())
+    }
+
+    fn restore(&mut self, id: &str, checkpoint: &str) -> Result<()> {
+        let index = self.index(id)?;
+        if !self.checkpoints.iter().any(|(i, name)| *i == index && name == checkpoint) {
+            bail!("No such checkpoint: {}", checkpoint);
+        }
+        self.set_running(id, true)
+    }
+
+    /// Advances the simulation by one refresh and returns the new stats.
+    fn tick(&mut self) -> Vec<ContainerStats> {
+        for c in &mut self.containers {
+            if !c.running {
+                continue;
+            }
+            c.uptime_ticks += 1;
+            if c.paused {
+                c.cpu = 0.0;
+                c.sampled_at = chrono::Utc::now().timestamp_millis();
+                continue;
+            }
+
+            // The database's stats lag behind now and then, as on a busy
+            // daemon: every 40 seconds, the sample freezes for 16.
+            if c.name == "shop-db-1" && c.uptime_ticks % 20 >= 12 {
+                c.latency_ms = 2000;
+                continue;
+            }
+            c.sampled_at = chrono::Utc::now().timestamp_millis();
+            c.latency_ms = 5 + self.rng.next() % 40;
+
+            // Mean-reverting random walk with the occasional burst.
+            let burst = if self.rng.unit() < 0.05 { c.base_cpu * 3.0 } else { 0.0 };
+            let noise = (self.rng.unit() - 0.5) * c.base_cpu.max(1.0);
+            c.cpu = (c.cpu + (c.base_cpu - c.cpu) * 0.3 + noise + burst).clamp(0.0, 100.0);
+            if c.cpu_quota > 0 {
+                c.cpu = c.cpu.min(c.cpu_quota as f64 / CPU_PERIOD as f64 * 100.0);
+            }
+
+            let drift = ((self.rng.unit() - 0.5) * 0.04 * c.base_memory as f64) as i64;
+            let cap = if c.memory_limit == 0 { HOST_MEMORY } else { c.memory_limit };
+            c.memory = (c.memory as i64 + drift).clamp(MB as i64, cap as i64) as u64;
+
+            // Traffic roughly follows CPU; a tick is two seconds.
+            let busy = c.cpu * 2.0 * (0.5 + self.rng.unit());
+            let (read_cap, net_cap) = demo_bandwidth_caps(c.name);
+            let capped = |bytes: f64, cap: u64| if cap > 0 { bytes.min(cap as f64 * 2.0) } else { bytes } as u64;
+            c.io.net_rx += capped(busy * 20_000.0, net_cap);
+            c.io.net_tx += capped(busy * 50_000.0, net_cap);
+            c.io.block_read += capped(busy * 4_000.0, read_cap);
+            c.io.block_write += (busy * 12_000.0) as u64;
+
+            // The single-threaded worker keeps to one core; the rest spread
+            // over all of them, unevenly. 100% is one core for two seconds.
+            let nanoseconds = c.cpu / 100.0 * 2e9;
+            if c.name == "shop-worker-1" {
+                c.cpu_cores[0] += nanoseconds as u64;
+            } else {
+                let weights: [f64; HOST_CORES] = std::array::from_fn(|_| 0.5 + self.rng.unit());
+                let total: f64 = weights.iter().sum();
+                for (core, weight) in c.cpu_cores.iter_mut().zip(weights) {
+                    *core += (nanoseconds * weight / total) as u64;
+                }
+            }
+        }
+
+        // Every so often the worker crashes and comes back. The newest is
+        // the one in use, should it have been recreated.
+        if let Some(index) = self.containers.iter().rposition(|c| c.name == "shop-worker-1") {
+            let worker = &mut self.containers[index];
+            let action = if worker.running && !worker.paused && self.rng.unit() < 0.01 {
+                worker.running = false;
+                Some(("die", Some("137")))
+            } else if !worker.running && self.rng.unit() < 0.3 {
+                worker.running = true;
+                worker.uptime_ticks = 0;
+                worker.io = IoCounters::default();
+                worker.cpu_cores = [0; HOST_CORES];
+                Some(("start", None))
+            } else {
+                None
+            };
+            if let Some((action, exit_code)) = action {
+                // The crash is the kernel's OOM killer, as its exit code says.
+                if action == "die" {
+                    self.push_event(index, "oom", None);
+                }
+                self.push_event(index, action, exit_code);
+            }
+        }
+
+        // Health checks pass on the third refresh after a start, as inspect says.
+        let healthy: Vec<usize> = (0..self.containers.len())
+            .filter(|&i| self.containers[i].running && self.containers[i].uptime_ticks == 3)
+            .collect();
+        for index in healthy {
+            self.push_event(index, "health_status: healthy", None);
+        }
+
+        self.containers
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !self.removed.contains(&demo_id(*i)))
+            .map(|(i, c)| ContainerStats {
+                id: demo_id(i),
+                name: c.display_name().to_string(),
+                cpu_usage: if c.running { c.cpu } else { 0.0 },
+                memory_usage: if c.running { c.memory } else { 0 },
+                memory_limit: if c.memory_limit == 0 { HOST_MEMORY } else { c.memory_limit },
+                memory_unlimited: c.memory_limit == 0,
+                memory_breakdown: c.running.then(|| demo_memory(c.name, c.memory)),
+                status: if c.paused {
+                    format!("{} (Paused)", format_uptime(c.uptime_ticks * 2))
+                } else if c.running {
+                    format_uptime(c.uptime_ticks * 2)
+                } else {
+                    "Exited (0) 2 hours ago".to_string()
+                },
+                // The one-off migration ran weeks before the rest came up.
+                created: if c.name == "migrate-once" {
+                    (self.created - 30 * 86_400).to_string()
+                } else {
+                    (self.created + i as i64 * 60).to_string()
+                },
+                image: c.image.to_string(),
+                image_id: c.image_id.clone(),
+                ports: c
+                    .port
+                    // Published on both families, as on a dual-stack host.
+                    .map(|p| {
+                        ["0.0.0.0", "::"]
+                            .map(|ip| Port {
+                                ip: Some(ip.to_string()),
+                                private_port: p,
+                                public_port: Some(p),
+                                typ: Some(PortTypeEnum::TCP),
+                            })
+                            .to_vec()
+                    })
+                    .unwrap_or_default(),
+                labels: demo_labels(c.name),
+                network_mode: if c.name == "metrics" {
+                    format!("container:{}", demo_id(0))
+                } else {
+                    "bridge".to_string()
+                },
+                networks: demo_networks(i, c.name),
+                mounts: demo_mounts(c.name),
+                io: c.io,
+                interfaces: demo_interfaces(demo_networks(i, c.name).len(), c.io),
+                cpu_cores: c.cpu_cores.to_vec(),
+                sampled_at: Some(c.sampled_at),
+                sample_latency_ms: Some(c.latency_ms),
+            })
+            .collect()
+    }
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+fn demo_id(index: usize) -> String {
+    format!("{:012x}{:052x}", 0xd0c4e7000000u64 + index as u64, 0)
+}
+
+/// Line `n` of a container's logs, with the odd warning and error mixed in.
+/// The `n`th line the container writes; errors and warnings go to stderr,
+/// as do all of Postgres's messages.
+fn demo_log_line(name: &str, n: u64, at: Option<chrono::DateTime<chrono::Utc>>) -> LogLine {
+    let text = demo_log_text(name, n);
+    let stderr = match name {
+        "shop-worker-1" => text.starts_with("ERROR") || text.starts_with("WARN"),
+        "shop-db-1" => true,
+        _ => false,
+    };
+    let text = match at {
+        Some(at) => format!("{} {}", at.to_rfc3339_opts(chrono::SecondsFormat::Nanos, true), text),
+        None => text,
+    };
+    LogLine { text, stderr }
+}
+
+fn demo_log_text(name: &str, n: u64) -> String {
+    match name {
+        "shop-worker-1" if n % 23 == 22 => {
+            format!("ERROR job {} failed: timeout talking to shop-db-1:5432 retries=3", 4000 + n)
+        }
+        "shop-worker-1" if n % 7 == 6 => format!("WARN slow job {} batch=128 took 2.{}s", 4000 + n, n % 10),
+        "shop-worker-1" => format!("processing job {} batch=128 queue=orders retries=0", 4000 + n),
+        "shop-web-1" if n % 13 == 12 => "172.20.0.1 - - \"GET /checkout HTTP/1.1\" 502 157".to_string(),
+        "shop-web-1" => "172.20.0.1 - - \"GET /products HTTP/1.1\" 200 5120".to_string(),
+        // Structured logs, as most API frameworks write them.
+        "shop-api-1" | "shop-api-2" if n % 11 == 10 => format!(
+            r#"{{"time":"{}","level":"error","msg":"GET /api/orders","status":500,"duration_ms":{},"request_id":"req-{:05}","error":"connection reset by peer"}}"#,
+            chrono::Utc::now().format("%H:%M:%S%.3f"),
+            200 + n % 50,
+            n
+        ),
+        "shop-api-1" | "shop-api-2" => format!(
+            r#"{{"time":"{}","level":"info","msg":"GET /api/products","status":200,"duration_ms":{},"request_id":"req-{:05}"}}"#,
+            chrono::Utc::now().format("%H:%M:%S%.3f"),
+            8 + n % 9,
+            n
+        ),
+        "shop-db-1" if n % 9 == 8 => {
+            "ERROR:  duplicate key value violates unique constraint \"orders_pkey\"".to_string()
+        }
+        "shop-db-1" => format!("LOG:  checkpoint complete: wrote {} buffers", 30 + n % 40),
+        _ => "checkpoint complete".to_string(),
+    }
+}
+
+/// Output of one command line in the demo shell; `None` once it exits.
+fn demo_shell(hostname: &str, line: &str) -> Option<String> {
+    let (command, args) = line.split_once(' ').unwrap_or((line, ""));
+    let output = match command {
+        "" => String::new(),
+        "exit" => return None,
+        "echo" => format!("{}\n", args),
+        "hostname" => format!("{}\n", hostname),
+        "whoami" => "root\n".to_string(),
+        "pwd" => "/\n".to_string(),
+        "ls" => "bin  dev  etc  home  lib  proc  root  run  sys  tmp  usr  var\n".to_string(),
+        "df" => concat!(
+            "Filesystem      Size  Used Avail Use% Mounted on\n",
+            "overlay          59G   41G   16G  73% /\n",
+            "tmpfs            64M     0   64M   0% /dev\n",
+            "shm              64M     0   64M   0% /dev/shm\n",
+        )
+        .to_string(),
+        "free" => concat!(
+            "               total        used        free      shared  buff/cache   available\n",
+            "Mem:           16384        9210        1877         312        5296        6540\n",
+            "Swap:           2048          64        1984\n",
+        )
+        .to_string(),
+        "netstat" | "ss" => concat!(
+            "Active Internet connections (only servers)\n",
+            "Proto Recv-Q Send-Q Local Address           Foreign Address         State\n",
+            "tcp        0      0 0.0.0.0:8080            0.0.0.0:*               LISTEN\n",
+        )
+        .to_string(),
+        "env" => format!(
+            "HOSTNAME={}\nPATH=/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin\nHOME=/root\n",
+            hostname
+        ),
+        "cat" => demo_file(hostname, args).unwrap_or_else(|| format!("cat: {}: No such file or directory\n", args)),
+        // What the file browser runs: `cd -- '<dir>' || exit 1; stat ...`
+        // and `head -c <bytes> -- '<file>'`.
+        "cd" => {
+            let path = args.split('\'').nth(1).unwrap_or_default();
+            demo_listing(hostname, path).unwrap_or_else(|| format!("sh: cd: can't cd to {}: No such file or directory\n", path))
+        }
+        "head" => {
+            let path = args.split('\'').nth(1).unwrap_or_default();
+            demo_file(hostname, path).unwrap_or_else(|| format!("head: {}: No such file or directory\n", path))
+        }
+        _ => format!("sh: {}: not found\n", command),
+    };
+    Some(output)
+}
+
+/// The files of every demo container's filesystem; directories are implied
+/// by the paths.
+const DEMO_FILES: &[(&str, &str)] = &[
+    ("/app/README.md", "# shop\n\nStart everything with `docker compose up`.\n"),
+    ("/app/config.json", "{\n  \"port\": 8080,\n  \"database\": \"postgres://db:5432/shop\"\n}\n"),
+    ("/etc/hostname", ""),
+    ("/etc/hosts", ""),
+    ("/etc/os-release", "NAME=\"Alpine Linux\"\nID=alpine\nVERSION_ID=3.19.1\n"),
+    ("/tmp/.keep", ""),
+    ("/var/log/app.log", "started\nlistening on :8080\n"),
+];
+
+fn demo_file(hostname: &str, path: &str) -> Option<String> {
+    let content = match path {
+        "/etc/hostname" => format!("{}\n", hostname),
+        "/etc/hosts" => format!(
+            "127.0.0.1       localhost\n::1             localhost ip6-localhost ip6-loopback\n172.20.0.5      {}\n",
+            hostname
+        ),
+        _ => DEMO_FILES.iter().find(|(file, _)| *file == path)?.1.to_string(),
+    };
+    Some(content)
+}
+
+/// The directory's entries as `stat -c '%F|%s|%Y|%n'` prints them.
+fn demo_listing(hostname: &str, directory: &str) -> Option<String> {
+    let prefix = format!("{}/", directory.trim_end_matches('/'));
+    let mut entries: Vec<(String, bool, usize)> = Vec::new();
+    for (path, _) in DEMO_FILES {
+        let Some(rest) = path.strip_prefix(&prefix) else {
+            continue;
+        };
+        let (name, is_directory) = match rest.split_once('/') {
+            Some((directory, _)) => (directory, true),
+            None => (rest, false),
+        };
+        if !entries.iter().any(|(n, _, _)| n == name) {
+            let size = demo_file(hostname, path).map_or(0, |content| content.len());
+            entries.push((name.to_string(), is_directory, size));
+        }
+    }
+    if entries.is_empty() {
+        return None;
+    }
+    Some(
+        entries
+            .into_iter()
+            .map(|(name, is_directory, size)| match (is_directory, size) {
+                (true, _) => format!("directory|4096|1714572300|{}\n", name),
+                (false, 0) => format!("regular empty file|0|1714572300|{}\n", name),
+                (false, size) => format!("regular file|{}|1714572300|{}\n", size, name),
+            })
+            .collect(),
+    )
+}
+
+/// A personal and a CI account on Docker Hub, and a GitHub token.
+fn demo_logins() -> Vec<RegistryLogin> {
+    let login = |server: &str, username: &str, store: &str| RegistryLogin {
+        server: server.to_string(),
+        username: username.to_string(),
+        store: store.to_string(),
+    };
+    vec![
+        login("https://index.docker.io/v1/", "jdoe", "desktop"),
+        login("docker.io", "shop-ci", "config.json"),
+        login("ghcr.io", "jdoe", "desktop"),
+    ]
+}
+
+fn demo_image_id(tag: &str) -> String {
+    // FNV-1a, just to get stable, distinct-looking IDs.
+    let hash = tag
+        .bytes()
+        .fold(0xcbf29ce484222325u64, |h, b| (h ^ b as u64).wrapping_mul(0x100000001b3));
+    format!("sha256:{:016x}{:048x}", hash, 0)
+}
+
+/// Front-end services sit on `shop_default`, data stores only on
+/// `shop_backend`, and the API bridges both. `shop_default` is dual-stack.
+fn demo_networks(index: usize, name: &str) -> Vec<NetworkAttachment> {
+    let attach = |network: &str, subnet: &str, subnet_v6: Option<&str>| NetworkAttachment {
+        network: network.to_string(),
+        ip_address: format!("{}.{}", subnet, index + 2),
+        ipv6_address: subnet_v6.map(|s| format!("{}{:x}", s, index + 2)).unwrap_or_default(),
+    };
+    let default = || attach("shop_default", "172.20.0", Some("fd00:20::"));
+    match name {
+        "shop-web-1" | "migrate-once" => vec![default()],
+        "shop-api-1" | "shop-api-2" | "shop-worker-1" => {
+            vec![attach("shop_backend", "172.21.0", None), default()]
+        }
+        "shop-db-1" | "shop-cache-1" => vec![attach("shop_backend", "172.21.0", None)],
+        _ => Vec::new(),
+    }
+}
+
+/// One interface per network, the first carrying most of the traffic.
+fn demo_interfaces(networks: usize, io: IoCounters) -> Vec<InterfaceCounters> {
+    let interface = |n: usize, rx: u64, tx: u64| InterfaceCounters {
+        name: format!("eth{}", n),
+        rx,
+        tx,
+    };
+    match networks {
+        0 => Vec::new(),
+        1 => vec![interface(0, io.net_rx, io.net_tx)],
+        _ => vec![
+            interface(0, io.net_rx / 4 * 3, io.net_tx / 4 * 3),
+            interface(1, io.net_rx - io.net_rx / 4 * 3, io.net_tx - io.net_tx / 4 * 3),
+        ],
+    }
+}
+
+/// The environment as `docker inspect` lists it, the image's included.
+fn demo_env(name: &str) -> Vec<String> {
+    let mut env = vec!["PATH=/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin".to_string()];
+    let service: &[&str] = match name {
+        "shop-api-1" | "shop-api-2" => &["NODE_ENV=production", "DATABASE_URL=postgres://shop@shop-db-1/shop", "PORT=3000"],
+        "shop-db-1" => &["POSTGRES_USER=shop", "POSTGRES_DB=shop", "PGDATA=/var/lib/postgresql/data"],
+        "shop-worker-1" => &["QUEUE=orders", "CONCURRENCY=4"],
+        "shop-cache-1" => &["REDIS_ARGS=--maxmemory 256mb"],
+        _ => &[],
+    };
+    env.extend(service.iter().map(|var| var.to_string()));
+    env
+}
+
+fn demo_mounts(name: &str) -> Vec<Mount> {
+    let volume = |volume: &str, destination: &str| Mount {
+        kind: "volume".to_string(),
+        name: volume.to_string(),
+        source: format!("/var/lib/docker/volumes/{}/_data", volume),
+        destination: destination.to_string(),
+        read_write: true,
+    };
+    match name {
+        "shop-db-1" => vec![volume("shop_pgdata", "/var/lib/postgresql/data")],
+        "shop-cache-1" => vec![volume("shop_redis", "/data")],
+        "metrics" => vec![volume("prometheus_data", "/prometheus")],
+        "shop-web-1" => vec![Mount {
+            kind: "bind".to_string(),
+            name: String::new(),
+            source: "/srv/shop/nginx.conf".to_string(),
+            destination: "/etc/nginx/nginx.conf".to_string(),
+            read_write: false,
+        }],
+        _ => Vec::new(),
+    }
+}
+
+/// Mimics Docker's "Up 3 hours" status strings.
+/// The number of a signal given as `SIGKILL`, `KILL` or `9`.
+fn signal_number(signal: &str) -> Option<u32> {
+    const NAMES: [(&str, u32); 7] = [("HUP", 1), ("INT", 2), ("QUIT", 3), ("KILL", 9), ("USR1", 10), ("USR2", 12), ("TERM", 15)];
+    let signal = signal.trim().to_ascii_uppercase();
+    if let Ok(number) = signal.parse::<u32>() {
+        return (1..=31).contains(&number).then_some(number);
+    }
+    let name = signal.strip_prefix("SIG").unwrap_or(&signal);
+    NAMES.iter().find(|(n, _)| *n == name).map(|(_, number)| *number)
+}
+
+fn format_uptime(seconds: u64) -> String {
+    match seconds {
+        0..=59 => format!("Up {} seconds", seconds.max(1)),
+        60..=3599 => format!("Up {} minutes", seconds / 60),
+        _ => format!("Up {} hours", seconds / 3600),
+    }
+}
+
+/// Bytes per second the container's block reads and network traffic are
+/// capped at, 0 for none: the database reads from a throttled disk and the
+/// web server is shaped by docker-tc.
+fn demo_bandwidth_caps(name: &str) -> (u64, u64) {
+    match name {
+        "shop-db-1" => (20_000, 0),
+        "shop-web-1" => (0, 125_000),
+        _ => (0, 0),
+    }
+}
+
+/// The database and Prometheus keep much of their data in the page cache,
+/// the rest little; the database has been swapping a bit.
+fn demo_memory(name: &str, usage: u64) -> MemoryBreakdown {
+    let cached = match name {
+        "shop-db-1" | "metrics" => 0.45,
+        "shop-cache-1" => 0.05,
+        _ => 0.15,
+    };
+    let cache = (usage as f64 * cached) as u64;
+    MemoryBreakdown {
+        rss: usage - cache,
+        cache,
+        swap: Some(if name == "shop-db-1" { 12 * MB } else { 0 }),
+        // About half the cache is inactive.
+        working_set: usage - cache / 2,
+    }
+}
+
+/// Compose labels for the `shop-*` containers, like a real compose project,
+/// and kubelet labels for the `k8s_*` ones.
+fn demo_labels(name: &str) -> HashMap<String, String> {
+    let mut labels = HashMap::new();
+    if let Some(rest) = name.strip_prefix("k8s_") {
+        let parts: Vec<&str> = rest.split('_').collect();
+        if let [container, pod, namespace, ..] = parts[..] {
+            labels.insert("io.kubernetes.container.name".to_string(), container.to_string());
+            labels.insert("io.kubernetes.pod.name".to_string(), pod.to_string());
+            labels.insert("io.kubernetes.pod.namespace".to_string(), namespace.to_string());
+        }
+    }
+    if let Some(rest) = name.strip_prefix("shop-") {
+        let service = rest.rsplit_once('-').map_or(rest, |(service, _)| service);
+        labels.insert("com.docker.compose.project".to_string(), "shop".to_string());
+        labels.insert("com.docker.compose.service".to_string(), service.to_string());
+        let depends_on = match service {
+            "web" => "api:service_started:false",
+            "api" => "db:service_healthy:false,cache:service_started:false",
+            "worker" => "db:service_healthy:false,cache:service_started:false",
+            _ => "",
+        };
+        if !depends_on.is_empty() {
+            labels.insert("com.docker.compose.depends_on".to_string(), depends_on.to_string());
+        }
+    }
+    if let (_, net_cap @ 1..) = demo_bandwidth_caps(name) {
+        labels.insert("com.docker-tc.limit".to_string(), format!("{}kbit", net_cap * 8 / 1000));
+    }
+    labels
+}
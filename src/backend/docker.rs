@@ -0,0 +1,968 @@
+//! The real thing: a Docker daemon reached through bollard.
+
+use super::limiter::ApiLimiter;
+use super::{
+    ApiMetrics, ContainerBackend, ContainerEvent, ContainerStats, DiskUsage, ExecStreams, InterfaceCounters, IoCounters,
+    LogLine, MemoryBreakdown, Mount, NetworkAttachment, PruneReport, PublishedPort, RegistryLogin, ResourceLimits, RunSpec, CPU_PERIOD,
+};
+use crate::config::ApiConfig;
+use crate::registries::PullLogins;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use bollard::container::{
+    AttachContainerOptions, AttachContainerResults, InspectContainerOptions, KillContainerOptions, ListContainersOptions, LogOutput, LogsOptions, PruneContainersOptions, RemoveContainerOptions,
+    RenameContainerOptions, RestartContainerOptions, StartContainerOptions, Stats, StatsOptions, StopContainerOptions, MemoryStatsStats,
+    Config, CreateContainerOptions, NetworkingConfig, TopOptions, UpdateContainerOptions,
+};
+use bollard::exec::{CreateExecOptions, StartExecOptions, StartExecResults};
+use bollard::image::{CommitContainerOptions, CreateImageOptions, ListImagesOptions, RemoveImageOptions};
+use bollard::models::{
+    ContainerConfig, ContainerInspectResponse, ContainerSummary, ContainerTopResponse, EndpointSettings, HostConfig, ImageSummary, MountTypeEnum, Network,
+    PortBinding,
+    ServiceUpdateStatus, Volume,
+};
+use bollard::network::{ConnectNetworkOptions, ListNetworksOptions};
+use bollard::service::{InspectServiceOptions, UpdateServiceOptions};
+use bollard::system::EventsOptions;
+use bollard::volume::{ListVolumesOptions, RemoveVolumeOptions};
+use bollard::Docker;
+use futures::{future::try_join_all, stream::BoxStream, Future, StreamExt, TryStreamExt};
+use std::{collections::HashMap, process::Stdio};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::OnceCell;
+
+pub struct DockerBackend {
+    docker: Docker,
+    metrics: ApiMetrics,
+    limiter: ApiLimiter,
+    /// Total memory of the daemon's host, fetched once.
+    host_memory: OnceCell<u64>,
+    /// `api.host`, handed to the `docker` CLI.
+    host: Option<String>,
+    pull_logins: PullLogins,
+}
+
+impl DockerBackend {
+    pub fn connect(api: &ApiConfig) -> Result<Self> {
+        let host = api.host.clone().filter(|h| !h.is_empty());
+        Ok(Self {
+            docker: match host.as_deref() {
+                None => Docker::connect_with_local_defaults()?,
+                Some(host) if host.starts_with("unix://") => {
+                    Docker::connect_with_socket(host, 120, bollard::API_DEFAULT_VERSION)?
+                }
+                Some(host) => Docker::connect_with_http(host, 120, bollard::API_DEFAULT_VERSION)?,
+            },
+            metrics: ApiMetrics::default(),
+            limiter: ApiLimiter::new(api),
+            host_memory: OnceCell::new(),
+            host,
+            pull_logins: PullLogins::default(),
+        })
+    }
+
+    /// The host's total memory, or 0 if the daemon does not say.
+    async fn host_memory(&self) -> u64 {
+        *self
+            .host_memory
+            .get_or_init(|| async {
+                let info = self.call("info", self.docker.info()).await;
+                info.ok().and_then(|i| i.mem_total).unwrap_or(0).max(0) as u64
+            })
+            .await
+    }
+
+    async fn container_stats(&self, container: ContainerSummary) -> Result<ContainerStats> {
+        let id = container.id.unwrap_or_default();
+        let started = std::time::Instant::now();
+        let stats = self
+            .call("stats", self.docker.stats(&id, None::<StatsOptions>).try_next())
+            .await
+            .context("Failed to get container stats")?
+            .context("Stats stream ended without a sample")?;
+        let sample_latency_ms = started.elapsed().as_millis() as u64;
+        let sampled_at = chrono::DateTime::parse_from_rfc3339(&stats.read)
+            .ok()
+            .map(|read| read.timestamp_millis());
+
+        let cpu_usage = calculate_cpu_usage(&stats);
+        let memory_usage = stats.memory_stats.usage.unwrap_or(0);
+        let memory_limit = stats.memory_stats.limit.unwrap_or(0);
+        // Without a limit, the cgroup reports the host's memory (or more, on
+        // cgroup v2), so anything at least that large means none is set.
+        let host_memory = self.host_memory().await;
+        let memory_unlimited = memory_limit == 0 || (host_memory > 0 && memory_limit >= host_memory);
+        let memory_limit = if memory_unlimited && host_memory > 0 { host_memory } else { memory_limit.max(1) };
+
+        let mut networks: Vec<NetworkAttachment> = container
+            .network_settings
+            .and_then(|n| n.networks)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(network, endpoint)| NetworkAttachment {
+                network,
+                ip_address: endpoint.ip_address.unwrap_or_default(),
+                ipv6_address: endpoint.global_ipv6_address.unwrap_or_default(),
+            })
+            .collect();
+        networks.sort_by(|a, b| a.network.cmp(&b.network));
+
+        let mounts = container
+            .mounts
+            .unwrap_or_default()
+            .into_iter()
+            .map(|m| Mount {
+                kind: m.typ.map(|t| t.to_string()).unwrap_or_default(),
+                name: m.name.unwrap_or_default(),
+                source: m.source.unwrap_or_default(),
+                destination: m.destination.unwrap_or_default(),
+                read_write: m.rw.unwrap_or(true),
+            })
+            .collect();
+
+        Ok(ContainerStats {
+            id,
+            name: container.names.unwrap_or_default()[0].trim_start_matches('/').to_string(),
+            cpu_usage,
+            memory_usage,
+            memory_limit,
+            memory_unlimited,
+            memory_breakdown: memory_breakdown(&stats),
+            status: container.status.unwrap_or_default(),
+            created: container.created.map(|t| t.to_string()).unwrap_or_default(),
+            image: container.image.unwrap_or_default(),
+            image_id: container.image_id.unwrap_or_default(),
+            ports: container.ports.unwrap_or_default(),
+            labels: container.labels.unwrap_or_default(),
+            network_mode: container
+                .host_config
+                .and_then(|h| h.network_mode)
+                .unwrap_or_default(),
+            networks,
+            mounts,
+            io: io_counters(&stats),
+            interfaces: interface_counters(&stats),
+            cpu_cores: stats.cpu_stats.cpu_usage.percpu_usage.clone().unwrap_or_default(),
+            sampled_at,
+            sample_latency_ms: Some(sample_latency_ms),
+        })
+    }
+
+    /// Fails unless the daemon has experimental features on, which
+    /// checkpoints need; the CLI's own error would only say the command is
+    /// unknown.
+    async fn require_experimental(&self) -> Result<()> {
+        let info = self.call("info", self.docker.info()).await.context("Failed to query daemon info")?;
+        if info.experimental_build != Some(true) {
+            anyhow::bail!("Checkpoints need a daemon with experimental features enabled");
+        }
+        Ok(())
+    }
+
+    /// Runs one API call within the configured limits, recording its metrics.
+    async fn call<T, E>(&self, endpoint: &'static str, call: impl Future<Output = Result<T, E>>) -> Result<T, E> {
+        let _permit = self.limiter.acquire().await;
+        self.metrics.observe(endpoint, call).await
+    }
+
+    /// Runs the `docker` CLI for what bollard does not cover (checkpoints and
+    /// registry logins) against the same daemon: `api.host` when one is
+    /// configured, otherwise the CLI reads `DOCKER_HOST` like bollard does.
+    async fn cli(&self, args: &[&str]) -> Result<String> {
+        self.cli_with_input(args, "").await
+    }
+
+    /// Runs the `docker` CLI with `input` on its stdin, e.g. a password.
+    async fn cli_with_input(&self, args: &[&str], input: &str) -> Result<String> {
+        let mut command = tokio::process::Command::new("docker");
+        if let Some(host) = &self.host {
+            // The CLI takes TCP daemons as tcp://, where bollard also
+            // accepts http://; a context would override the host.
+            let host = match host.strip_prefix("http://") {
+                Some(address) => format!("tcp://{}", address),
+                None => host.clone(),
+            };
+            command.env("DOCKER_HOST", host).env_remove("DOCKER_CONTEXT");
+        }
+        let mut child = command
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("Failed to run the docker CLI")?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(input.as_bytes()).await?;
+        }
+        let output = child.wait_with_output().await.context("Failed to run the docker CLI")?;
+        if !output.status.success() {
+            anyhow::bail!("docker {}: {}", args[..2].join(" "), String::from_utf8_lossy(&output.stderr).trim());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    async fn pull_if_missing(&self, image: &str) -> Result<()> {
+        if self.call("inspect_image", self.docker.inspect_image(image)).await.is_ok() {
+            return Ok(());
+        }
+        self.pull(image).await
+    }
+
+    async fn pull(&self, image: &str) -> Result<()> {
+        let (repository, tag) = crate::actions::parse_image(image).with_context(|| format!("Invalid image {}", image))?;
+        let options = CreateImageOptions {
+            from_image: repository.as_str(),
+            tag: tag.as_str(),
+            ..Default::default()
+        };
+        let credentials = crate::registries::credentials(image, &self.pull_logins.chosen()).await?;
+        self.call("create_image", self.docker.create_image(Some(options), None, credentials).try_collect::<Vec<_>>())
+            .await
+            .with_context(|| format!("Failed to pull {}", image))?;
+        Ok(())
+    }
+
+    /// Creates a container without starting it and returns its ID.
+    async fn create(&self, name: &str, config: Config<String>) -> Result<String> {
+        let options = CreateContainerOptions { name, platform: None };
+        let created = self
+            .call("create_container", self.docker.create_container(Some(options), config))
+            .await
+            .context("Failed to create container")?;
+        Ok(created.id)
+    }
+
+    /// Fetches logs in one go, split into lines.
+    async fn logs(&self, id: &str, options: LogsOptions<String>) -> Result<Vec<LogLine>> {
+        let chunks: Vec<_> = self
+            .call("logs", self.docker.logs(id, Some(options)).try_collect())
+            .await
+            .context("Failed to read logs")?;
+        Ok(chunks
+            .into_iter()
+            .flat_map(|chunk| {
+                let chunk = log_line(chunk);
+                let stderr = chunk.stderr;
+                chunk
+                    .text
+                    .lines()
+                    .map(|text| LogLine { text: text.to_string(), stderr })
+                    .collect::<Vec<_>>()
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl ContainerBackend for DockerBackend {
+    async fn containers(&self) -> Result<Vec<ContainerStats>> {
+        let options = ListContainersOptions::<String> {
+            all: true,
+            ..Default::default()
+        };
+
+        let containers = self
+            .call("list_containers", self.docker.list_containers(Some(options)))
+            .await
+            .context("Failed to list containers")?;
+
+        // Stats are fetched concurrently; the limiter keeps this polite.
+        try_join_all(
+            containers
+                .into_iter()
+                .filter(|c| c.id.is_some())
+                .map(|container| self.container_stats(container)),
+        )
+        .await
+    }
+
+    async fn inspect_container(&self, id: &str) -> Result<ContainerInspectResponse> {
+        self.call("inspect_container", self.docker.inspect_container(id, None::<InspectContainerOptions>))
+            .await
+            .context("Failed to inspect container")
+    }
+
+    async fn networks(&self) -> Result<Vec<Network>> {
+        self.call("list_networks", self.docker.list_networks(None::<ListNetworksOptions<String>>))
+            .await
+            .context("Failed to list networks")
+    }
+
+    async fn volumes(&self) -> Result<Vec<Volume>> {
+        let response = self
+            .call("list_volumes", self.docker.list_volumes(None::<ListVolumesOptions<String>>))
+            .await
+            .context("Failed to list volumes")?;
+        Ok(response.volumes.unwrap_or_default())
+    }
+
+    async fn images(&self) -> Result<Vec<ImageSummary>> {
+        self.call("list_images", self.docker.list_images(None::<ListImagesOptions<String>>))
+            .await
+            .context("Failed to list images")
+    }
+
+    async fn disk_usage(&self) -> Result<DiskUsage> {
+        let df = self.call("df", self.docker.df()).await.context("Failed to get disk usage")?;
+        let sum = |sizes: &mut dyn Iterator<Item = i64>| sizes.filter(|&s| s > 0).sum::<i64>() as u64;
+        Ok(DiskUsage {
+            images: df.layers_size.unwrap_or(0).max(0) as u64,
+            containers: sum(&mut df.containers.iter().flatten().filter_map(|c| c.size_rw)),
+            // Volumes report -1 when the size wasn't computed.
+            volumes: sum(&mut df.volumes.iter().flatten().filter_map(|v| Some(v.usage_data.as_ref()?.size))),
+            build_cache: sum(&mut df.build_cache.iter().flatten().filter_map(|b| b.size)),
+        })
+    }
+
+    async fn container_events(&self, id: &str, since: i64) -> Result<Vec<ContainerEvent>> {
+        // With `until` set the daemon returns the backlog and ends the stream.
+        let until = chrono::Utc::now().timestamp();
+        let options = EventsOptions::<&str> {
+            since: Some(since.to_string()),
+            until: Some(until.to_string()),
+            filters: HashMap::from([("type", vec!["container"]), ("container", vec![id])]),
+        };
+        let messages: Vec<_> = self
+            .call("events", self.docker.events(Some(options)).try_collect())
+            .await
+            .context("Failed to get container events")?;
+        Ok(messages
+            .into_iter()
+            .map(|message| ContainerEvent {
+                time: message.time.unwrap_or_default(),
+                action: message.action.unwrap_or_default(),
+                attributes: message.actor.and_then(|a| a.attributes).unwrap_or_default(),
+            })
+            .collect())
+    }
+
+    /// Log streams are long-lived, so they bypass the limiter and metrics.
+    fn follow_logs(&self, id: &str, tail: usize, timestamps: bool) -> BoxStream<'static, Result<LogLine>> {
+        let options = LogsOptions::<String> {
+            follow: true,
+            stdout: true,
+            stderr: true,
+            timestamps,
+            tail: tail.to_string(),
+            ..Default::default()
+        };
+        self.docker
+            .logs(id, Some(options))
+            .map(|output| Ok(log_line(output.context("Failed to read logs")?)))
+            .boxed()
+    }
+
+    async fn recent_logs(&self, id: &str, tail: usize, since: i64, timestamps: bool) -> Result<Vec<LogLine>> {
+        let options = LogsOptions::<String> {
+            stdout: true,
+            stderr: true,
+            timestamps,
+            since,
+            tail: tail.to_string(),
+            ..Default::default()
+        };
+        self.logs(id, options).await
+    }
+
+    async fn logs_since(&self, id: &str, since: i64) -> Result<Vec<LogLine>> {
+        let options = LogsOptions::<String> {
+            stdout: true,
+            stderr: true,
+            timestamps: true,
+            since,
+            tail: "all".to_string(),
+            ..Default::default()
+        };
+        self.logs(id, options).await
+    }
+
+    async fn processes(&self, id: &str) -> Result<ContainerTopResponse> {
+        self.call("top", self.docker.top_processes(id, None::<TopOptions<String>>))
+            .await
+            .context("Failed to list processes")
+    }
+
+    async fn start_container(&self, id: &str) -> Result<()> {
+        self.call("start_container", self.docker.start_container(id, None::<StartContainerOptions<String>>))
+            .await
+            .context("Failed to start container")
+    }
+
+    async fn stop_container(&self, id: &str) -> Result<()> {
+        self.call("stop_container", self.docker.stop_container(id, None::<StopContainerOptions>))
+            .await
+            .context("Failed to stop container")
+    }
+
+    async fn restart_container(&self, id: &str) -> Result<()> {
+        self.call("restart_container", self.docker.restart_container(id, None::<RestartContainerOptions>))
+            .await
+            .context("Failed to restart container")
+    }
+
+    async fn pause_container(&self, id: &str) -> Result<()> {
+        self.call("pause_container", self.docker.pause_container(id))
+            .await
+            .context("Failed to pause container")
+    }
+
+    async fn unpause_container(&self, id: &str) -> Result<()> {
+        self.call("unpause_container", self.docker.unpause_container(id))
+            .await
+            .context("Failed to unpause container")
+    }
+
+    async fn kill_container(&self, id: &str, signal: &str) -> Result<()> {
+        let options = KillContainerOptions { signal };
+        self.call("kill_container", self.docker.kill_container(id, Some(options)))
+            .await
+            .context("Failed to kill container")
+    }
+
+    async fn rename_container(&self, id: &str, name: &str) -> Result<()> {
+        let options = RenameContainerOptions { name };
+        self.call("rename_container", self.docker.rename_container(id, options))
+            .await
+            .context("Failed to rename container")
+    }
+
+    /// Raising the memory limit past the swap limit fails, so a new memory
+    /// limit takes the swap limit along, keeping its ratio to the memory.
+    async fn update_container(&self, id: &str, limits: &ResourceLimits) -> Result<()> {
+        let memory_swap = match limits.memory {
+            Some(memory) => {
+                let host_config = self.inspect_container(id).await?.host_config.unwrap_or_default();
+                scaled_swap(memory, host_config.memory.unwrap_or(0), host_config.memory_swap.unwrap_or(0))
+            }
+            None => None,
+        };
+        let options = UpdateContainerOptions::<String> {
+            cpu_shares: limits.cpu_shares.map(|shares| shares as isize),
+            cpu_period: limits.cpu_quota.map(|_| CPU_PERIOD),
+            cpu_quota: limits.cpu_quota,
+            memory: limits.memory,
+            memory_swap,
+            ..Default::default()
+        };
+        self.call("update_container", self.docker.update_container(id, options))
+            .await
+            .context("Failed to update container")
+    }
+
+    async fn commit_container(&self, id: &str, repository: &str, tag: &str) -> Result<()> {
+        let options = CommitContainerOptions {
+            container: id,
+            repo: repository,
+            tag,
+            pause: true,
+            ..Default::default()
+        };
+        self.call("commit", self.docker.commit_container(options, Config::<String>::default()))
+            .await
+            .context("Failed to commit container")
+            .map(drop)
+    }
+
+    async fn run_container(&self, spec: &RunSpec) -> Result<String> {
+        self.pull_if_missing(&spec.image).await?;
+        let (exposed_ports, port_bindings) = port_config(&spec.ports);
+        let config = Config {
+            image: Some(spec.image.clone()),
+            env: Some(spec.env.clone()),
+            labels: Some(spec.labels.clone()),
+            exposed_ports: Some(exposed_ports),
+            host_config: Some(HostConfig {
+                port_bindings: Some(port_bindings),
+                binds: Some(spec.volumes.clone()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let id = self.create(&spec.name, config).await?;
+        self.start_container(&id).await?;
+        Ok(id)
+    }
+
+    async fn recreate_container(&self, id: &str, spec: &RunSpec) -> Result<String> {
+        let inspect = self.inspect_container(id).await?;
+        let running = inspect.state.as_ref().and_then(|s| s.running).unwrap_or(false);
+        self.pull_if_missing(&spec.image).await?;
+
+        let mut config = Config::from(inspect.config.unwrap_or_default());
+        let (exposed_ports, port_bindings) = port_config(&spec.ports);
+        config.exposed_ports.get_or_insert_with(HashMap::new).extend(exposed_ports);
+        config.image = Some(spec.image.clone());
+        config.env = Some(spec.env.clone());
+        // Docker sets these per container; copied, they would clash.
+        config.hostname = None;
+        config.mac_address = None;
+        // What came from the old image rather than the container is left to
+        // the new image, or a stale `PATH` or version variable would stick.
+        if let Some(old_image) = inspect.image.as_deref() {
+            let old_image = self
+                .call("inspect_image", self.docker.inspect_image(old_image))
+                .await
+                .context("Failed to inspect the old image")?;
+            without_image_defaults(&mut config, &old_image.config.unwrap_or_default());
+        }
+        let mut host_config = inspect.host_config.unwrap_or_default();
+        host_config.port_bindings = Some(port_bindings);
+        // Volumes and binds are all in `spec.volumes`, by name or path, so
+        // anonymous volumes carry over too; only tmpfs mounts are left.
+        host_config.binds = Some(spec.volumes.clone());
+        host_config.mounts = host_config
+            .mounts
+            .map(|mounts| mounts.into_iter().filter(|m| m.typ == Some(MountTypeEnum::TMPFS)).collect());
+        let network_mode = host_config.network_mode.clone().unwrap_or_default();
+        config.host_config = Some(host_config);
+
+        // Aliases such as a compose service's name, without the old ID.
+        let endpoints: Vec<(String, EndpointSettings)> = inspect
+            .network_settings
+            .and_then(|settings| settings.networks)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(network, endpoint)| {
+                let aliases = endpoint.aliases.map(|aliases| aliases.into_iter().filter(|a| !id.starts_with(a.as_str())).collect());
+                let endpoint = EndpointSettings {
+                    aliases,
+                    links: endpoint.links,
+                    ipam_config: endpoint.ipam_config,
+                    driver_opts: endpoint.driver_opts,
+                    ..Default::default()
+                };
+                (network, endpoint)
+            })
+            .collect();
+        let (primary, others) = split_endpoints(&network_mode, endpoints);
+        config.networking_config = Some(NetworkingConfig {
+            endpoints_config: primary.into_iter().collect(),
+        });
+
+        // The old container steps aside under another name until the new
+        // one runs, and comes back if it does not.
+        let backup = format!("{}-replaced-{}", spec.name, &id[..id.len().min(12)]);
+        self.rename_container(id, &backup).await?;
+        if running {
+            if let Err(e) = self.stop_container(id).await {
+                let _ = self.rename_container(id, &spec.name).await;
+                return Err(e);
+            }
+        }
+        let mut created = None;
+        let result: Result<String> = async {
+            let new_id = self.create(&spec.name, config).await?;
+            created = Some(new_id.clone());
+            for (network, endpoint) in others {
+                let options = ConnectNetworkOptions {
+                    container: new_id.clone(),
+                    endpoint_config: endpoint,
+                };
+                self.call("connect_network", self.docker.connect_network(&network, options))
+                    .await
+                    .with_context(|| format!("Failed to connect to {}", network))?;
+            }
+            if running {
+                self.start_container(&new_id).await?;
+            }
+            Ok(new_id)
+        }
+        .await;
+        match result {
+            Ok(new_id) => Ok(new_id),
+            Err(e) => {
+                if let Some(new_id) = created {
+                    let _ = self.force_remove_container(&new_id).await;
+                }
+                let _ = self.rename_container(id, &spec.name).await;
+                if running {
+                    let _ = self.start_container(id).await;
+                }
+                Err(e.context("The old container was put back"))
+            }
+        }
+    }
+
+    async fn pull_image(&self, image: &str) -> Result<String> {
+        self.pull(image).await?;
+        let inspect = self
+            .call("inspect_image", self.docker.inspect_image(image))
+            .await
+            .with_context(|| format!("Failed to inspect {}", image))?;
+        Ok(inspect.id.unwrap_or_default())
+    }
+
+    async fn registry_logins(&self) -> Result<Vec<RegistryLogin>> {
+        crate::registries::stored_logins().await
+    }
+
+    async fn registry_login(&self, server: &str, username: &str, password: &str) -> Result<()> {
+        self.cli_with_input(&["login", server, "--username", username, "--password-stdin"], password).await.map(drop)
+    }
+
+    async fn registry_logout(&self, server: &str) -> Result<()> {
+        self.cli(&["logout", server]).await.map(drop)
+    }
+
+    async fn checkpoint_container(&self, id: &str, checkpoint: &str) -> Result<()> {
+        self.require_experimental().await?;
+        self.cli(&["checkpoint", "create", id, checkpoint]).await.map(drop)
+    }
+
+    async fn restore_container(&self, id: &str, checkpoint: &str) -> Result<()> {
+        self.require_experimental().await?;
+        self.cli(&["start", "--checkpoint", checkpoint, id]).await.map(drop)
+    }
+
+    async fn checkpoints(&self, id: &str) -> Result<Vec<String>> {
+        self.require_experimental().await?;
+        let output = self.cli(&["checkpoint", "ls", id]).await?;
+        // The first line is the `CHECKPOINT NAME` header.
+        Ok(output.lines().skip(1).map(|l| l.trim().to_string()).filter(|l| !l.is_empty()).collect())
+    }
+
+    async fn exec(&self, id: &str, command: &[&str]) -> Result<ExecStreams> {
+        let options = CreateExecOptions {
+            cmd: Some(command.to_vec()),
+            attach_stdin: Some(true),
+            attach_stdout: Some(true),
+            attach_stderr: Some(true),
+            tty: Some(false),
+            ..Default::default()
+        };
+        let exec = self
+            .call("create_exec", self.docker.create_exec(id, options))
+            .await
+            .context("Failed to create exec")?;
+        let started = self
+            .call("start_exec", self.docker.start_exec(&exec.id, None::<StartExecOptions>))
+            .await
+            .context("Failed to start exec")?;
+        let StartExecResults::Attached { output, input } = started else {
+            anyhow::bail!("Exec started detached");
+        };
+        Ok(ExecStreams {
+            output: output
+                .map(|output| {
+                    let output = output.context("Failed to read exec output")?;
+                    Ok(String::from_utf8_lossy(&output.into_bytes()).into_owned())
+                })
+                .boxed(),
+            input,
+        })
+    }
+
+    async fn attach(&self, id: &str, stdin: bool) -> Result<ExecStreams> {
+        let options = AttachContainerOptions::<String> {
+            stdin: Some(stdin),
+            stdout: Some(true),
+            stderr: Some(true),
+            stream: Some(true),
+            logs: Some(false),
+            detach_keys: Some("ctrl-p,ctrl-q".to_string()),
+        };
+        let AttachContainerResults { output, input } = self
+            .call("attach_container", self.docker.attach_container(id, Some(options)))
+            .await
+            .context("Failed to attach")?;
+        Ok(ExecStreams {
+            output: output
+                .map(|output| {
+                    let output = output.context("Failed to read attached output")?;
+                    Ok(String::from_utf8_lossy(&output.into_bytes()).into_owned())
+                })
+                .boxed(),
+            input,
+        })
+    }
+
+    async fn remove_container(&self, id: &str) -> Result<()> {
+        self.call("remove_container", self.docker.remove_container(id, None::<RemoveContainerOptions>))
+            .await
+            .context("Failed to remove container")
+    }
+
+    async fn force_remove_container(&self, id: &str) -> Result<()> {
+        let options = RemoveContainerOptions {
+            force: true,
+            v: true,
+            ..Default::default()
+        };
+        self.call("remove_container", self.docker.remove_container(id, Some(options)))
+            .await
+            .context("Failed to remove container")
+    }
+
+    async fn remove_volume(&self, name: &str) -> Result<()> {
+        self.call("remove_volume", self.docker.remove_volume(name, None::<RemoveVolumeOptions>))
+            .await
+            .context("Failed to remove volume")
+    }
+
+    async fn remove_network(&self, id: &str) -> Result<()> {
+        self.call("remove_network", self.docker.remove_network(id))
+            .await
+            .context("Failed to remove network")
+    }
+
+    async fn prune_containers(&self) -> Result<PruneReport> {
+        let response = self
+            .call("prune_containers", self.docker.prune_containers(None::<PruneContainersOptions<String>>))
+            .await
+            .context("Failed to prune containers")?;
+        Ok(PruneReport {
+            containers: response.containers_deleted.unwrap_or_default(),
+            space_reclaimed: response.space_reclaimed.unwrap_or(0).max(0) as u64,
+        })
+    }
+
+    async fn remove_image(&self, id: &str) -> Result<()> {
+        self.call("remove_image", self.docker.remove_image(id, None::<RemoveImageOptions>, None))
+            .await
+            .context("Failed to remove image")?;
+        Ok(())
+    }
+
+    /// Swarm replaces tasks according to the service's update config (one at
+    /// a time by default).
+    async fn update_service_image(&self, service_name: &str, image: &str) -> Result<()> {
+        let service = self
+            .call("inspect_service", self.docker.inspect_service(service_name, None::<InspectServiceOptions>))
+            .await
+            .context("Failed to inspect service")?;
+        let version = service
+            .version
+            .and_then(|v| v.index)
+            .context("Service has no version")?;
+        let mut spec = service.spec.context("Service has no spec")?;
+        spec.task_template
+            .get_or_insert_with(Default::default)
+            .container_spec
+            .get_or_insert_with(Default::default)
+            .image = Some(image.to_string());
+
+        let options = UpdateServiceOptions {
+            version,
+            ..Default::default()
+        };
+        self.call("update_service", self.docker.update_service(service_name, spec, options, None))
+            .await
+            .context("Failed to update service")?;
+        Ok(())
+    }
+
+    async fn service_update_status(&self, service: &str) -> Result<Option<ServiceUpdateStatus>> {
+        let service = self
+            .call("inspect_service", self.docker.inspect_service(service, None::<InspectServiceOptions>))
+            .await
+            .context("Failed to inspect service")?;
+        Ok(service.update_status)
+    }
+
+    fn api_metrics(&self) -> Option<&ApiMetrics> {
+        Some(&self.metrics)
+    }
+
+    fn pull_logins(&self) -> Option<&PullLogins> {
+        Some(&self.pull_logins)
+    }
+}
+
+/// `ExposedPorts` and `PortBindings` publishing `ports`.
+#[allow(clippy::type_complexity)]
+fn port_config(ports: &[PublishedPort]) -> (HashMap<String, HashMap<(), ()>>, HashMap<String, Option<Vec<PortBinding>>>) {
+    let mut exposed_ports = HashMap::new();
+    let mut port_bindings: HashMap<String, Option<Vec<PortBinding>>> = HashMap::new();
+    for port in ports {
+        let key = format!("{}/{}", port.container_port, port.protocol);
+        exposed_ports.insert(key.clone(), HashMap::new());
+        port_bindings.entry(key).or_default().get_or_insert_with(Vec::new).push(PortBinding {
+            host_ip: port.host_ip.clone(),
+            // Empty for any free port.
+            host_port: Some(if port.host_port == 0 { String::new() } else { port.host_port.to_string() }),
+        });
+    }
+    (exposed_ports, port_bindings)
+}
+
+fn log_line(output: LogOutput) -> LogLine {
+    let stderr = matches!(output, LogOutput::StdErr { .. });
+    LogLine {
+        text: String::from_utf8_lossy(&output.into_bytes()).into_owned(),
+        stderr,
+    }
+}
+
+fn io_counters(stats: &Stats) -> IoCounters {
+    let mut io = IoCounters::default();
+    for network in stats.networks.iter().flat_map(|n| n.values()) {
+        io.net_rx += network.rx_bytes;
+        io.net_tx += network.tx_bytes;
+    }
+    // cgroup v1 reports "Read"/"Write", v2 "read"/"write".
+    for entry in stats.blkio_stats.io_service_bytes_recursive.iter().flatten() {
+        match entry.op.to_ascii_lowercase().as_str() {
+            "read" => io.block_read += entry.value,
+            "write" => io.block_write += entry.value,
+            _ => {}
+        }
+    }
+    io
+}
+
+/// The kinds of memory in `memory.stat`, which cgroup v1 and v2 name
+/// differently. The v1 stats as bollard parses them leave out `swap` and
+/// `total_swap`, and v2 has no swap in `memory.stat`, so swap is unknown.
+fn memory_breakdown(stats: &Stats) -> Option<MemoryBreakdown> {
+    let usage = stats.memory_stats.usage?;
+    let (rss, cache, inactive_file) = match stats.memory_stats.stats? {
+        MemoryStatsStats::V1(v1) => (v1.total_rss, v1.total_cache, v1.total_inactive_file),
+        MemoryStatsStats::V2(v2) => (v2.anon, v2.file, v2.inactive_file),
+    };
+    Some(MemoryBreakdown {
+        rss,
+        cache,
+        swap: None,
+        working_set: usage.saturating_sub(inactive_file),
+    })
+}
+
+fn interface_counters(stats: &Stats) -> Vec<InterfaceCounters> {
+    let mut interfaces: Vec<InterfaceCounters> = stats
+        .networks
+        .iter()
+        .flatten()
+        .map(|(name, network)| InterfaceCounters {
+            name: name.clone(),
+            rx: network.rx_bytes,
+            tx: network.tx_bytes,
+        })
+        .collect();
+    interfaces.sort_by(|a, b| a.name.cmp(&b.name));
+    interfaces
+}
+
+/// Drops the environment, command, entrypoint, working directory and
+/// labels `config` has in common with the config of the image it was
+/// created from, so a new image's own values apply.
+fn without_image_defaults(config: &mut Config<String>, image: &ContainerConfig) {
+    if let (Some(env), Some(image_env)) = (&mut config.env, &image.env) {
+        env.retain(|entry| !image_env.contains(entry));
+    }
+    if let (Some(labels), Some(image_labels)) = (&mut config.labels, &image.labels) {
+        labels.retain(|key, value| image_labels.get(key) != Some(value));
+    }
+    if config.cmd.is_some() && config.cmd == image.cmd {
+        config.cmd = None;
+    }
+    if config.entrypoint.is_some() && config.entrypoint == image.entrypoint {
+        config.entrypoint = None;
+    }
+    if config.working_dir.is_some() && config.working_dir == image.working_dir {
+        config.working_dir = None;
+    }
+}
+
+/// A network's name and the container's endpoint on it.
+type Endpoint<T> = (String, T);
+
+/// The endpoint the container is created with, on its network mode's
+/// network, and the others to connect it to afterwards. Plain `docker run`
+/// containers have the mode `default`, which is the network `bridge`.
+fn split_endpoints<T>(network_mode: &str, endpoints: Vec<Endpoint<T>>) -> (Vec<Endpoint<T>>, Vec<Endpoint<T>>) {
+    let primary = match network_mode {
+        "default" => "bridge",
+        mode => mode,
+    };
+    endpoints.into_iter().partition(|(network, _)| network == primary)
+}
+
+/// The swap limit (memory plus swap) to go with a new memory limit, given
+/// the current ones: unlimited (-1) stays so, and a set limit keeps its
+/// ratio to the memory, so no swap (the same as the memory) stays no swap.
+/// Without either limit set, the daemon's default applies.
+fn scaled_swap(memory: i64, old_memory: i64, old_swap: i64) -> Option<i64> {
+    match (old_memory, old_swap) {
+        (_, -1) => Some(-1),
+        (old_memory, old_swap) if old_memory > 0 && old_swap > 0 => {
+            Some((memory as i128 * old_swap as i128 / old_memory as i128) as i64)
+        }
+        _ => None,
+    }
+}
+
+fn calculate_cpu_usage(stats: &Stats) -> f64 {
+    let cpu_delta = stats.cpu_stats.cpu_usage.total_usage - stats.precpu_stats.cpu_usage.total_usage;
+    let system_delta = stats.cpu_stats.system_cpu_usage.unwrap_or(0) - stats.precpu_stats.system_cpu_usage.unwrap_or(0);
+    
+    if system_delta > 0 {
+        (cpu_delta as f64 / system_delta as f64) * 100.0
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn what_the_old_image_set_is_left_to_the_new_one() {
+        let strings = |values: &[&str]| Some(values.iter().map(|v| v.to_string()).collect::<Vec<_>>());
+        let labels = |pairs: &[(&str, &str)]| Some(pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect::<HashMap<_, _>>());
+        let old_image = ContainerConfig {
+            env: strings(&["PATH=/usr/lib/postgresql/15/bin:/usr/bin", "PG_VERSION=15.4"]),
+            cmd: strings(&["postgres"]),
+            entrypoint: strings(&["docker-entrypoint.sh"]),
+            working_dir: Some("/".to_string()),
+            labels: labels(&[("maintainer", "pg"), ("version", "15")]),
+            ..Default::default()
+        };
+        let mut config = Config {
+            env: strings(&["PG_VERSION=15.4", "POSTGRES_PASSWORD=dev", "PATH=/opt/bin:/usr/bin"]),
+            cmd: strings(&["postgres", "-c", "fsync=off"]),
+            entrypoint: strings(&["docker-entrypoint.sh"]),
+            working_dir: Some("/".to_string()),
+            labels: labels(&[("maintainer", "pg"), ("version", "custom"), ("com.docker.compose.service", "db")]),
+            ..Default::default()
+        };
+        without_image_defaults(&mut config, &old_image);
+
+        assert_eq!(config.env, strings(&["POSTGRES_PASSWORD=dev", "PATH=/opt/bin:/usr/bin"]));
+        assert_eq!(config.cmd, strings(&["postgres", "-c", "fsync=off"]));
+        assert_eq!((config.entrypoint, config.working_dir), (None, None));
+        assert_eq!(config.labels, labels(&[("version", "custom"), ("com.docker.compose.service", "db")]));
+    }
+
+    #[test]
+    fn recreated_containers_start_on_their_network_modes_network() {
+        let networks = |names: &[&str]| names.iter().map(|n| (n.to_string(), ())).collect::<Vec<_>>();
+        let cases = [
+            ("default", vec!["bridge"], vec!["bridge"], vec![]),
+            ("default", vec!["bridge", "monitoring"], vec!["bridge"], vec!["monitoring"]),
+            ("bridge", vec!["bridge"], vec!["bridge"], vec![]),
+            ("shop_default", vec!["shop_default", "proxy"], vec!["shop_default"], vec!["proxy"]),
+            ("host", vec!["host"], vec!["host"], vec![]),
+        ];
+        for (mode, attached, primary, others) in cases {
+            let (first, rest) = split_endpoints(mode, networks(&attached));
+            assert_eq!((first, rest), (networks(&primary), networks(&others)), "{}", mode);
+        }
+    }
+
+    #[test]
+    fn swap_limits_follow_the_memory_limit() {
+        const MB: i64 = 1 << 20;
+        for (memory, old_memory, old_swap, swap) in [
+            // The default of `docker run --memory`: twice the memory.
+            (512 * MB, 256 * MB, 512 * MB, Some(1024 * MB)),
+            // No swap stays no swap.
+            (512 * MB, 256 * MB, 256 * MB, Some(512 * MB)),
+            (512 * MB, 256 * MB, -1, Some(-1)),
+            (512 * MB, 0, 0, None),
+            (512 * MB, 256 * MB, 0, None),
+        ] {
+            assert_eq!(scaled_swap(memory, old_memory, old_swap), swap, "{} {} {}", memory, old_memory, old_swap);
+        }
+    }
+}
@@ -0,0 +1,84 @@
+//! Client-side limits on Docker API traffic, protecting small daemons and
+//! remote links from being saturated by stats polling.
+
+use crate::config::ApiConfig;
+use std::time::Duration;
+use tokio::{
+    sync::{Mutex, Semaphore, SemaphorePermit},
+    time::{sleep_until, Instant},
+};
+
+pub struct ApiLimiter {
+    concurrency: Semaphore,
+    /// Minimum spacing between request starts, if a rate is configured.
+    spacing: Option<Duration>,
+    next_slot: Mutex<Instant>,
+}
+
+impl ApiLimiter {
+    pub fn new(config: &ApiConfig) -> Self {
+        Self {
+            concurrency: Semaphore::new(config.max_concurrent.max(1)),
+            spacing: (config.max_per_second > 0)
+                .then(|| Duration::from_secs_f64(1.0 / config.max_per_second as f64)),
+            next_slot: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Waits for a free request slot; hold the permit until the call is done.
+    pub async fn acquire(&self) -> SemaphorePermit<'_> {
+        let permit = self
+            .concurrency
+            .acquire()
+            .await
+            .expect("API semaphore is never closed");
+        if let Some(spacing) = self.spacing {
+            let slot = {
+                let mut next = self.next_slot.lock().await;
+                let slot = (*next).max(Instant::now());
+                *next = slot + spacing;
+                slot
+            };
+            sleep_until(slot).await;
+        }
+        permit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn requests_are_spaced_and_capped() {
+        let limiter = ApiLimiter::new(&ApiConfig {
+            max_concurrent: 1,
+            max_per_second: 50,
+            ..Default::default()
+        });
+        let started = Instant::now();
+        for _ in 0..3 {
+            drop(limiter.acquire().await);
+        }
+        assert!(started.elapsed() >= Duration::from_millis(40), "{:?}", started.elapsed());
+
+        let held = limiter.acquire().await;
+        assert!(tokio::time::timeout(Duration::from_millis(50), limiter.acquire()).await.is_err());
+        drop(held);
+        assert!(tokio::time::timeout(Duration::from_millis(50), limiter.acquire()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn no_rate_means_no_waiting() {
+        let limiter = ApiLimiter::new(&ApiConfig {
+            max_concurrent: 0,
+            max_per_second: 0,
+            ..Default::default()
+        });
+        let started = Instant::now();
+        for _ in 0..100 {
+            drop(limiter.acquire().await);
+        }
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,113 @@
+//! Per-endpoint accounting of Docker API calls, shown in the debug panel.
+
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Default, Clone)]
+pub struct EndpointStats {
+    pub calls: u64,
+    pub errors: u64,
+    pub total: Duration,
+    pub max: Duration,
+    pub last: Duration,
+}
+
+impl EndpointStats {
+    pub fn average(&self) -> Duration {
+        if self.calls == 0 {
+            Duration::ZERO
+        } else {
+            self.total / self.calls as u32
+        }
+    }
+
+    pub fn error_rate(&self) -> f64 {
+        if self.calls == 0 {
+            0.0
+        } else {
+            self.errors as f64 / self.calls as f64 * 100.0
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ApiMetrics {
+    endpoints: Mutex<BTreeMap<&'static str, EndpointStats>>,
+    in_flight: AtomicUsize,
+}
+
+impl ApiMetrics {
+    /// Awaits `call`, recording its latency and outcome under `endpoint`.
+    pub async fn observe<T, E>(&self, endpoint: &'static str, call: impl Future<Output = Result<T, E>>) -> Result<T, E> {
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+        let started = Instant::now();
+        let result = call.await;
+        let elapsed = started.elapsed();
+        self.in_flight.fetch_sub(1, Ordering::Relaxed);
+
+        let mut endpoints = self.endpoints.lock().unwrap();
+        let stats = endpoints.entry(endpoint).or_default();
+        stats.calls += 1;
+        stats.errors += u64::from(result.is_err());
+        stats.total += elapsed;
+        stats.max = stats.max.max(elapsed);
+        stats.last = elapsed;
+        result
+    }
+
+    pub fn snapshot(&self) -> Vec<(&'static str, EndpointStats)> {
+        self.endpoints
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, stats)| (*name, stats.clone()))
+            .collect()
+    }
+
+    /// Requests and streams currently waiting on the daemon.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn calls_are_counted_per_endpoint() {
+        let metrics = ApiMetrics::default();
+        let _ = metrics.observe("/containers/json", async { Ok::<_, ()>(()) }).await;
+        let _ = metrics.observe("/containers/json", async { Err::<(), _>("refused") }).await;
+        let _ = metrics.observe("/containers/json", async { Ok::<_, &str>(()) }).await;
+        let _ = metrics.observe("/events", async { Ok::<_, ()>(()) }).await;
+        assert_eq!(metrics.in_flight(), 0);
+
+        let snapshot = metrics.snapshot();
+        let summary: Vec<_> = snapshot.iter().map(|(name, s)| (*name, s.calls, s.errors)).collect();
+        assert_eq!(summary, [("/containers/json", 3, 1), ("/events", 1, 0)]);
+        assert!((snapshot[0].1.error_rate() - 100.0 / 3.0).abs() < 1e-9);
+        assert!(snapshot[0].1.max >= snapshot[0].1.average());
+    }
+
+    #[test]
+    fn endpoints_without_calls_average_zero() {
+        let stats = EndpointStats::default();
+        assert_eq!((stats.average(), stats.error_rate()), (Duration::ZERO, 0.0));
+
+        let stats = EndpointStats {
+            calls: 4,
+            errors: 1,
+            total: Duration::from_millis(100),
+            ..Default::default()
+        };
+        assert_eq!((stats.average(), stats.error_rate()), (Duration::from_millis(25), 25.0));
+    }
+}
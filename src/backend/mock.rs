@@ -0,0 +1,368 @@
+//! A scripted backend for tests: each refresh returns the next scripted
+//! snapshot (or error), and mutating calls are recorded for assertions.
+
+use super::{ContainerBackend, ContainerEvent, ContainerStats, DiskUsage, ExecStreams, LogLine, PruneReport, RegistryLogin, ResourceLimits, RunSpec};
+use crate::registries::PullLogins;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use bollard::models::{ContainerInspectResponse, ContainerTopResponse, ImageSummary, Network, ServiceUpdateStatus, Volume};
+use futures::stream::{self, BoxStream, StreamExt};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+use tokio::io::AsyncReadExt;
+
+/// A running container with the given name and no stats to speak of.
+pub fn container(name: &str) -> ContainerStats {
+    ContainerStats {
+        id: format!("{}-id", name),
+        name: name.to_string(),
+        cpu_usage: 0.0,
+        memory_usage: 0,
+        memory_limit: 1,
+        memory_unlimited: false,
+        memory_breakdown: None,
+        status: "Up 1 minute".to_string(),
+        created: String::new(),
+        image: format!("{}:latest", name),
+        image_id: String::new(),
+        ports: Vec::new(),
+        labels: HashMap::new(),
+        network_mode: "bridge".to_string(),
+        networks: Vec::new(),
+        mounts: Vec::new(),
+        io: Default::default(),
+        interfaces: Vec::new(),
+        cpu_cores: Vec::new(),
+        sampled_at: None,
+        sample_latency_ms: None,
+    }
+}
+
+/// One scripted response to [`ContainerBackend::containers`].
+pub enum Snapshot {
+    Containers(Vec<ContainerStats>),
+    Error(&'static str),
+}
+
+#[derive(Default)]
+pub struct MockBackend {
+    script: Mutex<VecDeque<Snapshot>>,
+    /// Returned once the script runs out, so tests can tick freely.
+    last: Mutex<Vec<ContainerStats>>,
+    pub service_updates: Mutex<Vec<(String, String)>>,
+    pub service_status: Mutex<Option<ServiceUpdateStatus>>,
+    /// What `volumes`, `networks` and `images` return.
+    pub volumes: Mutex<Vec<Volume>>,
+    pub networks: Mutex<Vec<Network>>,
+    pub images: Mutex<Vec<ImageSummary>>,
+    /// IDs passed to the `remove_*` calls, in order.
+    pub removed: Mutex<Vec<String>>,
+    /// IDs the `remove_*` calls fail for; they are still recorded in
+    /// `removed`.
+    pub failing_removals: Mutex<Vec<String>>,
+    /// `(action, id)` for every lifecycle call such as start and stop.
+    pub lifecycle: Mutex<Vec<(&'static str, String)>>,
+    /// What `recent_logs` returns for every container, dated
+    /// 2024-01-01T00:00:00Z when asked for timestamps.
+    pub logs: Mutex<Vec<String>>,
+    /// `(tail, since)` of every `recent_logs` call; `since` is not applied.
+    pub log_requests: Mutex<Vec<(usize, i64)>>,
+    /// Every `run_container` call, in order.
+    pub runs: Mutex<Vec<RunSpec>>,
+    /// `(id, spec)` of every `recreate_container` call.
+    pub recreated: Mutex<Vec<(String, RunSpec)>>,
+    /// Every `pull_image` call, in order.
+    pub pulls: Mutex<Vec<String>>,
+    /// The stored logins; `registry_login` and `registry_logout` change them.
+    pub logins: Mutex<Vec<RegistryLogin>>,
+    pub pull_logins: PullLogins,
+    /// What `follow_logs` streams for every container before it ends, and
+    /// what `attach` streams before it waits for more.
+    pub followed: Mutex<Vec<String>>,
+    /// Keeps `follow_logs` streams open after `followed`, as a running
+    /// container's would be.
+    pub follow_forever: Mutex<bool>,
+    /// `(id, command)` of every `exec` call.
+    pub execs: Mutex<Vec<(String, Vec<String>)>>,
+    /// What every `exec` prints, in turn; nothing once they run out.
+    pub exec_output: Mutex<VecDeque<String>>,
+    /// `(id, stdin)` of every `attach` call.
+    pub attached: Mutex<Vec<(String, bool)>>,
+    /// Everything written to the stdin of attached containers.
+    pub attach_input: Arc<Mutex<Vec<u8>>>,
+}
+
+impl MockBackend {
+    pub fn new(script: impl IntoIterator<Item = Snapshot>) -> Self {
+        Self {
+            script: Mutex::new(script.into_iter().collect()),
+            ..Default::default()
+        }
+    }
+
+    fn record_removal(&self, id: &str) -> Result<()> {
+        self.removed.lock().unwrap().push(id.to_string());
+        if self.failing_removals.lock().unwrap().iter().any(|failing| failing == id) {
+            return Err(anyhow!("{} is in use", id));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ContainerBackend for MockBackend {
+    async fn containers(&self) -> Result<Vec<ContainerStats>> {
+        match self.script.lock().unwrap().pop_front() {
+            Some(Snapshot::Containers(containers)) => {
+                *self.last.lock().unwrap() = containers.clone();
+                Ok(containers)
+            }
+            Some(Snapshot::Error(message)) => Err(anyhow!(message)),
+            None => Ok(self.last.lock().unwrap().clone()),
+        }
+    }
+
+    async fn inspect_container(&self, id: &str) -> Result<ContainerInspectResponse> {
+        Ok(ContainerInspectResponse {
+            id: Some(id.to_string()),
+            ..Default::default()
+        })
+    }
+
+    async fn networks(&self) -> Result<Vec<Network>> {
+        Ok(self.networks.lock().unwrap().clone())
+    }
+
+    async fn volumes(&self) -> Result<Vec<Volume>> {
+        Ok(self.volumes.lock().unwrap().clone())
+    }
+
+    async fn images(&self) -> Result<Vec<ImageSummary>> {
+        Ok(self.images.lock().unwrap().clone())
+    }
+
+    async fn disk_usage(&self) -> Result<DiskUsage> {
+        Ok(DiskUsage::default())
+    }
+
+    async fn container_events(&self, _id: &str, _since: i64) -> Result<Vec<ContainerEvent>> {
+        Ok(Vec::new())
+    }
+
+    fn follow_logs(&self, _id: &str, _tail: usize, _timestamps: bool) -> BoxStream<'static, Result<LogLine>> {
+        let lines: Vec<Result<LogLine>> = self
+            .followed
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|text| Ok(LogLine { text: text.clone(), stderr: false }))
+            .collect();
+        if *self.follow_forever.lock().unwrap() {
+            return stream::iter(lines).chain(stream::pending()).boxed();
+        }
+        stream::iter(lines).boxed()
+    }
+
+    async fn recent_logs(&self, _id: &str, tail: usize, since: i64, timestamps: bool) -> Result<Vec<LogLine>> {
+        self.log_requests.lock().unwrap().push((tail, since));
+        let logs = self.logs.lock().unwrap();
+        Ok(logs[logs.len().saturating_sub(tail)..]
+            .iter()
+            .map(|text| LogLine {
+                text: if timestamps { format!("2024-01-01T00:00:00Z {}", text) } else { text.clone() },
+                stderr: false,
+            })
+            .collect())
+    }
+
+    /// All of `logs` with timestamps, regardless of `since`.
+    async fn logs_since(&self, id: &str, _since: i64) -> Result<Vec<LogLine>> {
+        let tail = self.logs.lock().unwrap().len();
+        self.recent_logs(id, tail, 0, true).await
+    }
+
+    async fn processes(&self, _id: &str) -> Result<ContainerTopResponse> {
+        Ok(ContainerTopResponse::default())
+    }
+
+    async fn start_container(&self, id: &str) -> Result<()> {
+        self.lifecycle.lock().unwrap().push(("start", id.to_string()));
+        Ok(())
+    }
+
+    async fn stop_container(&self, id: &str) -> Result<()> {
+        self.lifecycle.lock().unwrap().push(("stop", id.to_string()));
+        Ok(())
+    }
+
+    async fn restart_container(&self, id: &str) -> Result<()> {
+        self.lifecycle.lock().unwrap().push(("restart", id.to_string()));
+        Ok(())
+    }
+
+    async fn pause_container(&self, id: &str) -> Result<()> {
+        self.lifecycle.lock().unwrap().push(("pause", id.to_string()));
+        Ok(())
+    }
+
+    async fn unpause_container(&self, id: &str) -> Result<()> {
+        self.lifecycle.lock().unwrap().push(("unpause", id.to_string()));
+        Ok(())
+    }
+
+    async fn kill_container(&self, id: &str, _signal: &str) -> Result<()> {
+        self.lifecycle.lock().unwrap().push(("kill", id.to_string()));
+        Ok(())
+    }
+
+    async fn rename_container(&self, id: &str, _name: &str) -> Result<()> {
+        self.lifecycle.lock().unwrap().push(("rename", id.to_string()));
+        Ok(())
+    }
+
+    async fn update_container(&self, id: &str, _limits: &ResourceLimits) -> Result<()> {
+        self.lifecycle.lock().unwrap().push(("update", id.to_string()));
+        Ok(())
+    }
+
+    async fn commit_container(&self, id: &str, _repository: &str, _tag: &str) -> Result<()> {
+        self.lifecycle.lock().unwrap().push(("commit", id.to_string()));
+        Ok(())
+    }
+
+    async fn run_container(&self, spec: &RunSpec) -> Result<String> {
+        self.runs.lock().unwrap().push(spec.clone());
+        Ok(format!("{}-id", spec.name))
+    }
+
+    async fn recreate_container(&self, id: &str, spec: &RunSpec) -> Result<String> {
+        self.recreated.lock().unwrap().push((id.to_string(), spec.clone()));
+        Ok(format!("{}-new", id))
+    }
+
+    /// Always a newer image than the containers' `image_id`.
+    async fn pull_image(&self, image: &str) -> Result<String> {
+        self.pulls.lock().unwrap().push(image.to_string());
+        Ok(format!("sha256:{}", image))
+    }
+
+    async fn registry_logins(&self) -> Result<Vec<RegistryLogin>> {
+        Ok(self.logins.lock().unwrap().clone())
+    }
+
+    /// Accepts any password but an empty one.
+    async fn registry_login(&self, server: &str, username: &str, password: &str) -> Result<()> {
+        if password.is_empty() {
+            return Err(anyhow!("unauthorized: incorrect username or password"));
+        }
+        let mut logins = self.logins.lock().unwrap();
+        logins.retain(|l| l.server != server);
+        logins.push(RegistryLogin {
+            server: server.to_string(),
+            username: username.to_string(),
+            store: "config.json".to_string(),
+        });
+        Ok(())
+    }
+
+    async fn registry_logout(&self, server: &str) -> Result<()> {
+        self.logins.lock().unwrap().retain(|l| l.server != server);
+        Ok(())
+    }
+
+    async fn checkpoint_container(&self, id: &str, _checkpoint: &str) -> Result<()> {
+        self.lifecycle.lock().unwrap().push(("checkpoint", id.to_string()));
+        Ok(())
+    }
+
+    async fn restore_container(&self, id: &str, _checkpoint: &str) -> Result<()> {
+        self.lifecycle.lock().unwrap().push(("restore", id.to_string()));
+        Ok(())
+    }
+
+    async fn checkpoints(&self, _id: &str) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    async fn exec(&self, id: &str, command: &[&str]) -> Result<ExecStreams> {
+        self.execs.lock().unwrap().push((id.to_string(), command.iter().map(|c| c.to_string()).collect()));
+        let output = self.exec_output.lock().unwrap().pop_front().map(Ok);
+        Ok(ExecStreams {
+            output: stream::iter(output).boxed(),
+            input: Box::pin(tokio::io::sink()),
+        })
+    }
+
+    async fn attach(&self, id: &str, stdin: bool) -> Result<ExecStreams> {
+        self.attached.lock().unwrap().push((id.to_string(), stdin));
+        let lines: Vec<Result<String>> = self.followed.lock().unwrap().iter().map(|text| Ok(text.clone())).collect();
+        let (input, mut process) = tokio::io::duplex(4096);
+        let received = self.attach_input.clone();
+        tokio::spawn(async move {
+            let mut buffer = [0; 4096];
+            while let Ok(read @ 1..) = process.read(&mut buffer).await {
+                received.lock().unwrap().extend_from_slice(&buffer[..read]);
+            }
+        });
+        Ok(ExecStreams {
+            output: stream::iter(lines).chain(stream::pending()).boxed(),
+            input: Box::pin(input),
+        })
+    }
+
+    async fn remove_container(&self, id: &str) -> Result<()> {
+        self.record_removal(id)
+    }
+
+    async fn force_remove_container(&self, id: &str) -> Result<()> {
+        self.lifecycle.lock().unwrap().push(("force remove", id.to_string()));
+        self.record_removal(id)
+    }
+
+    /// Removes the stopped containers of the last snapshot, at 1 KB each.
+    async fn prune_containers(&self) -> Result<PruneReport> {
+        let stopped: Vec<String> = self
+            .last
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|c| !c.is_running())
+            .map(|c| c.id.clone())
+            .collect();
+        self.removed.lock().unwrap().extend(stopped.iter().cloned());
+        Ok(PruneReport {
+            space_reclaimed: stopped.len() as u64 * 1024,
+            containers: stopped,
+        })
+    }
+
+    async fn remove_volume(&self, name: &str) -> Result<()> {
+        self.record_removal(name)
+    }
+
+    async fn remove_network(&self, id: &str) -> Result<()> {
+        self.record_removal(id)
+    }
+
+    async fn remove_image(&self, id: &str) -> Result<()> {
+        self.record_removal(id)
+    }
+
+    async fn update_service_image(&self, service: &str, image: &str) -> Result<()> {
+        self.service_updates
+            .lock()
+            .unwrap()
+            .push((service.to_string(), image.to_string()));
+        Ok(())
+    }
+
+    async fn service_update_status(&self, _service: &str) -> Result<Option<ServiceUpdateStatus>> {
+        Ok(self.service_status.lock().unwrap().clone())
+    }
+
+    fn pull_logins(&self) -> Option<&PullLogins> {
+        Some(&self.pull_logins)
+    }
+}
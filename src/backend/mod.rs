@@ -0,0 +1,463 @@
+//! Everything dockertop knows about containers goes through
+//! [`ContainerBackend`], so the app can run against Docker, the `--demo`
+//! simulation, or a scripted mock in tests.
+
+mod audit;
+mod demo;
+mod docker;
+mod limiter;
+mod metrics;
+#[cfg(test)]
+pub mod mock;
+mod trace;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use bollard::models::{ContainerInspectResponse, ContainerTopResponse, ImageSummary, Network, Port, ServiceUpdateStatus, Volume};
+use futures::stream::BoxStream;
+use serde::{Deserialize, Serialize};
+use crate::registries::PullLogins;
+use std::{collections::HashMap, fmt, pin::Pin};
+use tokio::io::AsyncWrite;
+
+pub use audit::{AuditBackend, AuditLog};
+pub use demo::DemoBackend;
+pub use docker::DockerBackend;
+pub use metrics::ApiMetrics;
+pub use trace::{RecordingBackend, ReplayBackend};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContainerStats {
+    pub id: String,
+    pub name: String,
+    pub cpu_usage: f64,
+    pub memory_usage: u64,
+    /// The host's memory when [`ContainerStats::memory_unlimited`].
+    pub memory_limit: u64,
+    /// No memory limit is set. Docker then reports the host's memory as the
+    /// limit, which makes for a misleading percentage unless labeled as such.
+    #[serde(default)]
+    pub memory_unlimited: bool,
+    /// What `memory_usage` is made of, where the daemon reports it.
+    #[serde(default)]
+    pub memory_breakdown: Option<MemoryBreakdown>,
+    pub status: String,
+    pub created: String,
+    pub image: String,
+    #[serde(default)]
+    pub image_id: String,
+    pub ports: Vec<Port>,
+    pub labels: HashMap<String, String>,
+    /// `HostConfig.NetworkMode`, e.g. `bridge` or `container:<id>`.
+    #[serde(default)]
+    pub network_mode: String,
+    #[serde(default)]
+    pub networks: Vec<NetworkAttachment>,
+    #[serde(default)]
+    pub mounts: Vec<Mount>,
+    #[serde(default)]
+    pub io: IoCounters,
+    /// Network counters per interface, by name; `io` has their sums.
+    #[serde(default)]
+    pub interfaces: Vec<InterfaceCounters>,
+    /// Cumulative CPU time per core in nanoseconds, from `percpu_usage`;
+    /// empty where the daemon does not report it, as on cgroup v2.
+    #[serde(default)]
+    pub cpu_cores: Vec<u64>,
+    /// When the daemon read the stats, in Unix milliseconds, if known.
+    #[serde(default)]
+    pub sampled_at: Option<i64>,
+    /// How long the stats request took.
+    #[serde(default)]
+    pub sample_latency_ms: Option<u64>,
+}
+
+/// Cumulative byte counters of one network interface (`eth0`, ...).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InterfaceCounters {
+    pub name: String,
+    pub rx: u64,
+    pub tx: u64,
+}
+
+/// A container's memory by kind, in bytes, from the cgroup's `memory.stat`.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct MemoryBreakdown {
+    /// Anonymous memory: heaps and stacks.
+    pub rss: u64,
+    /// Page cache, which counts towards the usage but is given back under
+    /// memory pressure.
+    pub cache: u64,
+    /// `None` where the daemon does not report it.
+    pub swap: Option<u64>,
+    /// The usage less inactive page cache, the figure `docker stats` shows.
+    pub working_set: u64,
+}
+
+/// Cumulative network and block I/O byte counters; they restart from zero
+/// when the container does.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct IoCounters {
+    pub net_rx: u64,
+    pub net_tx: u64,
+    pub block_read: u64,
+    pub block_write: u64,
+}
+
+/// Space used by Docker, in bytes, as reported by `system df`.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct DiskUsage {
+    pub images: u64,
+    /// Writable layers of all containers.
+    pub containers: u64,
+    pub volumes: u64,
+    pub build_cache: u64,
+}
+
+impl DiskUsage {
+    pub fn total(&self) -> u64 {
+        self.images + self.containers + self.volumes + self.build_cache
+    }
+}
+
+/// The CFS period CPU quotas are given for, in microseconds.
+pub const CPU_PERIOD: i64 = 100_000;
+
+/// Ctrl-P Ctrl-Q, the keys the daemon detaches an attached client on
+/// without closing the container's stdin.
+pub const DETACH_KEYS: &[u8] = b"\x10\x11";
+
+/// A container for [`ContainerBackend::run_container`] to create.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RunSpec {
+    pub name: String,
+    /// `repository:tag`.
+    pub image: String,
+    /// `KEY=value`.
+    pub env: Vec<String>,
+    pub ports: Vec<PublishedPort>,
+    /// Binds and named volumes, e.g. `pgdata:/var/lib/postgresql/data`.
+    pub volumes: Vec<String>,
+    pub labels: HashMap<String, String>,
+}
+
+/// A container port published on the host, as with `docker run -p`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublishedPort {
+    /// All interfaces when `None`.
+    pub host_ip: Option<String>,
+    /// 0 for any free port.
+    pub host_port: u16,
+    pub container_port: u16,
+    /// `tcp` or `udp`.
+    pub protocol: String,
+}
+
+/// New limits for [`ContainerBackend::update_container`]; `None` leaves one
+/// as it is.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResourceLimits {
+    /// Relative CPU weight; 1024 is Docker's default.
+    pub cpu_shares: Option<i64>,
+    /// Microseconds of CPU time per [`CPU_PERIOD`], -1 for no quota.
+    pub cpu_quota: Option<i64>,
+    /// Bytes.
+    pub memory: Option<i64>,
+}
+
+/// The `docker update` flags that would set the same limits.
+impl fmt::Display for ResourceLimits {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut flags = Vec::new();
+        if let Some(shares) = self.cpu_shares {
+            flags.push(format!("--cpu-shares {}", shares));
+        }
+        if let Some(quota) = self.cpu_quota {
+            flags.push(format!("--cpus {}", quota.max(0) as f64 / CPU_PERIOD as f64));
+        }
+        if let Some(memory) = self.memory {
+            flags.push(format!("--memory {}", crate::limits::format_memory(memory)));
+        }
+        write!(f, "{}", flags.join(" "))
+    }
+}
+
+/// What [`ContainerBackend::prune_containers`] removed.
+#[derive(Debug, Clone, Default)]
+pub struct PruneReport {
+    /// IDs of the removed containers.
+    pub containers: Vec<String>,
+    /// Bytes freed by their writable layers.
+    pub space_reclaimed: u64,
+}
+
+/// A registry login stored by the Docker client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegistryLogin {
+    /// The key it is stored under, e.g. `https://index.docker.io/v1/` or
+    /// `ghcr.io`.
+    pub server: String,
+    pub username: String,
+    /// `config.json`, or the credential helper keeping the secret, e.g.
+    /// `desktop` or `pass`.
+    pub store: String,
+}
+
+/// Container output, as one line or as the chunk Docker sent it in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogLine {
+    pub text: String,
+    /// Written to stderr. Docker can only tell the streams apart for
+    /// containers without a TTY; with one, all output counts as stdout.
+    pub stderr: bool,
+}
+
+/// One entry of the daemon's event log for a container.
+#[derive(Debug, Clone)]
+pub struct ContainerEvent {
+    /// Unix seconds.
+    pub time: i64,
+    /// `start`, `die`, `health_status: healthy`, `exec_create: sh`, ...
+    pub action: String,
+    /// Extra details such as `exitCode` or `signal`.
+    pub attributes: HashMap<String, String>,
+}
+
+/// The attached streams of a command started with [`ContainerBackend::exec`].
+/// Dropping `input` closes the command's stdin.
+pub struct ExecStreams {
+    /// Stdout and stderr interleaved, one item per chunk.
+    pub output: BoxStream<'static, Result<String>>,
+    pub input: Pin<Box<dyn AsyncWrite + Send>>,
+}
+
+/// A volume, bind or tmpfs mounted into a container.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Mount {
+    /// `volume`, `bind`, `tmpfs` or `npipe`.
+    pub kind: String,
+    /// Volume name; empty for other kinds.
+    pub name: String,
+    pub source: String,
+    pub destination: String,
+    pub read_write: bool,
+}
+
+/// A container's endpoint on one network.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkAttachment {
+    pub network: String,
+    pub ip_address: String,
+    /// Empty unless the network has IPv6 enabled.
+    #[serde(default)]
+    pub ipv6_address: String,
+}
+
+impl NetworkAttachment {
+    /// The IPv4 and IPv6 addresses the container has, in that order.
+    pub fn addresses(&self) -> impl Iterator<Item = &str> {
+        [self.ip_address.as_str(), self.ipv6_address.as_str()].into_iter().filter(|a| !a.is_empty())
+    }
+}
+
+/// `ip:port`, with an IPv6 address bracketed as URLs and `docker run -p`
+/// take it.
+pub fn host_port(ip: &str, port: impl std::fmt::Display) -> String {
+    if ip.contains(':') {
+        format!("[{}]:{}", ip, port)
+    } else {
+        format!("{}:{}", ip, port)
+    }
+}
+
+/// Whether a port is published on every address of its family, as with
+/// `0.0.0.0` and `::`.
+pub fn is_unspecified(ip: &str) -> bool {
+    ip.parse::<std::net::IpAddr>().map_or(ip.is_empty(), |ip| ip.is_unspecified())
+}
+
+impl ContainerStats {
+    pub fn is_running(&self) -> bool {
+        self.status.starts_with("Up")
+    }
+
+    /// Seconds since the stats were read, once that is more than two
+    /// refreshes ago; the numbers are then frozen rather than live.
+    pub fn stale_for(&self, now_ms: i64) -> Option<i64> {
+        let age = now_ms - self.sampled_at?;
+        (self.is_running() && age > 5_000).then_some(age / 1000)
+    }
+
+    /// Memory usage as a percentage of the limit, or of the host's memory
+    /// if there is none.
+    pub fn memory_percent(&self) -> f64 {
+        self.memory_usage as f64 / self.memory_limit.max(1) as f64 * 100.0
+    }
+
+    /// The compose project the container belongs to, if any.
+    pub fn project(&self) -> Option<&str> {
+        self.labels.get("com.docker.compose.project").map(String::as_str)
+    }
+
+    /// Paused containers still report `Up ...`, with a `(Paused)` suffix.
+    pub fn is_paused(&self) -> bool {
+        self.status.ends_with("(Paused)")
+    }
+
+    /// Whether this container was created from `image`. Falls back to
+    /// comparing tags when the image ID is unknown (e.g. old traces).
+    pub fn uses_image(&self, image: &ImageSummary) -> bool {
+        if self.image_id.is_empty() {
+            image.repo_tags.contains(&self.image)
+        } else {
+            self.image_id == image.id
+        }
+    }
+
+    /// Name of the swarm service this container is a task of, if any.
+    pub fn service_name(&self) -> Option<&str> {
+        self.labels.get("com.docker.swarm.service.name").map(String::as_str)
+    }
+}
+
+#[async_trait]
+pub trait ContainerBackend: Send + Sync {
+    /// All containers, running or not, each with a fresh stats sample.
+    async fn containers(&self) -> Result<Vec<ContainerStats>>;
+
+    /// Full configuration and state of one container.
+    async fn inspect_container(&self, id: &str) -> Result<ContainerInspectResponse>;
+
+    /// All networks, including those without containers.
+    async fn networks(&self) -> Result<Vec<Network>>;
+
+    async fn volumes(&self) -> Result<Vec<Volume>>;
+
+    async fn images(&self) -> Result<Vec<ImageSummary>>;
+
+    async fn disk_usage(&self) -> Result<DiskUsage>;
+
+    /// Events for one container since `since` (Unix seconds), oldest first.
+    /// The daemon only keeps a limited backlog, so old events may be missing.
+    async fn container_events(&self, id: &str, since: i64) -> Result<Vec<ContainerEvent>>;
+
+    /// Follows a container's output, starting with the last `tail` lines, one
+    /// item per chunk Docker sends (usually a line). With `timestamps`, each
+    /// line starts with its RFC 3339 time and a space. Ends when the container stops.
+    fn follow_logs(&self, id: &str, tail: usize, timestamps: bool) -> BoxStream<'static, Result<LogLine>>;
+
+    /// The last `tail` lines of the container's logs written since `since`
+    /// (Unix seconds; 0 for any age), oldest first, with timestamps like
+    /// [`ContainerBackend::follow_logs`].
+    async fn recent_logs(&self, id: &str, tail: usize, since: i64, timestamps: bool) -> Result<Vec<LogLine>>;
+
+    /// All of the container's logs written since `since` (Unix seconds; 0
+    /// for all of them), oldest first, each line starting with its timestamp.
+    async fn logs_since(&self, id: &str, since: i64) -> Result<Vec<LogLine>>;
+
+    /// The container's processes, as `docker top` shows them.
+    async fn processes(&self, id: &str) -> Result<ContainerTopResponse>;
+
+    async fn start_container(&self, id: &str) -> Result<()>;
+
+    /// Stops gracefully, killing after Docker's default grace period.
+    async fn stop_container(&self, id: &str) -> Result<()>;
+
+    /// Stops (as [`ContainerBackend::stop_container`]) and starts again.
+    async fn restart_container(&self, id: &str) -> Result<()>;
+
+    /// Freezes the processes of a running container.
+    async fn pause_container(&self, id: &str) -> Result<()>;
+
+    async fn unpause_container(&self, id: &str) -> Result<()>;
+
+    /// Sends `signal`, e.g. `SIGKILL`, `HUP` or `9`, to the container's main
+    /// process.
+    async fn kill_container(&self, id: &str, signal: &str) -> Result<()>;
+
+    async fn rename_container(&self, id: &str, name: &str) -> Result<()>;
+
+    /// Changes the CPU and memory limits of a container in place, as
+    /// `docker update` does.
+    async fn update_container(&self, id: &str, limits: &ResourceLimits) -> Result<()>;
+
+    /// Snapshots the container's filesystem as a new image, pausing it
+    /// meanwhile, as `docker commit` does.
+    async fn commit_container(&self, id: &str, repository: &str, tag: &str) -> Result<()>;
+
+    /// Creates a container and starts it, as `docker run -d` does, pulling
+    /// the image first if it is missing. Returns the new container's ID.
+    async fn run_container(&self, spec: &RunSpec) -> Result<String>;
+
+    /// Replaces the container with one run from `spec` (whose labels are
+    /// ignored) and the rest of its configuration: command, networks,
+    /// restart policy and so on. The old container is stopped and renamed to
+    /// `<name>-replaced-<short ID>`, and left for the caller to remove once
+    /// the new one will do; if the new one cannot be created or started, the
+    /// old one is put back. Returns the new container's ID.
+    async fn recreate_container(&self, id: &str, spec: &RunSpec) -> Result<String>;
+
+    /// Pulls the image even if it is there already, as `docker pull` does,
+    /// and returns the ID of the image it now refers to.
+    async fn pull_image(&self, image: &str) -> Result<String>;
+
+    /// The registry logins the Docker client has stored.
+    async fn registry_logins(&self) -> Result<Vec<RegistryLogin>>;
+
+    /// Logs in to the registry and stores the login, as `docker login` does.
+    async fn registry_login(&self, server: &str, username: &str, password: &str) -> Result<()>;
+
+    /// Removes the stored login, as `docker logout` does.
+    async fn registry_logout(&self, server: &str) -> Result<()>;
+
+    /// Saves the state of a running container with CRIU and stops it.
+    /// Needs a daemon with experimental features enabled.
+    async fn checkpoint_container(&self, id: &str, checkpoint: &str) -> Result<()>;
+
+    /// Starts a stopped container from one of its checkpoints.
+    async fn restore_container(&self, id: &str, checkpoint: &str) -> Result<()>;
+
+    /// Names of the container's checkpoints, oldest first.
+    async fn checkpoints(&self, id: &str) -> Result<Vec<String>>;
+
+    /// Starts `command` inside a running container, without a TTY.
+    async fn exec(&self, id: &str, command: &[&str]) -> Result<ExecStreams>;
+
+    /// Attaches to the main process of a running container like `docker
+    /// attach`: its output from now on, and its stdin if `stdin`. Dropping
+    /// the streams detaches, but closes the container's stdin with them;
+    /// writing [`DETACH_KEYS`] first leaves it open.
+    async fn attach(&self, id: &str, stdin: bool) -> Result<ExecStreams>;
+
+    async fn remove_container(&self, id: &str) -> Result<()>;
+
+    /// Removes the container even while it runs, along with its anonymous
+    /// volumes (`docker rm -fv`).
+    async fn force_remove_container(&self, id: &str) -> Result<()>;
+
+    /// Removes every stopped container, as `docker container prune` does.
+    async fn prune_containers(&self) -> Result<PruneReport>;
+
+    async fn remove_volume(&self, name: &str) -> Result<()>;
+
+    async fn remove_network(&self, id: &str) -> Result<()>;
+
+    async fn remove_image(&self, id: &str) -> Result<()>;
+
+    /// Points a swarm service at a new image, starting a rolling update.
+    async fn update_service_image(&self, service: &str, image: &str) -> Result<()>;
+
+    /// Current state of the service's last update, if it has had one.
+    async fn service_update_status(&self, service: &str) -> Result<Option<ServiceUpdateStatus>>;
+
+    /// API call accounting, for backends that talk to a real daemon.
+    fn api_metrics(&self) -> Option<&ApiMetrics> {
+        None
+    }
+
+    /// The logins chosen for pulls, for backends that pull with the stored
+    /// registry logins.
+    fn pull_logins(&self) -> Option<&PullLogins> {
+        None
+    }
+}
@@ -0,0 +1,516 @@
+//! Recording and replaying container snapshots, so rendering and state bugs
+//! seen on someone's host can be reproduced offline.
+//!
+//! A trace is a JSON-lines file with one [`TraceEntry`] per refresh.
+
+use super::{
+    ApiMetrics, ContainerBackend, ContainerEvent, ContainerStats, DiskUsage, ExecStreams, LogLine, PruneReport, RegistryLogin, ResourceLimits,
+    RunSpec,
+};
+use crate::registries::PullLogins;
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use bollard::models::{ContainerInspectResponse, ContainerTopResponse, ImageSummary, Network, ServiceUpdateStatus, Volume};
+use futures::stream::{self, BoxStream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::Path,
+    sync::Mutex,
+    time::Instant,
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TraceEntry {
+    /// Milliseconds since recording started.
+    elapsed_ms: u128,
+    #[serde(flatten)]
+    result: TraceResult,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum TraceResult {
+    Containers(Vec<ContainerStats>),
+    Error(String),
+}
+
+/// Wraps another backend and appends every refresh it serves to a trace file.
+pub struct RecordingBackend {
+    inner: Box<dyn ContainerBackend>,
+    file: Mutex<File>,
+    started: Instant,
+}
+
+impl RecordingBackend {
+    pub fn new(inner: Box<dyn ContainerBackend>, path: &Path) -> Result<Self> {
+        let file = File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+        Ok(Self {
+            inner,
+            file: Mutex::new(file),
+            started: Instant::now(),
+        })
+    }
+
+    fn record(&self, result: TraceResult) -> Result<()> {
+        let entry = TraceEntry {
+            elapsed_ms: self.started.elapsed().as_millis(),
+            result,
+        };
+        let mut line = serde_json::to_string(&entry)?;
+        line.push('\n');
+        self.file
+            .lock()
+            .unwrap()
+            .write_all(line.as_bytes())
+            .context("Failed to write trace")
+    }
+}
+
+#[async_trait]
+impl ContainerBackend for RecordingBackend {
+    async fn containers(&self) -> Result<Vec<ContainerStats>> {
+        let result = self.inner.containers().await;
+        self.record(match &result {
+            Ok(containers) => TraceResult::Containers(containers.clone()),
+            Err(e) => TraceResult::Error(format!("{:#}", e)),
+        })?;
+        result
+    }
+
+    async fn inspect_container(&self, id: &str) -> Result<ContainerInspectResponse> {
+        self.inner.inspect_container(id).await
+    }
+
+    async fn networks(&self) -> Result<Vec<Network>> {
+        self.inner.networks().await
+    }
+
+    async fn volumes(&self) -> Result<Vec<Volume>> {
+        self.inner.volumes().await
+    }
+
+    async fn images(&self) -> Result<Vec<ImageSummary>> {
+        self.inner.images().await
+    }
+
+    async fn disk_usage(&self) -> Result<DiskUsage> {
+        self.inner.disk_usage().await
+    }
+
+    async fn container_events(&self, id: &str, since: i64) -> Result<Vec<ContainerEvent>> {
+        self.inner.container_events(id, since).await
+    }
+
+    fn follow_logs(&self, id: &str, tail: usize, timestamps: bool) -> BoxStream<'static, Result<LogLine>> {
+        self.inner.follow_logs(id, tail, timestamps)
+    }
+
+    async fn recent_logs(&self, id: &str, tail: usize, since: i64, timestamps: bool) -> Result<Vec<LogLine>> {
+        self.inner.recent_logs(id, tail, since, timestamps).await
+    }
+
+    async fn logs_since(&self, id: &str, since: i64) -> Result<Vec<LogLine>> {
+        self.inner.logs_since(id, since).await
+    }
+
+    async fn processes(&self, id: &str) -> Result<ContainerTopResponse> {
+        self.inner.processes(id).await
+    }
+
+    async fn start_container(&self, id: &str) -> Result<()> {
+        self.inner.start_container(id).await
+    }
+
+    async fn stop_container(&self, id: &str) -> Result<()> {
+        self.inner.stop_container(id).await
+    }
+
+    async fn restart_container(&self, id: &str) -> Result<()> {
+        self.inner.restart_container(id).await
+    }
+
+    async fn pause_container(&self, id: &str) -> Result<()> {
+        self.inner.pause_container(id).await
+    }
+
+    async fn unpause_container(&self, id: &str) -> Result<()> {
+        self.inner.unpause_container(id).await
+    }
+
+    async fn kill_container(&self, id: &str, signal: &str) -> Result<()> {
+        self.inner.kill_container(id, signal).await
+    }
+
+    async fn rename_container(&self, id: &str, name: &str) -> Result<()> {
+        self.inner.rename_container(id, name).await
+    }
+
+    async fn update_container(&self, id: &str, limits: &ResourceLimits) -> Result<()> {
+        self.inner.update_container(id, limits).await
+    }
+
+    async fn commit_container(&self, id: &str, repository: &str, tag: &str) -> Result<()> {
+        self.inner.commit_container(id, repository, tag).await
+    }
+
+    async fn run_container(&self, spec: &RunSpec) -> Result<String> {
+        self.inner.run_container(spec).await
+    }
+
+    async fn recreate_container(&self, id: &str, spec: &RunSpec) -> Result<String> {
+        self.inner.recreate_container(id, spec).await
+    }
+
+    async fn pull_image(&self, image: &str) -> Result<String> {
+        self.inner.pull_image(image).await
+    }
+
+    async fn registry_logins(&self) -> Result<Vec<RegistryLogin>> {
+        self.inner.registry_logins().await
+    }
+
+    async fn registry_login(&self, server: &str, username: &str, password: &str) -> Result<()> {
+        self.inner.registry_login(server, username, password).await
+    }
+
+    async fn registry_logout(&self, server: &str) -> Result<()> {
+        self.inner.registry_logout(server).await
+    }
+
+    async fn checkpoint_container(&self, id: &str, checkpoint: &str) -> Result<()> {
+        self.inner.checkpoint_container(id, checkpoint).await
+    }
+
+    async fn restore_container(&self, id: &str, checkpoint: &str) -> Result<()> {
+        self.inner.restore_container(id, checkpoint).await
+    }
+
+    async fn checkpoints(&self, id: &str) -> Result<Vec<String>> {
+        self.inner.checkpoints(id).await
+    }
+
+    async fn exec(&self, id: &str, command: &[&str]) -> Result<ExecStreams> {
+        self.inner.exec(id, command).await
+    }
+
+    async fn attach(&self, id: &str, stdin: bool) -> Result<ExecStreams> {
+        self.inner.attach(id, stdin).await
+    }
+
+    async fn remove_container(&self, id: &str) -> Result<()> {
+        self.inner.remove_container(id).await
+    }
+
+    async fn force_remove_container(&self, id: &str) -> Result<()> {
+        self.inner.force_remove_container(id).await
+    }
+
+    async fn prune_containers(&self) -> Result<PruneReport> {
+        self.inner.prune_containers().await
+    }
+
+    async fn remove_volume(&self, name: &str) -> Result<()> {
+        self.inner.remove_volume(name).await
+    }
+
+    async fn remove_network(&self, id: &str) -> Result<()> {
+        self.inner.remove_network(id).await
+    }
+
+    async fn remove_image(&self, id: &str) -> Result<()> {
+        self.inner.remove_image(id).await
+    }
+
+    async fn update_service_image(&self, service: &str, image: &str) -> Result<()> {
+        self.inner.update_service_image(service, image).await
+    }
+
+    async fn service_update_status(&self, service: &str) -> Result<Option<ServiceUpdateStatus>> {
+        self.inner.service_update_status(service).await
+    }
+
+    fn api_metrics(&self) -> Option<&ApiMetrics> {
+        self.inner.api_metrics()
+    }
+
+    fn pull_logins(&self) -> Option<&PullLogins> {
+        self.inner.pull_logins()
+    }
+}
+
+/// Serves a recorded trace, one entry per refresh, then keeps repeating the
+/// last snapshot. Mutating calls are refused since there is no daemon.
+pub struct ReplayBackend {
+    entries: Mutex<std::vec::IntoIter<TraceEntry>>,
+    last: Mutex<Vec<ContainerStats>>,
+}
+
+impl ReplayBackend {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let entries = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                serde_json::from_str(line).with_context(|| format!("{}:{}: invalid trace entry", path.display(), i + 1))
+            })
+            .collect::<Result<Vec<TraceEntry>>>()?;
+        Ok(Self {
+            entries: Mutex::new(entries.into_iter()),
+            last: Mutex::new(Vec::new()),
+        })
+    }
+}
+
+#[async_trait]
+impl ContainerBackend for ReplayBackend {
+    async fn containers(&self) -> Result<Vec<ContainerStats>> {
+        match self.entries.lock().unwrap().next().map(|e| e.result) {
+            Some(TraceResult::Containers(containers)) => {
+                *self.last.lock().unwrap() = containers.clone();
+                Ok(containers)
+            }
+            Some(TraceResult::Error(message)) => Err(anyhow!(message)),
+            None => Ok(self.last.lock().unwrap().clone()),
+        }
+    }
+
+    async fn inspect_container(&self, _id: &str) -> Result<ContainerInspectResponse> {
+        bail!("Inspect is not available while replaying a trace")
+    }
+
+    /// Networks are derived from the replayed containers' attachments.
+    async fn networks(&self) -> Result<Vec<Network>> {
+        let mut names: Vec<String> = self
+            .last
+            .lock()
+            .unwrap()
+            .iter()
+            .flat_map(|c| c.networks.iter().map(|n| n.network.clone()))
+            .collect();
+        names.sort();
+        names.dedup();
+        Ok(names
+            .into_iter()
+            .map(|name| Network {
+                name: Some(name),
+                ..Default::default()
+            })
+            .collect())
+    }
+
+    /// Volumes are derived from the replayed containers' mounts.
+    async fn volumes(&self) -> Result<Vec<Volume>> {
+        let mut names: Vec<String> = self
+            .last
+            .lock()
+            .unwrap()
+            .iter()
+            .flat_map(|c| c.mounts.iter().filter(|m| m.kind == "volume").map(|m| m.name.clone()))
+            .collect();
+        names.sort();
+        names.dedup();
+        Ok(names
+            .into_iter()
+            .map(|name| Volume {
+                name,
+                ..Default::default()
+            })
+            .collect())
+    }
+
+    /// Images are derived from the replayed containers; sizes are unknown.
+    async fn images(&self) -> Result<Vec<ImageSummary>> {
+        let mut images: Vec<ImageSummary> = Vec::new();
+        for container in self.last.lock().unwrap().iter() {
+            if !images.iter().any(|i| container.uses_image(i)) {
+                images.push(ImageSummary {
+                    id: container.image_id.clone(),
+                    repo_tags: vec![container.image.clone()],
+                    ..Default::default()
+                });
+            }
+        }
+        Ok(images)
+    }
+
+    async fn disk_usage(&self) -> Result<DiskUsage> {
+        bail!("Disk usage is not recorded in traces")
+    }
+
+    /// Traces don't contain events.
+    async fn container_events(&self, _id: &str, _since: i64) -> Result<Vec<ContainerEvent>> {
+        Ok(Vec::new())
+    }
+
+    /// Traces don't contain logs.
+    fn follow_logs(&self, _id: &str, _tail: usize, _timestamps: bool) -> BoxStream<'static, Result<LogLine>> {
+        stream::empty().boxed()
+    }
+
+    /// Traces don't contain logs.
+    async fn recent_logs(&self, _id: &str, _tail: usize, _since: i64, _timestamps: bool) -> Result<Vec<LogLine>> {
+        Ok(Vec::new())
+    }
+
+    /// Traces don't contain logs.
+    async fn logs_since(&self, _id: &str, _since: i64) -> Result<Vec<LogLine>> {
+        Ok(Vec::new())
+    }
+
+    /// Traces don't contain processes.
+    async fn processes(&self, _id: &str) -> Result<ContainerTopResponse> {
+        Ok(ContainerTopResponse::default())
+    }
+
+    async fn start_container(&self, _id: &str) -> Result<()> {
+        bail!("Containers cannot be started or stopped while replaying a trace")
+    }
+
+    async fn stop_container(&self, _id: &str) -> Result<()> {
+        bail!("Containers cannot be started or stopped while replaying a trace")
+    }
+
+    async fn restart_container(&self, _id: &str) -> Result<()> {
+        bail!("Containers cannot be started or stopped while replaying a trace")
+    }
+
+    async fn pause_container(&self, _id: &str) -> Result<()> {
+        bail!("Containers cannot be paused while replaying a trace")
+    }
+
+    async fn unpause_container(&self, _id: &str) -> Result<()> {
+        bail!("Containers cannot be unpaused while replaying a trace")
+    }
+
+    async fn kill_container(&self, _id: &str, _signal: &str) -> Result<()> {
+        bail!("Containers cannot be killed while replaying a trace")
+    }
+
+    async fn rename_container(&self, _id: &str, _name: &str) -> Result<()> {
+        bail!("Containers cannot be renamed while replaying a trace")
+    }
+
+    async fn update_container(&self, _id: &str, _limits: &ResourceLimits) -> Result<()> {
+        bail!("Limits cannot be changed while replaying a trace")
+    }
+
+    async fn commit_container(&self, _id: &str, _repository: &str, _tag: &str) -> Result<()> {
+        bail!("Images cannot be committed while replaying a trace")
+    }
+
+    async fn run_container(&self, _spec: &RunSpec) -> Result<String> {
+        bail!("Containers cannot be run while replaying a trace")
+    }
+
+    async fn recreate_container(&self, _id: &str, _spec: &RunSpec) -> Result<String> {
+        bail!("Containers cannot be recreated while replaying a trace")
+    }
+
+    async fn pull_image(&self, _image: &str) -> Result<String> {
+        bail!("Images cannot be pulled while replaying a trace")
+    }
+
+    /// Logins are not recorded.
+    async fn registry_logins(&self) -> Result<Vec<RegistryLogin>> {
+        Ok(Vec::new())
+    }
+
+    async fn registry_login(&self, _server: &str, _username: &str, _password: &str) -> Result<()> {
+        bail!("Cannot log in while replaying a trace")
+    }
+
+    async fn registry_logout(&self, _server: &str) -> Result<()> {
+        bail!("Cannot log out while replaying a trace")
+    }
+
+    async fn checkpoint_container(&self, _id: &str, _checkpoint: &str) -> Result<()> {
+        bail!("Checkpoints are not available while replaying a trace")
+    }
+
+    async fn restore_container(&self, _id: &str, _checkpoint: &str) -> Result<()> {
+        bail!("Checkpoints are not available while replaying a trace")
+    }
+
+    async fn checkpoints(&self, _id: &str) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    async fn exec(&self, _id: &str, _command: &[&str]) -> Result<ExecStreams> {
+        bail!("Nothing can be executed while replaying a trace")
+    }
+
+    async fn attach(&self, _id: &str, _stdin: bool) -> Result<ExecStreams> {
+        bail!("Nothing can be attached to while replaying a trace")
+    }
+
+    async fn remove_container(&self, _id: &str) -> Result<()> {
+        bail!("Nothing can be removed while replaying a trace")
+    }
+
+    async fn force_remove_container(&self, _id: &str) -> Result<()> {
+        bail!("Nothing can be removed while replaying a trace")
+    }
+
+    async fn prune_containers(&self) -> Result<PruneReport> {
+        bail!("Nothing can be removed while replaying a trace")
+    }
+
+    async fn remove_volume(&self, _name: &str) -> Result<()> {
+        bail!("Nothing can be removed while replaying a trace")
+    }
+
+    async fn remove_network(&self, _id: &str) -> Result<()> {
+        bail!("Nothing can be removed while replaying a trace")
+    }
+
+    async fn remove_image(&self, _id: &str) -> Result<()> {
+        bail!("Nothing can be removed while replaying a trace")
+    }
+
+    async fn update_service_image(&self, _service: &str, _image: &str) -> Result<()> {
+        bail!("Service updates are not available while replaying a trace")
+    }
+
+    async fn service_update_status(&self, _service: &str) -> Result<Option<ServiceUpdateStatus>> {
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::mock::{container, MockBackend, Snapshot};
+
+    #[tokio::test]
+    async fn recorded_refreshes_replay_in_order() {
+        let path = std::env::temp_dir().join(format!("dockertop-trace-{}.jsonl", std::process::id()));
+        let mock = MockBackend::new([
+            Snapshot::Containers(vec![container("web")]),
+            Snapshot::Error("connection refused"),
+            Snapshot::Containers(vec![container("web"), container("db")]),
+        ]);
+        let recording = RecordingBackend::new(Box::new(mock), &path).unwrap();
+        for _ in 0..3 {
+            let _ = recording.containers().await;
+        }
+        assert!(recording.stop_container("web-id").await.is_ok(), "mutations pass through while recording");
+        drop(recording);
+
+        let replay = ReplayBackend::load(&path).unwrap();
+        let names = |containers: Vec<ContainerStats>| containers.into_iter().map(|c| c.name).collect::<Vec<_>>();
+        assert_eq!(names(replay.containers().await.unwrap()), ["web"]);
+        assert_eq!(replay.containers().await.unwrap_err().to_string(), "connection refused");
+        assert_eq!(names(replay.containers().await.unwrap()), ["web", "db"]);
+        assert_eq!(names(replay.containers().await.unwrap()), ["web", "db"]);
+        assert!(replay.stop_container("web-id").await.is_err());
+
+        fs::write(&path, "{\"elapsed_ms\": 0, \"containers\": []}\n\nnot json\n").unwrap();
+        let error = ReplayBackend::load(&path).err().unwrap();
+        let _ = fs::remove_file(&path);
+        assert!(error.to_string().ends_with(":3: invalid trace entry"), "{}", error);
+    }
+}
@@ -0,0 +1,209 @@
+//! Bandwidth caps and how close containers run to them: block I/O throttled
+//! with `--device-read-bps`/`--device-write-bps`, and network traffic shaped
+//! by docker-tc, whose `com.docker-tc.limit` label holds the rate. Docker
+//! has no network limit of its own, so tc rules set up some other way are
+//! not seen.
+
+use crate::backend::{ContainerBackend, ContainerStats};
+use crate::config::FormatConfig;
+use crate::io_rate::{IoMeter, IoRate};
+use crate::units::{format_bits, format_percent, format_rate};
+use bollard::models::{HostConfig, ThrottleDevice};
+use std::collections::{HashMap, HashSet};
+
+/// The label docker-tc shapes a container's traffic by, as a tc rate such
+/// as `1mbit` or `500kbps`.
+pub const TC_LIMIT_LABEL: &str = "com.docker-tc.limit";
+/// A cap counts as saturated once usage reaches this share of it.
+const SATURATION: f64 = 0.9;
+
+/// Caps in bytes per second. A throttle on several devices is summed, as
+/// the block counters are.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Caps {
+    pub block_read: Option<f64>,
+    pub block_write: Option<f64>,
+    /// Applies to each direction.
+    pub network: Option<f64>,
+}
+
+impl Caps {
+    pub fn new(host_config: &HostConfig, labels: &HashMap<String, String>) -> Self {
+        let sum = |devices: &Option<Vec<ThrottleDevice>>| {
+            let total: i64 = devices.iter().flatten().filter_map(|d| d.rate).filter(|&r| r > 0).sum();
+            (total > 0).then_some(total as f64)
+        };
+        Self {
+            block_read: sum(&host_config.blkio_device_read_bps),
+            block_write: sum(&host_config.blkio_device_write_bps),
+            network: labels.get(TC_LIMIT_LABEL).and_then(|rate| parse_tc_rate(rate)),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// One cap with the rate it is used at.
+#[derive(Debug, Clone, Copy)]
+pub struct CapUsage {
+    pub what: &'static str,
+    /// Bytes per second.
+    pub rate: f64,
+    pub cap: f64,
+}
+
+impl CapUsage {
+    pub fn is_saturated(&self) -> bool {
+        self.rate >= self.cap * SATURATION
+    }
+
+    /// E.g. `block read 19K/s of 20K/s (97.5%)`.
+    pub fn describe(&self, format: &FormatConfig) -> String {
+        let (rate, cap) = if self.what.starts_with("network") {
+            (format_bits(format, self.rate), format_bits(format, self.cap))
+        } else {
+            (format!("{}/s", format_rate(format, self.rate)), format!("{}/s", format_rate(format, self.cap)))
+        };
+        format!("{} {} of {} ({})", self.what, rate, cap, format_percent(format, self.rate / self.cap * 100.0))
+    }
+}
+
+#[derive(Default)]
+pub struct Bandwidth {
+    /// By container ID. Neither throttles nor labels can change without
+    /// recreating the container, so each is inspected once.
+    caps: HashMap<String, Caps>,
+    /// Names of the containers saturating a cap at the last refresh, so
+    /// each is warned about once.
+    saturated: HashSet<String>,
+}
+
+impl Bandwidth {
+    /// Reads the caps of containers not seen before and returns a warning
+    /// for each container that has just started saturating one.
+    pub async fn update(
+        &mut self,
+        backend: &dyn ContainerBackend,
+        containers: &[ContainerStats],
+        io: &IoMeter,
+        format: &FormatConfig,
+    ) -> Vec<String> {
+        self.caps.retain(|id, _| containers.iter().any(|c| &c.id == id));
+        for container in containers.iter().filter(|c| c.is_running()) {
+            if self.caps.contains_key(&container.id) {
+                continue;
+            }
+            // Tried again on the next refresh.
+            let Ok(inspect) = backend.inspect_container(&container.id).await else {
+                continue;
+            };
+            let caps = Caps::new(&inspect.host_config.unwrap_or_default(), &container.labels);
+            self.caps.insert(container.id.clone(), caps);
+        }
+
+        let mut warnings = Vec::new();
+        let mut saturated = HashSet::new();
+        for container in containers.iter().filter(|c| c.is_running()) {
+            let at_cap: Vec<String> = self
+                .usage(container, io)
+                .into_iter()
+                .filter(CapUsage::is_saturated)
+                .map(|usage| usage.describe(format))
+                .collect();
+            if at_cap.is_empty() {
+                continue;
+            }
+            if !self.saturated.contains(&container.name) {
+                warnings.push(format!("{} is at its bandwidth cap: {}", container.name, at_cap.join(", ")));
+            }
+            saturated.insert(container.name.clone());
+        }
+        self.saturated = saturated;
+        warnings
+    }
+
+    /// Usage of each of the container's caps, once its rates are known.
+    pub fn usage(&self, container: &ContainerStats, io: &IoMeter) -> Vec<CapUsage> {
+        let (Some(caps), Some(rate)) = (self.caps.get(&container.id), io.rate(&container.id)) else {
+            return Vec::new();
+        };
+        if caps.is_empty() {
+            return Vec::new();
+        }
+        let IoRate { net_rx, net_tx, block_read, block_write } = rate;
+        [
+            ("block read", block_read, caps.block_read),
+            ("block write", block_write, caps.block_write),
+            ("network in", net_rx, caps.network),
+            ("network out", net_tx, caps.network),
+        ]
+        .into_iter()
+        .filter_map(|(what, rate, cap)| Some(CapUsage { what, rate, cap: cap? }))
+        .collect()
+    }
+
+    /// Containers saturating a cap, sorted.
+    pub fn saturated(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.saturated.iter().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+}
+
+/// A rate as `tc` takes it, in bytes per second: a number followed by
+/// `bit`, `kbit`, `mbit`, ... for bits or `bps`, `kbps`, `mbps`, ... for
+/// bytes, with `kibit`, `kibps` and so on for powers of 1024. A bare number
+/// is bits.
+pub fn parse_tc_rate(rate: &str) -> Option<f64> {
+    let rate = rate.trim().to_ascii_lowercase();
+    let split = rate.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(rate.len());
+    let (number, unit) = rate.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    let (prefix, bytes) = match unit.strip_suffix("bps") {
+        Some(prefix) => (prefix, true),
+        None => (unit.strip_suffix("bit").unwrap_or(unit), false),
+    };
+    let scale = match prefix {
+        "" => 1.0,
+        "k" => 1e3,
+        "m" => 1e6,
+        "g" => 1e9,
+        "t" => 1e12,
+        "ki" => 1024.0,
+        "mi" => 1024.0 * 1024.0,
+        "gi" => 1024.0 * 1024.0 * 1024.0,
+        "ti" => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+        _ => return None,
+    };
+    let bytes_per_second = number * scale / if bytes { 1.0 } else { 8.0 };
+    (bytes_per_second > 0.0).then_some(bytes_per_second)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tc_rates_are_read_in_bytes_per_second() {
+        let cases = [
+            ("8000", Some(1000.0)),
+            ("8bit", Some(1.0)),
+            ("10mbit", Some(1.25e6)),
+            ("10Mbit", Some(1.25e6)),
+            ("1gbit", Some(1.25e8)),
+            ("100kbps", Some(1e5)),
+            ("1mibit", Some(131072.0)),
+            ("2kibps", Some(2048.0)),
+            (" 1.5mbit ", Some(187500.0)),
+            ("0mbit", None),
+            ("10furlongs", None),
+            ("mbit", None),
+            ("", None),
+        ];
+        for (rate, expected) in cases {
+            assert_eq!(parse_tc_rate(rate), expected, "{:?}", rate);
+        }
+    }
+}
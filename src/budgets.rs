@@ -0,0 +1,117 @@
+//! Soft CPU and memory budgets for compose projects, e.g. to keep everyone's
+//! stacks in check on a shared dev server. Nothing is enforced; a project
+//! over its budget is only flagged.
+
+use crate::backend::ContainerStats;
+use crate::config::{Budget, FormatConfig};
+use crate::units::{format_bytes, format_percent};
+use std::collections::HashSet;
+
+/// A budgeted project's summed usage as of the last refresh.
+#[derive(Debug, Clone)]
+pub struct ProjectUsage {
+    pub budget: Budget,
+    pub cpu_percent: f64,
+    pub memory: u64,
+}
+
+impl ProjectUsage {
+    /// What is over budget, e.g. `CPU 62.0% of 50%`; empty if nothing is.
+    pub fn excess(&self, format: &FormatConfig) -> Vec<String> {
+        let mut excess = Vec::new();
+        if let Some(cpu) = self.budget.cpu_percent.filter(|&cpu| self.cpu_percent > cpu) {
+            excess.push(format!("CPU {} of {}%", format_percent(format, self.cpu_percent), cpu));
+        }
+        if let Some(memory) = self.budget.memory.filter(|&memory| self.memory > memory) {
+            excess.push(format!("memory {} of {}", format_bytes(format, self.memory), format_bytes(format, memory)));
+        }
+        excess
+    }
+
+    /// Usage against every budgeted resource, for the details pane.
+    pub fn summary(&self, format: &FormatConfig) -> String {
+        let mut parts = Vec::new();
+        if let Some(cpu) = self.budget.cpu_percent {
+            parts.push(format!("CPU {} of {}%", format_percent(format, self.cpu_percent), cpu));
+        }
+        if let Some(memory) = self.budget.memory {
+            parts.push(format!("memory {} of {}", format_bytes(format, self.memory), format_bytes(format, memory)));
+        }
+        parts.join(", ")
+    }
+}
+
+#[derive(Default)]
+pub struct Budgets {
+    budgets: Vec<Budget>,
+    /// One per budget, in config order.
+    usage: Vec<ProjectUsage>,
+    /// Projects that were over budget at the last refresh, so each excess
+    /// is warned about once.
+    over: HashSet<String>,
+}
+
+impl Budgets {
+    pub fn new(budgets: Vec<Budget>) -> Self {
+        Self {
+            budgets,
+            ..Self::default()
+        }
+    }
+
+    /// New budgets apply from the next refresh.
+    pub fn reconfigure(&mut self, budgets: Vec<Budget>) {
+        self.budgets = budgets;
+    }
+
+    /// Sums the running containers of each budgeted project and returns a
+    /// warning for each project that has just gone over budget.
+    pub fn update(&mut self, containers: &[ContainerStats], format: &FormatConfig) -> Vec<String> {
+        self.usage = self
+            .budgets
+            .iter()
+            .map(|budget| {
+                let members = containers
+                    .iter()
+                    .filter(|c| c.is_running() && c.project() == Some(budget.project.as_str()));
+                let (cpu_percent, memory) =
+                    members.fold((0.0, 0), |(cpu, memory), c| (cpu + c.cpu_usage, memory + c.memory_usage));
+                ProjectUsage {
+                    budget: budget.clone(),
+                    cpu_percent,
+                    memory,
+                }
+            })
+            .collect();
+
+        let mut warnings = Vec::new();
+        let mut over = HashSet::new();
+        for usage in &self.usage {
+            let excess = usage.excess(format);
+            if excess.is_empty() {
+                continue;
+            }
+            if !self.over.contains(&usage.budget.project) {
+                warnings.push(format!("Project {} is over budget: {}", usage.budget.project, excess.join(", ")));
+            }
+            over.insert(usage.budget.project.clone());
+        }
+        self.over = over;
+        warnings
+    }
+
+    /// The usage of the container's project, if it has a budget.
+    pub fn of(&self, container: &ContainerStats) -> Option<&ProjectUsage> {
+        let project = container.project()?;
+        self.usage.iter().find(|u| u.budget.project == project)
+    }
+
+    /// Projects currently over budget, in config order.
+    pub fn exceeded(&self) -> Vec<&str> {
+        self.usage
+            .iter()
+            .filter(|u| self.over.contains(&u.budget.project))
+            .map(|u| u.budget.project.as_str())
+            .collect()
+    }
+}
@@ -0,0 +1,162 @@
+//! Command-line interface.
+
+use crate::actions::parse_age;
+use crate::log_view::TAIL;
+use crate::query::Query;
+use crate::report::Metric;
+use clap::{CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+};
+
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Only show containers whose name contains this text, or matching an expression like 'cpu > 50'
+    #[arg(long, value_name = "QUERY", value_parser = Query::parse)]
+    pub filter: Option<Query>,
+
+    /// Show simulated containers instead of connecting to Docker
+    #[arg(long, conflicts_with = "replay")]
+    pub demo: bool,
+
+    /// Append every refresh to a trace file, for reproducing issues later
+    #[arg(long, value_name = "FILE")]
+    pub record: Option<PathBuf>,
+
+    /// Replay a trace recorded with --record instead of connecting to Docker
+    #[arg(long, value_name = "FILE")]
+    pub replay: Option<PathBuf>,
+
+    /// List a second Docker host's containers beside the first, e.g. staging next to production
+    #[arg(long, value_name = "HOST")]
+    pub compare: Option<String>,
+
+    /// Lines the log view fetches from the end of a container's logs
+    #[arg(long, value_name = "LINES", default_value_t = TAIL as u64, value_parser = clap::value_parser!(u64).range(1..))]
+    pub tail: u64,
+
+    /// Only fetch log lines up to this old in the log view, e.g. 30m, 2h or 1d
+    #[arg(long, value_name = "AGE", value_parser = parse_age)]
+    pub since: Option<i64>,
+
+    /// Print a recap of the session on quitting: peaks, restarts, OOM kills and alerts
+    #[arg(long)]
+    pub recap: bool,
+
+    #[command(subcommand)]
+    pub command: Option<CliCommand>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CliCommand {
+    /// Print a completion script for the given shell
+    Completions { shell: Shell },
+    /// Update dockertop to the latest GitHub release
+    SelfUpdate,
+    /// Print the top consumers of a resource over a recent window, from the history the journal keeps
+    Report {
+        /// How far back to look, e.g. 30m, 1h or 1d
+        #[arg(long, value_name = "AGE", default_value = "1h", value_parser = parse_age)]
+        since: i64,
+        /// How many containers to list
+        #[arg(long, value_name = "N", default_value_t = 10)]
+        top: usize,
+        /// What to rank containers by
+        #[arg(value_enum, default_value_t = Metric::Cpu)]
+        metric: Metric,
+    },
+    /// Open a full-screen dashboard for one container, following it across recreations
+    Watch {
+        /// Container name
+        name: String,
+    },
+}
+
+/// Argument the completion scripts pass to list container names. It is
+/// matched before clap parses, so it never shows up as a completion itself.
+pub const LIST_CONTAINERS_ARG: &str = "__complete-containers";
+
+/// Command the completion scripts run to list container names.
+const LIST_CONTAINERS: &str = "dockertop __complete-containers 2>/dev/null";
+
+/// Writes the completion script for `shell` to stdout.
+pub fn print_completions(shell: Shell) -> io::Result<()> {
+    let mut command = Cli::command();
+    let name = command.get_name().to_string();
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut command, &name, &mut script);
+    let script = with_container_completion(shell, String::from_utf8_lossy(&script).into_owned());
+    io::stdout().write_all(script.as_bytes())
+}
+
+/// clap_complete only knows static values, so patch the generated script to
+/// complete `--filter` with the names of the current containers.
+fn with_container_completion(shell: Shell, script: String) -> String {
+    match shell {
+        Shell::Bash => {
+            let Some(start) = script.find("--filter)") else {
+                return script;
+            };
+            let files = r#"compgen -f "${cur}""#;
+            match script[start..].find(files) {
+                Some(offset) => {
+                    let at = start + offset;
+                    format!(
+                        r#"{}compgen -W "$({})" -- "${{cur}}"{}"#,
+                        &script[..at],
+                        LIST_CONTAINERS,
+                        &script[at + files.len()..]
+                    )
+                }
+                None => script,
+            }
+        }
+        Shell::Zsh => {
+            let function = format!(
+                "_dockertop_containers() {{\n    local -a names\n    names=(${{(f)\"$({})\"}})\n    _describe 'container' names\n}}\n",
+                LIST_CONTAINERS
+            );
+            let script = script.replace(":QUERY:_default'", ":QUERY:_dockertop_containers'");
+            match script.split_once('\n') {
+                Some((compdef, rest)) => format!("{}\n{}{}", compdef, function, rest),
+                None => script,
+            }
+        }
+        Shell::Fish => format!(
+            "{}complete -c dockertop -n \"__fish_dockertop_needs_command\" -l filter -f -a \"({})\"\n",
+            script, LIST_CONTAINERS
+        ),
+        _ => script,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn script(shell: Shell) -> String {
+        let mut command = Cli::command();
+        let mut script = Vec::new();
+        clap_complete::generate(shell, &mut command, "dockertop", &mut script);
+        with_container_completion(shell, String::from_utf8(script).unwrap())
+    }
+
+    #[test]
+    fn the_cli_is_consistent() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn filter_completes_container_names() {
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish] {
+            let script = script(shell);
+            assert_eq!(script.matches(LIST_CONTAINERS).count(), 1, "{:?}", shell);
+        }
+        assert!(script(Shell::Zsh).starts_with("#compdef dockertop\n_dockertop_containers() {"));
+        assert!(script(Shell::Zsh).contains(":QUERY:_dockertop_containers'"));
+        assert!(!script(Shell::PowerShell).contains(LIST_CONTAINERS));
+    }
+}
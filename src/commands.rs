@@ -0,0 +1,156 @@
+//! Running user-configured shell commands against containers.
+
+use crate::config::CustomColumn;
+use crate::backend::ContainerStats;
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+use tokio::{
+    process::Command,
+    sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+};
+
+/// Substitutes `{{id}}`, `{{name}}`, `{{image}}` and `{{port}}` in `template`.
+pub fn expand_placeholders(template: &str, container: &ContainerStats) -> String {
+    let port = container
+        .ports
+        .iter()
+        .find_map(|p| p.public_port)
+        .map(|p| p.to_string())
+        .unwrap_or_default();
+    template
+        .replace("{{id}}", &container.id)
+        .replace("{{name}}", &container.name)
+        .replace("{{image}}", &container.image)
+        .replace("{{port}}", &port)
+}
+
+/// Runs `command` through `sh -c` and returns the first line of its output.
+async fn run_for_value(command: String, timeout: Duration) -> String {
+    let output = tokio::time::timeout(timeout, Command::new("sh").arg("-c").arg(&command).output()).await;
+    match output {
+        Ok(Ok(output)) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_string(),
+        Ok(Ok(output)) => format!("exit {}", output.status.code().unwrap_or(-1)),
+        Ok(Err(_)) => "error".to_string(),
+        Err(_) => "timeout".to_string(),
+    }
+}
+
+/// Runs `command` through `sh -c` and returns its combined stdout and stderr.
+pub async fn capture_output(command: &str) -> Vec<String> {
+    match Command::new("sh").arg("-c").arg(command).output().await {
+        Ok(output) => {
+            let mut lines: Vec<String> = String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .chain(String::from_utf8_lossy(&output.stderr).lines())
+                .map(str::to_string)
+                .collect();
+            if !output.status.success() {
+                lines.push(format!("[exited with {}]", output.status));
+            }
+            lines
+        }
+        Err(e) => vec![format!("Failed to run command: {}", e)],
+    }
+}
+
+type ColumnKey = (usize, String);
+
+/// Periodically evaluates custom columns for every container in the
+/// background, so slow commands never hold up the UI.
+pub struct ColumnRunner {
+    columns: Vec<CustomColumn>,
+    values: HashMap<ColumnKey, String>,
+    last_run: HashMap<ColumnKey, Instant>,
+    in_flight: HashSet<ColumnKey>,
+    tx: UnboundedSender<(ColumnKey, String)>,
+    rx: UnboundedReceiver<(ColumnKey, String)>,
+}
+
+impl ColumnRunner {
+    pub fn new(columns: Vec<CustomColumn>) -> Self {
+        let (tx, rx) = unbounded_channel();
+        Self {
+            columns,
+            values: HashMap::new(),
+            last_run: HashMap::new(),
+            in_flight: HashSet::new(),
+            tx,
+            rx,
+        }
+    }
+
+    pub fn columns(&self) -> &[CustomColumn] {
+        &self.columns
+    }
+
+    pub fn value(&self, column: usize, container_id: &str) -> Option<&str> {
+        self.values
+            .get(&(column, container_id.to_string()))
+            .map(String::as_str)
+    }
+
+    /// Starts the commands that are due for the given containers.
+    pub fn schedule(&mut self, containers: &[ContainerStats]) {
+        for (index, column) in self.columns.iter().enumerate() {
+            let interval = Duration::from_secs(column.interval.max(1));
+            for container in containers {
+                let key = (index, container.id.clone());
+                let due = self.last_run.get(&key).is_none_or(|t| t.elapsed() >= interval);
+                if !due || self.in_flight.contains(&key) {
+                    continue;
+                }
+                self.last_run.insert(key.clone(), Instant::now());
+                self.in_flight.insert(key.clone());
+
+                let command = expand_placeholders(&column.command, container);
+                let tx = self.tx.clone();
+                tokio::spawn(async move {
+                    let value = run_for_value(command, interval).await;
+                    let _ = tx.send((key, value));
+                });
+            }
+        }
+    }
+
+    /// Picks up the results of finished commands.
+    pub fn collect(&mut self) {
+        while let Ok((key, value)) = self.rx.try_recv() {
+            self.in_flight.remove(&key);
+            self.values.insert(key, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::mock::container;
+    use bollard::models::Port;
+
+    #[test]
+    fn placeholders_are_filled_from_the_container() {
+        let mut web = container("web");
+        web.ports = vec![
+            Port { private_port: 9000, public_port: None, ..Default::default() },
+            Port { private_port: 80, public_port: Some(8080), ..Default::default() },
+        ];
+        let cases = [
+            ("curl -s localhost:{{port}}/health", "curl -s localhost:8080/health"),
+            ("docker logs {{id}} | tail -1", "docker logs web-id | tail -1"),
+            ("{{name}} runs {{image}}", "web runs web:latest"),
+            ("{{name}}/{{name}}", "web/web"),
+            ("{{unknown}} stays", "{{unknown}} stays"),
+        ];
+        for (template, command) in cases {
+            assert_eq!(expand_placeholders(template, &web), command);
+        }
+        assert_eq!(expand_placeholders("port {{port}}", &container("db")), "port ");
+    }
+}
@@ -0,0 +1,83 @@
+//! A second daemon's container list shown beside the first, e.g. staging
+//! next to production. It is read-only: actions still go to the main host.
+
+use crate::backend::{ContainerBackend, ContainerStats};
+use std::sync::Arc;
+
+pub struct Compare {
+    backend: Arc<dyn ContainerBackend>,
+    /// The host as configured, for the pane's title.
+    pub host: String,
+    pub containers: Vec<ContainerStats>,
+    pub selected_index: usize,
+    /// Whether ↑/↓ move this list's selection rather than the main one.
+    pub focused: bool,
+    /// Why the last refresh failed, shown instead of a stale list.
+    pub error: Option<String>,
+}
+
+impl Compare {
+    pub fn new(backend: Arc<dyn ContainerBackend>, host: String) -> Self {
+        Self {
+            backend,
+            host,
+            containers: Vec::new(),
+            selected_index: 0,
+            focused: false,
+            error: None,
+        }
+    }
+
+    /// The host's containers, or `None` with the error kept for display.
+    pub async fn fetch(&mut self) -> Option<Vec<ContainerStats>> {
+        match self.backend.containers().await {
+            Ok(containers) => {
+                self.error = None;
+                Some(containers)
+            }
+            Err(e) => {
+                self.error = Some(format!("{:#}", e));
+                self.containers.clear();
+                None
+            }
+        }
+    }
+
+    pub fn move_selection(&mut self, down: bool) {
+        self.selected_index = if down {
+            (self.selected_index + 1).min(self.containers.len().saturating_sub(1))
+        } else {
+            self.selected_index.saturating_sub(1)
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::mock::{container, MockBackend, Snapshot};
+
+    #[tokio::test]
+    async fn failed_refreshes_clear_the_list_and_keep_the_error() {
+        let backend = MockBackend::new([
+            Snapshot::Containers(vec![container("web"), container("db")]),
+            Snapshot::Error("staging is unreachable"),
+            Snapshot::Containers(vec![container("web")]),
+        ]);
+        let mut compare = Compare::new(Arc::new(backend), "tcp://staging:2376".to_string());
+
+        compare.containers = compare.fetch().await.unwrap();
+        compare.move_selection(true);
+        compare.move_selection(true);
+        assert_eq!(compare.selected_index, 1);
+
+        assert!(compare.fetch().await.is_none());
+        assert_eq!((compare.containers.len(), compare.error.as_deref()), (0, Some("staging is unreachable")));
+        compare.move_selection(false);
+        compare.move_selection(false);
+        assert_eq!(compare.selected_index, 0);
+
+        assert_eq!(compare.fetch().await.map(|c| c.len()), Some(1));
+        assert!(compare.error.is_none());
+    }
+}
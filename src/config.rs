@@ -0,0 +1,712 @@
+//! User configuration, read from `$XDG_CONFIG_HOME/dockertop/config.toml`
+//! (falling back to `~/.config/dockertop/config.toml`).
+
+use crate::query::Query;
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+use std::{collections::BTreeMap, env, fs, path::PathBuf, time::SystemTime};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Extra list columns whose values come from external commands.
+    pub columns: Vec<CustomColumn>,
+    /// Keybound commands run against the selected container.
+    pub commands: Vec<CustomCommand>,
+    /// Optional built-in columns, off by default because they cost extra API traffic.
+    pub builtin_columns: Vec<BuiltinColumn>,
+    pub api: ApiConfig,
+    pub orphans: OrphansConfig,
+    pub audit: AuditConfig,
+    pub journal: JournalConfig,
+    pub auto_prune: AutoPruneConfig,
+    pub auto_actions: AutoActionsConfig,
+    pub list: ListConfig,
+    pub charts: ChartsConfig,
+    /// How sizes and percentages are written everywhere.
+    pub format: FormatConfig,
+    pub keys: KeysConfig,
+    pub cost: CostConfig,
+    pub idle: IdleConfig,
+    pub error_rate: ErrorRateConfig,
+    pub diagnostics: DiagnosticsConfig,
+    pub status_line: StatusLineConfig,
+    /// Soft CPU and memory budgets of compose projects.
+    pub budgets: Vec<Budget>,
+    /// Actions that need a typed phrase on some containers.
+    pub guards: Vec<Guard>,
+    /// Containers to start in one go from the `a` picker.
+    pub templates: Vec<Template>,
+    /// HTTP or TCP checks run by dockertop, shown in the APP column.
+    pub probes: Vec<Probe>,
+    /// Colors for log lines, first matching rule wins. The built-in rules
+    /// come after these; see [`with_builtin_highlights`].
+    pub highlights: Vec<HighlightRule>,
+}
+
+/// A soft budget for the compose project `project`, compared against the
+/// summed usage of its running containers. Exceeding it only warns.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Budget {
+    pub project: String,
+    /// In the units of the CPU column, i.e. percent of the host.
+    pub cpu_percent: Option<f64>,
+    /// Like `docker run --memory`, e.g. `2g`.
+    #[serde(default, deserialize_with = "deserialize_memory")]
+    pub memory: Option<u64>,
+}
+
+fn deserialize_memory<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+    let size = String::deserialize(deserializer)?;
+    let bytes = crate::limits::parse_memory(&size).map_err(|e| serde::de::Error::custom(format!("{:#}", e)))?;
+    Ok(Some(bytes as u64))
+}
+
+/// Asks for `phrase` to be typed before any of `actions` is taken on a
+/// container matching `containers`, a filter expression as for `/`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Guard {
+    #[serde(deserialize_with = "deserialize_query")]
+    pub containers: Query,
+    pub actions: Vec<GuardedAction>,
+    /// `{{name}}` stands for the container's name, or for e.g. `3
+    /// containers` when acting on several at once.
+    #[serde(default = "default_phrase")]
+    pub phrase: String,
+}
+
+fn default_phrase() -> String {
+    "{{name}}".to_string()
+}
+
+fn deserialize_query<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Query, D::Error> {
+    let source = String::deserialize(deserializer)?;
+    Query::parse(&source).map_err(|e| serde::de::Error::custom(format!("{:#}", e)))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GuardedAction {
+    Start,
+    Stop,
+    Restart,
+    Pause,
+    Unpause,
+    Kill,
+    Remove,
+    ForceRemove,
+    Rename,
+    Update,
+    Commit,
+    Recreate,
+    Export,
+}
+
+/// A saved `docker run -d`, e.g. the Postgres one always spins up to test
+/// against.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Template {
+    pub name: String,
+    pub image: String,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// As for `docker run -p`, e.g. `5432:5432` or `127.0.0.1:6379:6379/tcp`.
+    #[serde(default)]
+    pub ports: Vec<String>,
+    /// As for `docker run -v`, e.g. `pgdata:/var/lib/postgresql/data`.
+    #[serde(default)]
+    pub volumes: Vec<String>,
+    /// `{{template}}` stands for the template's name and `{{n}}` for the
+    /// lowest number that makes the name unique.
+    #[serde(default = "default_container_name")]
+    pub container_name: String,
+}
+
+fn default_container_name() -> String {
+    "{{template}}-{{n}}".to_string()
+}
+
+/// Colors log lines matching `pattern`, e.g. `ERROR|panic` in red.
+#[derive(Debug, Clone, Deserialize)]
+pub struct HighlightRule {
+    #[serde(deserialize_with = "deserialize_regex")]
+    pub pattern: Regex,
+    pub color: HighlightColor,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HighlightColor {
+    Red,
+    Yellow,
+    Green,
+    Blue,
+    Magenta,
+    Cyan,
+    Gray,
+}
+
+/// The configured rules followed by the built-in ones, which color errors
+/// red and warnings yellow, in any case and as JSON log levels too, unless a
+/// configured rule matches first.
+pub fn with_builtin_highlights(mut rules: Vec<HighlightRule>) -> Vec<HighlightRule> {
+    let builtin = [
+        (r"(?i)\b(error|fatal|panic)\b", HighlightColor::Red),
+        (r"(?i)\bwarn(ing)?\b", HighlightColor::Yellow),
+    ];
+    rules.extend(builtin.into_iter().map(|(pattern, color)| HighlightRule {
+        pattern: Regex::new(pattern).expect("built-in highlight pattern"),
+        color,
+    }));
+    rules
+}
+
+fn deserialize_regex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Regex, D::Error> {
+    let pattern = String::deserialize(deserializer)?;
+    Regex::new(&pattern).map_err(serde::de::Error::custom)
+}
+
+/// A readiness check for containers whose name matches `containers` (`*`
+/// matches anything). Set either `http` or `tcp`; both take the same
+/// placeholders as [`CustomColumn`].
+#[derive(Debug, Clone, Deserialize)]
+pub struct Probe {
+    pub containers: String,
+    /// URL to GET, e.g. `http://localhost:{{port}}/health`.
+    pub http: Option<String>,
+    /// Address to connect to, e.g. `localhost:{{port}}`.
+    pub tcp: Option<String>,
+    /// Seconds between probes; also the timeout, capped at 5 seconds.
+    #[serde(default = "default_probe_interval")]
+    pub interval: u64,
+    #[serde(default = "default_expect_status")]
+    pub expect_status: u16,
+}
+
+fn default_probe_interval() -> u64 {
+    10
+}
+
+fn default_expect_status() -> u16 {
+    200
+}
+
+/// Behaviour of the container list.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ListConfig {
+    /// Seconds a container that exited or was removed stays dimmed with its
+    /// final stats; 0 turns this off.
+    pub fade_out: u64,
+    /// Seconds between refreshes of the stats.
+    pub refresh_seconds: u64,
+}
+
+impl Default for ListConfig {
+    fn default() -> Self {
+        Self {
+            fade_out: 10,
+            refresh_seconds: 2,
+        }
+    }
+}
+
+/// How the CPU and memory history charts are drawn.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default)]
+pub struct ChartsConfig {
+    pub style: ChartStyle,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChartStyle {
+    /// A braille line, four dots high per row.
+    #[default]
+    Line,
+    /// The area under the line filled with braille dots.
+    Braille,
+    /// Columns of eighth blocks, like btop's; no axis labels.
+    Block,
+}
+
+/// Units, decimal separator and precision of sizes and percentages.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct FormatConfig {
+    pub units: Units,
+    /// E.g. `,` for `1,50 GB`.
+    pub decimal_separator: char,
+    /// Digits after the separator in sizes such as `1.50 GB`.
+    pub precision: usize,
+    /// Digits after the separator in percentages such as `12.5%`.
+    pub percent_precision: usize,
+}
+
+impl Default for FormatConfig {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl FormatConfig {
+    pub const DEFAULT: Self = Self {
+        units: Units::Jedec,
+        decimal_separator: '.',
+        precision: 2,
+        percent_precision: 1,
+    };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Units {
+    /// Powers of 1024 written KB, MB, GB, like most tools do.
+    Jedec,
+    /// Powers of 1024 written KiB, MiB, GiB.
+    Iec,
+    /// Powers of 1000 written kB, MB, GB, like disk vendors do.
+    Si,
+}
+
+/// Key bindings.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct KeysConfig {
+    /// `j`/`k` move the selection like `↓`/`↑`.
+    pub vim: bool,
+}
+
+/// Floors below which a running container counts as quiet; quiet for
+/// `minutes` on end, it is flagged as idle.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct IdleConfig {
+    pub cpu_percent: f64,
+    /// Received plus sent.
+    pub net_bytes_per_second: f64,
+    /// Read plus written.
+    pub disk_bytes_per_second: f64,
+    pub minutes: u64,
+}
+
+impl Default for IdleConfig {
+    fn default() -> Self {
+        Self {
+            cpu_percent: 1.0,
+            net_bytes_per_second: 1024.0,
+            disk_bytes_per_second: 1024.0,
+            minutes: 60,
+        }
+    }
+}
+
+/// Where the one-line summary goes besides the screen.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct StatusLineConfig {
+    pub terminal_title: bool,
+    /// Rewritten whenever the summary changes, e.g. for tmux's
+    /// `#(cat ...)`.
+    pub file: Option<PathBuf>,
+}
+
+/// Commands of the `b` diagnostics bundle, each run with `sh -c` inside
+/// the container. Keep them read-only.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DiagnosticsConfig {
+    pub commands: Vec<String>,
+}
+
+impl Default for DiagnosticsConfig {
+    fn default() -> Self {
+        Self {
+            commands: ["df -h", "free -m", "netstat -tln 2>/dev/null || ss -tln", "env | head -n 20"]
+                .map(String::from)
+                .to_vec(),
+        }
+    }
+}
+
+/// Prices for the COST column, which shows once either price is set.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CostConfig {
+    /// Price of one vCPU for an hour.
+    pub vcpu_hour: f64,
+    /// Price of 1 GiB of memory for an hour.
+    pub gb_hour: f64,
+    /// Put before amounts, e.g. `$` or `€`.
+    pub currency: String,
+    pub basis: CostBasis,
+    /// CPUs of the Docker host, as CPU percentages are of the whole host.
+    /// Defaults to this machine's.
+    pub host_cpus: Option<usize>,
+}
+
+impl Default for CostConfig {
+    fn default() -> Self {
+        Self {
+            vcpu_hour: 0.0,
+            gb_hour: 0.0,
+            currency: "$".to_string(),
+            basis: CostBasis::Usage,
+            host_cpus: None,
+        }
+    }
+}
+
+/// What memory is charged for. CPU is always charged by average usage,
+/// since CPU quotas are not part of the stats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CostBasis {
+    /// Average memory in use since dockertop started.
+    Usage,
+    /// The memory limit, where one is set, as it is reserved either way.
+    Limits,
+}
+
+/// Background cleanup while dockertop runs. Off unless `enabled` is set.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AutoPruneConfig {
+    pub enabled: bool,
+    /// Seconds between runs.
+    pub interval: u64,
+    /// Exited containers created more than this many days ago are removed;
+    /// 0 leaves containers alone.
+    pub exited_days: u64,
+    pub dangling_images: bool,
+}
+
+impl Default for AutoPruneConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval: 3600,
+            exited_days: 7,
+            dangling_images: true,
+        }
+    }
+}
+
+/// Automatic responses to unhealthy or memory-hungry containers. Nothing
+/// happens unless `enabled` is set.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct AutoActionsConfig {
+    pub enabled: bool,
+    pub rules: Vec<AutoActionRule>,
+}
+
+/// Takes `action` on running containers whose name matches `containers`
+/// (`*` matches anything) once any of the conditions holds.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AutoActionRule {
+    pub containers: String,
+    #[serde(default)]
+    pub action: AutoAction,
+    /// Consecutive failed healthchecks.
+    pub unhealthy_checks: Option<u32>,
+    /// Memory usage in percent of the limit (of host memory without one),
+    /// exceeded for `for_minutes` in a row.
+    pub memory_percent: Option<f64>,
+    #[serde(default)]
+    pub for_minutes: u64,
+    /// Log lines matching `[error_rate] pattern` in the last minute.
+    pub errors_per_minute: Option<f64>,
+    /// Minutes before the rule may act on the same container again.
+    #[serde(default = "default_cooldown_minutes")]
+    pub cooldown_minutes: u64,
+}
+
+fn default_cooldown_minutes() -> u64 {
+    15
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AutoAction {
+    #[default]
+    Restart,
+    Stop,
+}
+
+/// The log of mutating actions taken against the Docker daemon.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AuditConfig {
+    pub enabled: bool,
+    /// Defaults to `$XDG_STATE_HOME/dockertop/audit.log` (or
+    /// `~/.local/state/dockertop/audit.log`); point it somewhere shared on
+    /// multi-user hosts.
+    pub path: Option<PathBuf>,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            path: None,
+        }
+    }
+}
+
+impl AuditConfig {
+    pub fn path(&self) -> Option<PathBuf> {
+        if let Some(path) = &self.path {
+            return Some(path.clone());
+        }
+        Some(state_dir()?.join("audit.log"))
+    }
+}
+
+/// `$XDG_STATE_HOME/dockertop`, falling back to `~/.local/state/dockertop`.
+fn state_dir() -> Option<PathBuf> {
+    let base = env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("state")))?;
+    Some(base.join("dockertop"))
+}
+
+/// Periodic snapshots of the session's history, restored on the next start
+/// so a crash or a closed terminal does not lose the charts.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct JournalConfig {
+    pub enabled: bool,
+    /// Seconds between snapshots.
+    pub interval: u64,
+    /// Defaults to `journal.json` next to the audit log.
+    pub path: Option<PathBuf>,
+}
+
+impl Default for JournalConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval: 60,
+            path: None,
+        }
+    }
+}
+
+impl JournalConfig {
+    pub fn path(&self) -> Option<PathBuf> {
+        if let Some(path) = &self.path {
+            return Some(path.clone());
+        }
+        Some(state_dir()?.join("journal.json"))
+    }
+}
+
+/// What the orphans screen considers abandoned.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct OrphansConfig {
+    /// Stopped containers created more than this many days ago are listed.
+    pub stopped_days: u64,
+}
+
+impl Default for OrphansConfig {
+    fn default() -> Self {
+        Self { stopped_days: 7 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BuiltinColumn {
+    /// Log lines and bytes per second; follows the logs of every running container.
+    LogRate,
+    /// Log lines matching `[error_rate] pattern` per minute; also follows
+    /// the logs of every running container.
+    ErrorRate,
+    /// Memory growth per minute over the last ten minutes.
+    MemoryGrowth,
+    /// Restarts seen in the last hour.
+    RestartRate,
+    /// Network bytes received and sent per second.
+    NetIo,
+    /// Bytes read from and written to disk since the container started.
+    BlockIo,
+}
+
+/// What counts as an error line for the `error_rate` column and the
+/// `errors_per_minute` auto-action condition.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ErrorRateConfig {
+    #[serde(deserialize_with = "deserialize_regex")]
+    pub pattern: Regex,
+}
+
+impl Default for ErrorRateConfig {
+    fn default() -> Self {
+        Self {
+            pattern: Regex::new(r"(?i)\b(error|fatal|panic|exception)\b").unwrap(),
+        }
+    }
+}
+
+/// Limits on Docker API traffic.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ApiConfig {
+    /// Requests allowed in flight at once.
+    pub max_concurrent: usize,
+    /// Requests started per second; 0 means unlimited.
+    pub max_per_second: u32,
+    /// Daemon to connect to, e.g. `unix:///run/user/1000/docker.sock` or
+    /// `tcp://10.0.0.5:2375`. Unset uses `DOCKER_HOST` or the local socket.
+    pub host: Option<String>,
+    /// A second daemon whose containers are listed beside the first, like
+    /// `--compare`.
+    pub compare_host: Option<String>,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent: 4,
+            max_per_second: 0,
+            host: None,
+            compare_host: None,
+        }
+    }
+}
+
+/// A column whose value is the first line printed by `command`, run once per
+/// container every `interval` seconds. `{{id}}`, `{{name}}`, `{{image}}` and
+/// `{{port}}` (first published port) are substituted before running.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CustomColumn {
+    pub name: String,
+    pub command: String,
+    #[serde(default = "default_column_interval")]
+    pub interval: u64,
+}
+
+fn default_column_interval() -> u64 {
+    10
+}
+
+/// A command bound to `key`, with the same placeholders as [`CustomColumn`].
+/// Built-in keybindings take precedence over custom ones.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CustomCommand {
+    pub key: char,
+    pub command: String,
+    #[serde(default)]
+    pub mode: CommandMode,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CommandMode {
+    /// Suspend the UI and hand the terminal to the command.
+    #[default]
+    Terminal,
+    /// Run in the background and show the output in a pane.
+    Capture,
+}
+
+impl Config {
+    /// Whether logs need following for error lines: for the column or for
+    /// an auto-action rule.
+    pub fn counts_errors(&self) -> bool {
+        self.builtin_columns.contains(&BuiltinColumn::ErrorRate)
+            || self.auto_actions.rules.iter().any(|r| r.errors_per_minute.is_some())
+    }
+
+    pub fn path() -> Option<PathBuf> {
+        let base = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(base.join("dockertop").join("config.toml"))
+    }
+
+    /// Loads the config file, or the defaults if there is none.
+    pub fn load() -> Result<Self> {
+        let Some(path) = Self::path().filter(|p| p.exists()) else {
+            return Ok(Self::default());
+        };
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        toml::from_str(&text).with_context(|| format!("Invalid config file {}", path.display()))
+    }
+}
+
+/// Notices edits to the config file by its modification time, so changes
+/// apply without a restart.
+pub struct ConfigWatch {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl ConfigWatch {
+    pub fn new() -> Option<Self> {
+        let path = Config::path()?;
+        let modified = Self::modified(&path);
+        Some(Self { path, modified })
+    }
+
+    fn modified(path: &PathBuf) -> Option<SystemTime> {
+        fs::metadata(path).and_then(|m| m.modified()).ok()
+    }
+
+    /// Whether the file was written, created or deleted since the last call.
+    pub fn changed(&mut self) -> bool {
+        let modified = Self::modified(&self.path);
+        let changed = modified != self.modified;
+        self.modified = modified;
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_example_in_the_readme_is_a_valid_config() {
+        let readme = include_str!("../README.md");
+        let examples: Vec<&str> = readme
+            .split("```toml\n")
+            .skip(1)
+            .map(|block| block.split("```").next().unwrap_or_default())
+            .collect();
+        assert!(!examples.is_empty());
+        for example in examples {
+            if let Err(e) = toml::from_str::<Config>(example) {
+                panic!("{}\n{}", e, example);
+            }
+        }
+    }
+
+    #[test]
+    fn values_are_checked_as_they_are_read() {
+        let cases = [
+            ("[[budgets]]\nproject = \"shop\"\nmemory = \"2g\"", true),
+            ("[[budgets]]\nproject = \"shop\"\nmemory = \"2 apples\"", false),
+            ("[[guards]]\ncontainers = \"name:prod-*\"\nactions = [\"stop\"]", true),
+            ("[[guards]]\ncontainers = \"cpu > \"\nactions = [\"stop\"]", false),
+            ("[[highlights]]\npattern = \"timeout\"\ncolor = \"magenta\"", true),
+            ("[[highlights]]\npattern = \"(unclosed\"\ncolor = \"magenta\"", false),
+            ("[[highlights]]\npattern = \"timeout\"\ncolor = \"pink\"", false),
+        ];
+        for (toml, valid) in cases {
+            assert_eq!(toml::from_str::<Config>(toml).is_ok(), valid, "{}", toml);
+        }
+    }
+
+    #[test]
+    fn probes_default_to_every_ten_seconds_expecting_200() {
+        let config: Config = toml::from_str("[[probes]]\ncontainers = \"web*\"\nhttp = \"http://localhost:{{port}}/\"").unwrap();
+        let probe = &config.probes[0];
+        assert_eq!((probe.interval, probe.expect_status, probe.tcp.as_deref()), (10, 200, None));
+    }
+}
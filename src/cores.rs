@@ -0,0 +1,43 @@
+//! Per-core CPU usage, derived from the cumulative `percpu_usage` counters
+//! of consecutive refreshes. Only cgroup v1 hosts report them.
+
+use crate::backend::ContainerStats;
+use std::{collections::HashMap, time::Instant};
+
+#[derive(Default)]
+pub struct CoreMeter {
+    last: HashMap<String, (Instant, Vec<u64>)>,
+    /// Percent of each core, by container ID.
+    usage: HashMap<String, Vec<f64>>,
+}
+
+impl CoreMeter {
+    pub fn update(&mut self, containers: &[ContainerStats]) {
+        let now = Instant::now();
+        let mut last = HashMap::with_capacity(containers.len());
+        self.usage.clear();
+        for container in containers.iter().filter(|c| !c.cpu_cores.is_empty()) {
+            if let Some((then, before)) = self.last.get(&container.id) {
+                let elapsed = now.duration_since(*then).as_nanos() as f64;
+                // Cores coming online change the count; that sample is skipped.
+                if elapsed > 0.0 && before.len() == container.cpu_cores.len() {
+                    let usage = container
+                        .cpu_cores
+                        .iter()
+                        .zip(before)
+                        .map(|(after, before)| (after.saturating_sub(*before) as f64 / elapsed * 100.0).min(100.0))
+                        .collect();
+                    self.usage.insert(container.id.clone(), usage);
+                }
+            }
+            last.insert(container.id.clone(), (now, container.cpu_cores.clone()));
+        }
+        self.last = last;
+    }
+
+    /// Empty until the container has been seen twice, or if its cores are
+    /// not reported.
+    pub fn usage(&self, id: &str) -> &[f64] {
+        self.usage.get(id).map_or(&[], Vec::as_slice)
+    }
+}
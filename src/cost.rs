@@ -0,0 +1,146 @@
+//! Estimated running costs for chargeback discussions, from configured
+//! prices and the usage averaged over the session.
+
+use crate::backend::ContainerStats;
+use crate::config::{CostBasis, CostConfig};
+use std::collections::HashMap;
+
+const GIB: f64 = 1024.0 * 1024.0 * 1024.0;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Average {
+    /// Sums over the samples: CPU in percent of the host, memory in bytes.
+    cpu: f64,
+    memory: f64,
+    samples: u64,
+}
+
+pub struct CostMeter {
+    config: CostConfig,
+    host_cpus: f64,
+    /// By container ID, over the samples taken while it ran.
+    averages: HashMap<String, Average>,
+}
+
+impl CostMeter {
+    /// `None` unless a price is configured.
+    pub fn new(config: CostConfig) -> Option<Self> {
+        if config.vcpu_hour <= 0.0 && config.gb_hour <= 0.0 {
+            return None;
+        }
+        let host_cpus = config
+            .host_cpus
+            .or_else(|| std::thread::available_parallelism().ok().map(|n| n.get()))
+            .unwrap_or(1);
+        Some(Self {
+            config,
+            host_cpus: host_cpus as f64,
+            averages: HashMap::new(),
+        })
+    }
+
+    /// Carries the averages of `previous` over, so a config reload only
+    /// changes the prices.
+    pub fn keeping(mut self, previous: Option<CostMeter>) -> Self {
+        if let Some(previous) = previous {
+            self.averages = previous.averages;
+        }
+        self
+    }
+
+    /// Adds a sample of every running container and forgets removed ones.
+    pub fn update(&mut self, containers: &[ContainerStats]) {
+        self.averages.retain(|id, _| containers.iter().any(|c| &c.id == id));
+        for container in containers.iter().filter(|c| c.is_running()) {
+            let average = self.averages.entry(container.id.clone()).or_default();
+            average.cpu += container.cpu_usage;
+            average.memory += container.memory_usage as f64;
+            average.samples += 1;
+        }
+    }
+
+    /// Estimated cost of an hour at the container's average usage, if it
+    /// has been seen running.
+    pub fn hourly(&self, container: &ContainerStats) -> Option<f64> {
+        let average = self.averages.get(&container.id).filter(|a| a.samples > 0)?;
+        let samples = average.samples as f64;
+        let vcpus = average.cpu / samples / 100.0 * self.host_cpus;
+        let memory = match self.config.basis {
+            CostBasis::Limits if !container.memory_unlimited => container.memory_limit as f64,
+            _ => average.memory / samples,
+        };
+        Some(vcpus * self.config.vcpu_hour + memory / GIB * self.config.gb_hour)
+    }
+
+    /// The summed hourly cost of `containers`.
+    pub fn total<'a>(&self, containers: impl IntoIterator<Item = &'a ContainerStats>) -> f64 {
+        containers.into_iter().filter_map(|c| self.hourly(c)).sum()
+    }
+
+    /// An amount with the configured currency, with enough decimals for
+    /// small containers.
+    pub fn format(&self, amount: f64) -> String {
+        let decimals = if amount < 1.0 { 3 } else { 2 };
+        format!("{}{:.*}", self.config.currency, decimals, amount)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::mock::container;
+
+    fn meter(basis: CostBasis) -> CostMeter {
+        CostMeter::new(CostConfig {
+            vcpu_hour: 0.04,
+            gb_hour: 0.01,
+            currency: "€".to_string(),
+            basis,
+            host_cpus: Some(4),
+        })
+        .unwrap()
+    }
+
+    fn sample(name: &str, cpu: f64, memory: u64) -> ContainerStats {
+        let mut c = container(name);
+        c.cpu_usage = cpu;
+        c.memory_usage = memory;
+        c.memory_limit = 4 << 30;
+        c
+    }
+
+    #[test]
+    fn costs_need_a_price() {
+        assert!(CostMeter::new(CostConfig::default()).is_none());
+    }
+
+    #[test]
+    fn hourly_costs_use_the_average_usage_or_the_limits() {
+        let mut stopped = sample("db", 0.0, 0);
+        stopped.status = "Exited (0) 1 hour ago".to_string();
+        let mut unlimited = sample("cache", 25.0, 1 << 30);
+        unlimited.memory_unlimited = true;
+
+        let cases = [(CostBasis::Usage, 0.015, 0.01), (CostBasis::Limits, 0.04, 0.01)];
+        for (basis, web, cache) in cases {
+            let mut meter = meter(basis);
+            meter.update(&[sample("web", 50.0, 1 << 30), stopped.clone(), unlimited.clone()]);
+            meter.update(&[sample("web", 100.0, 2 << 30), stopped.clone(), unlimited.clone()]);
+            // Web averages 75% of 4 CPUs and 1.5 GiB of its 4 GiB limit; cache
+            // has no limit, so it costs its 1 GiB either way.
+            let web_hourly = meter.hourly(&sample("web", 0.0, 0)).unwrap();
+            assert!((web_hourly - (3.0 * 0.04 + web)).abs() < 1e-9, "{:?}: {}", basis, web_hourly);
+            assert!((meter.hourly(&unlimited).unwrap() - (0.04 + cache)).abs() < 1e-9);
+            assert_eq!(meter.hourly(&stopped), None);
+        }
+    }
+
+    #[test]
+    fn amounts_get_more_decimals_below_one() {
+        let meter = meter(CostBasis::Usage);
+        let cases = [(0.0, "€0.000"), (0.0123, "€0.012"), (1.0, "€1.00"), (12.345, "€12.35")];
+        for (amount, text) in cases {
+            assert_eq!(meter.format(amount), text);
+        }
+    }
+}
@@ -0,0 +1,33 @@
+//! Recent CPU usage of every listed container, charted in the details pane
+//! so bursts between refreshes of the list stay visible.
+
+use crate::backend::ContainerStats;
+use crate::watch::HISTORY;
+use std::collections::{HashMap, VecDeque};
+
+#[derive(Default)]
+pub struct CpuHistory {
+    /// `(sample number, percent)` pairs by container ID, oldest first.
+    samples: HashMap<String, VecDeque<(f64, f64)>>,
+    count: u64,
+}
+
+impl CpuHistory {
+    /// Takes in a refresh; containers no longer listed are forgotten.
+    pub fn update(&mut self, containers: &[ContainerStats]) {
+        self.count += 1;
+        self.samples.retain(|id, _| containers.iter().any(|c| c.id == *id));
+        for container in containers.iter().filter(|c| c.is_running()) {
+            let history = self.samples.entry(container.id.clone()).or_default();
+            if history.len() == HISTORY {
+                history.pop_front();
+            }
+            history.push_back((self.count as f64, container.cpu_usage));
+        }
+    }
+
+    /// Empty for containers not seen running.
+    pub fn of(&self, id: &str) -> Vec<(f64, f64)> {
+        self.samples.get(id).map_or(Vec::new(), |h| h.iter().copied().collect())
+    }
+}
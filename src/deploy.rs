@@ -0,0 +1,431 @@
+//! Rollouts that run in the background: a blue/green switch between two
+//! containers of the same compose service (start the new one, wait until it
+//! is healthy, then stop the old one), a soft redeploy of a whole compose
+//! project in dependency order, and updating a container to the newest
+//! image of its tag.
+
+use crate::backend::{ContainerBackend, ContainerStats};
+use crate::graph;
+use crate::recreate::RecreateForm;
+use anyhow::{bail, Context, Result};
+use bollard::models::HealthStatusEnum;
+use futures::future::join_all;
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+/// How long a started container gets to become healthy.
+const HEALTH_TIMEOUT: Duration = Duration::from_secs(120);
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The label pairing the two containers.
+pub fn service_label(container: &ContainerStats) -> Option<(&str, &str)> {
+    let project = container.labels.get("com.docker.compose.project")?;
+    let service = container.labels.get("com.docker.compose.service")?;
+    Some((project, service))
+}
+
+/// For the selected container, the running old version and the stopped
+/// new one of its service. Fails unless there is exactly one of each.
+pub fn find_pair(
+    containers: &[ContainerStats],
+    selected: &ContainerStats,
+) -> Result<(ContainerStats, ContainerStats)> {
+    let Some(service) = service_label(selected) else {
+        bail!("{} has no compose service label", selected.name);
+    };
+    let siblings: Vec<&ContainerStats> = containers
+        .iter()
+        .filter(|c| service_label(c) == Some(service))
+        .collect();
+    let running: Vec<&&ContainerStats> = siblings.iter().filter(|c| c.is_running()).collect();
+    let stopped: Vec<&&ContainerStats> = siblings
+        .iter()
+        .filter(|c| !c.is_running() && running.iter().all(|r| r.image != c.image))
+        .collect();
+    match (running.as_slice(), stopped.as_slice()) {
+        ([old], [new]) => Ok(((**old).clone(), (**new).clone())),
+        _ => bail!(
+            "Blue/green needs one running and one stopped container of service {} with different images ({} running, {} candidates)",
+            service.1,
+            running.len(),
+            stopped.len()
+        ),
+    }
+}
+
+enum Progress {
+    Step(String),
+    Done(Result<String>),
+}
+
+/// Stages of a soft redeploy: each stage's containers depend only on those
+/// of earlier stages. Containers caught in a dependency cycle share the
+/// last stage.
+pub fn stages(members: &[ContainerStats]) -> Vec<Vec<ContainerStats>> {
+    let dependencies = graph::dependencies(members, &HashMap::new());
+    let mut remaining: Vec<usize> = (0..members.len()).collect();
+    let mut stages = Vec::new();
+    while !remaining.is_empty() {
+        let ready: Vec<usize> = remaining
+            .iter()
+            .copied()
+            .filter(|&i| !dependencies.iter().any(|d| d.from == i && remaining.contains(&d.to)))
+            .collect();
+        let stage = if ready.is_empty() { std::mem::take(&mut remaining) } else { ready };
+        remaining.retain(|i| !stage.contains(i));
+        stages.push(stage.into_iter().map(|i| members[i].clone()).collect());
+    }
+    stages
+}
+
+/// One line per stage, e.g. `db, cache → api → web`.
+pub fn describe(stages: &[Vec<ContainerStats>]) -> String {
+    let names = |stage: &Vec<ContainerStats>| stage.iter().map(|c| c.name.as_str()).collect::<Vec<_>>().join(", ");
+    stages.iter().map(names).collect::<Vec<_>>().join(" → ")
+}
+
+/// A rollout running in the background, reporting progress as it goes.
+pub struct Deployment {
+    /// What is being rolled out, e.g. `Blue/green web`.
+    pub title: String,
+    pub message: String,
+    pub finished: bool,
+    pub failed: bool,
+    rx: UnboundedReceiver<Progress>,
+}
+
+impl Deployment {
+    pub fn start(backend: Arc<dyn ContainerBackend>, old: ContainerStats, new: ContainerStats) -> Self {
+        let (tx, rx) = unbounded_channel();
+        let service = service_label(&new).map(|(_, s)| s.to_string()).unwrap_or_default();
+        tokio::spawn(async move {
+            let outcome = switch(backend.as_ref(), &old, &new, &tx).await;
+            let _ = tx.send(Progress::Done(outcome));
+        });
+        Self {
+            title: format!("Blue/green {}", service),
+            message: "starting".to_string(),
+            finished: false,
+            failed: false,
+            rx,
+        }
+    }
+
+    /// Stops the project's containers, dependents first, then starts them
+    /// again stage by stage, each once the previous stage is healthy.
+    pub fn redeploy(backend: Arc<dyn ContainerBackend>, project: String, stages: Vec<Vec<ContainerStats>>) -> Self {
+        let (tx, rx) = unbounded_channel();
+        tokio::spawn(async move {
+            let outcome = redeploy(backend.as_ref(), &stages, &tx).await;
+            let _ = tx.send(Progress::Done(outcome));
+        });
+        Self {
+            title: format!("Redeploy {}", project),
+            message: "stopping".to_string(),
+            finished: false,
+            failed: false,
+            rx,
+        }
+    }
+
+    /// Pulls the container's image and, if its tag now refers to a newer
+    /// image, recreates the container from it. The old container stays
+    /// stopped until the new one is healthy, and is started again if the new
+    /// one is not.
+    pub fn update(backend: Arc<dyn ContainerBackend>, container: ContainerStats) -> Self {
+        let (tx, rx) = unbounded_channel();
+        let title = format!("Update {}", container.name);
+        tokio::spawn(async move {
+            let outcome = update(backend.as_ref(), &container, &tx).await;
+            let _ = tx.send(Progress::Done(outcome));
+        });
+        Self {
+            title,
+            message: "pulling".to_string(),
+            finished: false,
+            failed: false,
+            rx,
+        }
+    }
+
+    /// Picks up progress messages.
+    pub fn poll(&mut self) {
+        while let Ok(progress) = self.rx.try_recv() {
+            match progress {
+                Progress::Step(message) => self.message = message,
+                Progress::Done(outcome) => {
+                    self.finished = true;
+                    self.failed = outcome.is_err();
+                    self.message = outcome.unwrap_or_else(|e| format!("{:#}", e));
+                }
+            }
+        }
+    }
+}
+
+/// How a started container came out of waiting for its healthcheck.
+enum Health {
+    Healthy,
+    Exited,
+    Unhealthy,
+    TimedOut,
+}
+
+/// Waits for the container to pass its healthcheck, or just to keep
+/// running if it has none, reporting the seconds waited so far.
+async fn wait_healthy(backend: &dyn ContainerBackend, id: &str, mut waiting: impl FnMut(u64)) -> Result<Health> {
+    let started = Instant::now();
+    loop {
+        tokio::time::sleep(POLL_INTERVAL).await;
+        let state = backend.inspect_container(id).await?.state.unwrap_or_default();
+        if state.running != Some(true) {
+            return Ok(Health::Exited);
+        }
+        match state.health.and_then(|h| h.status) {
+            Some(HealthStatusEnum::HEALTHY) => return Ok(Health::Healthy),
+            // Without a healthcheck, running is all we can wait for.
+            None | Some(HealthStatusEnum::NONE) | Some(HealthStatusEnum::EMPTY) => return Ok(Health::Healthy),
+            Some(HealthStatusEnum::UNHEALTHY) => return Ok(Health::Unhealthy),
+            Some(HealthStatusEnum::STARTING) => {}
+        }
+        if started.elapsed() > HEALTH_TIMEOUT {
+            return Ok(Health::TimedOut);
+        }
+        waiting(started.elapsed().as_secs());
+    }
+}
+
+async fn switch(
+    backend: &dyn ContainerBackend,
+    old: &ContainerStats,
+    new: &ContainerStats,
+    progress: &UnboundedSender<Progress>,
+) -> Result<String> {
+    let _ = progress.send(Progress::Step(format!("starting {} ({})", new.name, new.image)));
+    backend.start_container(&new.id).await?;
+
+    let waiting = |waited| {
+        let _ = progress.send(Progress::Step(format!("waiting for {} to become healthy ({}s)", new.name, waited)));
+    };
+    match wait_healthy(backend, &new.id, waiting).await? {
+        Health::Healthy => {}
+        Health::Exited => bail!("{} exited before becoming healthy; {} left running", new.name, old.name),
+        Health::Unhealthy => {
+            backend.stop_container(&new.id).await?;
+            bail!("{} became unhealthy and was stopped; {} left running", new.name, old.name);
+        }
+        Health::TimedOut => {
+            backend.stop_container(&new.id).await?;
+            bail!(
+                "{} was not healthy after {}s and was stopped; {} left running",
+                new.name,
+                HEALTH_TIMEOUT.as_secs(),
+                old.name
+            );
+        }
+    }
+
+    let _ = progress.send(Progress::Step(format!("{} is healthy, stopping {}", new.name, old.name)));
+    backend.stop_container(&old.id).await?;
+    Ok(format!("switched from {} ({}) to {} ({})", old.name, old.image, new.name, new.image))
+}
+
+async fn redeploy(
+    backend: &dyn ContainerBackend,
+    stages: &[Vec<ContainerStats>],
+    progress: &UnboundedSender<Progress>,
+) -> Result<String> {
+    for stage in stages.iter().rev() {
+        let _ = progress.send(Progress::Step(format!("stopping {}", describe(std::slice::from_ref(stage)))));
+        let stops = join_all(stage.iter().map(|c| backend.stop_container(&c.id))).await;
+        for (container, stopped) in stage.iter().zip(stops) {
+            if let Err(e) = stopped {
+                bail!("could not stop {}: {:#}", container.name, e);
+            }
+        }
+    }
+
+    for (i, stage) in stages.iter().enumerate() {
+        let step = format!("stage {}/{}", i + 1, stages.len());
+        let _ = progress.send(Progress::Step(format!("{}: starting {}", step, describe(std::slice::from_ref(stage)))));
+        let starts = join_all(stage.iter().map(|c| backend.start_container(&c.id))).await;
+        for (container, started) in stage.iter().zip(starts) {
+            let Err(e) = started else {
+                continue;
+            };
+            // Its restart policy may have brought it back already.
+            let state = backend.inspect_container(&container.id).await?.state.unwrap_or_default();
+            if state.running != Some(true) {
+                bail!("{}: could not start {}: {:#}", step, container.name, e);
+            }
+        }
+        let waits = join_all(stage.iter().map(|c| {
+            let step = &step;
+            wait_healthy(backend, &c.id, move |waited| {
+                let _ = progress.send(Progress::Step(format!("{}: waiting for {} ({}s)", step, c.name, waited)));
+            })
+        }))
+        .await;
+        for (container, health) in stage.iter().zip(waits) {
+            match health? {
+                Health::Healthy => {}
+                Health::Exited => bail!("{}: {} exited; later stages not started", step, container.name),
+                Health::Unhealthy => bail!("{}: {} is unhealthy; later stages not started", step, container.name),
+                Health::TimedOut => bail!(
+                    "{}: {} was not healthy after {}s; later stages not started",
+                    step,
+                    container.name,
+                    HEALTH_TIMEOUT.as_secs()
+                ),
+            }
+        }
+    }
+    let count: usize = stages.iter().map(Vec::len).sum();
+    Ok(format!("{} containers restarted in {} stages", count, stages.len()))
+}
+
+async fn update(backend: &dyn ContainerBackend, container: &ContainerStats, progress: &UnboundedSender<Progress>) -> Result<String> {
+    let inspect = backend.inspect_container(&container.id).await?;
+    // The same configuration as now, as the `M` form would show it.
+    let spec = RecreateForm::new(container.clone(), &inspect).spec()?;
+    let _ = progress.send(Progress::Step(format!("pulling {}", spec.image)));
+    let image_id = backend.pull_image(&spec.image).await?;
+    if image_id == container.image_id {
+        return Ok(format!("{} is already on the newest {}", container.name, spec.image));
+    }
+
+    let _ = progress.send(Progress::Step(format!("recreating {}", container.name)));
+    let new_id = backend.recreate_container(&container.id, &spec).await?;
+    let waiting = |waited| {
+        let _ = progress.send(Progress::Step(format!("waiting for the new {} to become healthy ({}s)", container.name, waited)));
+    };
+    let failure = match wait_healthy(backend, &new_id, waiting).await {
+        Ok(Health::Healthy) => None,
+        Ok(Health::Exited) => Some("exited".to_string()),
+        Ok(Health::Unhealthy) => Some("became unhealthy".to_string()),
+        Ok(Health::TimedOut) => Some(format!("was not healthy after {}s", HEALTH_TIMEOUT.as_secs())),
+        Err(e) => Some(format!("could not be checked: {:#}", e)),
+    };
+    if let Some(failure) = failure {
+        let _ = progress.send(Progress::Step(format!("the new {} {}, putting the old one back", container.name, failure)));
+        // Every step is tried, so the old container comes back even if the
+        // new one cannot be removed.
+        let steps = [
+            ("remove the new container", backend.force_remove_container(&new_id).await),
+            ("rename the old one back", backend.rename_container(&container.id, &container.name).await),
+            ("start the old one", backend.start_container(&container.id).await),
+        ];
+        let errors: Vec<String> = steps
+            .into_iter()
+            .filter_map(|(step, result)| result.err().map(|e| format!("failed to {}: {:#}", step, e)))
+            .collect();
+        if !errors.is_empty() {
+            bail!("the new {} {}, and putting the old one back went wrong: {}", container.name, failure, errors.join("; "));
+        }
+        bail!("the new {} {}; the old one is running again", container.name, failure);
+    }
+
+    let _ = progress.send(Progress::Step(format!("the new {} is healthy, removing the old one", container.name)));
+    backend
+        .remove_container(&container.id)
+        .await
+        .context("Updated, but the old container is still there")?;
+    Ok(format!("{} updated to the newest {}", container.name, spec.image))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::mock::{container, MockBackend};
+
+    fn member(name: &str, service: &str, image: &str, running: bool, depends_on: &str) -> ContainerStats {
+        let mut c = container(name);
+        c.image = image.to_string();
+        if !running {
+            c.status = "Exited (0) 1 minute ago".to_string();
+        }
+        c.labels.insert("com.docker.compose.project".to_string(), "shop".to_string());
+        c.labels.insert("com.docker.compose.service".to_string(), service.to_string());
+        c.labels.insert("com.docker.compose.depends_on".to_string(), depends_on.to_string());
+        c
+    }
+
+    #[test]
+    fn pairs_need_one_running_and_one_stopped_container_with_another_image() {
+        let blue = member("web-blue", "web", "web:1", true, "");
+        let green = member("web-green", "web", "web:2", false, "");
+        let (old, new) = find_pair(&[blue.clone(), green.clone()], &green).unwrap();
+        assert_eq!((old.name.as_str(), new.name.as_str()), ("web-blue", "web-green"));
+
+        let other_service = member("api", "api", "api:2", false, "");
+        let same_image = member("web-old", "web", "web:1", false, "");
+        let also_running = member("web-green", "web", "web:2", true, "");
+        let cases = [
+            vec![blue.clone()],
+            vec![blue.clone(), other_service],
+            vec![blue.clone(), same_image],
+            vec![blue.clone(), also_running],
+            vec![blue.clone(), green.clone(), member("web-red", "web", "web:3", false, "")],
+        ];
+        for containers in cases {
+            let error = find_pair(&containers, &blue).unwrap_err().to_string();
+            assert!(error.starts_with("Blue/green needs one running"), "{:?}: {}", containers, error);
+        }
+
+        let error = find_pair(&[container("solo")], &container("solo")).unwrap_err();
+        assert_eq!(error.to_string(), "solo has no compose service label");
+    }
+
+    #[test]
+    fn redeploy_stages_follow_dependencies() {
+        let web = member("web", "web", "web", true, "api:service_started:false");
+        let api = member("api", "api", "api", true, "db:service_healthy:false,cache:service_started:false");
+        let db = member("db", "db", "db", true, "");
+        let cache = member("cache", "cache", "cache", true, "");
+        assert_eq!(describe(&stages(&[web, api, db, cache])), "db, cache → api → web");
+
+        let ping = member("ping", "ping", "ping", true, "pong:service_started:false");
+        let pong = member("pong", "pong", "pong", true, "ping:service_started:false");
+        let db = member("db", "db", "db", true, "");
+        assert_eq!(describe(&stages(&[ping, pong, db])), "db → ping, pong");
+        assert_eq!(describe(&stages(&[])), "");
+    }
+
+    #[tokio::test]
+    async fn switching_leaves_the_old_container_running_when_the_new_one_exits() {
+        let backend = MockBackend::new([]);
+        let blue = member("web-blue", "web", "web:1", true, "");
+        let green = member("web-green", "web", "web:2", false, "");
+        let (tx, _rx) = unbounded_channel();
+
+        let error = switch(&backend, &blue, &green, &tx).await.unwrap_err();
+        assert_eq!(error.to_string(), "web-green exited before becoming healthy; web-blue left running");
+        assert_eq!(*backend.lifecycle.lock().unwrap(), [("start", "web-green-id".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn a_failed_update_puts_the_old_container_back_even_if_the_new_one_stays() {
+        let backend = MockBackend::new([]);
+        *backend.failing_removals.lock().unwrap() = vec!["web-id-new".to_string()];
+        let (tx, _rx) = unbounded_channel();
+
+        let error = update(&backend, &container("web"), &tx).await.unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "the new web exited, and putting the old one back went wrong: failed to remove the new container: web-id-new is in use"
+        );
+        let lifecycle = backend.lifecycle.lock().unwrap();
+        assert_eq!(
+            lifecycle[lifecycle.len() - 3..],
+            [
+                ("force remove", "web-id-new".to_string()),
+                ("rename", "web-id".to_string()),
+                ("start", "web-id".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,70 @@
+//! The `b` diagnostics bundle: a few read-only commands run inside the
+//! container, gathered into one report to read in a pane or save. One-off
+//! commands typed after `!` run the same way.
+
+use crate::backend::{ContainerBackend, ContainerStats, ExecStreams};
+use anyhow::{anyhow, Result};
+use futures::StreamExt;
+use std::{sync::Arc, time::Duration};
+
+/// Per command, so one that hangs does not hold up the report for good.
+const TIMEOUT: Duration = Duration::from_secs(10);
+/// One-off commands may do real work, like `du` over a data directory.
+pub const ONE_OFF_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Runs `commands` one after the other and returns the report's lines:
+/// each command's output under a `$ command` heading.
+pub async fn collect(backend: Arc<dyn ContainerBackend>, container: ContainerStats, commands: Vec<String>) -> Vec<String> {
+    let mut lines = vec![format!(
+        "Diagnostics of {} at {}",
+        container.name,
+        chrono::Local::now().format("%Y-%m-%d %H:%M:%S")
+    )];
+    for command in &commands {
+        lines.push(String::new());
+        lines.push(format!("$ {}", command));
+        match run(backend.as_ref(), &container.id, command, TIMEOUT).await {
+            Ok(output) => lines.extend(output.lines().map(str::to_string)),
+            Err(e) => lines.push(format!("(failed: {:#})", e)),
+        }
+    }
+    lines
+}
+
+/// Runs `command` with `sh -c` in the container, without stdin, and
+/// returns its stdout and stderr as they interleaved.
+pub async fn run(backend: &dyn ContainerBackend, id: &str, command: &str, timeout: Duration) -> Result<String> {
+    let ExecStreams { output, input } = backend.exec(id, &["sh", "-c", command]).await?;
+    drop(input);
+    let chunks: Vec<Result<String>> = tokio::time::timeout(timeout, output.collect())
+        .await
+        .map_err(|_| anyhow!("timed out after {}s", timeout.as_secs()))?;
+    chunks.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::mock::{container, MockBackend};
+
+    #[tokio::test]
+    async fn the_report_runs_each_command_under_its_own_heading() {
+        let backend = Arc::new(MockBackend::new([]));
+        backend
+            .exec_output
+            .lock()
+            .unwrap()
+            .extend(["Linux web 6.1\n".to_string(), "Filesystem  Use%\n/dev/sda1   42%\n".to_string()]);
+        let commands = vec!["uname -a".to_string(), "df -h".to_string(), "free -m".to_string()];
+
+        let lines = collect(backend.clone(), container("web"), commands).await;
+        assert!(lines[0].starts_with("Diagnostics of web at "), "{}", lines[0]);
+        assert_eq!(
+            lines[1..],
+            ["", "$ uname -a", "Linux web 6.1", "", "$ df -h", "Filesystem  Use%", "/dev/sda1   42%", "", "$ free -m"]
+        );
+        let execs = backend.execs.lock().unwrap();
+        assert_eq!(execs[0], ("web-id".to_string(), vec!["sh".to_string(), "-c".to_string(), "uname -a".to_string()]));
+        assert_eq!(execs.len(), 3);
+    }
+}
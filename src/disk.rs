@@ -0,0 +1,143 @@
+//! Disk usage trend over the session, sampled from `system df`.
+
+use crate::backend::DiskUsage;
+use crate::journal;
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// `system df` walks every layer and volume, so it is sampled far less often
+/// than container stats.
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// A day's worth of samples.
+const MAX_SAMPLES: usize = 2880;
+
+pub struct DiskTrend {
+    started: Instant,
+    last_sample: Option<Instant>,
+    /// Minutes since the session started, with the usage at that point.
+    pub samples: VecDeque<(f64, DiskUsage)>,
+}
+
+impl Default for DiskTrend {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            last_sample: None,
+            samples: VecDeque::new(),
+        }
+    }
+}
+
+impl DiskTrend {
+    pub fn due(&self) -> bool {
+        self.last_sample.is_none_or(|t| t.elapsed() >= SAMPLE_INTERVAL)
+    }
+
+    /// Marks a sample as taken, whether or not it succeeded, so a failing
+    /// `system df` isn't retried every tick.
+    pub fn attempted(&mut self) {
+        self.last_sample = Some(Instant::now());
+    }
+
+    pub fn push(&mut self, usage: DiskUsage) {
+        if self.samples.len() == MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples
+            .push_back((self.started.elapsed().as_secs_f64() / 60.0, usage));
+    }
+
+    pub fn latest(&self) -> Option<&DiskUsage> {
+        self.samples.back().map(|(_, usage)| usage)
+    }
+
+    /// Growth of the total since the first sample, in bytes.
+    pub fn growth(&self) -> i64 {
+        match (self.samples.front(), self.samples.back()) {
+            (Some((_, first)), Some((_, last))) => last.total() as i64 - first.total() as i64,
+            _ => 0,
+        }
+    }
+
+    /// The samples with Unix seconds, for the journal.
+    pub fn history(&self) -> Vec<(i64, DiskUsage)> {
+        self.samples
+            .iter()
+            .map(|(minutes, usage)| (journal::unix_at(self.started + Duration::from_secs_f64(minutes * 60.0)), *usage))
+            .collect()
+    }
+
+    /// Takes the samples of an earlier run, as if this session had started
+    /// with the first of them.
+    pub fn restore(&mut self, history: Vec<(i64, DiskUsage)>) {
+        let Some(started) = history.first().and_then(|(first, _)| journal::instant_at(*first)) else {
+            return;
+        };
+        let first = history[0].0;
+        self.started = started;
+        self.samples = history
+            .into_iter()
+            .map(|(unix, usage)| ((unix - first) as f64 / 60.0, usage))
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(images: u64, volumes: u64) -> DiskUsage {
+        DiskUsage {
+            images,
+            containers: 10,
+            volumes,
+            build_cache: 0,
+        }
+    }
+
+    #[test]
+    fn growth_is_measured_from_the_first_sample() {
+        let mut trend = DiskTrend::default();
+        assert!(trend.due());
+        assert_eq!((trend.growth(), trend.latest().is_none()), (0, true));
+
+        trend.attempted();
+        assert!(!trend.due());
+        trend.push(usage(1000, 500));
+        trend.push(usage(1200, 100));
+        trend.push(usage(900, 900));
+        assert_eq!(trend.growth(), 300);
+        assert_eq!(trend.latest().map(DiskUsage::total), Some(1810));
+    }
+
+    #[test]
+    fn samples_are_capped_at_a_day() {
+        let mut trend = DiskTrend::default();
+        for i in 0..MAX_SAMPLES as u64 + 2 {
+            trend.push(usage(i, 0));
+        }
+        assert_eq!(trend.samples.len(), MAX_SAMPLES);
+        assert_eq!(trend.samples.front().map(|(_, u)| u.images), Some(2));
+    }
+
+    #[test]
+    fn history_restores_minutes_relative_to_the_first_sample() {
+        let now = chrono::Utc::now().timestamp();
+        let mut trend = DiskTrend::default();
+        trend.restore(vec![(now - 600, usage(1, 0)), (now - 300, usage(2, 0)), (now - 60, usage(3, 0))]);
+        let minutes: Vec<f64> = trend.samples.iter().map(|(m, _)| *m).collect();
+        assert_eq!(minutes, [0.0, 5.0, 9.0]);
+
+        let history = trend.history();
+        assert_eq!(history.len(), 3);
+        for ((unix, usage), expected) in history.iter().zip([now - 600, now - 300, now - 60]) {
+            assert!((unix - expected).abs() <= 1, "{} {:?}", unix, usage);
+        }
+
+        trend.restore(Vec::new());
+        assert_eq!(trend.samples.len(), 3);
+    }
+}
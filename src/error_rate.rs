@@ -0,0 +1,131 @@
+//! A crude application error rate: log lines matching a pattern, per
+//! container per minute, from following every running container's logs.
+
+use crate::backend::{ContainerBackend, ContainerStats};
+use futures::StreamExt;
+use regex::Regex;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::task::JoinHandle;
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+struct Follower {
+    task: JoinHandle<()>,
+    since: Instant,
+    /// Matches counted at each sync within the last minute.
+    window: VecDeque<(Instant, u64)>,
+}
+
+pub struct ErrorMeter {
+    pattern: Regex,
+    counts: Arc<Mutex<HashMap<String, u64>>>,
+    followers: HashMap<String, Follower>,
+}
+
+impl ErrorMeter {
+    pub fn new(pattern: Regex) -> Self {
+        Self {
+            pattern,
+            counts: Arc::default(),
+            followers: HashMap::new(),
+        }
+    }
+
+    pub fn pattern(&self) -> &Regex {
+        &self.pattern
+    }
+
+    /// Drops stopped containers, adds the matches since the previous call to
+    /// each window and starts following newly started containers.
+    pub fn sync(&mut self, backend: &Arc<dyn ContainerBackend>, containers: &[ContainerStats]) {
+        self.followers.retain(|id, follower| {
+            let keep = !follower.task.is_finished() && containers.iter().any(|c| &c.id == id && c.is_running());
+            if !keep {
+                follower.task.abort();
+            }
+            keep
+        });
+        let now = Instant::now();
+        let counts = std::mem::take(&mut *self.counts.lock().unwrap());
+        for (id, follower) in &mut self.followers {
+            follower.window.push_back((now, counts.get(id).copied().unwrap_or(0)));
+            while follower.window.front().is_some_and(|(at, _)| now.duration_since(*at) > WINDOW) {
+                follower.window.pop_front();
+            }
+        }
+
+        for container in containers.iter().filter(|c| c.is_running()) {
+            if self.followers.contains_key(&container.id) {
+                continue;
+            }
+            let mut logs = backend.follow_logs(&container.id, 0, false);
+            let (counts, pattern, id) = (self.counts.clone(), self.pattern.clone(), container.id.clone());
+            let task = tokio::spawn(async move {
+                while let Some(Ok(chunk)) = logs.next().await {
+                    let matches = chunk.text.lines().filter(|line| pattern.is_match(line)).count() as u64;
+                    if matches > 0 {
+                        *counts.lock().unwrap().entry(id.clone()).or_default() += matches;
+                    }
+                }
+            });
+            let follower = Follower {
+                task,
+                since: now,
+                window: VecDeque::new(),
+            };
+            self.followers.insert(container.id.clone(), follower);
+        }
+    }
+
+    /// Matching lines per minute over the last minute, scaled up while the
+    /// container has been followed for less. `None` until it has been
+    /// followed for a full tick.
+    pub fn per_minute(&self, id: &str) -> Option<f64> {
+        let follower = self.followers.get(id).filter(|f| !f.window.is_empty())?;
+        let followed = follower.since.elapsed().min(WINDOW).as_secs_f64();
+        let matches: u64 = follower.window.iter().map(|(_, n)| n).sum();
+        Some(matches as f64 * 60.0 / followed.max(1.0))
+    }
+}
+
+impl Drop for ErrorMeter {
+    fn drop(&mut self) {
+        for follower in self.followers.values() {
+            follower.task.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::mock::{container, MockBackend};
+
+    #[tokio::test]
+    async fn matching_lines_are_counted_per_minute() {
+        let mock = MockBackend::new([]);
+        *mock.followed.lock().unwrap() = vec!["GET / 200\nERROR timeout\n".to_string(), "ERROR again\nok\n".to_string()];
+        *mock.follow_forever.lock().unwrap() = true;
+        let backend: Arc<dyn ContainerBackend> = Arc::new(mock);
+        let mut meter = ErrorMeter::new(Regex::new("ERROR").unwrap());
+
+        meter.sync(&backend, &[container("web")]);
+        assert!(meter.per_minute("web-id").is_none());
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        meter.sync(&backend, &[container("web")]);
+        // Followed for well under a second, which counts as one.
+        assert_eq!(meter.per_minute("web-id"), Some(120.0));
+
+        meter.sync(&backend, &[container("web")]);
+        assert_eq!(meter.per_minute("web-id"), Some(120.0), "the minute's window keeps earlier matches");
+
+        meter.sync(&backend, &[]);
+        assert!(meter.per_minute("web-id").is_none());
+    }
+}
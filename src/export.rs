@@ -0,0 +1,182 @@
+//! Snapshots of the screen as plain text or HTML, for pasting into tickets
+//! and chat.
+
+use anyhow::{Context, Result};
+use std::{fmt::Write, path::Path};
+use tui::{
+    buffer::{Buffer, Cell},
+    style::{Color, Modifier},
+};
+
+/// Writes the drawn screen to `path`: HTML with its colors if the file name
+/// ends in `.html` or `.htm`, plain text otherwise.
+pub fn write(buffer: &Buffer, path: &Path) -> Result<()> {
+    let html = path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("html") || e.eq_ignore_ascii_case("htm"));
+    let contents = if html { to_html(buffer) } else { to_text(buffer) };
+    std::fs::write(path, contents).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn rows(buffer: &Buffer) -> impl Iterator<Item = &[Cell]> {
+    buffer.content.chunks(buffer.area.width.max(1) as usize)
+}
+
+fn to_text(buffer: &Buffer) -> String {
+    let mut text = String::new();
+    for row in rows(buffer) {
+        let line: String = row.iter().map(|c| c.symbol.as_str()).collect();
+        text.push_str(line.trim_end());
+        text.push('\n');
+    }
+    text
+}
+
+fn to_html(buffer: &Buffer) -> String {
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>dockertop</title></head>\n<body>\n\
+         <pre style=\"background:#000;color:#ddd;font-family:monospace;line-height:1.2\">",
+    );
+    for row in rows(buffer) {
+        // One span per run of cells with the same style.
+        let mut start = 0;
+        while start < row.len() {
+            let style = css(&row[start]);
+            let end = row[start..]
+                .iter()
+                .position(|c| css(c) != style)
+                .map_or(row.len(), |n| start + n);
+            let text: String = row[start..end].iter().map(|c| escape(&c.symbol)).collect();
+            if style.is_empty() {
+                html.push_str(&text);
+            } else {
+                let _ = write!(html, "<span style=\"{}\">{}</span>", style, text);
+            }
+            start = end;
+        }
+        html.push('\n');
+    }
+    html.push_str("</pre>\n</body>\n</html>\n");
+    html
+}
+
+fn css(cell: &Cell) -> String {
+    let (mut fg, mut bg) = (color(cell.fg), color(cell.bg));
+    if cell.modifier.contains(Modifier::REVERSED) {
+        (fg, bg) = (Some(bg.unwrap_or("#000".to_string())), Some(fg.unwrap_or("#ddd".to_string())));
+    }
+    let mut style = String::new();
+    if let Some(fg) = fg {
+        let _ = write!(style, "color:{};", fg);
+    }
+    if let Some(bg) = bg {
+        let _ = write!(style, "background:{};", bg);
+    }
+    if cell.modifier.contains(Modifier::BOLD) {
+        style.push_str("font-weight:bold;");
+    }
+    if cell.modifier.contains(Modifier::DIM) {
+        style.push_str("opacity:0.6;");
+    }
+    if cell.modifier.contains(Modifier::ITALIC) {
+        style.push_str("font-style:italic;");
+    }
+    if cell.modifier.contains(Modifier::UNDERLINED) {
+        style.push_str("text-decoration:underline;");
+    }
+    style
+}
+
+/// The xterm palette for the 16 named colors.
+const PALETTE: [&str; 16] = [
+    "#000000", "#cd0000", "#00cd00", "#cdcd00", "#0000ee", "#cd00cd", "#00cdcd", "#e5e5e5", "#7f7f7f", "#ff0000",
+    "#00ff00", "#ffff00", "#5c5cff", "#ff00ff", "#00ffff", "#ffffff",
+];
+
+/// CSS for a terminal color, `None` for the terminal's default.
+fn color(color: Color) -> Option<String> {
+    let index = match color {
+        Color::Reset => return None,
+        Color::Rgb(r, g, b) => return Some(format!("#{:02x}{:02x}{:02x}", r, g, b)),
+        Color::Indexed(i) => i,
+        Color::Black => 0,
+        Color::Red => 1,
+        Color::Green => 2,
+        Color::Yellow => 3,
+        Color::Blue => 4,
+        Color::Magenta => 5,
+        Color::Cyan => 6,
+        Color::Gray => 7,
+        Color::DarkGray => 8,
+        Color::LightRed => 9,
+        Color::LightGreen => 10,
+        Color::LightYellow => 11,
+        Color::LightBlue => 12,
+        Color::LightMagenta => 13,
+        Color::LightCyan => 14,
+        Color::White => 15,
+    };
+    Some(indexed(index))
+}
+
+/// The 256-color palette: the 16 named colors, a 6×6×6 cube, then grays.
+fn indexed(i: u8) -> String {
+    match i {
+        0..=15 => PALETTE[i as usize].to_string(),
+        16..=231 => {
+            let level = |n: u8| if n == 0 { 0 } else { 55 + n * 40 };
+            let n = i - 16;
+            format!("#{:02x}{:02x}{:02x}", level(n / 36), level(n / 6 % 6), level(n % 6))
+        }
+        232..=255 => {
+            let gray = 8 + (i - 232) * 10;
+            format!("#{:02x}{:02x}{:02x}", gray, gray, gray)
+        }
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tui::{layout::Rect, style::Style};
+
+    #[test]
+    fn terminal_colors_become_css_colors() {
+        let cases = [
+            (Color::Reset, None),
+            (Color::Red, Some("#cd0000")),
+            (Color::White, Some("#ffffff")),
+            (Color::Rgb(1, 2, 255), Some("#0102ff")),
+            (Color::Indexed(9), Some("#ff0000")),
+            (Color::Indexed(16), Some("#000000")),
+            (Color::Indexed(196), Some("#ff0000")),
+            (Color::Indexed(110), Some("#87afd7")),
+            (Color::Indexed(232), Some("#080808")),
+            (Color::Indexed(255), Some("#eeeeee")),
+        ];
+        for (terminal, css) in cases {
+            assert_eq!(color(terminal).as_deref(), css, "{:?}", terminal);
+        }
+    }
+
+    #[test]
+    fn screens_export_as_text_and_as_html() {
+        let mut buffer = Buffer::empty(Rect::new(0, 0, 8, 2));
+        buffer.set_string(0, 0, "a<b & c", Style::default());
+        buffer.set_string(0, 1, "ok", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD));
+        buffer.set_string(3, 1, "!", Style::default().add_modifier(Modifier::REVERSED));
+
+        assert_eq!(to_text(&buffer), "a<b & c\nok !\n");
+        let html = to_html(&buffer);
+        assert!(html.contains("a&lt;b &amp; c \n"), "{}", html);
+        assert!(
+            html.contains("<span style=\"color:#00cd00;font-weight:bold;\">ok</span> <span style=\"color:#000;background:#ddd;\">!</span>    \n"),
+            "{}",
+            html
+        );
+    }
+}
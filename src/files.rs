@@ -0,0 +1,172 @@
+//! The `O` file browser: a container's filesystem listed with `stat` run
+//! inside it, and small text files shown with `head`, so poking around does
+//! not need a shell. Containers without `sh`, such as distroless ones,
+//! cannot be browsed.
+
+use crate::backend::{ContainerBackend, ContainerStats};
+use crate::diagnostics;
+use anyhow::{bail, Result};
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+/// Larger files are not shown, only listed.
+pub const MAX_VIEW_BYTES: u64 = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    Directory,
+    File,
+    /// Sockets, devices and the like.
+    Other,
+}
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub name: String,
+    pub kind: EntryKind,
+    /// Bytes.
+    pub size: u64,
+    /// Unix seconds.
+    pub modified: i64,
+}
+
+/// A file shown in place of the listing.
+pub struct FileView {
+    pub path: String,
+    pub lines: Vec<String>,
+    pub scroll: u16,
+}
+
+pub struct FileBrowser {
+    pub container: ContainerStats,
+    /// The listed directory, absolute.
+    pub path: String,
+    /// Directories first, each group by name.
+    pub entries: Vec<Entry>,
+    pub cursor: usize,
+    pub viewing: Option<FileView>,
+    /// Why the last listing or file could not be read.
+    pub error: Option<String>,
+}
+
+impl FileBrowser {
+    /// A browser listing the container's root directory.
+    pub async fn open(backend: &dyn ContainerBackend, container: ContainerStats) -> Self {
+        let mut browser = Self {
+            container,
+            path: "/".to_string(),
+            entries: Vec::new(),
+            cursor: 0,
+            viewing: None,
+            error: None,
+        };
+        browser.list(backend, "/".to_string()).await;
+        browser
+    }
+
+    pub fn move_cursor(&mut self, down: bool) {
+        self.cursor = if down {
+            (self.cursor + 1).min(self.entries.len().saturating_sub(1))
+        } else {
+            self.cursor.saturating_sub(1)
+        };
+    }
+
+    pub fn selected(&self) -> Option<&Entry> {
+        self.entries.get(self.cursor)
+    }
+
+    /// Lists the selected directory, or shows the selected file.
+    pub async fn enter(&mut self, backend: &dyn ContainerBackend) {
+        let Some(entry) = self.selected().cloned() else {
+            return;
+        };
+        let path = join(&self.path, &entry.name);
+        match entry.kind {
+            EntryKind::Directory => self.list(backend, path).await,
+            EntryKind::File if entry.size > MAX_VIEW_BYTES => {
+                self.error = Some(format!("{} is over {} KB; only smaller files are shown", entry.name, MAX_VIEW_BYTES / 1024));
+            }
+            EntryKind::File => match read(backend, &self.container.id, &path).await {
+                Ok(lines) => {
+                    self.error = None;
+                    self.viewing = Some(FileView { path, lines, scroll: 0 });
+                }
+                Err(e) => self.error = Some(format!("{:#}", e)),
+            },
+            EntryKind::Other => self.error = Some(format!("{} is not a regular file", entry.name)),
+        }
+    }
+
+    /// Lists the parent directory, with the one left selected.
+    pub async fn up(&mut self, backend: &dyn ContainerBackend) {
+        let Some((parent, name)) = self.path.rsplit_once('/') else {
+            return;
+        };
+        if name.is_empty() {
+            return;
+        }
+        let name = name.to_string();
+        self.list(backend, if parent.is_empty() { "/".to_string() } else { parent.to_string() }).await;
+        self.cursor = self.entries.iter().position(|e| e.name == name).unwrap_or(0);
+    }
+
+    /// Switches to `path`, or keeps the current listing if it cannot be read.
+    async fn list(&mut self, backend: &dyn ContainerBackend, path: String) {
+        match list(backend, &self.container.id, &path).await {
+            Ok(entries) => {
+                self.path = path;
+                self.entries = entries;
+                self.cursor = 0;
+                self.error = None;
+            }
+            Err(e) => self.error = Some(format!("{:#}", e)),
+        }
+    }
+}
+
+async fn list(backend: &dyn ContainerBackend, id: &str, path: &str) -> Result<Vec<Entry>> {
+    // Globs that match nothing stay as they are and make stat fail quietly;
+    // -L lists links as what they point to.
+    let command = format!(
+        "cd -- {} || exit 1; stat -L -c '%F|%s|%Y|%n' -- * .[!.]* ..?* 2>/dev/null; exit 0",
+        quote(path)
+    );
+    let output = diagnostics::run(backend, id, &command, TIMEOUT).await?;
+    let mut entries = Vec::new();
+    for line in output.lines() {
+        let fields: Vec<&str> = line.splitn(4, '|').collect();
+        let [kind, size, modified, name] = fields[..] else {
+            bail!("{}", line.trim_start_matches("sh: "));
+        };
+        let (Ok(size), Ok(modified)) = (size.parse(), modified.parse()) else {
+            bail!("Unexpected output from stat: {}", line);
+        };
+        let kind = match kind {
+            "directory" => EntryKind::Directory,
+            "regular file" | "regular empty file" => EntryKind::File,
+            _ => EntryKind::Other,
+        };
+        entries.push(Entry { name: name.to_string(), kind, size, modified });
+    }
+    entries.sort_by(|a, b| (a.kind != EntryKind::Directory, &a.name).cmp(&(b.kind != EntryKind::Directory, &b.name)));
+    Ok(entries)
+}
+
+async fn read(backend: &dyn ContainerBackend, id: &str, path: &str) -> Result<Vec<String>> {
+    let command = format!("head -c {} -- {}", MAX_VIEW_BYTES, quote(path));
+    let text = diagnostics::run(backend, id, &command, TIMEOUT).await?;
+    if text.contains('\0') {
+        bail!("{} looks binary; only text files are shown", path);
+    }
+    Ok(text.lines().map(|line| line.replace('\t', "    ")).collect())
+}
+
+fn join(directory: &str, name: &str) -> String {
+    format!("{}/{}", directory.trim_end_matches('/'), name)
+}
+
+/// Single-quotes `text` for `sh`.
+fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
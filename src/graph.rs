@@ -0,0 +1,262 @@
+//! Container dependency graph, built from compose `depends_on` labels,
+//! legacy links and `network_mode: container:` references.
+
+use crate::backend::ContainerStats;
+use std::collections::{HashMap, HashSet};
+
+const PROJECT_LABEL: &str = "com.docker.compose.project";
+const SERVICE_LABEL: &str = "com.docker.compose.service";
+const DEPENDS_ON_LABEL: &str = "com.docker.compose.depends_on";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DependencyKind {
+    DependsOn,
+    Link,
+    NetworkMode,
+}
+
+impl DependencyKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            DependencyKind::DependsOn => "depends_on",
+            DependencyKind::Link => "link",
+            DependencyKind::NetworkMode => "network_mode",
+        }
+    }
+}
+
+/// `from` needs `to` to be up first. Both are indices into the container list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dependency {
+    pub from: usize,
+    pub to: usize,
+    pub kind: DependencyKind,
+}
+
+/// Finds every dependency between the given containers. `links` maps
+/// container IDs to their legacy links (`/target:/self/alias`), which are
+/// only available from inspect.
+pub fn dependencies(containers: &[ContainerStats], links: &HashMap<String, Vec<String>>) -> Vec<Dependency> {
+    let by_name: HashMap<&str, usize> = containers
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (c.name.as_str(), i))
+        .collect();
+    let find = |reference: &str| {
+        by_name.get(reference).copied().or_else(|| {
+            containers
+                .iter()
+                .position(|c| !reference.is_empty() && c.id.starts_with(reference))
+        })
+    };
+
+    let mut result = Vec::new();
+    let mut add = |from: usize, to: Option<usize>, kind| {
+        if let Some(to) = to.filter(|&to| to != from) {
+            let dependency = Dependency { from, to, kind };
+            if !result.contains(&dependency) {
+                result.push(dependency);
+            }
+        }
+    };
+
+    for (from, container) in containers.iter().enumerate() {
+        if let (Some(project), Some(depends_on)) =
+            (container.labels.get(PROJECT_LABEL), container.labels.get(DEPENDS_ON_LABEL))
+        {
+            // "db:service_healthy:false,cache:service_started:false"
+            for service in depends_on.split(',').filter_map(|d| d.split(':').next()) {
+                for (to, other) in containers.iter().enumerate() {
+                    if other.labels.get(PROJECT_LABEL) == Some(project)
+                        && other.labels.get(SERVICE_LABEL).map(String::as_str) == Some(service.trim())
+                    {
+                        add(from, Some(to), DependencyKind::DependsOn);
+                    }
+                }
+            }
+        }
+
+        for link in links.get(&container.id).into_iter().flatten() {
+            let target = link.split(':').next().unwrap_or_default().trim_start_matches('/');
+            add(from, find(target), DependencyKind::Link);
+        }
+
+        if let Some(target) = container.network_mode.strip_prefix("container:") {
+            add(from, find(target), DependencyKind::NetworkMode);
+        }
+    }
+    result
+}
+
+/// One line of the rendered tree.
+#[derive(Debug, Clone)]
+pub struct TreeLine {
+    /// Box-drawing prefix, e.g. `│   └── `.
+    pub prefix: String,
+    pub container: usize,
+    /// How the parent line depends on this one; `None` for roots.
+    pub kind: Option<DependencyKind>,
+    /// The parent is running while this dependency is not.
+    pub problem: bool,
+    /// This container already appears above on the same branch.
+    pub cycle: bool,
+}
+
+/// Lays the graph out as a tree: each root is a container nothing else
+/// depends on, with its dependencies (what must start first) nested below.
+/// Containers without any dependency in either direction are returned
+/// separately so they don't clutter the tree.
+pub fn tree(containers: &[ContainerStats], dependencies: &[Dependency]) -> (Vec<TreeLine>, Vec<usize>) {
+    let needed: HashSet<usize> = dependencies.iter().map(|d| d.to).collect();
+    let involved: HashSet<usize> = dependencies.iter().flat_map(|d| [d.from, d.to]).collect();
+
+    let mut roots: Vec<usize> = (0..containers.len())
+        .filter(|i| involved.contains(i) && !needed.contains(i))
+        .collect();
+    // Pure cycles have no natural root; start from their first member.
+    for i in 0..containers.len() {
+        if involved.contains(&i) && !roots.contains(&i) && !reachable_from(&roots, i, dependencies) {
+            roots.push(i);
+        }
+    }
+
+    let mut lines = Vec::new();
+    for root in roots {
+        lines.push(TreeLine {
+            prefix: String::new(),
+            container: root,
+            kind: None,
+            problem: false,
+            cycle: false,
+        });
+        walk(containers, dependencies, root, "", &mut vec![root], &mut lines);
+    }
+
+    let independent = (0..containers.len()).filter(|i| !involved.contains(i)).collect();
+    (lines, independent)
+}
+
+fn walk(
+    containers: &[ContainerStats],
+    dependencies: &[Dependency],
+    node: usize,
+    indent: &str,
+    path: &mut Vec<usize>,
+    lines: &mut Vec<TreeLine>,
+) {
+    let children: Vec<&Dependency> = dependencies.iter().filter(|d| d.from == node).collect();
+    for (i, dependency) in children.iter().enumerate() {
+        let last = i + 1 == children.len();
+        let cycle = path.contains(&dependency.to);
+        lines.push(TreeLine {
+            prefix: format!("{}{}", indent, if last { "└── " } else { "├── " }),
+            container: dependency.to,
+            kind: Some(dependency.kind),
+            problem: containers[node].is_running() && !containers[dependency.to].is_running(),
+            cycle,
+        });
+        if !cycle {
+            path.push(dependency.to);
+            let indent = format!("{}{}", indent, if last { "    " } else { "│   " });
+            walk(containers, dependencies, dependency.to, &indent, path, lines);
+            path.pop();
+        }
+    }
+}
+
+fn reachable_from(roots: &[usize], target: usize, dependencies: &[Dependency]) -> bool {
+    let mut seen: HashSet<usize> = HashSet::new();
+    let mut stack: Vec<usize> = roots.to_vec();
+    while let Some(node) = stack.pop() {
+        if node == target {
+            return true;
+        }
+        if seen.insert(node) {
+            stack.extend(dependencies.iter().filter(|d| d.from == node).map(|d| d.to));
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::mock::container;
+
+    fn service(name: &str, depends_on: &str) -> ContainerStats {
+        let mut c = container(name);
+        c.labels.insert(PROJECT_LABEL.to_string(), "shop".to_string());
+        c.labels.insert(SERVICE_LABEL.to_string(), name.to_string());
+        c.labels.insert(DEPENDS_ON_LABEL.to_string(), depends_on.to_string());
+        c
+    }
+
+    #[test]
+    fn dependencies_come_from_labels_links_and_network_mode() {
+        let mut sidecar = container("sidecar");
+        sidecar.network_mode = "container:web-id".to_string();
+        let mut other_project = service("db", "");
+        other_project.labels.insert(PROJECT_LABEL.to_string(), "blog".to_string());
+        let containers = [
+            service("web", "api:service_started:false"),
+            service("api", "db:service_healthy:false, cache:service_started:false"),
+            service("db", ""),
+            service("cache", "cache:service_started:false"),
+            sidecar,
+            container("legacy"),
+            other_project,
+        ];
+        let links = HashMap::from([("legacy-id".to_string(), vec!["/db:/legacy/db".to_string(), "/gone:/legacy/gone".to_string()])]);
+
+        let found: Vec<(&str, &str, &str)> = dependencies(&containers, &links)
+            .iter()
+            .map(|d| (containers[d.from].name.as_str(), containers[d.to].name.as_str(), d.kind.label()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("web", "api", "depends_on"),
+                ("api", "db", "depends_on"),
+                ("api", "cache", "depends_on"),
+                ("sidecar", "web", "network_mode"),
+                ("legacy", "db", "link"),
+            ]
+        );
+    }
+
+    #[test]
+    fn trees_nest_dependencies_under_what_needs_them() {
+        let mut cache = service("cache", "");
+        cache.status = "Exited (1) 1 minute ago".to_string();
+        let containers = [
+            service("web", "api:service_started:false"),
+            service("api", "db:service_healthy:false,cache:service_started:false"),
+            service("db", ""),
+            cache,
+            container("solo"),
+            service("ping", "pong:service_started:false"),
+            service("pong", "ping:service_started:false"),
+        ];
+        let (lines, independent) = tree(&containers, &dependencies(&containers, &HashMap::new()));
+        let rendered: Vec<String> = lines
+            .iter()
+            .map(|l| {
+                let marks = format!("{}{}", if l.problem { " !" } else { "" }, if l.cycle { " ↺" } else { "" });
+                format!("{}{}{}", l.prefix, containers[l.container].name, marks)
+            })
+            .collect();
+        assert_eq!(
+            rendered,
+            [
+                "web",
+                "└── api",
+                "    ├── db",
+                "    └── cache !",
+                "ping",
+                "└── pong",
+                "    └── ping ↺",
+            ]
+        );
+        assert_eq!(independent, [4]);
+    }
+}
@@ -0,0 +1,57 @@
+//! CPU and memory of the machine dockertop runs on, for context next to the
+//! containers' own figures. Read through sysinfo rather than `/proc`, so it
+//! works on macOS and Windows too; with Docker Desktop these are the host's
+//! figures, not those of the VM the containers run in.
+
+use sysinfo::{CpuRefreshKind, MemoryRefreshKind, RefreshKind, System};
+
+#[derive(Debug, Clone, Copy)]
+pub struct HostUsage {
+    /// Averaged over all CPUs.
+    pub cpu_percent: f64,
+    pub cpus: usize,
+    pub memory_used: u64,
+    pub memory_total: u64,
+}
+
+pub struct HostMonitor {
+    system: System,
+    /// Whether CPU times have been read once, which usage is measured from.
+    primed: bool,
+    pub usage: Option<HostUsage>,
+}
+
+impl Default for HostMonitor {
+    fn default() -> Self {
+        let refresh = RefreshKind::new()
+            .with_cpu(CpuRefreshKind::new().with_cpu_usage())
+            .with_memory(MemoryRefreshKind::new().with_ram());
+        Self {
+            system: System::new_with_specifics(refresh),
+            primed: false,
+            usage: None,
+        }
+    }
+}
+
+impl HostMonitor {
+    /// Takes a new sample; CPU usage is averaged since the previous one.
+    pub fn sample(&mut self) {
+        self.system.refresh_cpu_usage();
+        self.system.refresh_memory();
+        if !self.primed {
+            self.primed = true;
+            return;
+        }
+        let memory_total = self.system.total_memory();
+        if memory_total == 0 {
+            return;
+        }
+        self.usage = Some(HostUsage {
+            cpu_percent: self.system.global_cpu_info().cpu_usage() as f64,
+            cpus: self.system.cpus().len(),
+            memory_used: self.system.used_memory(),
+            memory_total,
+        });
+    }
+}
@@ -0,0 +1,116 @@
+//! Containers that have done next to nothing for a long time, as candidates
+//! for shutting down to reclaim memory.
+
+use crate::backend::ContainerStats;
+use crate::config::IdleConfig;
+use crate::io_rate::IoMeter;
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+pub struct IdleTracker {
+    config: IdleConfig,
+    /// By container ID: since when CPU and I/O have stayed below the floors.
+    quiet_since: HashMap<String, Instant>,
+}
+
+impl IdleTracker {
+    pub fn new(config: IdleConfig) -> Self {
+        Self {
+            config,
+            quiet_since: HashMap::new(),
+        }
+    }
+
+    /// New floors apply from the next refresh; quiet spells so far are kept.
+    pub fn reconfigure(&mut self, config: IdleConfig) {
+        self.config = config;
+    }
+
+    pub fn config(&self) -> &IdleConfig {
+        &self.config
+    }
+
+    /// Starts or ends the quiet spell of each container. One busy sample
+    /// ends it, as do stopping and the first refresh without I/O rates.
+    pub fn update(&mut self, containers: &[ContainerStats], io: &IoMeter) {
+        let now = Instant::now();
+        let quiet: Vec<&str> = containers
+            .iter()
+            .filter(|c| c.is_running() && c.cpu_usage < self.config.cpu_percent)
+            .filter(|c| {
+                io.rate(&c.id).is_some_and(|rate| {
+                    rate.net_rx + rate.net_tx < self.config.net_bytes_per_second
+                        && rate.block_read + rate.block_write < self.config.disk_bytes_per_second
+                })
+            })
+            .map(|c| c.id.as_str())
+            .collect();
+        self.quiet_since.retain(|id, _| quiet.contains(&id.as_str()));
+        for id in quiet {
+            self.quiet_since.entry(id.to_string()).or_insert(now);
+        }
+    }
+
+    /// How long the container has been quiet, once that is past the window.
+    pub fn idle_for(&self, id: &str) -> Option<Duration> {
+        let quiet = self.quiet_since.get(id)?.elapsed();
+        (quiet >= Duration::from_secs(self.config.minutes * 60)).then_some(quiet)
+    }
+
+    /// Idle containers, the longest idle first.
+    pub fn candidates<'a>(&self, containers: &'a [ContainerStats]) -> Vec<(&'a ContainerStats, Duration)> {
+        let mut idle: Vec<_> = containers
+            .iter()
+            .filter_map(|c| self.idle_for(&c.id).map(|d| (c, d)))
+            .collect();
+        idle.sort_by_key(|&(_, idle)| Reverse(idle));
+        idle
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::mock::container;
+
+    fn busy(name: &str, cpu: f64, net: u64) -> ContainerStats {
+        let mut c = container(name);
+        c.cpu_usage = cpu;
+        c.io.net_rx = net;
+        c
+    }
+
+    #[test]
+    fn only_containers_quiet_in_cpu_and_io_go_idle() {
+        let config = IdleConfig {
+            minutes: 0,
+            ..Default::default()
+        };
+        let mut tracker = IdleTracker::new(config.clone());
+        let mut io = IoMeter::default();
+        let mut stopped = busy("stopped", 0.0, 0);
+        stopped.status = "Exited (0) 1 hour ago".to_string();
+
+        let first = [busy("quiet", 0.5, 0), busy("cpu", 30.0, 0), busy("net", 0.0, 0), stopped.clone()];
+        io.update(&first);
+        tracker.update(&first, &io);
+        // Without I/O rates yet, nothing counts as quiet.
+        assert!(tracker.candidates(&first).is_empty());
+
+        std::thread::sleep(Duration::from_millis(20));
+        let second = [busy("quiet", 0.5, 10), busy("cpu", 30.0, 0), busy("net", 0.0, 1 << 20), stopped];
+        io.update(&second);
+        tracker.update(&second, &io);
+        let idle: Vec<&str> = tracker.candidates(&second).iter().map(|(c, _)| c.name.as_str()).collect();
+        assert_eq!(idle, ["quiet"]);
+
+        tracker.reconfigure(IdleConfig::default());
+        assert!(tracker.idle_for("quiet-id").is_none(), "an hour has not passed");
+        tracker.update(&[busy("quiet", 5.0, 10)], &io);
+        tracker.reconfigure(config);
+        assert!(tracker.idle_for("quiet-id").is_none(), "a busy sample ends the quiet spell");
+    }
+}
@@ -0,0 +1,135 @@
+//! Network and block I/O rates, derived from the cumulative counters of
+//! consecutive refreshes.
+
+use crate::backend::{ContainerStats, InterfaceCounters, IoCounters};
+use std::{collections::HashMap, time::Instant};
+
+/// Bytes per second.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IoRate {
+    pub net_rx: f64,
+    pub net_tx: f64,
+    pub block_read: f64,
+    pub block_write: f64,
+}
+
+/// Rates of one network interface, in bytes per second, with its totals.
+#[derive(Debug, Clone, Default)]
+pub struct InterfaceRate {
+    pub name: String,
+    pub rx: f64,
+    pub tx: f64,
+    pub rx_total: u64,
+    pub tx_total: u64,
+}
+
+#[derive(Default)]
+pub struct IoMeter {
+    last: HashMap<String, (Instant, IoCounters, Vec<InterfaceCounters>)>,
+    rates: HashMap<String, IoRate>,
+    interfaces: HashMap<String, Vec<InterfaceRate>>,
+}
+
+impl IoMeter {
+    pub fn update(&mut self, containers: &[ContainerStats]) {
+        let now = Instant::now();
+        let mut last = HashMap::with_capacity(containers.len());
+        self.rates.clear();
+        self.interfaces.clear();
+        for container in containers {
+            if let Some((then, before, interfaces)) = self.last.get(&container.id) {
+                let elapsed = now.duration_since(*then).as_secs_f64();
+                if elapsed > 0.0 {
+                    // Counters restart with the container; that sample reads as zero.
+                    let rate = |after: u64, before: u64| after.saturating_sub(before) as f64 / elapsed;
+                    let io = container.io;
+                    self.rates.insert(
+                        container.id.clone(),
+                        IoRate {
+                            net_rx: rate(io.net_rx, before.net_rx),
+                            net_tx: rate(io.net_tx, before.net_tx),
+                            block_read: rate(io.block_read, before.block_read),
+                            block_write: rate(io.block_write, before.block_write),
+                        },
+                    );
+                    let interface_rates = container
+                        .interfaces
+                        .iter()
+                        .map(|after| {
+                            let before = interfaces.iter().find(|i| i.name == after.name);
+                            InterfaceRate {
+                                name: after.name.clone(),
+                                rx: rate(after.rx, before.map_or(after.rx, |b| b.rx)),
+                                tx: rate(after.tx, before.map_or(after.tx, |b| b.tx)),
+                                rx_total: after.rx,
+                                tx_total: after.tx,
+                            }
+                        })
+                        .collect();
+                    self.interfaces.insert(container.id.clone(), interface_rates);
+                }
+            }
+            last.insert(container.id.clone(), (now, container.io, container.interfaces.clone()));
+        }
+        self.last = last;
+    }
+
+    /// `None` until the container has been seen in two refreshes.
+    pub fn rate(&self, id: &str) -> Option<IoRate> {
+        self.rates.get(id).copied()
+    }
+
+    /// Per-interface rates, empty until the container has been seen twice.
+    pub fn interfaces(&self, id: &str) -> &[InterfaceRate] {
+        self.interfaces.get(id).map_or(&[], Vec::as_slice)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::mock::container;
+    use std::time::Duration;
+
+    fn counting(net_rx: u64, net_tx: u64, eth0: u64) -> ContainerStats {
+        let mut c = container("web");
+        c.io = IoCounters {
+            net_rx,
+            net_tx,
+            block_read: 0,
+            block_write: 0,
+        };
+        c.interfaces = vec![InterfaceCounters {
+            name: "eth0".to_string(),
+            rx: eth0,
+            tx: eth0,
+        }];
+        c
+    }
+
+    #[test]
+    fn rates_come_from_consecutive_counters() {
+        let mut meter = IoMeter::default();
+        meter.update(&[counting(1000, 1000, 500)]);
+        assert!(meter.rate("web-id").is_none());
+        assert!(meter.interfaces("web-id").is_empty());
+
+        std::thread::sleep(Duration::from_millis(20));
+        meter.update(&[counting(1000 + 4000, 1000 + 1000, 700)]);
+        let rate = meter.rate("web-id").unwrap();
+        assert!(rate.net_rx > 0.0 && (rate.net_rx / rate.net_tx - 4.0).abs() < 1e-9, "{:?}", rate);
+        assert_eq!(rate.block_read, 0.0);
+        let eth0 = &meter.interfaces("web-id")[0];
+        assert_eq!((eth0.name.as_str(), eth0.rx_total), ("eth0", 700));
+        assert!((eth0.rx / rate.net_tx - 0.2).abs() < 1e-9);
+
+        // A restarted container starts its counters over.
+        std::thread::sleep(Duration::from_millis(20));
+        meter.update(&[counting(10, 10, 10)]);
+        let rate = meter.rate("web-id").unwrap();
+        assert_eq!((rate.net_rx, rate.net_tx, meter.interfaces("web-id")[0].rx), (0.0, 0.0, 0.0));
+
+        meter.update(&[]);
+        assert!(meter.rate("web-id").is_none());
+    }
+}
@@ -0,0 +1,147 @@
+//! Crash-safe journal of the session's history: the disk trend, the watched
+//! container's charts, the auto-action timers and the usage samples of
+//! `dockertop report`, written periodically and restored on the next start.
+
+use crate::backend::DiskUsage;
+use crate::config::JournalConfig;
+use crate::report::UsageSample;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+/// Journals older than this are ignored; the longest history kept, the disk
+/// trend, covers a day.
+const MAX_AGE: Duration = Duration::from_secs(24 * 3600);
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Unix seconds.
+    pub saved_at: i64,
+    /// Unix seconds and the usage then.
+    #[serde(default)]
+    pub disk: Vec<(i64, DiskUsage)>,
+    #[serde(default)]
+    pub watch: Option<WatchHistory>,
+    #[serde(default)]
+    pub auto_actions: AutoActionTimers,
+    #[serde(default)]
+    pub usage: Vec<UsageSample>,
+}
+
+/// The charts of `dockertop watch`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct WatchHistory {
+    pub name: String,
+    pub recreations: usize,
+    /// `(sample number, percent)` pairs, oldest first.
+    pub cpu: Vec<(f64, f64)>,
+    pub memory: Vec<(f64, f64)>,
+}
+
+/// Rule index, container ID and Unix seconds, so cool-downs and `for_minutes`
+/// windows carry over instead of starting again.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AutoActionTimers {
+    pub last_fired: Vec<(usize, String, i64)>,
+    pub over_since: Vec<(usize, String, i64)>,
+}
+
+pub struct Journal {
+    path: PathBuf,
+    interval: Duration,
+    last_saved: Instant,
+}
+
+impl Journal {
+    pub fn open(config: &JournalConfig) -> Option<Self> {
+        Some(Self {
+            path: config.path().filter(|_| config.enabled)?,
+            interval: Duration::from_secs(config.interval.max(1)),
+            last_saved: Instant::now(),
+        })
+    }
+
+    /// The last snapshot, unless there is none or it is too old to matter.
+    pub fn load(&self) -> Option<Snapshot> {
+        let text = fs::read_to_string(&self.path).ok()?;
+        let snapshot: Snapshot = serde_json::from_str(&text).ok()?;
+        let age = chrono::Utc::now().timestamp() - snapshot.saved_at;
+        (age < MAX_AGE.as_secs() as i64).then_some(snapshot)
+    }
+
+    pub fn due(&self) -> bool {
+        self.last_saved.elapsed() >= self.interval
+    }
+
+    /// Writes the snapshot to a temporary file first and renames it over the
+    /// journal, so a crash mid-write leaves the previous one intact.
+    pub fn save(&mut self, snapshot: &Snapshot) -> Result<()> {
+        self.last_saved = Instant::now();
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let temporary = self.path.with_extension("json.tmp");
+        fs::write(&temporary, serde_json::to_vec(snapshot)?)
+            .with_context(|| format!("Failed to write {}", temporary.display()))?;
+        fs::rename(&temporary, &self.path).with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+}
+
+/// Unix seconds at an instant of this process.
+pub fn unix_at(instant: Instant) -> i64 {
+    let now = chrono::Utc::now().timestamp();
+    match instant.checked_duration_since(Instant::now()) {
+        Some(ahead) => now + ahead.as_secs() as i64,
+        None => now - instant.elapsed().as_secs() as i64,
+    }
+}
+
+/// The instant matching Unix seconds from an earlier run, if the monotonic
+/// clock reaches back that far.
+pub fn instant_at(unix: i64) -> Option<Instant> {
+    let ago = (chrono::Utc::now().timestamp() - unix).max(0) as u64;
+    Instant::now().checked_sub(Duration::from_secs(ago))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn journals_older_than_a_day_are_not_restored() {
+        let path = std::env::temp_dir().join(format!("dockertop-journal-age-{}.json", std::process::id()));
+        let config = JournalConfig {
+            path: Some(path.clone()),
+            ..Default::default()
+        };
+        let mut journal = Journal::open(&config).unwrap();
+        let now = chrono::Utc::now().timestamp();
+        let cases = [(now - 60, true), (now - MAX_AGE.as_secs() as i64 + 60, true), (now - MAX_AGE.as_secs() as i64, false)];
+        for (saved_at, restored) in cases {
+            journal.save(&Snapshot { saved_at, ..Default::default() }).unwrap();
+            assert_eq!(journal.load().map(|s| s.saved_at), restored.then_some(saved_at));
+        }
+        std::fs::write(&path, "{ half a jour").unwrap();
+        assert!(journal.load().is_none());
+        let _ = std::fs::remove_file(&path);
+
+        let disabled = JournalConfig { enabled: false, ..config };
+        assert!(Journal::open(&disabled).is_none());
+    }
+
+    #[test]
+    fn instants_convert_to_unix_seconds_and_back() {
+        let now = chrono::Utc::now().timestamp();
+        let earlier = Instant::now() - Duration::from_secs(90);
+        assert!((unix_at(earlier) - (now - 90)).abs() <= 1);
+        assert!((unix_at(Instant::now() + Duration::from_secs(30)) - (now + 30)).abs() <= 1);
+
+        let back = instant_at(now - 90).unwrap();
+        assert!(back.elapsed().as_secs().abs_diff(90) <= 1);
+        assert!(instant_at(now + 60).unwrap().elapsed() < Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,202 @@
+//! Rendering of JSON log lines, as written by most structured loggers.
+
+use serde_json::{Map, Value};
+
+/// Keys shown first, without their name, in this order. The first of each
+/// group present in a line is used.
+const TIME_KEYS: &[&str] = &["time", "ts", "timestamp", "@timestamp"];
+const LEVEL_KEYS: &[&str] = &["level", "lvl", "severity"];
+const MESSAGE_KEYS: &[&str] = &["msg", "message"];
+/// Messages are padded to this width so the remaining fields line up.
+const MESSAGE_WIDTH: usize = 40;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum JsonView {
+    /// Lines as the container wrote them.
+    #[default]
+    Raw,
+    /// Time, level and message in columns, then the other fields as `key=value`.
+    Fields,
+    /// Every JSON line expanded over several indented lines.
+    Pretty,
+}
+
+impl JsonView {
+    pub fn next(self) -> Self {
+        match self {
+            JsonView::Raw => JsonView::Fields,
+            JsonView::Fields => JsonView::Pretty,
+            JsonView::Pretty => JsonView::Raw,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            JsonView::Raw => "raw",
+            JsonView::Fields => "fields",
+            JsonView::Pretty => "pretty",
+        }
+    }
+}
+
+/// Keeps JSON lines whose `key` field equals `value`, ignoring case.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldFilter {
+    pub key: String,
+    pub value: String,
+}
+
+impl FieldFilter {
+    /// Parses `key=value`.
+    pub fn parse(text: &str) -> Option<Self> {
+        let (key, value) = text.split_once('=')?;
+        let (key, value) = (key.trim(), value.trim());
+        if key.is_empty() {
+            return None;
+        }
+        Some(Self {
+            key: key.to_string(),
+            value: value.to_string(),
+        })
+    }
+
+    fn matches(&self, fields: &Map<String, Value>) -> bool {
+        fields.get(&self.key).is_some_and(|v| display(v).eq_ignore_ascii_case(&self.value))
+    }
+}
+
+impl std::fmt::Display for FieldFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}={}", self.key, self.value)
+    }
+}
+
+/// The fields of a line that is a JSON object.
+pub fn parse(line: &str) -> Option<Map<String, Value>> {
+    let line = line.trim();
+    if !line.starts_with('{') {
+        return None;
+    }
+    match serde_json::from_str(line) {
+        Ok(Value::Object(fields)) => Some(fields),
+        _ => None,
+    }
+}
+
+/// The lines to show for `line`, or `None` if the filter hides it. Lines that
+/// are not JSON are hidden by any filter and otherwise shown as they are.
+pub fn render(line: &str, view: JsonView, filter: Option<&FieldFilter>) -> Option<Vec<String>> {
+    if view == JsonView::Raw && filter.is_none() {
+        return Some(vec![line.to_string()]);
+    }
+    let Some(fields) = parse(line) else {
+        return filter.is_none().then(|| vec![line.to_string()]);
+    };
+    if filter.is_some_and(|f| !f.matches(&fields)) {
+        return None;
+    }
+    Some(match view {
+        JsonView::Raw => vec![line.to_string()],
+        JsonView::Fields => vec![key_values(fields)],
+        JsonView::Pretty => serde_json::to_string_pretty(&Value::Object(fields))
+            .unwrap_or_default()
+            .lines()
+            .map(str::to_string)
+            .collect(),
+    })
+}
+
+fn key_values(mut fields: Map<String, Value>) -> String {
+    let mut take = |keys: &[&str]| keys.iter().find_map(|k| fields.remove(*k)).map(|v| display(&v));
+    let time = take(TIME_KEYS);
+    let level = take(LEVEL_KEYS);
+    let message = take(MESSAGE_KEYS);
+
+    let mut parts = Vec::new();
+    if let Some(time) = time {
+        parts.push(time);
+    }
+    if let Some(level) = level {
+        parts.push(format!("{:<5}", level.to_uppercase()));
+    }
+    if let Some(message) = message {
+        parts.push(format!("{:<width$}", message, width = MESSAGE_WIDTH));
+    }
+    for (key, value) in &fields {
+        let value = display(value);
+        if value.is_empty() || value.contains(char::is_whitespace) {
+            parts.push(format!("{}={:?}", key, value));
+        } else {
+            parts.push(format!("{}={}", key, value));
+        }
+    }
+    parts.join(" ").trim_end().to_string()
+}
+
+/// Strings without their quotes, anything else as JSON.
+fn display(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINE: &str = r#"{"ts":"12:00:01","level":"warn","msg":"slow query","ms":812,"sql":"select 1","ok":false}"#;
+
+    #[test]
+    fn filters_are_key_equals_value() {
+        let cases = [
+            ("level=error", Some(("level", "error"))),
+            (" user = ann ", Some(("user", "ann"))),
+            ("path=/a=b", Some(("path", "/a=b"))),
+            ("empty=", Some(("empty", ""))),
+            ("=error", None),
+            ("error", None),
+        ];
+        for (text, expected) in cases {
+            let parsed = FieldFilter::parse(text);
+            assert_eq!(parsed.as_ref().map(|f| (f.key.as_str(), f.value.as_str())), expected, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn only_json_objects_have_fields() {
+        assert_eq!(parse(LINE).unwrap().len(), 6);
+        assert_eq!(parse("  {\"a\":1}  ").unwrap()["a"], 1);
+        for line in ["plain text", "[1, 2]", "{not json", "\"quoted\"", ""] {
+            assert!(parse(line).is_none(), "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn lines_render_in_each_view() {
+        assert_eq!(render(LINE, JsonView::Raw, None).unwrap(), [LINE]);
+        assert_eq!(
+            render(LINE, JsonView::Fields, None).unwrap(),
+            [format!("12:00:01 WARN  {:<40} ms=812 ok=false sql=\"select 1\"", "slow query")]
+        );
+        let pretty = render(LINE, JsonView::Pretty, None).unwrap();
+        assert_eq!((pretty.first().unwrap().as_str(), pretty.len()), ("{", 8));
+        assert_eq!(render("plain text", JsonView::Fields, None).unwrap(), ["plain text"]);
+    }
+
+    #[test]
+    fn filters_keep_matching_json_lines_only() {
+        let filter = |text| FieldFilter::parse(text).unwrap();
+        let cases = [
+            ("level=WARN", LINE, true),
+            ("ms=812", LINE, true),
+            ("ok=false", LINE, true),
+            ("level=error", LINE, false),
+            ("user=ann", LINE, false),
+            ("level=warn", "plain warn text", false),
+        ];
+        for (text, line, shown) in cases {
+            assert_eq!(render(line, JsonView::Raw, Some(&filter(text))).is_some(), shown, "{} on {}", text, line);
+        }
+    }
+}
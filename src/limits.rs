@@ -0,0 +1,159 @@
+//! The `u` form for changing a running container's CPU and memory limits in
+//! place, as `docker update` does, e.g. between rounds of a load test.
+
+use crate::backend::{ContainerStats, ResourceLimits, CPU_PERIOD};
+use anyhow::{bail, Context, Result};
+use bollard::models::HostConfig;
+
+/// The form's fields, with a hint on what to type.
+pub const FIELDS: [(&str, &str); 3] = [
+    ("CPU shares", "relative weight, 1024 by default"),
+    ("CPUs", "quota, e.g. 1.5; 0 for none"),
+    ("Memory", "limit, e.g. 512m or 2g"),
+];
+
+pub struct LimitsForm {
+    pub container: ContainerStats,
+    /// One per entry of [`FIELDS`].
+    pub inputs: [String; 3],
+    /// The limits the form opened with; only changed fields are sent.
+    initial: [String; 3],
+    /// Index into [`FIELDS`].
+    pub cursor: usize,
+    /// Why the last submit was refused.
+    pub error: Option<String>,
+}
+
+impl LimitsForm {
+    /// A form filled in with the current limits from the container's inspect.
+    pub fn new(container: ContainerStats, host_config: &HostConfig) -> Self {
+        let shares = host_config.cpu_shares.filter(|&s| s > 0).unwrap_or(1024);
+        let cpus = match (host_config.nano_cpus, host_config.cpu_quota, host_config.cpu_period) {
+            (Some(nano), _, _) if nano > 0 => nano as f64 / 1e9,
+            (_, Some(quota), period) if quota > 0 => quota as f64 / period.filter(|&p| p > 0).unwrap_or(CPU_PERIOD) as f64,
+            _ => 0.0,
+        };
+        let memory = host_config.memory.filter(|&m| m > 0).map(format_memory).unwrap_or_default();
+        let inputs = [shares.to_string(), cpus.to_string(), memory];
+        Self {
+            container,
+            initial: inputs.clone(),
+            inputs,
+            cursor: 0,
+            error: None,
+        }
+    }
+
+    pub fn move_cursor(&mut self, down: bool) {
+        self.cursor = if down {
+            (self.cursor + 1) % FIELDS.len()
+        } else {
+            (self.cursor + FIELDS.len() - 1) % FIELDS.len()
+        };
+    }
+
+    pub fn input(&mut self) -> &mut String {
+        &mut self.inputs[self.cursor]
+    }
+
+    /// The changed fields as limits to send, or why they are invalid.
+    pub fn limits(&self) -> Result<ResourceLimits> {
+        let changed = |i: usize| Some(self.inputs[i].trim()).filter(|input| *input != self.initial[i].trim());
+        let mut limits = ResourceLimits::default();
+        if let Some(input) = changed(0) {
+            let shares: i64 = input.parse().with_context(|| format!("CPU shares: {:?} is not a number", input))?;
+            if !(2..=262_144).contains(&shares) {
+                bail!("CPU shares must be between 2 and 262144");
+            }
+            limits.cpu_shares = Some(shares);
+        }
+        if let Some(input) = changed(1) {
+            let cpus: f64 = input.parse().with_context(|| format!("CPUs: {:?} is not a number", input))?;
+            if !cpus.is_finite() || cpus < 0.0 {
+                bail!("CPUs must be 0 or more");
+            }
+            let quota = (cpus * CPU_PERIOD as f64).round() as i64;
+            // The kernel's minimum quota is 1 ms.
+            if cpus > 0.0 && quota < 1000 {
+                bail!("CPUs must be at least 0.01");
+            }
+            limits.cpu_quota = Some(if quota > 0 { quota } else { -1 });
+        }
+        if let Some(input) = changed(2) {
+            if input.is_empty() {
+                bail!("Memory limits cannot be removed once set");
+            }
+            limits.memory = Some(parse_memory(input)?);
+        }
+        Ok(limits)
+    }
+}
+
+/// A size as `docker run --memory` takes it: bytes, or a number with `b`,
+/// `k`, `m` or `g`.
+pub fn parse_memory(input: &str) -> Result<i64> {
+    let input = input.trim().to_ascii_lowercase();
+    let (number, unit) = match input.find(|c: char| c.is_ascii_alphabetic()) {
+        Some(at) => input.split_at(at),
+        None => (input.as_str(), "b"),
+    };
+    let factor: i64 = match unit.trim_end_matches("ib").trim_end_matches('b') {
+        "" => 1,
+        "k" => 1 << 10,
+        "m" => 1 << 20,
+        "g" => 1 << 30,
+        _ => bail!("Memory: unknown unit {:?}; use b, k, m or g", unit),
+    };
+    let number: f64 = number
+        .trim()
+        .parse()
+        .with_context(|| format!("Memory: {:?} is not a number", number.trim()))?;
+    if !number.is_finite() || number <= 0.0 {
+        bail!("Memory must be more than 0");
+    }
+    Ok((number * factor as f64) as i64)
+}
+
+/// The shortest exact form, e.g. `512m`.
+pub fn format_memory(bytes: i64) -> String {
+    match bytes {
+        b if b % (1 << 30) == 0 => format!("{}g", b >> 30),
+        b if b % (1 << 20) == 0 => format!("{}m", b >> 20),
+        b if b % (1 << 10) == 0 => format!("{}k", b >> 10),
+        b => b.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_sizes_are_read_like_docker_run_reads_them() {
+        let cases = [
+            ("1024", 1024),
+            ("512m", 512 << 20),
+            ("512M", 512 << 20),
+            ("512mb", 512 << 20),
+            ("512MiB", 512 << 20),
+            ("1.5g", 3 << 29),
+            (" 64 k ", 64 << 10),
+            ("100b", 100),
+        ];
+        for (input, bytes) in cases {
+            assert_eq!(parse_memory(input).unwrap(), bytes, "{:?}", input);
+        }
+        for input in ["", "0", "-1m", "12x", "m", "nan"] {
+            assert!(parse_memory(input).is_err(), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn memory_sizes_are_shown_in_the_largest_exact_unit() {
+        let cases = [(1 << 30, "1g"), (1536 << 20, "1536m"), (512 << 10, "512k"), (1000, "1000")];
+        for (bytes, text) in cases {
+            assert_eq!(format_memory(bytes), text);
+            assert_eq!(parse_memory(text).unwrap(), bytes);
+        }
+    }
+}
@@ -0,0 +1,137 @@
+//! Log throughput per container, measured by following every running
+//! container's log stream while the column is enabled.
+
+use crate::backend::{ContainerBackend, ContainerStats};
+use futures::StreamExt;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+use tokio::task::JoinHandle;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Counter {
+    lines: u64,
+    bytes: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LogRate {
+    pub lines_per_sec: f64,
+    pub bytes_per_sec: f64,
+}
+
+pub struct LogMeter {
+    counters: Arc<Mutex<HashMap<String, Counter>>>,
+    followers: HashMap<String, JoinHandle<()>>,
+    rates: HashMap<String, LogRate>,
+    last_sync: Instant,
+}
+
+impl Default for LogMeter {
+    fn default() -> Self {
+        Self {
+            counters: Arc::default(),
+            followers: HashMap::new(),
+            rates: HashMap::new(),
+            last_sync: Instant::now(),
+        }
+    }
+}
+
+impl LogMeter {
+    /// Drops stopped containers, turns the counts since the previous call
+    /// into rates and starts following newly started containers.
+    pub fn sync(&mut self, backend: &Arc<dyn ContainerBackend>, containers: &[ContainerStats]) {
+        self.followers.retain(|id, follower| {
+            let keep = !follower.is_finished() && containers.iter().any(|c| &c.id == id && c.is_running());
+            if !keep {
+                follower.abort();
+            }
+            keep
+        });
+        let elapsed = self.last_sync.elapsed().as_secs_f64();
+        self.last_sync = Instant::now();
+        let counts = std::mem::take(&mut *self.counters.lock().unwrap());
+        if elapsed > 0.0 {
+            self.rates = self
+                .followers
+                .keys()
+                .map(|id| {
+                    let count = counts.get(id).copied().unwrap_or_default();
+                    let rate = LogRate {
+                        lines_per_sec: count.lines as f64 / elapsed,
+                        bytes_per_sec: count.bytes as f64 / elapsed,
+                    };
+                    (id.clone(), rate)
+                })
+                .collect();
+        }
+
+        for container in containers.iter().filter(|c| c.is_running()) {
+            if self.followers.contains_key(&container.id) {
+                continue;
+            }
+            let mut logs = backend.follow_logs(&container.id, 0, false);
+            let counters = self.counters.clone();
+            let id = container.id.clone();
+            let follower = tokio::spawn(async move {
+                while let Some(Ok(chunk)) = logs.next().await {
+                    let mut counters = counters.lock().unwrap();
+                    let counter = counters.entry(id.clone()).or_default();
+                    counter.lines += chunk.text.lines().count().max(1) as u64;
+                    counter.bytes += chunk.text.len() as u64;
+                }
+            });
+            self.followers.insert(container.id.clone(), follower);
+        }
+    }
+
+    /// `None` until the container has been followed for a full tick.
+    pub fn rate(&self, id: &str) -> Option<LogRate> {
+        self.rates.get(id).copied()
+    }
+}
+
+impl Drop for LogMeter {
+    fn drop(&mut self) {
+        for follower in self.followers.values() {
+            follower.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::mock::{container, MockBackend};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn rates_count_lines_and_bytes_since_the_last_sync() {
+        let mock = MockBackend::new([]);
+        *mock.followed.lock().unwrap() = vec!["one\ntwo\n".to_string(), "partial".to_string()];
+        *mock.follow_forever.lock().unwrap() = true;
+        let backend: Arc<dyn ContainerBackend> = Arc::new(mock);
+        let mut stopped = container("db");
+        stopped.status = "Exited (0) 1 minute ago".to_string();
+        let containers = [container("web"), stopped];
+
+        let mut meter = LogMeter::default();
+        meter.sync(&backend, &containers);
+        assert!(meter.rate("web-id").is_none());
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        meter.sync(&backend, &containers);
+
+        let rate = meter.rate("web-id").unwrap();
+        assert!((rate.bytes_per_sec / rate.lines_per_sec - 15.0 / 3.0).abs() < 1e-9, "{:?}", rate);
+        assert!(rate.lines_per_sec <= 3.0 / 0.05);
+        assert!(meter.rate("db-id").is_none());
+
+        meter.sync(&backend, &containers);
+        assert_eq!(meter.rate("web-id").map(|r| r.lines_per_sec), Some(0.0));
+        meter.sync(&backend, &[]);
+        assert!(meter.rate("web-id").is_none());
+    }
+}
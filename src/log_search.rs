@@ -0,0 +1,156 @@
+//! Searching the recent logs of every listed container at once.
+
+use crate::backend::{ContainerBackend, ContainerStats};
+use regex::Regex;
+use std::sync::Arc;
+use tokio::sync::oneshot;
+
+/// Lines fetched per container.
+const SCAN_LINES: usize = 1000;
+/// Lines shown above and below a match in the context view.
+pub const CONTEXT_LINES: usize = 10;
+
+/// The scanned logs of one container and which of their lines match.
+pub struct ContainerMatches {
+    pub container: String,
+    pub lines: Vec<String>,
+    /// Indices into `lines`.
+    pub matches: Vec<usize>,
+    pub error: Option<String>,
+}
+
+pub struct LogSearch {
+    pub pattern: Regex,
+    /// Containers with matches or errors, in list order; empty while scanning.
+    pub results: Vec<ContainerMatches>,
+    pub scanning: bool,
+    /// Selected match, counted across all containers.
+    pub cursor: usize,
+    /// Whether the lines around the selected match are shown instead of the list.
+    pub context: bool,
+    pending: Option<oneshot::Receiver<Vec<ContainerMatches>>>,
+}
+
+impl LogSearch {
+    /// Starts fetching and scanning the logs of `containers` concurrently.
+    pub fn start(backend: Arc<dyn ContainerBackend>, containers: &[ContainerStats], pattern: Regex) -> Self {
+        let (tx, rx) = oneshot::channel();
+        let targets: Vec<(String, String)> = containers.iter().map(|c| (c.id.clone(), c.name.clone())).collect();
+        let regex = pattern.clone();
+        tokio::spawn(async move {
+            let scans = futures::future::join_all(targets.into_iter().map(|(id, container)| {
+                let (backend, regex) = (backend.clone(), regex.clone());
+                async move {
+                    match backend.recent_logs(&id, SCAN_LINES, 0, false).await {
+                        Ok(lines) => {
+                            let lines: Vec<String> = lines.into_iter().map(|line| line.text).collect();
+                            let matches = (0..lines.len()).filter(|&i| regex.is_match(&lines[i])).collect();
+                            ContainerMatches {
+                                container,
+                                lines,
+                                matches,
+                                error: None,
+                            }
+                        }
+                        Err(e) => ContainerMatches {
+                            container,
+                            lines: Vec::new(),
+                            matches: Vec::new(),
+                            error: Some(format!("{:#}", e)),
+                        },
+                    }
+                }
+            }))
+            .await;
+            let _ = tx.send(scans.into_iter().filter(|s| !s.matches.is_empty() || s.error.is_some()).collect());
+        });
+        Self {
+            pattern,
+            results: Vec::new(),
+            scanning: true,
+            cursor: 0,
+            context: false,
+            pending: Some(rx),
+        }
+    }
+
+    /// Picks up the scan results once they are in.
+    pub fn poll(&mut self) {
+        let Some(rx) = &mut self.pending else {
+            return;
+        };
+        match rx.try_recv() {
+            Ok(results) => self.results = results,
+            Err(oneshot::error::TryRecvError::Empty) => return,
+            Err(oneshot::error::TryRecvError::Closed) => {}
+        }
+        self.pending = None;
+        self.scanning = false;
+    }
+
+    pub fn total(&self) -> usize {
+        self.results.iter().map(|r| r.matches.len()).sum()
+    }
+
+    /// The container and line index of the selected match.
+    pub fn selected(&self) -> Option<(&ContainerMatches, usize)> {
+        let mut cursor = self.cursor;
+        for result in &self.results {
+            if cursor < result.matches.len() {
+                return Some((result, result.matches[cursor]));
+            }
+            cursor -= result.matches.len();
+        }
+        None
+    }
+
+    pub fn move_cursor(&mut self, down: bool) {
+        self.cursor = if down {
+            (self.cursor + 1).min(self.total().saturating_sub(1))
+        } else {
+            self.cursor.saturating_sub(1)
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::mock::{container, MockBackend};
+
+    #[tokio::test]
+    async fn matches_are_counted_and_selected_across_containers() {
+        let backend = MockBackend::new([]);
+        *backend.logs.lock().unwrap() = ["ok", "ERROR disk full", "ok", "error: retrying"].map(String::from).to_vec();
+        let pattern = Regex::new("(?i)error").unwrap();
+        let mut search = LogSearch::start(Arc::new(backend), &[container("web"), container("db")], pattern);
+        while search.scanning {
+            tokio::task::yield_now().await;
+            search.poll();
+        }
+
+        assert_eq!(search.total(), 4);
+        let selected = |search: &LogSearch| search.selected().map(|(r, line)| (r.container.clone(), line));
+        assert_eq!(selected(&search), Some(("web".to_string(), 1)));
+        let steps = [(true, "web", 3), (true, "db", 1), (true, "db", 3), (true, "db", 3), (false, "db", 1)];
+        for (down, container, line) in steps {
+            search.move_cursor(down);
+            assert_eq!(selected(&search), Some((container.to_string(), line)));
+        }
+    }
+
+    #[tokio::test]
+    async fn containers_without_matches_are_left_out() {
+        let backend = MockBackend::new([]);
+        *backend.logs.lock().unwrap() = vec!["all good".to_string()];
+        let mut search = LogSearch::start(Arc::new(backend), &[container("web")], Regex::new("error").unwrap());
+        while search.scanning {
+            tokio::task::yield_now().await;
+            search.poll();
+        }
+        assert!(search.results.is_empty());
+        assert_eq!((search.total(), search.selected().is_none()), (0, true));
+        search.move_cursor(true);
+        assert_eq!(search.cursor, 0);
+    }
+}
@@ -0,0 +1,336 @@
+//! The `l` pane: the last lines of one container's logs, to scroll through
+//! without opening a session. In follow mode new lines are appended as the
+//! container writes them. A search highlights its matches and can hide the
+//! other lines, which are kept to come back when the filter is cleared.
+//! Opened on several containers, their lines are merged by when they were
+//! written, as `docker compose logs` does.
+
+use crate::backend::{ContainerBackend, ContainerStats, LogLine};
+use anyhow::Context;
+use futures::{future::join_all, stream::select_all, StreamExt};
+use regex::Regex;
+use std::sync::Arc;
+use tokio::sync::{
+    mpsc::{error::TryRecvError, unbounded_channel, UnboundedReceiver},
+    oneshot,
+};
+use tokio::task::JoinHandle;
+
+/// Lines fetched from the end of the logs unless `--tail` says otherwise.
+pub const TAIL: usize = 500;
+/// Lines kept while following; the oldest are dropped beyond this.
+const MAX_LINES: usize = 10_000;
+
+/// A line and the index in `LogView::containers` of its container.
+type SourcedLine = (usize, LogLine);
+
+struct Follower {
+    task: JoinHandle<()>,
+    lines: UnboundedReceiver<SourcedLine>,
+}
+
+pub struct LogView {
+    /// One container, or those whose logs are merged.
+    pub containers: Vec<ContainerStats>,
+    pub lines: Vec<LogLine>,
+    /// Index in `containers` of the container each line is from.
+    pub sources: Vec<usize>,
+    /// Lines fetched from the end of the logs.
+    pub tail: usize,
+    /// Only lines written in the last this many seconds are fetched; 0 for
+    /// any age.
+    pub age: i64,
+    /// Lines start with Docker's timestamp of when they were written.
+    pub timestamps: bool,
+    pub loading: bool,
+    pub error: Option<String>,
+    /// Shown lines scrolled up from the end; 0 shows the newest lines.
+    pub scroll: usize,
+    /// Set with `/`.
+    pub search: Option<Regex>,
+    /// Only lines matching the search are shown.
+    pub filtering: bool,
+    /// Index in `lines` of the match jumped to last.
+    pub current: Option<usize>,
+    pending: Option<oneshot::Receiver<anyhow::Result<Vec<SourcedLine>>>>,
+    follower: Option<Follower>,
+}
+
+impl LogView {
+    /// Starts fetching the containers' logs, `tail` lines of each.
+    pub fn open(backend: Arc<dyn ContainerBackend>, containers: Vec<ContainerStats>, tail: usize, age: i64) -> Self {
+        let mut view = Self {
+            containers,
+            lines: Vec::new(),
+            sources: Vec::new(),
+            tail,
+            age,
+            timestamps: false,
+            loading: false,
+            error: None,
+            scroll: 0,
+            search: None,
+            filtering: false,
+            current: None,
+            pending: None,
+            follower: None,
+        };
+        view.reload(backend);
+        view
+    }
+
+    /// Fetches the logs again; the lines stay in place until they are in.
+    pub fn reload(&mut self, backend: Arc<dyn ContainerBackend>) {
+        let (tx, rx) = oneshot::channel();
+        let containers = self.containers.clone();
+        let (tail, timestamps) = (self.tail, self.timestamps);
+        let since = match self.age {
+            0 => 0,
+            age => chrono::Utc::now().timestamp() - age,
+        };
+        tokio::spawn(async move {
+            let _ = tx.send(fetch(&*backend, &containers, tail, since, timestamps).await);
+        });
+        self.loading = true;
+        self.pending = Some(rx);
+    }
+
+    pub fn merged(&self) -> bool {
+        self.containers.len() > 1
+    }
+
+    pub fn following(&self) -> bool {
+        self.follower.is_some()
+    }
+
+    /// Starts or stops appending new lines as they are written, in the order
+    /// they come in. Following ends when every followed container stops.
+    pub fn toggle_follow(&mut self, backend: Arc<dyn ContainerBackend>) {
+        match self.follower.take() {
+            Some(follower) => follower.task.abort(),
+            None => self.follow(backend),
+        }
+    }
+
+    fn follow(&mut self, backend: Arc<dyn ContainerBackend>) {
+        let (tx, rx) = unbounded_channel();
+        let streams = self.containers.iter().enumerate().map(|(source, container)| {
+            backend
+                .follow_logs(&container.id, 0, self.timestamps)
+                .map(move |chunk| (source, chunk))
+                .boxed()
+        });
+        let mut stream = select_all(streams);
+        let task = tokio::spawn(async move {
+            while let Some((source, Ok(chunk))) = stream.next().await {
+                for text in chunk.text.lines() {
+                    let line = LogLine {
+                        text: text.to_string(),
+                        stderr: chunk.stderr,
+                    };
+                    if tx.send((source, line)).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        self.follower = Some(Follower { task, lines: rx });
+    }
+
+    /// Shows or hides the timestamps, fetching the lines again with or
+    /// without them, and following on with the same setting.
+    pub fn toggle_timestamps(&mut self, backend: Arc<dyn ContainerBackend>) {
+        self.timestamps = !self.timestamps;
+        self.reload(backend.clone());
+        if let Some(follower) = self.follower.take() {
+            follower.task.abort();
+            self.follow(backend);
+        }
+    }
+
+    /// Picks up fetched and followed lines.
+    pub fn poll(&mut self) {
+        self.poll_follower();
+        let Some(rx) = &mut self.pending else {
+            return;
+        };
+        match rx.try_recv() {
+            Ok(Ok(lines)) => {
+                (self.sources, self.lines) = lines.into_iter().unzip();
+                self.error = None;
+                self.current = None;
+            }
+            Ok(Err(e)) => self.error = Some(format!("{:#}", e)),
+            Err(oneshot::error::TryRecvError::Empty) => return,
+            Err(oneshot::error::TryRecvError::Closed) => {}
+        }
+        self.pending = None;
+        self.loading = false;
+        self.scroll = self.scroll.min(self.lines.len());
+    }
+
+    /// Appends followed lines. Scrolled up, the view stays on the same lines
+    /// so reading is not interrupted; at the end it keeps showing the newest.
+    fn poll_follower(&mut self) {
+        let Some(follower) = &mut self.follower else {
+            return;
+        };
+        let mut received = Vec::new();
+        let ended = loop {
+            match follower.lines.try_recv() {
+                Ok(line) => received.push(line),
+                Err(TryRecvError::Empty) => break false,
+                Err(TryRecvError::Disconnected) => break true,
+            }
+        };
+        if ended {
+            self.follower = None;
+        }
+        for (source, line) in received {
+            if self.scroll > 0 && (!self.filtering || self.matches(&line.text)) {
+                self.scroll += 1;
+            }
+            self.lines.push(line);
+            self.sources.push(source);
+        }
+        let excess = self.lines.len().saturating_sub(MAX_LINES);
+        self.lines.drain(..excess);
+        self.sources.drain(..excess);
+        self.current = self.current.and_then(|i| i.checked_sub(excess));
+        self.scroll = self.scroll.min(self.lines.len());
+    }
+
+    pub fn matches(&self, line: &str) -> bool {
+        self.search.as_ref().is_some_and(|search| search.is_match(line))
+    }
+
+    /// Indices in `lines` of the lines shown: all, or the matching ones
+    /// while filtering.
+    pub fn shown(&self) -> Vec<usize> {
+        (0..self.lines.len())
+            .filter(|&i| !self.filtering || self.matches(&self.lines[i].text))
+            .collect()
+    }
+
+    pub fn match_count(&self) -> usize {
+        self.lines.iter().filter(|line| self.matches(&line.text)).count()
+    }
+
+    /// Searches for `pattern`, or clears the search and filter for `None`,
+    /// and jumps to the newest match. Returns whether any line matches.
+    pub fn search(&mut self, pattern: Option<Regex>) -> bool {
+        self.search = pattern;
+        self.current = None;
+        if self.search.is_none() {
+            self.filtering = false;
+            self.scroll = 0;
+            return true;
+        }
+        self.scroll = 0;
+        self.jump(true)
+    }
+
+    /// Jumps to the next older match, or the next newer one. Returns false
+    /// if there is none, staying put.
+    pub fn jump(&mut self, older: bool) -> bool {
+        let next = if older {
+            let before = self.current.unwrap_or(self.lines.len());
+            (0..before).rev().find(|&i| self.matches(&self.lines[i].text))
+        } else {
+            let Some(current) = self.current else {
+                return false;
+            };
+            (current + 1..self.lines.len()).find(|&i| self.matches(&self.lines[i].text))
+        };
+        let Some(next) = next else {
+            return false;
+        };
+        self.current = Some(next);
+        self.scroll_to(next);
+        true
+    }
+
+    /// Shows only the matching lines, or all of them again. Returns false if
+    /// there is no search to filter by.
+    pub fn toggle_filter(&mut self) -> bool {
+        if self.search.is_none() {
+            return false;
+        }
+        self.filtering = !self.filtering;
+        match self.current {
+            Some(current) => self.scroll_to(current),
+            None => self.scroll = 0,
+        }
+        true
+    }
+
+    /// Scrolls so the line is the bottom one shown.
+    fn scroll_to(&mut self, index: usize) {
+        let shown = self.shown();
+        if let Some(position) = shown.iter().position(|&i| i == index) {
+            self.scroll = shown.len() - 1 - position;
+        }
+    }
+
+    /// Scrolls towards older lines for positive `lines`.
+    pub fn scroll_by(&mut self, lines: isize) {
+        self.scroll = self.scroll.saturating_add_signed(lines).min(self.shown().len());
+    }
+
+    /// Indices in `lines` of those that fit in `height` rows at the current
+    /// scroll position.
+    pub fn visible_indices(&self, height: usize) -> Vec<usize> {
+        let shown = self.shown();
+        let scroll = self.scroll.min(shown.len().saturating_sub(height));
+        let end = shown.len() - scroll;
+        shown[end.saturating_sub(height)..end].to_vec()
+    }
+
+    /// The lines that fit in `height` rows at the current scroll position.
+    #[cfg(test)]
+    pub fn visible(&self, height: usize) -> Vec<&str> {
+        self.visible_indices(height).into_iter().map(|i| self.lines[i].text.as_str()).collect()
+    }
+}
+
+/// The last `tail` lines of each container's logs, merged by the time they
+/// were written. Docker's timestamps are fetched to merge by and dropped
+/// again unless `timestamps` asks for them.
+async fn fetch(
+    backend: &dyn ContainerBackend,
+    containers: &[ContainerStats],
+    tail: usize,
+    since: i64,
+    timestamps: bool,
+) -> anyhow::Result<Vec<SourcedLine>> {
+    if let [container] = containers {
+        let lines = backend.recent_logs(&container.id, tail, since, timestamps).await?;
+        return Ok(lines.into_iter().map(|line| (0, line)).collect());
+    }
+    let fetches = containers.iter().map(|c| backend.recent_logs(&c.id, tail, since, true));
+    let mut merged = Vec::new();
+    for (source, lines) in join_all(fetches).await.into_iter().enumerate() {
+        let lines = lines.with_context(|| format!("Logs of {}", containers[source].name))?;
+        for mut line in lines {
+            let stamp = line.text.split_once(' ').and_then(|(stamp, _)| {
+                let written = chrono::DateTime::parse_from_rfc3339(stamp).ok()?;
+                Some((written, stamp.len() + 1))
+            });
+            if let (Some((_, end)), false) = (stamp, timestamps) {
+                line.text.drain(..end);
+            }
+            merged.push((stamp.map(|(written, _)| written), source, line));
+        }
+    }
+    // Stable, so lines written at the same time stay in container order.
+    merged.sort_by_key(|(written, _, _)| *written);
+    Ok(merged.into_iter().map(|(_, source, line)| (source, line)).collect())
+}
+
+impl Drop for LogView {
+    fn drop(&mut self) {
+        if let Some(follower) = &self.follower {
+            follower.task.abort();
+        }
+    }
+}
@@ -1,217 +1,154 @@
+mod actions;
+mod app;
+mod auto_actions;
+mod backend;
+mod bandwidth;
+mod budgets;
+mod cli;
+mod commands;
+mod compare;
+mod config;
+mod cores;
+mod cost;
+mod cpu_history;
+mod deploy;
+mod diagnostics;
+mod disk;
+mod error_rate;
+mod export;
+mod files;
+mod graph;
+mod host;
+mod idle;
+mod io_rate;
+mod journal;
+mod json_log;
+mod limits;
+mod log_rate;
+mod log_search;
+mod log_view;
+mod orphans;
+mod pods;
+mod probes;
+mod query;
+mod recap;
+mod recreate;
+mod registries;
+mod report;
+mod self_usage;
+mod sessions;
+mod setup;
+mod stats_diff;
+mod status_line;
+mod templates;
+mod timeline;
+mod trend;
+mod ui;
+mod units;
+mod update;
+mod watch;
+
 use anyhow::{Context, Result};
-use bollard::container::{ListContainersOptions, Stats, StatsOptions};
-use bollard::Docker;
+use app::{App, Effect};
+use backend::{AuditBackend, AuditLog, ContainerBackend, DemoBackend, DockerBackend, RecordingBackend, ReplayBackend};
+use config::Config;
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event},
     execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen, SetTitle},
 };
-use futures::TryStreamExt;
 use std::{
-    io,
+    io::{self, Stdout},
+    sync::Arc,
     time::{Duration, Instant},
 };
-use tui::{
-    backend::{Backend, CrosstermBackend},
-    layout::{Constraint, Direction, Layout},
-    style::{Color, Modifier, Style},
-    text::{Span, Spans},
-    widgets::{Block, Borders, List, ListItem, Paragraph},
-    Frame, Terminal,
-};
-
-struct ContainerStats {
-    #[allow(dead_code)]
-    id: String,
-    name: String,
-    cpu_usage: f64,
-    memory_usage: u64,
-    memory_limit: u64,
-    status: String,
-    created: String,
-}
+use tui::{backend::CrosstermBackend, Terminal};
 
-struct App {
-    containers: Vec<ContainerStats>,
-    selected_index: usize,
-    should_quit: bool,
-}
-
-impl App {
-    fn new() -> Self {
-        Self {
-            containers: Vec::new(),
-            selected_index: 0,
-            should_quit: false,
-        }
+/// Prints one container name per line, for shell completion.
+async fn print_container_names() -> Result<()> {
+    let config = Config::load()?;
+    for container in DockerBackend::connect(&config.api)?.containers().await? {
+        println!("{}", container.name);
     }
+    Ok(())
+}
 
-    async fn update_stats(&mut self, docker: &Docker) -> Result<()> {
-        let options = ListContainersOptions::<String> {
-            all: true,
-            ..Default::default()
-        };
-
-        let containers = docker
-            .list_containers(Some(options))
-            .await
-            .context("Failed to list containers")?;
-
-        self.containers = Vec::with_capacity(containers.len());
-
-        for container in containers {
-            if let Some(id) = container.id {
-                let stats = docker
-                    .stats(&id, None::<StatsOptions>)
-                    .try_next()
-                    .await
-                    .context("Failed to get container stats")?
-                    .unwrap();
+/// Hands the terminal to `command` and restores the UI once it exits.
+fn run_in_terminal(terminal: &mut Terminal<CrosstermBackend<Stdout>>, command: &str) -> Result<()> {
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen, DisableMouseCapture)?;
+    terminal.show_cursor()?;
 
-                let cpu_usage = calculate_cpu_usage(&stats);
-                let memory_usage = stats.memory_stats.usage.unwrap_or(0);
-                let memory_limit = stats.memory_stats.limit.unwrap_or(1);
+    let status = std::process::Command::new("sh").arg("-c").arg(command).status();
 
-                self.containers.push(ContainerStats {
-                    id,
-                    name: container.names.unwrap_or_default()[0].trim_start_matches('/').to_string(),
-                    cpu_usage,
-                    memory_usage,
-                    memory_limit,
-                    status: container.status.unwrap_or_default(),
-                    created: container.created.map(|t| t.to_string()).unwrap_or_default(),
-                });
-            }
-        }
+    enable_raw_mode()?;
+    execute!(terminal.backend_mut(), EnterAlternateScreen, EnableMouseCapture)?;
+    terminal.clear()?;
 
-        Ok(())
+    let status = status.with_context(|| format!("Failed to run {}", command))?;
+    if !status.success() {
+        anyhow::bail!("{} exited with {}", command, status);
     }
+    Ok(())
 }
 
-fn calculate_cpu_usage(stats: &Stats) -> f64 {
-    let cpu_delta = stats.cpu_stats.cpu_usage.total_usage - stats.precpu_stats.cpu_usage.total_usage;
-    let system_delta = stats.cpu_stats.system_cpu_usage.unwrap_or(0) - stats.precpu_stats.system_cpu_usage.unwrap_or(0);
-    
-    if system_delta > 0 {
-        (cpu_delta as f64 / system_delta as f64) * 100.0
-    } else {
-        0.0
+#[tokio::main]
+async fn main() -> Result<()> {
+    if std::env::args().nth(1).as_deref() == Some(cli::LIST_CONTAINERS_ARG) {
+        return print_container_names().await;
     }
-}
-
-fn format_bytes(bytes: u64) -> String {
-    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
-    let mut size = bytes as f64;
-    let mut unit_index = 0;
-
-    while size >= 1024.0 && unit_index < UNITS.len() - 1 {
-        size /= 1024.0;
-        unit_index += 1;
+    let cli = <cli::Cli as clap::Parser>::parse();
+    match cli.command {
+        Some(cli::CliCommand::Completions { shell }) => return Ok(cli::print_completions(shell)?),
+        Some(cli::CliCommand::SelfUpdate) => return update::self_update().await,
+        Some(cli::CliCommand::Report { since, top, metric }) => return report::print(&Config::load()?, since, top, metric),
+        Some(cli::CliCommand::Watch { .. }) | None => {}
     }
-
-    format!("{:.2} {}", size, UNITS[unit_index])
-}
-
-fn ui<B: Backend>(f: &mut Frame<B>, app: &App) {
-    // Create a vertical layout for the entire screen
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Min(0),  // Main content
-            Constraint::Length(3), // Help bar
-        ].as_ref())
-        .split(f.size());
-
-    // Split the main content area horizontally
-    let main_chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
-        .split(chunks[0]);
-
-    // Container list with enhanced styling
-    let items: Vec<ListItem> = app
-        .containers
-        .iter()
-        .map(|c| {
-            let memory_percent = (c.memory_usage as f64 / c.memory_limit as f64) * 100.0;
-            let status_style = match c.status.as_str() {
-                "running" => Style::default().fg(Color::Green),
-                "exited" => Style::default().fg(Color::Red),
-                _ => Style::default().fg(Color::Yellow),
-            };
-            
-            ListItem::new(Spans::from(vec![
-                Span::styled(
-                    format!("{} [{}] - CPU: {:.1}% | MEM: {:.1}%",
-                        c.name, c.status, c.cpu_usage, memory_percent
-                    ),
-                    status_style
-                ),
-            ]))
-        })
-        .collect();
-
-    let containers = List::new(items)
-        .block(
-            Block::default()
-                .title(" Containers (↑/↓ to navigate) ")
-                .borders(Borders::ALL)
-                .border_type(tui::widgets::BorderType::Rounded)
-        )
-        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
-
-    let mut state = tui::widgets::ListState::default();
-    state.select(Some(app.selected_index));
-    f.render_stateful_widget(containers, main_chunks[0], &mut state);
-
-    // Container details with enhanced styling
-    if let Some(container) = app.containers.get(app.selected_index) {
-        let details = vec![
-            format!("Container: {}", container.name),
-            format!("Status: {}", container.status),
-            format!("CPU Usage: {:.1}%", container.cpu_usage),
-            format!(
-                "Memory Usage: {:.1}% ({})",
-                (container.memory_usage as f64 / container.memory_limit as f64) * 100.0,
-                format_bytes(container.memory_usage)
-            ),
-            format!("Created: {}", container.created),
-        ];
-
-        let details_text = details.join("\n");
-        let details_widget = Paragraph::new(details_text)
-            .block(
-                Block::default()
-                    .title(" Container Details ")
-                    .borders(Borders::ALL)
-                    .border_type(tui::widgets::BorderType::Rounded)
-            );
-
-        f.render_widget(details_widget, main_chunks[1]);
+    if !cli.demo && cli.replay.is_none() && setup::needed() {
+        setup::run()?;
     }
+    let config = Config::load()?;
 
-    // Help bar at the bottom
-    let help_text = vec![
-        Span::styled("q", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
-        Span::raw(": Quit  "),
-        Span::styled("↑/↓", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
-        Span::raw(": Navigate  "),
-        Span::styled("Enter", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
-        Span::raw(": Select Container"),
-    ];
-
-    let help_widget = Paragraph::new(Spans::from(help_text))
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .border_type(tui::widgets::BorderType::Rounded)
-        );
+    // Only actions against a real daemon are audited.
+    let audit_log = match config.audit.path() {
+        Some(path) if config.audit.enabled && !cli.demo && cli.replay.is_none() => {
+            Some(Arc::new(AuditLog::open(&path)?))
+        }
+        _ => None,
+    };
+    // Demo and replayed sessions are not real history either.
+    let journal = if cli.demo || cli.replay.is_some() {
+        None
+    } else {
+        journal::Journal::open(&config.journal)
+    };
 
-    f.render_widget(help_widget, chunks[1]);
-}
+    let mut container_backend: Box<dyn ContainerBackend> = if cli.demo {
+        Box::new(DemoBackend::new())
+    } else if let Some(path) = &cli.replay {
+        Box::new(ReplayBackend::load(path)?)
+    } else {
+        Box::new(DockerBackend::connect(&config.api)?)
+    };
+    if let Some(log) = &audit_log {
+        container_backend = Box::new(AuditBackend::new(container_backend, log.clone()));
+    }
+    if let Some(path) = &cli.record {
+        container_backend = Box::new(RecordingBackend::new(container_backend, path)?);
+    }
+    // Only listed, so neither audited nor recorded.
+    let compare = match cli.compare.clone().or_else(|| config.api.compare_host.clone()) {
+        Some(host) if cli.demo => Some(compare::Compare::new(Arc::new(DemoBackend::new()), host)),
+        Some(host) => {
+            let api = config::ApiConfig {
+                host: Some(host.clone()),
+                ..config.api.clone()
+            };
+            Some(compare::Compare::new(Arc::new(DockerBackend::connect(&api)?), host))
+        }
+        None => None,
+    };
 
-#[tokio::main]
-async fn main() -> Result<()> {
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -220,43 +157,63 @@ async fn main() -> Result<()> {
     let mut terminal = Terminal::new(backend)?;
 
     // Create app and run it
-    let docker = Docker::connect_with_local_defaults()?;
-    let mut app = App::new();
-    let tick_rate = Duration::from_millis(2000);
-    let mut last_tick = Instant::now();
+    let mut app = App::new(config, Arc::from(container_backend));
+    app.filter = cli.filter;
+    app.log_tail = cli.tail as usize;
+    app.log_age = cli.since.unwrap_or(0);
+    app.audit_log = audit_log;
+    app.config_watch = config::ConfigWatch::new();
+    app.compare = compare;
+    if let Some(cli::CliCommand::Watch { name }) = cli.command {
+        app.watch = Some(watch::Watch::new(name));
+        app.screen = app::Screen::Watch;
+    }
+    app.journal = journal;
+    app.restore_journal();
+    // The first refresh is due right after the first frame.
+    let mut last_tick = Instant::now().checked_sub(app.refresh).unwrap_or_else(Instant::now);
 
     loop {
-        terminal.draw(|f| ui(f, &app))?;
+        app.collect();
+        terminal.draw(|f| ui::ui(f, &app))?;
 
-        let timeout = tick_rate
+        let timeout = app
+            .refresh
             .checked_sub(last_tick.elapsed())
             .unwrap_or_else(|| Duration::from_secs(0));
 
         if crossterm::event::poll(timeout)? {
             if let Event::Key(key) = event::read()? {
-                match key.code {
-                    KeyCode::Char('q') => app.should_quit = true,
-                    KeyCode::Up => {
-                        if app.selected_index > 0 {
-                            app.selected_index -= 1;
+                match app.handle_key(key).await {
+                    Some(Effect::RunInTerminal(command)) => {
+                        if let Err(e) = run_in_terminal(&mut terminal, &command) {
+                            app.status_message = Some(format!("{:#}", e));
                         }
                     }
-                    KeyCode::Down => {
-                        if app.selected_index < app.containers.len().saturating_sub(1) {
-                            app.selected_index += 1;
-                        }
+                    Some(Effect::ExportScreen(path)) => {
+                        // Redrawn first so the prompt is gone from the snapshot.
+                        let frame = terminal.draw(|f| ui::ui(f, &app))?;
+                        app.status_message = Some(match export::write(frame.buffer, &path) {
+                            Ok(()) => format!("Screen saved to {}", path.display()),
+                            Err(e) => format!("{:#}", e),
+                        });
                     }
-                    _ => {}
+                    None => {}
                 }
             }
         }
 
-        if last_tick.elapsed() >= tick_rate {
-            app.update_stats(&docker).await?;
+        if last_tick.elapsed() >= app.refresh {
+            app.tick().await;
             last_tick = Instant::now();
         }
+        if let Some(title) = app.status_line.take_title() {
+            execute!(terminal.backend_mut(), SetTitle(title))?;
+        }
 
         if app.should_quit {
+            app.save_journal(true);
+            app.status_line.clear();
             break;
         }
     }
@@ -270,5 +227,11 @@ async fn main() -> Result<()> {
     )?;
     terminal.show_cursor()?;
 
+    if cli.recap {
+        for line in app.recap.lines(app.backend.as_ref(), &app.format).await {
+            println!("{}", line);
+        }
+    }
+
     Ok(())
 }
@@ -0,0 +1,247 @@
+//! Resources nothing uses any more: dangling volumes and images, networks
+//! without containers, and containers that stopped long ago.
+
+use crate::backend::{ContainerBackend, ContainerStats};
+use crate::config::FormatConfig;
+use bollard::models::{ImageSummary, Network, Volume};
+
+/// Networks Docker creates itself and which cannot be removed.
+const BUILTIN_NETWORKS: [&str; 3] = ["bridge", "host", "none"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrphanKind {
+    StoppedContainer,
+    DanglingVolume,
+    UnusedNetwork,
+    DanglingImage,
+}
+
+impl OrphanKind {
+    /// In removal order: containers first, so nothing they hold is still in use.
+    pub const ALL: [OrphanKind; 4] = [
+        OrphanKind::StoppedContainer,
+        OrphanKind::DanglingVolume,
+        OrphanKind::UnusedNetwork,
+        OrphanKind::DanglingImage,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            OrphanKind::StoppedContainer => "Stopped containers",
+            OrphanKind::DanglingVolume => "Dangling volumes",
+            OrphanKind::UnusedNetwork => "Unused networks",
+            OrphanKind::DanglingImage => "Dangling images",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Orphan {
+    pub kind: OrphanKind,
+    /// What the remove call takes: container/image ID, volume or network name.
+    pub id: String,
+    pub name: String,
+    pub detail: String,
+}
+
+/// Collects every orphan. Containers count when they are stopped and were
+/// created more than `stopped_days` before `now` (Unix seconds).
+pub fn find(
+    containers: &[ContainerStats],
+    volumes: &[Volume],
+    networks: &[Network],
+    images: &[ImageSummary],
+    stopped_days: u64,
+    now: i64,
+    format: &FormatConfig,
+) -> Vec<Orphan> {
+    let mut orphans = Vec::new();
+    let cutoff = now - stopped_days as i64 * 86_400;
+
+    for container in containers.iter().filter(|c| !c.is_running()) {
+        let Ok(created) = container.created.parse::<i64>() else {
+            continue;
+        };
+        if created < cutoff {
+            orphans.push(Orphan {
+                kind: OrphanKind::StoppedContainer,
+                id: container.id.clone(),
+                name: container.name.clone(),
+                detail: format!("{}, created {} days ago", container.status, (now - created) / 86_400),
+            });
+        }
+    }
+
+    for volume in volumes {
+        let mounted = containers
+            .iter()
+            .any(|c| c.mounts.iter().any(|m| m.kind == "volume" && m.name == volume.name));
+        if !mounted {
+            orphans.push(Orphan {
+                kind: OrphanKind::DanglingVolume,
+                id: volume.name.clone(),
+                name: volume.name.clone(),
+                detail: volume.driver.clone(),
+            });
+        }
+    }
+
+    for network in networks {
+        let name = network.name.clone().unwrap_or_default();
+        if BUILTIN_NETWORKS.contains(&name.as_str())
+            || containers.iter().any(|c| c.networks.iter().any(|n| n.network == name))
+        {
+            continue;
+        }
+        orphans.push(Orphan {
+            kind: OrphanKind::UnusedNetwork,
+            id: network.id.clone().unwrap_or_else(|| name.clone()),
+            name,
+            detail: network.driver.clone().unwrap_or_default(),
+        });
+    }
+
+    for image in images {
+        let untagged = image.repo_tags.iter().all(|t| t == "<none>:<none>");
+        if untagged && !containers.iter().any(|c| c.uses_image(image)) {
+            let id = image.id.trim_start_matches("sha256:");
+            orphans.push(Orphan {
+                kind: OrphanKind::DanglingImage,
+                id: image.id.clone(),
+                name: id[..id.len().min(12)].to_string(),
+                detail: crate::units::format_bytes(format, image.size.max(0) as u64),
+            });
+        }
+    }
+
+    orphans
+}
+
+/// Removes the given orphans one by one, carrying on past failures. Returns
+/// how many were removed and a message per failure.
+pub async fn remove(backend: &dyn ContainerBackend, orphans: &[Orphan]) -> (usize, Vec<String>) {
+    let mut removed = 0;
+    let mut failures = Vec::new();
+    for orphan in orphans {
+        match remove_one(backend, orphan).await {
+            Ok(()) => removed += 1,
+            Err(e) => failures.push(format!("{}: {:#}", orphan.name, e)),
+        }
+    }
+    (removed, failures)
+}
+
+pub async fn remove_one(backend: &dyn ContainerBackend, orphan: &Orphan) -> anyhow::Result<()> {
+    match orphan.kind {
+        OrphanKind::StoppedContainer => backend.remove_container(&orphan.id).await,
+        OrphanKind::DanglingVolume => backend.remove_volume(&orphan.id).await,
+        OrphanKind::UnusedNetwork => backend.remove_network(&orphan.id).await,
+        OrphanKind::DanglingImage => backend.remove_image(&orphan.id).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::mock::container;
+    use crate::backend::{Mount, NetworkAttachment};
+
+    const NOW: i64 = 100 * 86_400;
+
+    fn stopped(name: &str, days_ago: i64) -> ContainerStats {
+        ContainerStats {
+            status: "Exited (0) 3 days ago".to_string(),
+            created: (NOW - days_ago * 86_400).to_string(),
+            ..container(name)
+        }
+    }
+
+    fn with_volume_and_network(mut container: ContainerStats, volume: &str, network: &str) -> ContainerStats {
+        container.mounts.push(Mount {
+            kind: "volume".to_string(),
+            name: volume.to_string(),
+            source: String::new(),
+            destination: "/data".to_string(),
+            read_write: true,
+        });
+        container.networks.push(NetworkAttachment {
+            network: network.to_string(),
+            ip_address: "172.18.0.2".to_string(),
+            ipv6_address: String::new(),
+        });
+        container
+    }
+
+    fn volume(name: &str) -> Volume {
+        Volume {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn network(name: &str) -> Network {
+        Network {
+            name: Some(name.to_string()),
+            id: Some(format!("{}-net", name)),
+            ..Default::default()
+        }
+    }
+
+    fn image(id: &str, tags: &[&str]) -> ImageSummary {
+        ImageSummary {
+            id: id.to_string(),
+            repo_tags: tags.iter().map(|t| t.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn found(orphans: &[Orphan]) -> Vec<(OrphanKind, &str)> {
+        orphans.iter().map(|o| (o.kind, o.id.as_str())).collect()
+    }
+
+    #[test]
+    fn finds_what_no_container_uses() {
+        let containers = [
+            with_volume_and_network(stopped("db", 2), "dbdata", "backend"),
+            stopped("old", 30),
+            ContainerStats {
+                image_id: "sha256:used".to_string(),
+                ..container("api")
+            },
+        ];
+        let orphans = find(
+            &containers,
+            &[volume("dbdata"), volume("stale")],
+            &[network("backend"), network("bridge"), network("unused")],
+            &[image("sha256:used", &["<none>:<none>"]), image("sha256:dangling", &["<none>:<none>"]), image("sha256:tagged", &["app:1"])],
+            7,
+            NOW,
+            &FormatConfig::DEFAULT,
+        );
+
+        assert_eq!(
+            found(&orphans),
+            [
+                (OrphanKind::StoppedContainer, "old-id"),
+                (OrphanKind::DanglingVolume, "stale"),
+                (OrphanKind::UnusedNetwork, "unused-net"),
+                (OrphanKind::DanglingImage, "sha256:dangling"),
+            ]
+        );
+    }
+
+    #[test]
+    fn resources_of_containers_left_out_count_as_orphans() {
+        // Why the caller must pass every container, not the filtered list:
+        // leaving `db` out makes its volume and network look unused.
+        let db = with_volume_and_network(stopped("db", 2), "dbdata", "backend");
+        let volumes = [volume("dbdata")];
+        let networks = [network("backend")];
+
+        assert!(find(&[db, container("api")], &volumes, &networks, &[], 7, NOW, &FormatConfig::DEFAULT).is_empty());
+        assert_eq!(
+            found(&find(&[container("api")], &volumes, &networks, &[], 7, NOW, &FormatConfig::DEFAULT)),
+            [(OrphanKind::DanglingVolume, "dbdata"), (OrphanKind::UnusedNetwork, "backend-net")]
+        );
+    }
+}
@@ -0,0 +1,57 @@
+//! Pods of kubelet-managed containers, for nodes where Docker is the CRI
+//! (kind, k3s with `--docker`, kubeadm with cri-dockerd). The kubelet labels
+//! every container it creates, including the pod's pause container.
+
+use crate::backend::ContainerStats;
+
+const NAMESPACE_LABEL: &str = "io.kubernetes.pod.namespace";
+const POD_LABEL: &str = "io.kubernetes.pod.name";
+const CONTAINER_LABEL: &str = "io.kubernetes.container.name";
+
+/// Namespace and name of the pod, for containers the kubelet created.
+pub fn pod_of(container: &ContainerStats) -> Option<(&str, &str)> {
+    let namespace = container.labels.get(NAMESPACE_LABEL)?;
+    let pod = container.labels.get(POD_LABEL)?;
+    Some((namespace, pod))
+}
+
+/// The container's name in the pod spec, shorter than the
+/// `k8s_<container>_<pod>_<namespace>_<uid>_<attempt>` Docker name.
+pub fn container_name(container: &ContainerStats) -> Option<&str> {
+    container.labels.get(CONTAINER_LABEL).map(String::as_str)
+}
+
+/// Whether any container belongs to a pod, i.e. grouping by pod is useful.
+pub fn any(containers: &[ContainerStats]) -> bool {
+    containers.iter().any(|c| c.labels.contains_key(POD_LABEL))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::mock::container;
+
+    #[test]
+    fn pods_are_read_from_kubelet_labels() {
+        let mut app = container("k8s_app_web-7d9_default_1234_0");
+        for (key, value) in [(NAMESPACE_LABEL, "default"), (POD_LABEL, "web-7d9"), (CONTAINER_LABEL, "app")] {
+            app.labels.insert(key.to_string(), value.to_string());
+        }
+        let mut pause = container("k8s_POD_web-7d9_default_1234_0");
+        pause.labels.insert(NAMESPACE_LABEL.to_string(), "default".to_string());
+        pause.labels.insert(POD_LABEL.to_string(), "web-7d9".to_string());
+        let mut namespace_only = container("odd");
+        namespace_only.labels.insert(NAMESPACE_LABEL.to_string(), "default".to_string());
+
+        let cases = [
+            (&app, Some(("default", "web-7d9")), Some("app")),
+            (&pause, Some(("default", "web-7d9")), None),
+            (&namespace_only, None, None),
+        ];
+        for (container, pod, name) in cases {
+            assert_eq!((pod_of(container), container_name(container)), (pod, name), "{}", container.name);
+        }
+        assert!(any(&[namespace_only.clone(), pause]));
+        assert!(!any(&[namespace_only]));
+    }
+}
@@ -0,0 +1,191 @@
+//! Readiness probes run by dockertop itself, for containers without a
+//! Docker healthcheck. Results fill the APP column.
+
+use crate::backend::ContainerStats;
+use crate::commands::expand_placeholders;
+use crate::config::Probe;
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+use tokio::{
+    net::TcpStream,
+    sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+};
+
+/// Outcome of the latest probe of a container.
+#[derive(Debug, Clone)]
+pub enum ProbeStatus {
+    Ready(Duration),
+    /// Why the probe failed: an unexpected status, a refused connection, ...
+    Failing(String),
+}
+
+impl ProbeStatus {
+    pub fn label(&self) -> String {
+        match self {
+            ProbeStatus::Ready(latency) => format!("ok {}ms", latency.as_millis()),
+            ProbeStatus::Failing(reason) => reason.clone(),
+        }
+    }
+}
+
+/// A probe with the placeholders filled in for one container.
+enum ProbeCheck {
+    Http { url: String, expect_status: u16 },
+    Tcp { address: String },
+}
+
+impl ProbeCheck {
+    /// `None` if the probe has neither `http` nor `tcp` set.
+    fn new(probe: &Probe, container: &ContainerStats) -> Option<Self> {
+        if let Some(url) = &probe.http {
+            return Some(ProbeCheck::Http {
+                url: expand_placeholders(url, container),
+                expect_status: probe.expect_status,
+            });
+        }
+        let address = expand_placeholders(probe.tcp.as_ref()?, container);
+        Some(ProbeCheck::Tcp { address })
+    }
+}
+
+/// Whether `name` matches `pattern`, where `*` stands for any run of characters.
+pub fn matches(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+async fn run(check: ProbeCheck, client: reqwest::Client, timeout: Duration) -> ProbeStatus {
+    let started = Instant::now();
+    let outcome = match check {
+        ProbeCheck::Http { url, expect_status } => match client.get(&url).timeout(timeout).send().await {
+            Ok(response) if response.status().as_u16() == expect_status => Ok(()),
+            Ok(response) => Err(format!("HTTP {}", response.status().as_u16())),
+            Err(e) if e.is_timeout() => Err("timeout".to_string()),
+            Err(_) => Err("down".to_string()),
+        },
+        ProbeCheck::Tcp { address } => match tokio::time::timeout(timeout, TcpStream::connect(&address)).await {
+            Ok(Ok(_)) => Ok(()),
+            Ok(Err(_)) => Err("down".to_string()),
+            Err(_) => Err("timeout".to_string()),
+        },
+    };
+    match outcome {
+        Ok(()) => ProbeStatus::Ready(started.elapsed()),
+        Err(reason) => ProbeStatus::Failing(reason),
+    }
+}
+
+/// Probes running containers in the background on each probe's interval.
+pub struct ProbeRunner {
+    probes: Vec<Probe>,
+    client: reqwest::Client,
+    results: HashMap<String, ProbeStatus>,
+    last_run: HashMap<String, Instant>,
+    in_flight: HashSet<String>,
+    tx: UnboundedSender<(String, ProbeStatus)>,
+    rx: UnboundedReceiver<(String, ProbeStatus)>,
+}
+
+impl ProbeRunner {
+    pub fn new(probes: Vec<Probe>) -> Self {
+        let (tx, rx) = unbounded_channel();
+        Self {
+            probes,
+            client: reqwest::Client::new(),
+            results: HashMap::new(),
+            last_run: HashMap::new(),
+            in_flight: HashSet::new(),
+            tx,
+            rx,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.probes.is_empty()
+    }
+
+    /// The latest result for the container, if a probe covers it.
+    pub fn status(&self, container_id: &str) -> Option<&ProbeStatus> {
+        self.results.get(container_id)
+    }
+
+    /// Starts the probes that are due. Each container gets the first probe
+    /// whose pattern matches its name.
+    pub fn schedule(&mut self, containers: &[ContainerStats]) {
+        for container in containers.iter().filter(|c| c.is_running()) {
+            let Some(probe) = self.probes.iter().find(|p| matches(&p.containers, &container.name)) else {
+                continue;
+            };
+            let interval = Duration::from_secs(probe.interval.max(1));
+            let due = self.last_run.get(&container.id).is_none_or(|t| t.elapsed() >= interval);
+            if !due || self.in_flight.contains(&container.id) {
+                continue;
+            }
+            let Some(check) = ProbeCheck::new(probe, container) else {
+                continue;
+            };
+            self.last_run.insert(container.id.clone(), Instant::now());
+            self.in_flight.insert(container.id.clone());
+
+            let (id, tx, client) = (container.id.clone(), self.tx.clone(), self.client.clone());
+            tokio::spawn(async move {
+                let status = run(check, client, interval.min(Duration::from_secs(5))).await;
+                let _ = tx.send((id, status));
+            });
+        }
+        // Stopped containers keep no stale "ok".
+        self.results
+            .retain(|id, _| containers.iter().any(|c| c.id == *id && c.is_running()));
+    }
+
+    /// Picks up the results of finished probes.
+    pub fn collect(&mut self) {
+        while let Ok((id, status)) = self.rx.try_recv() {
+            self.in_flight.remove(&id);
+            self.results.insert(id, status);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn container_names_are_matched_against_wildcard_patterns() {
+        let cases = [
+            ("web", "web", true),
+            ("web", "web-1", false),
+            ("web*", "web-1", true),
+            ("web*", "api", false),
+            ("*-db", "shop-db", true),
+            ("*-db", "shop-db-1", false),
+            ("shop-*-1", "shop-web-1", true),
+            ("shop-*-1", "shop-web-2", false),
+            ("*web*", "shop-web-1", true),
+            ("a*b*c", "abc", true),
+            ("a*b*c", "acb", false),
+            ("ab*ba", "aba", false),
+            ("*", "anything", true),
+        ];
+        for (pattern, name, expected) in cases {
+            assert_eq!(matches(pattern, name), expected, "{:?} ~ {:?}", pattern, name);
+        }
+    }
+}
@@ -0,0 +1,455 @@
+//! Filter expressions over the live stats, such as
+//! `cpu > 50 && status == running && label("env") == "prod"`.
+//!
+//! Comparisons are `==`, `!=`, `<`, `<=`, `>`, `>=` and `~` (contains),
+//! combined with `&&`, `||`, `!` and parentheses. Operands are the fields
+//! `name`, `id`, `image`, `status` (running, paused or exited), `cpu` and
+//! `mem` (percentages) and `memory` (bytes), `ip` (any of the container's
+//! IPv4 and IPv6 addresses), `label("key")`, numbers with an optional
+//! `K`/`M`/`G` suffix, quoted strings and bare words. A bare word on its own
+//! matches container names containing it, so a plain `--filter shop` works
+//! as before.
+//!
+//! Addresses compare as addresses, so `ip == fd00::3` matches `fd00:0::3`,
+//! and `~` with a subnet such as `ip ~ 172.20.0.0/16` or `ip ~ fd00::/8`
+//! tests whether the address is in it.
+
+use crate::app::StatusGroup;
+use crate::backend::ContainerStats;
+use anyhow::{bail, Result};
+use std::{cmp::Ordering, fmt, net::IpAddr};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Str(String),
+    Op(&'static str),
+    Open,
+    Close,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Word(word) => f.write_str(word),
+            Token::Str(text) => write!(f, "\"{}\"", text),
+            Token::Op(op) => f.write_str(op),
+            Token::Open => f.write_str("("),
+            Token::Close => f.write_str(")"),
+        }
+    }
+}
+
+const OPERATORS: [&str; 10] = ["&&", "||", "==", "!=", "<=", ">=", "<", ">", "~", "!"];
+
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut rest = source.trim_start();
+    while let Some(c) = rest.chars().next() {
+        if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op)) {
+            tokens.push(Token::Op(op));
+            rest = &rest[op.len()..];
+        } else if c == '(' || c == ')' {
+            tokens.push(if c == '(' { Token::Open } else { Token::Close });
+            rest = &rest[1..];
+        } else if c == '"' {
+            let Some(end) = rest[1..].find('"') else {
+                bail!("Unterminated string: {}", rest);
+            };
+            tokens.push(Token::Str(rest[1..=end].to_string()));
+            rest = &rest[end + 2..];
+        } else {
+            let end = rest
+                .find(|c: char| c.is_whitespace() || "()\"&|=!<>~".contains(c))
+                .unwrap_or(rest.len());
+            if end == 0 {
+                bail!("Unexpected {:?}", c);
+            }
+            tokens.push(Token::Word(rest[..end].to_string()));
+            rest = &rest[end..];
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone)]
+enum Operand {
+    Field(Field),
+    Label(String),
+    Literal(String),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Field {
+    Name,
+    Id,
+    Image,
+    Status,
+    Cpu,
+    Mem,
+    Memory,
+    Ip,
+}
+
+impl Field {
+    fn parse(word: &str) -> Option<Self> {
+        Some(match word {
+            "name" => Field::Name,
+            "id" => Field::Id,
+            "image" => Field::Image,
+            "status" => Field::Status,
+            "cpu" => Field::Cpu,
+            "mem" => Field::Mem,
+            "memory" => Field::Memory,
+            "ip" => Field::Ip,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Operand, &'static str, Operand),
+    /// A bare word: the name contains it.
+    NameContains(String),
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn eat(&mut self, op: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Op(o)) if *o == op);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn or(&mut self) -> Result<Expr> {
+        let mut expr = self.and()?;
+        while self.eat("||") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        let mut expr = self.unary()?;
+        while self.eat("&&") {
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        if self.eat("!") {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        if self.peek() == Some(&Token::Open) {
+            self.position += 1;
+            let expr = self.or()?;
+            if self.next() != Some(Token::Close) {
+                bail!("Missing )");
+            }
+            return Ok(expr);
+        }
+        if let Some(Token::Word(word) | Token::Str(word)) = self.peek() {
+            if word != "label" && !self.comparison_at(self.position + 1) {
+                let word = word.clone();
+                self.position += 1;
+                return Ok(Expr::NameContains(word));
+            }
+        }
+        let left = self.operand()?;
+        match self.next() {
+            Some(Token::Op(op)) if !["&&", "||", "!"].contains(&op) => Ok(Expr::Compare(left, op, self.operand()?)),
+            _ => bail!("Expected ==, !=, <, <=, >, >= or ~ after label(...)"),
+        }
+    }
+
+    fn comparison_at(&self, position: usize) -> bool {
+        matches!(self.tokens.get(position), Some(Token::Op(op)) if !["&&", "||", "!"].contains(op))
+    }
+
+    fn operand(&mut self) -> Result<Operand> {
+        match self.next() {
+            Some(Token::Str(text)) => Ok(Operand::Literal(text)),
+            Some(Token::Word(word)) if word == "label" => {
+                match (self.next(), self.next(), self.next()) {
+                    (Some(Token::Open), Some(Token::Str(key) | Token::Word(key)), Some(Token::Close)) => {
+                        Ok(Operand::Label(key))
+                    }
+                    _ => bail!("Expected label(\"key\")"),
+                }
+            }
+            Some(Token::Word(word)) => Ok(Field::parse(&word).map_or(Operand::Literal(word), Operand::Field)),
+            Some(token) => bail!("Unexpected {}", token),
+            None => bail!("Unexpected end of filter"),
+        }
+    }
+}
+
+/// A parsed filter, keeping its source for display.
+#[derive(Debug, Clone)]
+pub struct Query {
+    source: String,
+    expr: Expr,
+}
+
+impl Query {
+    pub fn parse(source: &str) -> Result<Self> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            position: 0,
+        };
+        let expr = parser.or()?;
+        if let Some(token) = parser.peek() {
+            bail!("Unexpected {}", token);
+        }
+        Ok(Self {
+            source: source.trim().to_string(),
+            expr,
+        })
+    }
+
+    pub fn matches(&self, container: &ContainerStats) -> bool {
+        eval(&self.expr, container)
+    }
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+fn eval(expr: &Expr, container: &ContainerStats) -> bool {
+    match expr {
+        Expr::And(left, right) => eval(left, container) && eval(right, container),
+        Expr::Or(left, right) => eval(left, container) || eval(right, container),
+        Expr::Not(inner) => !eval(inner, container),
+        Expr::NameContains(word) => container.name.contains(word.as_str()),
+        Expr::Compare(left, op, right) => {
+            let (left, right) = (values(left, container), values(right, container));
+            let any = |op: &str| left.iter().any(|l| right.iter().any(|r| compare(l, op, r)));
+            // None of the addresses is equal, rather than any one differing.
+            if *op == "!=" {
+                return !any("==");
+            }
+            any(op)
+        }
+    }
+}
+
+fn compare(left: &str, op: &str, right: &str) -> bool {
+    if op == "~" {
+        return match (left.parse::<IpAddr>(), right.split_once('/')) {
+            (Ok(address), Some((network, prefix))) => in_subnet(address, network, prefix),
+            _ => left.contains(right),
+        };
+    }
+    let ordering = match (number(left), number(right)) {
+        (Some(l), Some(r)) => l.partial_cmp(&r).unwrap_or(Ordering::Equal),
+        _ => match (left.parse::<IpAddr>(), right.parse::<IpAddr>()) {
+            (Ok(l), Ok(r)) => l.cmp(&r),
+            _ => left.cmp(right),
+        },
+    };
+    match op {
+        "==" => ordering == Ordering::Equal,
+        "!=" => ordering != Ordering::Equal,
+        "<" => ordering == Ordering::Less,
+        "<=" => ordering != Ordering::Greater,
+        ">" => ordering == Ordering::Greater,
+        _ => ordering != Ordering::Less,
+    }
+}
+
+/// Whether `address` is in `network/prefix` of the same family.
+fn in_subnet(address: IpAddr, network: &str, prefix: &str) -> bool {
+    let (Ok(network), Ok(prefix)) = (network.parse::<IpAddr>(), prefix.parse::<u32>()) else {
+        return false;
+    };
+    let (address, network, bits) = match (address, network) {
+        (IpAddr::V4(a), IpAddr::V4(n)) => (u32::from(a) as u128, u32::from(n) as u128, 32),
+        (IpAddr::V6(a), IpAddr::V6(n)) => (u128::from(a), u128::from(n), 128),
+        _ => return false,
+    };
+    if prefix > bits {
+        return false;
+    }
+    let shift = bits - prefix;
+    shift == 128 || address >> shift == network >> shift
+}
+
+/// The operand's values: every address for `ip`, one for the rest.
+fn values(operand: &Operand, container: &ContainerStats) -> Vec<String> {
+    match operand {
+        Operand::Field(Field::Ip) => container
+            .networks
+            .iter()
+            .flat_map(|n| n.addresses())
+            .map(String::from)
+            .collect(),
+        _ => vec![value(operand, container)],
+    }
+}
+
+/// The operand as text; a missing label is empty.
+fn value(operand: &Operand, container: &ContainerStats) -> String {
+    match operand {
+        Operand::Literal(text) => text.clone(),
+        Operand::Label(key) => container.labels.get(key).cloned().unwrap_or_default(),
+        Operand::Field(field) => match field {
+            Field::Name => container.name.clone(),
+            Field::Id => container.id.clone(),
+            Field::Image => container.image.clone(),
+            Field::Status => StatusGroup::of(container).label().to_lowercase(),
+            Field::Cpu => container.cpu_usage.to_string(),
+            Field::Mem => container.memory_percent().to_string(),
+            Field::Memory => container.memory_usage.to_string(),
+            Field::Ip => container.networks.iter().flat_map(|n| n.addresses()).collect::<Vec<_>>().join(" "),
+        },
+    }
+}
+
+/// A number, allowing a `K`, `M` or `G` suffix (with an optional `B`) for
+/// powers of 1024.
+fn number(text: &str) -> Option<f64> {
+    let upper = text.to_ascii_uppercase();
+    let digits = upper.strip_suffix('B').unwrap_or(&upper);
+    let (digits, scale) = match digits.chars().last()? {
+        'K' => (&digits[..digits.len() - 1], 1024.0),
+        'M' => (&digits[..digits.len() - 1], 1024.0 * 1024.0),
+        'G' => (&digits[..digits.len() - 1], 1024.0 * 1024.0 * 1024.0),
+        _ => (digits, 1.0),
+    };
+    digits.parse::<f64>().ok().map(|n| n * scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::mock::container;
+    use crate::backend::NetworkAttachment;
+
+    fn matches(query: &str, container: &ContainerStats) -> bool {
+        Query::parse(query).unwrap_or_else(|e| panic!("{}: {:#}", query, e)).matches(container)
+    }
+
+    fn attached(ip_address: &str, ipv6_address: &str) -> ContainerStats {
+        let mut web = container("web");
+        web.networks.push(NetworkAttachment {
+            network: "shop_default".to_string(),
+            ip_address: ip_address.to_string(),
+            ipv6_address: ipv6_address.to_string(),
+        });
+        web
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or_and_not_tightest() {
+        let idle_api = container("api");
+        for (query, expected) in [
+            ("name == api || name == db && cpu > 50", true),
+            ("(name == api || name == db) && cpu > 50", false),
+            ("cpu > 50 && name == db || name == api", true),
+            ("!name == api || cpu < 1", true),
+            ("!(name == api || cpu < 1)", false),
+            ("!!api", true),
+            ("api && !db", true),
+        ] {
+            assert_eq!(matches(query, &idle_api), expected, "{}", query);
+        }
+    }
+
+    #[test]
+    fn quoted_strings_keep_spaces_and_operators() {
+        let mut odd = container("a && b (copy)");
+        odd.labels.insert("team name".to_string(), "ops || dev".to_string());
+        for (query, expected) in [
+            ("name == \"a && b (copy)\"", true),
+            ("\"b (copy)\"", true),
+            ("label(\"team name\") == \"ops || dev\"", true),
+            ("label(\"team name\") ~ \"||\"", true),
+            ("label(missing) == \"\"", true),
+            ("name == \"a\"", false),
+        ] {
+            assert_eq!(matches(query, &odd), expected, "{}", query);
+        }
+    }
+
+    #[test]
+    fn numbers_compare_as_numbers_with_size_suffixes() {
+        let mut db = container("db");
+        db.memory_usage = 300 << 20;
+        db.cpu_usage = 9.5;
+        for (query, expected) in [
+            ("memory > 256M", true),
+            ("memory > 256MB && memory < 1g", true),
+            ("memory >= 300M && memory <= 300m", true),
+            ("cpu > 10", false),
+            ("cpu >= 9.5", true),
+        ] {
+            assert_eq!(matches(query, &db), expected, "{}", query);
+        }
+    }
+
+    #[test]
+    fn subnets_match_addresses_of_their_own_family() {
+        let web = attached("172.20.0.3", "fd00:20::3");
+        for (query, expected) in [
+            ("ip ~ 172.20.0.0/16", true),
+            ("ip ~ 172.20.0.3/32", true),
+            ("ip ~ 172.21.0.0/16", false),
+            ("ip ~ 0.0.0.0/0", true),
+            ("ip ~ fd00::/8", true),
+            ("ip ~ fd00:21::/32", false),
+            ("ip ~ ::/0", true),
+            // Out of range, or not a subnet at all.
+            ("ip ~ 172.20.0.0/33", false),
+            ("ip ~ 172.20.0.0/x", false),
+            ("ip == 172.20.0.3", true),
+            ("ip == fd00:20:0::3", true),
+            ("ip != 172.20.0.3", false),
+            ("ip != 10.0.0.1", true),
+        ] {
+            assert_eq!(matches(query, &web), expected, "{}", query);
+        }
+        assert!(!matches("ip ~ 172.20.0.0/16", &attached("", "fd00:20::3")));
+    }
+
+    #[test]
+    fn malformed_filters_are_rejected() {
+        for query in [
+            "name == \"unterminated",
+            "(api || db",
+            "api)",
+            "cpu >",
+            "cpu > > 5",
+            "label(env == prod",
+            "label == prod",
+            "&& api",
+            "",
+        ] {
+            assert!(Query::parse(query).is_err(), "{}", query);
+        }
+    }
+}
@@ -0,0 +1,123 @@
+//! A recap of the session: how long it ran, the peak CPU and memory of all
+//! containers together, which containers restarted or were OOM-killed and
+//! the alerts raised, shown with `H` and printed on quitting with
+//! `--recap`.
+
+use crate::backend::{ContainerBackend, ContainerStats};
+use crate::config::FormatConfig;
+use crate::units::{format_bytes, format_percent};
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Clone)]
+pub struct Recap {
+    /// Unix seconds.
+    started: i64,
+    /// Summed over running containers, with when it was reached.
+    peak_cpu: Option<(f64, i64)>,
+    peak_memory: Option<(u64, i64)>,
+    /// Names of every container seen, by ID, for looking up OOM kills.
+    seen: HashMap<String, String>,
+    /// By name, so a recreated container keeps its count.
+    restarts: BTreeMap<String, usize>,
+    alerts: Vec<(i64, String)>,
+}
+
+impl Default for Recap {
+    fn default() -> Self {
+        Self {
+            started: chrono::Utc::now().timestamp(),
+            peak_cpu: None,
+            peak_memory: None,
+            seen: HashMap::new(),
+            restarts: BTreeMap::new(),
+            alerts: Vec::new(),
+        }
+    }
+}
+
+impl Recap {
+    /// Takes in a refresh; `restarted` are the IDs of containers that
+    /// restarted since the last one.
+    pub fn sample(&mut self, containers: &[ContainerStats], restarted: &[String]) {
+        let now = chrono::Utc::now().timestamp();
+        let running: Vec<&ContainerStats> = containers.iter().filter(|c| c.is_running()).collect();
+        let cpu = running.iter().fold(0.0, |sum, c| sum + c.cpu_usage);
+        let memory: u64 = running.iter().map(|c| c.memory_usage).sum();
+        if self.peak_cpu.is_none_or(|(peak, _)| cpu > peak) {
+            self.peak_cpu = Some((cpu, now));
+        }
+        if self.peak_memory.is_none_or(|(peak, _)| memory > peak) {
+            self.peak_memory = Some((memory, now));
+        }
+        for container in containers {
+            self.seen.insert(container.id.clone(), container.name.clone());
+            if restarted.contains(&container.id) {
+                *self.restarts.entry(container.name.clone()).or_default() += 1;
+            }
+        }
+    }
+
+    pub fn alert(&mut self, message: &str) {
+        self.alerts.push((chrono::Utc::now().timestamp(), message.to_string()));
+    }
+
+    /// The recap as lines of text. OOM kills come from the daemon's events
+    /// of every container seen; those that cannot be read are left out.
+    pub async fn lines(&self, backend: &dyn ContainerBackend, format: &FormatConfig) -> Vec<String> {
+        let now = chrono::Utc::now().timestamp();
+        let mut ooms: BTreeMap<&str, usize> = BTreeMap::new();
+        for (id, name) in &self.seen {
+            let Ok(events) = backend.container_events(id, self.started).await else {
+                continue;
+            };
+            let count = events.iter().filter(|e| e.action == "oom").count();
+            if count > 0 {
+                *ooms.entry(name.as_str()).or_default() += count;
+            }
+        }
+        let counts = |counts: Vec<(&str, usize)>| {
+            if counts.is_empty() {
+                return "none".to_string();
+            }
+            counts.iter().map(|(name, n)| format!("{} ×{}", name, n)).collect::<Vec<_>>().join(", ")
+        };
+
+        let mut lines = vec![format!(
+            "Session: {} ({} to {})",
+            format_duration(now - self.started),
+            clock(self.started),
+            clock(now)
+        )];
+        if let Some((cpu, at)) = self.peak_cpu {
+            lines.push(format!("Peak CPU: {} at {}", format_percent(format, cpu), clock(at)));
+        }
+        if let Some((memory, at)) = self.peak_memory {
+            lines.push(format!("Peak memory: {} at {}", format_bytes(format, memory), clock(at)));
+        }
+        lines.push(format!("Restarts: {}", counts(self.restarts.iter().map(|(name, n)| (name.as_str(), *n)).collect())));
+        lines.push(format!("OOM kills: {}", counts(ooms.into_iter().collect())));
+        if self.alerts.is_empty() {
+            lines.push("Alerts: none".to_string());
+        } else {
+            lines.push(format!("Alerts ({}):", self.alerts.len()));
+            lines.extend(self.alerts.iter().map(|(at, message)| format!("  {}  {}", clock(*at), message)));
+        }
+        lines
+    }
+}
+
+/// Local time of day, e.g. `14:05`.
+fn clock(unix: i64) -> String {
+    chrono::DateTime::from_timestamp(unix, 0)
+        .map(|t| t.with_timezone(&chrono::Local).format("%H:%M").to_string())
+        .unwrap_or_default()
+}
+
+/// E.g. `2h 5m`, or `40s` under a minute.
+fn format_duration(seconds: i64) -> String {
+    match seconds {
+        s if s < 60 => format!("{}s", s.max(0)),
+        s if s < 3600 => format!("{}m", s / 60),
+        s => format!("{}h {}m", s / 3600, s % 3600 / 60),
+    }
+}
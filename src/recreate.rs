@@ -0,0 +1,191 @@
+//! The `M` form for changing what a container runs with — its image,
+//! environment, published ports and mounts — and recreating it with the
+//! changes. Docker cannot change these in place, and doing it by hand means
+//! copying every flag of the old `docker run` without losing a volume.
+
+use crate::actions::{join_image, parse_image};
+use crate::backend::{host_port, is_unspecified, ContainerStats, RunSpec};
+use crate::templates::parse_port;
+use anyhow::{bail, Context, Result};
+use bollard::models::{ContainerInspectResponse, PortTypeEnum};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    Image,
+    Env,
+    Ports,
+    Mounts,
+}
+
+impl Section {
+    /// The heading and a hint on what to type.
+    pub fn describe(self) -> (&'static str, &'static str) {
+        match self {
+            Section::Image => ("Image", "repository:tag"),
+            Section::Env => ("Environment", "KEY=value"),
+            Section::Ports => ("Ports", "host:container, e.g. 8080:80 or 127.0.0.1:5432:5432/tcp"),
+            Section::Mounts => ("Mounts", "volume or host path:destination, :ro for read-only"),
+        }
+    }
+}
+
+pub struct RecreateForm {
+    pub container: ContainerStats,
+    /// In section order. Every section but the image ends with an empty
+    /// field to add an entry in; an emptied field removes its entry.
+    pub fields: Vec<(Section, String)>,
+    /// The fields the form opened with, to tell whether anything changed.
+    initial: Vec<(Section, String)>,
+    /// Index into `fields`.
+    pub cursor: usize,
+    /// Why the last submit was refused.
+    pub error: Option<String>,
+}
+
+impl RecreateForm {
+    /// A form filled in from the container's inspect and its listed ports
+    /// and mounts.
+    pub fn new(container: ContainerStats, inspect: &ContainerInspectResponse) -> Self {
+        let config = inspect.config.clone().unwrap_or_default();
+        let mut fields = vec![(Section::Image, config.image.unwrap_or_else(|| container.image.clone()))];
+        fields.extend(config.env.unwrap_or_default().into_iter().map(|env| (Section::Env, env)));
+        fields.push((Section::Env, String::new()));
+
+        let mut ports: Vec<String> = Vec::new();
+        for port in container.ports.iter().filter(|p| p.public_port.is_some()) {
+            let public = port.public_port.unwrap_or_default();
+            let mut text = match port.ip.as_deref().filter(|ip| !is_unspecified(ip)) {
+                Some(ip) => host_port(ip, public),
+                None => public.to_string(),
+            };
+            text.push_str(&format!(":{}", port.private_port));
+            if port.typ == Some(PortTypeEnum::UDP) {
+                text.push_str("/udp");
+            }
+            // Published on IPv4 and IPv6 alike, listed twice.
+            if !ports.contains(&text) {
+                ports.push(text);
+            }
+        }
+        fields.extend(ports.into_iter().map(|port| (Section::Ports, port)));
+        fields.push((Section::Ports, String::new()));
+
+        for mount in &container.mounts {
+            let source = match mount.kind.as_str() {
+                "volume" => &mount.name,
+                "bind" => &mount.source,
+                _ => continue,
+            };
+            let read_only = if mount.read_write { "" } else { ":ro" };
+            fields.push((Section::Mounts, format!("{}:{}{}", source, mount.destination, read_only)));
+        }
+        fields.push((Section::Mounts, String::new()));
+
+        Self {
+            container,
+            initial: fields.clone(),
+            fields,
+            cursor: 0,
+            error: None,
+        }
+    }
+
+    pub fn move_cursor(&mut self, down: bool) {
+        let len = self.fields.len();
+        self.cursor = if down { (self.cursor + 1) % len } else { (self.cursor + len - 1) % len };
+    }
+
+    pub fn push(&mut self, c: char) {
+        let (section, input) = &mut self.fields[self.cursor];
+        let was_empty = input.is_empty();
+        input.push(c);
+        // Typing in a section's last field opens another one below it.
+        let section = *section;
+        let last = self.fields.get(self.cursor + 1).is_none_or(|(next, _)| *next != section);
+        if section != Section::Image && was_empty && last {
+            self.fields.insert(self.cursor + 1, (section, String::new()));
+        }
+    }
+
+    pub fn pop(&mut self) {
+        self.fields[self.cursor].1.pop();
+    }
+
+    /// Whether any entry was edited, added or removed.
+    pub fn changed(&self) -> bool {
+        entries(&self.fields) != entries(&self.initial)
+    }
+
+    /// What to recreate the container from, or why the fields are invalid.
+    pub fn spec(&self) -> Result<RunSpec> {
+        let entries = entries(&self.fields);
+        let of = |section| entries.iter().filter(move |(s, _)| *s == section).map(|(_, text)| *text);
+        let image = of(Section::Image).next().context("Image: the image is missing")?;
+        let (repository, tag) = parse_image(image).context("Image")?;
+        let env: Vec<String> = of(Section::Env).map(str::to_string).collect();
+        if let Some(bad) = env.iter().find(|env| env.split_once('=').is_none_or(|(key, _)| key.is_empty())) {
+            bail!("Environment: {:?} is not KEY=value", bad);
+        }
+        let ports = of(Section::Ports)
+            .map(|port| parse_port(port).with_context(|| format!("Ports: {:?}", port)))
+            .collect::<Result<_>>()?;
+        let volumes: Vec<String> = of(Section::Mounts).map(str::to_string).collect();
+        for volume in &volumes {
+            check_mount(volume).with_context(|| format!("Mounts: {:?}", volume))?;
+        }
+        Ok(RunSpec {
+            name: self.container.name.clone(),
+            image: join_image(&repository, &tag),
+            env,
+            ports,
+            volumes,
+            labels: HashMap::new(),
+        })
+    }
+}
+
+/// The non-empty fields, trimmed.
+fn entries(fields: &[(Section, String)]) -> Vec<(Section, &str)> {
+    fields
+        .iter()
+        .map(|(section, text)| (*section, text.trim()))
+        .filter(|(_, text)| !text.is_empty())
+        .collect()
+}
+
+/// A mount as `docker run -v` takes it: `source:destination`, optionally
+/// followed by `:ro` or `:rw`.
+fn check_mount(mount: &str) -> Result<()> {
+    let parts: Vec<&str> = mount.split(':').collect();
+    let destination = match parts[..] {
+        [source, destination] | [source, destination, "ro" | "rw"] if !source.is_empty() => destination,
+        _ => bail!("expected source:destination, optionally with :ro"),
+    };
+    if !destination.starts_with('/') {
+        bail!("the destination must be an absolute path");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mounts_are_checked_like_docker_run_checks_them() {
+        let cases = [
+            ("pgdata:/var/lib/postgresql/data", true),
+            ("/srv/config:/etc/app:ro", true),
+            ("./site:/usr/share/nginx/html:rw", true),
+            ("data:relative/path", false),
+            (":/data", false),
+            ("/data", false),
+            ("a:/b:rx", false),
+            ("a:/b:ro:extra", false),
+        ];
+        for (mount, valid) in cases {
+            assert_eq!(check_mount(mount).is_ok(), valid, "{:?}", mount);
+        }
+    }
+}
@@ -0,0 +1,284 @@
+//! Registry logins stored by the Docker client, for the registries screen
+//! and for the pulls dockertop makes itself. Bollard does not read the
+//! client's config, so the credentials are looked up here the way
+//! `docker pull` does: from `auths` in `config.json`, or from the credential
+//! helper it names.
+//!
+//! Several logins can be stored for one registry, e.g. Docker Hub under both
+//! `https://index.docker.io/v1/` and `docker.io`. Which one pulls use can be
+//! chosen on the registries screen; the backend that pulls keeps the choice
+//! in its [`PullLogins`].
+
+use crate::backend::RegistryLogin;
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bollard::auth::DockerCredentials;
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    env,
+    path::PathBuf,
+    process::Stdio,
+    sync::Mutex,
+};
+use tokio::io::AsyncWriteExt;
+
+/// [`RegistryLogin::store`] of logins kept in `config.json` itself.
+pub const CONFIG_FILE: &str = "config.json";
+/// Where `docker login` stores Docker Hub logins.
+const DOCKER_HUB_SERVER: &str = "https://index.docker.io/v1/";
+
+/// Registry host to the server of the login chosen for it.
+#[derive(Debug, Default)]
+pub struct PullLogins(Mutex<BTreeMap<String, String>>);
+
+impl PullLogins {
+    /// Makes pulls from the login's registry use it.
+    pub fn choose(&self, login: &RegistryLogin) {
+        self.0.lock().unwrap().insert(registry_host(&login.server), login.server.clone());
+    }
+
+    pub fn chosen(&self) -> BTreeMap<String, String> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+/// The parts of `~/.docker/config.json` about logins.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct ClientConfig {
+    auths: BTreeMap<String, AuthEntry>,
+    creds_store: Option<String>,
+    cred_helpers: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct AuthEntry {
+    /// Base64 of `username:password`; empty when a helper keeps the secret.
+    auth: Option<String>,
+    identitytoken: Option<String>,
+}
+
+/// What `docker-credential-<helper> get` prints.
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct HelperCredentials {
+    username: String,
+    secret: String,
+}
+
+/// The host a login's server or an image's registry goes by, with Docker
+/// Hub's aliases as `docker.io`.
+pub fn registry_host(server: &str) -> String {
+    let server = server.trim_start_matches("https://").trim_start_matches("http://");
+    let host = server.split('/').next().unwrap_or_default();
+    match host {
+        "index.docker.io" | "registry-1.docker.io" => "docker.io".to_string(),
+        host => host.to_string(),
+    }
+}
+
+/// The registry an image is pulled from, e.g. `ghcr.io` for
+/// `ghcr.io/org/app:1` and `docker.io` for `redis:7`.
+pub fn image_registry(image: &str) -> String {
+    match image.split_once('/') {
+        Some((first, _)) if first.contains(['.', ':']) || first == "localhost" => registry_host(first),
+        _ => "docker.io".to_string(),
+    }
+}
+
+/// The login pulls from `host` use: the one `chosen` for it, or else the
+/// one under the server `docker login` would have stored it as.
+pub fn pull_login<'a>(
+    host: &str,
+    logins: &'a [RegistryLogin],
+    chosen: &BTreeMap<String, String>,
+) -> Option<&'a RegistryLogin> {
+    let chosen = chosen.get(host);
+    let default = if host == "docker.io" { DOCKER_HUB_SERVER } else { host };
+    logins
+        .iter()
+        .filter(|l| registry_host(&l.server) == host)
+        .min_by_key(|l| (Some(&l.server) != chosen, l.server != default))
+}
+
+/// The logins in the client config, with the usernames of those kept by a
+/// credential helper asked from the helper. Secrets are not read.
+pub async fn stored_logins() -> Result<Vec<RegistryLogin>> {
+    logins(&read_config()?).await
+}
+
+/// Credentials for pulling `image`, if there is a login for its registry.
+pub async fn credentials(image: &str, chosen: &BTreeMap<String, String>) -> Result<Option<DockerCredentials>> {
+    let config = read_config()?;
+    let logins = logins(&config).await?;
+    let Some(login) = pull_login(&image_registry(image), &logins, chosen) else {
+        return Ok(None);
+    };
+    let mut credentials = DockerCredentials {
+        serveraddress: Some(login.server.clone()),
+        ..Default::default()
+    };
+    if login.store == CONFIG_FILE {
+        let entry = &config.auths[&login.server];
+        let (username, password) = decode_auth(entry.auth.as_deref().unwrap_or_default())?;
+        credentials.username = Some(username);
+        credentials.password = Some(password);
+        credentials.identitytoken = entry.identitytoken.clone();
+    } else {
+        let output = helper(&login.store, "get", &login.server).await?;
+        let found: HelperCredentials = serde_json::from_str(&output)
+            .with_context(|| format!("Unexpected output from docker-credential-{}", login.store))?;
+        // Helpers keep identity tokens under this username.
+        if found.username == "<token>" {
+            credentials.identitytoken = Some(found.secret);
+        } else {
+            credentials.username = Some(found.username);
+            credentials.password = Some(found.secret);
+        }
+    }
+    Ok(Some(credentials))
+}
+
+async fn logins(config: &ClientConfig) -> Result<Vec<RegistryLogin>> {
+    let mut listed: HashMap<&str, BTreeMap<String, String>> = HashMap::new();
+    let mut logins = Vec::new();
+    let servers = config.auths.keys().chain(config.cred_helpers.keys().filter(|s| !config.auths.contains_key(*s)));
+    for server in servers {
+        let auth = config.auths.get(server).and_then(|e| e.auth.as_deref()).filter(|a| !a.is_empty());
+        let store = match (config.cred_helpers.get(server), auth, &config.creds_store) {
+            (Some(helper), _, _) => helper.as_str(),
+            (None, Some(auth), _) => {
+                logins.push(RegistryLogin {
+                    server: server.clone(),
+                    username: decode_auth(auth)?.0,
+                    store: CONFIG_FILE.to_string(),
+                });
+                continue;
+            }
+            (None, None, Some(store)) => store.as_str(),
+            (None, None, None) => continue,
+        };
+        if !listed.contains_key(store) {
+            let output = helper(store, "list", "").await?;
+            let list = serde_json::from_str(&output).with_context(|| format!("Unexpected output from docker-credential-{}", store))?;
+            listed.insert(store, list);
+        }
+        if let Some(username) = listed[store].get(server) {
+            logins.push(RegistryLogin {
+                server: server.clone(),
+                username: username.clone(),
+                store: store.to_string(),
+            });
+        }
+    }
+    Ok(logins)
+}
+
+/// `$DOCKER_CONFIG/config.json`, or `~/.docker/config.json`. Without one
+/// there are no logins.
+fn read_config() -> Result<ClientConfig> {
+    let dir = match env::var_os("DOCKER_CONFIG") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env::var_os("HOME").context("HOME is not set")?).join(".docker"),
+    };
+    let path = dir.join("config.json");
+    if !path.exists() {
+        return Ok(ClientConfig::default());
+    }
+    let text = std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&text).with_context(|| format!("Invalid Docker client config {}", path.display()))
+}
+
+fn decode_auth(auth: &str) -> Result<(String, String)> {
+    let decoded = STANDARD.decode(auth).context("Invalid auth in the Docker client config")?;
+    let decoded = String::from_utf8(decoded).context("Invalid auth in the Docker client config")?;
+    let (username, password) = decoded.split_once(':').context("Invalid auth in the Docker client config")?;
+    Ok((username.to_string(), password.to_string()))
+}
+
+/// Runs `docker-credential-<name> <command>` with `input` on its stdin.
+async fn helper(name: &str, command: &str, input: &str) -> Result<String> {
+    let program = format!("docker-credential-{}", name);
+    let mut child = tokio::process::Command::new(&program)
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run {}", program))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input.as_bytes()).await?;
+    }
+    let output = child.wait_with_output().await.with_context(|| format!("Failed to run {}", program))?;
+    if !output.status.success() {
+        bail!("{} {}: {}", program, command, String::from_utf8_lossy(&output.stdout).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn login(server: &str, username: &str) -> RegistryLogin {
+        RegistryLogin {
+            server: server.to_string(),
+            username: username.to_string(),
+            store: "config.json".to_string(),
+        }
+    }
+
+    #[test]
+    fn servers_and_images_map_to_registry_hosts() {
+        let servers = [
+            ("https://index.docker.io/v1/", "docker.io"),
+            ("registry-1.docker.io", "docker.io"),
+            ("ghcr.io", "ghcr.io"),
+            ("http://registry.local:5000/v2/", "registry.local:5000"),
+        ];
+        for (server, host) in servers {
+            assert_eq!(registry_host(server), host, "{:?}", server);
+        }
+        let images = [
+            ("redis:7", "docker.io"),
+            ("library/redis", "docker.io"),
+            ("bitnami/redis:7", "docker.io"),
+            ("ghcr.io/org/app:1", "ghcr.io"),
+            ("registry.local:5000/app", "registry.local:5000"),
+            ("localhost/app", "localhost"),
+            ("index.docker.io/library/redis", "docker.io"),
+        ];
+        for (image, host) in images {
+            assert_eq!(image_registry(image), host, "{:?}", image);
+        }
+    }
+
+    #[test]
+    fn pulls_use_the_chosen_login_or_the_default_server() {
+        let logins = [
+            login("registry-1.docker.io", "mirror"),
+            login("https://index.docker.io/v1/", "ann"),
+            login("quay.io", "bob"),
+            login("https://quay.io", "carol"),
+        ];
+        let chosen = PullLogins::default();
+        let user = |host| pull_login(host, &logins, &chosen.chosen()).map(|l| l.username.as_str());
+        assert_eq!(user("docker.io"), Some("ann"));
+        assert_eq!(user("quay.io"), Some("bob"));
+        assert_eq!(user("ghcr.io"), None);
+
+        chosen.choose(&logins[3]);
+        assert_eq!(user("quay.io"), Some("carol"));
+        assert_eq!(user("docker.io"), Some("ann"));
+    }
+
+    #[test]
+    fn auth_decodes_to_a_username_and_password() {
+        assert_eq!(decode_auth("YW5uOnMzY3I6dA==").unwrap(), ("ann".to_string(), "s3cr:t".to_string()));
+        for auth in ["not base64!", "bm8tY29sb24=", "/w=="] {
+            assert!(decode_auth(auth).is_err(), "{:?}", auth);
+        }
+    }
+}
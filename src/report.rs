@@ -0,0 +1,175 @@
+//! Per-container usage sampled once a minute into the journal, and
+//! `dockertop report` printing the top consumers over a window of it, for
+//! pasting into incident timelines.
+
+use crate::backend::ContainerStats;
+use crate::config::{Config, FormatConfig};
+use crate::io_rate::IoMeter;
+use crate::journal::Journal;
+use crate::units::{format_bits, format_bytes, format_percent, format_rate};
+use anyhow::{bail, Result};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(60);
+/// A day, as long as the journal is kept.
+const MAX_AGE: i64 = 24 * 3600;
+
+/// A running container's usage at one point.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageSample {
+    /// Unix seconds.
+    pub time: i64,
+    pub name: String,
+    /// In the units of the CPU column.
+    pub cpu: f64,
+    /// Bytes.
+    pub memory: u64,
+    /// Received and sent, in bytes per second.
+    pub net: f64,
+    /// Read and written, in bytes per second.
+    pub block: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Metric {
+    Cpu,
+    Memory,
+    Net,
+    Block,
+}
+
+impl Metric {
+    fn label(self) -> &'static str {
+        match self {
+            Metric::Cpu => "CPU",
+            Metric::Memory => "memory",
+            Metric::Net => "network I/O",
+            Metric::Block => "block I/O",
+        }
+    }
+
+    fn of(self, sample: &UsageSample) -> f64 {
+        match self {
+            Metric::Cpu => sample.cpu,
+            Metric::Memory => sample.memory as f64,
+            Metric::Net => sample.net,
+            Metric::Block => sample.block,
+        }
+    }
+
+    fn format(self, format: &FormatConfig, value: f64) -> String {
+        match self {
+            Metric::Cpu => format_percent(format, value),
+            Metric::Memory => format_bytes(format, value as u64),
+            Metric::Net => format_bits(format, value),
+            Metric::Block => format!("{}/s", format_rate(format, value)),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct UsageHistory {
+    last_sample: Option<Instant>,
+    samples: VecDeque<UsageSample>,
+}
+
+impl UsageHistory {
+    /// Samples the running containers, at most once a minute.
+    pub fn sample(&mut self, containers: &[ContainerStats], io: &IoMeter) {
+        if self.last_sample.is_some_and(|t| t.elapsed() < SAMPLE_INTERVAL) {
+            return;
+        }
+        self.last_sample = Some(Instant::now());
+        let time = chrono::Utc::now().timestamp();
+        while self.samples.front().is_some_and(|s| s.time < time - MAX_AGE) {
+            self.samples.pop_front();
+        }
+        for container in containers.iter().filter(|c| c.is_running()) {
+            let rate = io.rate(&container.id).unwrap_or_default();
+            self.samples.push_back(UsageSample {
+                time,
+                name: container.name.clone(),
+                cpu: container.cpu_usage,
+                memory: container.memory_usage,
+                net: rate.net_rx + rate.net_tx,
+                block: rate.block_read + rate.block_write,
+            });
+        }
+    }
+
+    /// The samples, oldest first, for the journal.
+    pub fn history(&self) -> Vec<UsageSample> {
+        self.samples.iter().cloned().collect()
+    }
+
+    /// Takes the samples of an earlier run, ahead of this one's.
+    pub fn restore(&mut self, history: Vec<UsageSample>) {
+        let mut samples: VecDeque<UsageSample> = history.into();
+        samples.extend(self.samples.drain(..));
+        self.samples = samples;
+    }
+}
+
+/// Prints the report from the journal's samples.
+pub fn print(config: &Config, since: i64, top: usize, metric: Metric) -> Result<()> {
+    let Some(journal) = Journal::open(&config.journal) else {
+        bail!("The journal is disabled, so no history is kept; enable it under [journal]");
+    };
+    let Some(snapshot) = journal.load() else {
+        bail!("No history in the last day; it is kept while dockertop runs");
+    };
+    print!("{}", table(&snapshot.usage, chrono::Utc::now().timestamp() - since, top, metric, &config.format));
+    Ok(())
+}
+
+/// The `top` containers by their average `metric` in samples from `from`
+/// (Unix seconds) on, with their peaks.
+pub fn table(samples: &[UsageSample], from: i64, top: usize, metric: Metric, format: &FormatConfig) -> String {
+    let window: Vec<&UsageSample> = samples.iter().filter(|s| s.time >= from).collect();
+    let (Some(first), Some(last)) = (window.first(), window.last()) else {
+        return "No samples in that window\n".to_string();
+    };
+    let time = |unix: i64| {
+        chrono::DateTime::from_timestamp(unix, 0)
+            .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default()
+    };
+    let mut by_name: BTreeMap<&str, Vec<f64>> = BTreeMap::new();
+    for sample in &window {
+        by_name.entry(&sample.name).or_default().push(metric.of(sample));
+    }
+    let mut rows: Vec<(&str, f64, f64, usize)> = by_name
+        .into_iter()
+        .map(|(name, values)| {
+            let average = values.iter().sum::<f64>() / values.len() as f64;
+            let peak = values.iter().copied().fold(0.0, f64::max);
+            (name, average, peak, values.len())
+        })
+        .collect();
+    rows.sort_by(|a, b| b.1.total_cmp(&a.1));
+    rows.truncate(top);
+
+    let cells: Vec<[String; 5]> = rows
+        .iter()
+        .enumerate()
+        .map(|(i, (name, average, peak, count))| {
+            [(i + 1).to_string(), name.to_string(), metric.format(format, *average), metric.format(format, *peak), count.to_string()]
+        })
+        .collect();
+    let header = ["#", "CONTAINER", "AVERAGE", "PEAK", "SAMPLES"].map(String::from);
+    let widths: Vec<usize> = (0..5)
+        .map(|column| cells.iter().chain([&header]).map(|row| row[column].chars().count()).max().unwrap_or(0))
+        .collect();
+    let mut out = format!("Top {} by {}, {} to {}\n", rows.len(), metric.label(), time(first.time), time(last.time));
+    for row in [&header].into_iter().chain(&cells) {
+        let line: Vec<String> = row.iter().zip(&widths).map(|(cell, width)| format!("{:<width$}", cell, width = width)).collect();
+        out.push_str(line.join("  ").trim_end());
+        out.push('\n');
+    }
+    out
+}
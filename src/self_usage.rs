@@ -0,0 +1,110 @@
+//! dockertop's own CPU and memory footprint, read from `/proc/self`, so users
+//! can check the monitor isn't what is loading the box. Where there is no
+//! `/proc`, as on macOS and Windows, sysinfo is asked instead.
+
+use std::{fs, time::Instant};
+use sysinfo::{ProcessRefreshKind, System};
+
+/// Kernel clock ticks per second as exposed to userspace (`USER_HZ`), which
+/// is 100 on all mainstream Linux architectures.
+const USER_HZ: f64 = 100.0;
+
+#[derive(Debug, Clone, Copy)]
+pub struct SelfUsage {
+    pub cpu_percent: f64,
+    pub rss_bytes: u64,
+}
+
+#[derive(Default)]
+pub struct SelfMonitor {
+    last: Option<(Instant, u64)>,
+    /// Only created once `/proc/self` turns out to be missing.
+    system: Option<System>,
+    pub usage: Option<SelfUsage>,
+}
+
+impl SelfMonitor {
+    /// Takes a new sample; CPU usage is averaged since the previous one.
+    pub fn sample(&mut self) {
+        let (Some(ticks), Some(rss_bytes)) = (cpu_ticks(), rss_bytes()) else {
+            self.sample_sysinfo();
+            return;
+        };
+        let now = Instant::now();
+        if let Some((then, last_ticks)) = self.last {
+            let elapsed = now.duration_since(then).as_secs_f64();
+            if elapsed > 0.0 {
+                let cpu_seconds = ticks.saturating_sub(last_ticks) as f64 / USER_HZ;
+                self.usage = Some(SelfUsage {
+                    cpu_percent: cpu_seconds / elapsed * 100.0,
+                    rss_bytes,
+                });
+            }
+        }
+        self.last = Some((now, ticks));
+    }
+
+    /// sysinfo keeps the previous CPU time itself, so the first refresh
+    /// only primes it.
+    fn sample_sysinfo(&mut self) {
+        let Ok(pid) = sysinfo::get_current_pid() else {
+            return;
+        };
+        let primed = self.system.is_some();
+        let system = self.system.get_or_insert_with(System::new);
+        if !system.refresh_process_specifics(pid, ProcessRefreshKind::new().with_cpu().with_memory()) {
+            return;
+        }
+        if let Some(process) = system.process(pid).filter(|_| primed) {
+            self.usage = Some(SelfUsage {
+                cpu_percent: process.cpu_usage() as f64,
+                rss_bytes: process.memory(),
+            });
+        }
+    }
+}
+
+/// User plus system time of this process, in clock ticks.
+fn cpu_ticks() -> Option<u64> {
+    let stat = fs::read_to_string("/proc/self/stat").ok()?;
+    // The command name may contain spaces, so count fields from after it.
+    let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+    let utime: u64 = fields.get(11)?.parse().ok()?;
+    let stime: u64 = fields.get(12)?.parse().ok()?;
+    Some(utime + stime)
+}
+
+fn rss_bytes() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let kb: u64 = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()?;
+    Some(kb * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn the_second_sample_reports_usage() {
+        let mut monitor = SelfMonitor::default();
+        monitor.sample();
+        assert!(monitor.usage.is_none());
+
+        let busy_until = Instant::now() + Duration::from_millis(50);
+        while Instant::now() < busy_until {
+            std::hint::black_box(0);
+        }
+        monitor.sample();
+        let usage = monitor.usage.expect("usage after two samples");
+        assert!(usage.rss_bytes > 0);
+        assert!(usage.cpu_percent >= 0.0);
+    }
+}
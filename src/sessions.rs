@@ -0,0 +1,492 @@
+//! Exec shells, log follows and attaches that keep running in the
+//! background, so several can be open at once and switched between without
+//! restarting them.
+
+use crate::backend::{ContainerBackend, ContainerStats, ExecStreams, DETACH_KEYS};
+use crate::json_log::{self, FieldFilter, JsonView};
+use chrono::{DateTime, FixedOffset};
+use futures::StreamExt;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{
+    io::AsyncWriteExt,
+    sync::mpsc::{unbounded_channel, UnboundedSender},
+    task::JoinHandle,
+};
+
+/// Lines kept per session.
+const SCROLLBACK: usize = 5000;
+/// Present in nearly every image, unlike bash.
+const SHELL: &str = "sh";
+/// Lines of backlog per container in a combined tail.
+const TAIL_LINES: usize = 50;
+/// How long a combined tail collects backlogs before sorting them; after
+/// that, lines are shown as they arrive.
+const BACKLOG_WINDOW: Duration = Duration::from_millis(500);
+/// Separates the container name from the line in a combined tail.
+pub const TAIL_SEPARATOR: &str = " | ";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionKind {
+    Exec,
+    Logs,
+    /// Logs of several containers merged into one stream.
+    Tail,
+    /// The main process's live output, and its stdin if `stdin`.
+    Attach { stdin: bool },
+}
+
+/// Output collected by a session's background task.
+#[derive(Default)]
+struct Scrollback {
+    lines: VecDeque<String>,
+    /// Whether the last line still waits for its newline, e.g. a prompt.
+    open: bool,
+}
+
+impl Scrollback {
+    fn push(&mut self, chunk: &str) {
+        if chunk.is_empty() {
+            return;
+        }
+        for (i, part) in chunk.split('\n').enumerate() {
+            match self.lines.back_mut() {
+                Some(last) if i == 0 && self.open => last.push_str(part),
+                _ => self.lines.push_back(part.to_string()),
+            }
+        }
+        // A trailing newline leaves an empty line behind; it is the open one.
+        if chunk.ends_with('\n') {
+            self.lines.pop_back();
+            self.open = false;
+        } else {
+            self.open = true;
+        }
+        let excess = self.lines.len().saturating_sub(SCROLLBACK);
+        self.lines.drain(..excess);
+    }
+
+    fn push_line(&mut self, line: &str) {
+        if self.open {
+            self.push("\n");
+        }
+        self.push(&format!("{}\n", line));
+    }
+}
+
+pub struct Session {
+    pub kind: SessionKind,
+    /// The container's name, or what the containers of a tail have in common.
+    pub container: String,
+    /// The line being typed into an exec session or an attach with stdin,
+    /// sent on Enter.
+    pub input: String,
+    /// How many lines the view is scrolled up from the bottom.
+    pub scroll: usize,
+    /// How JSON lines of a log session are shown.
+    pub json_view: JsonView,
+    /// Hides log lines whose JSON field does not match.
+    pub filter: Option<FieldFilter>,
+    /// Lines sent so far, recalled with Up/Down.
+    history: Vec<String>,
+    history_cursor: Option<usize>,
+    output: Arc<Mutex<Scrollback>>,
+    stdin: Option<UnboundedSender<String>>,
+    task: JoinHandle<()>,
+}
+
+impl Session {
+    /// Starts a shell in the container.
+    pub fn exec(backend: &Arc<dyn ContainerBackend>, container: &ContainerStats) -> Self {
+        let output: Arc<Mutex<Scrollback>> = Arc::default();
+        let (stdin, mut lines) = unbounded_channel::<String>();
+        let (backend, id, scrollback) = (backend.clone(), container.id.clone(), output.clone());
+        let task = tokio::spawn(async move {
+            let ExecStreams { output: mut stream, mut input } = match backend.exec(&id, &[SHELL]).await {
+                Ok(streams) => streams,
+                Err(e) => {
+                    scrollback.lock().unwrap().push_line(&format!("{:#}", e));
+                    return;
+                }
+            };
+            loop {
+                tokio::select! {
+                    chunk = stream.next() => match chunk {
+                        Some(Ok(chunk)) => scrollback.lock().unwrap().push(&chunk),
+                        Some(Err(e)) => {
+                            scrollback.lock().unwrap().push_line(&format!("{:#}", e));
+                            break;
+                        }
+                        None => break,
+                    },
+                    Some(line) = lines.recv() => {
+                        let sent = input.write_all(format!("{}\n", line).as_bytes()).await;
+                        if sent.is_err() || input.flush().await.is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+            scrollback.lock().unwrap().push_line("── session ended ──");
+        });
+        Self::new(SessionKind::Exec, container.name.clone(), output, Some(stdin), task)
+    }
+
+    /// Follows the container's logs, starting with the last 100 lines.
+    pub fn logs(backend: &Arc<dyn ContainerBackend>, container: &ContainerStats) -> Self {
+        let output: Arc<Mutex<Scrollback>> = Arc::default();
+        let mut stream = backend.follow_logs(&container.id, 100, false);
+        let scrollback = output.clone();
+        let task = tokio::spawn(async move {
+            while let Some(chunk) = stream.next().await {
+                match chunk {
+                    Ok(chunk) => scrollback.lock().unwrap().push(&chunk.text),
+                    Err(e) => {
+                        scrollback.lock().unwrap().push_line(&format!("{:#}", e));
+                        break;
+                    }
+                }
+            }
+            scrollback.lock().unwrap().push_line("── session ended ──");
+        });
+        Self::new(SessionKind::Logs, container.name.clone(), output, None, task)
+    }
+
+    /// Follows the logs of all `containers` in one stream, like `docker
+    /// compose logs -f`: each line is prefixed with its container's name, and
+    /// the backlogs are interleaved by time.
+    pub fn tail(backend: &Arc<dyn ContainerBackend>, label: String, containers: &[ContainerStats]) -> Self {
+        let output: Arc<Mutex<Scrollback>> = Arc::default();
+        let width = containers.iter().map(|c| c.name.len()).max().unwrap_or(0);
+        let mut merged = futures::stream::select_all(containers.iter().enumerate().map(|(i, c)| {
+            let prefix = format!("{:<width$}{}", c.name, TAIL_SEPARATOR, width = width);
+            backend
+                .follow_logs(&c.id, TAIL_LINES, true)
+                .map(move |chunk| (i, prefix.clone(), chunk))
+                .boxed()
+        }));
+        let scrollback = output.clone();
+        let task = tokio::spawn(async move {
+            // Chunks need not end on a line break; the rest waits for the next one.
+            let mut partial: HashMap<usize, String> = HashMap::new();
+            let mut backlog = Some(Vec::new());
+            let window = tokio::time::sleep(BACKLOG_WINDOW);
+            tokio::pin!(window);
+            loop {
+                tokio::select! {
+                    item = merged.next() => {
+                        let Some((i, prefix, chunk)) = item else {
+                            break;
+                        };
+                        let chunk = match chunk {
+                            Ok(chunk) => chunk.text,
+                            Err(e) => format!("{:#}\n", e),
+                        };
+                        let pending = partial.entry(i).or_default();
+                        pending.push_str(&chunk);
+                        let Some(end) = pending.rfind('\n') else {
+                            continue;
+                        };
+                        let complete: String = pending.drain(..=end).collect();
+                        for line in complete.lines() {
+                            let (time, text) = split_timestamp(line);
+                            let line = format!("{}{}", prefix, text);
+                            match &mut backlog {
+                                Some(backlog) => backlog.push((time, line)),
+                                None => scrollback.lock().unwrap().push_line(&line),
+                            }
+                        }
+                    }
+                    _ = &mut window, if backlog.is_some() => {
+                        let mut lines = backlog.take().unwrap_or_default();
+                        // Stable, so lines without a time keep their order.
+                        lines.sort_by_key(|(time, _)| *time);
+                        let mut scrollback = scrollback.lock().unwrap();
+                        for (_, line) in lines {
+                            scrollback.push_line(&line);
+                        }
+                    }
+                }
+            }
+            let mut scrollback = scrollback.lock().unwrap();
+            for (_, line) in backlog.unwrap_or_default() {
+                scrollback.push_line(&line);
+            }
+            scrollback.push_line("── session ended ──");
+        });
+        Self::new(SessionKind::Tail, label, output, None, task)
+    }
+
+    /// Attaches to the container's main process like `docker attach`, with
+    /// typed lines going to its stdin if `stdin`. Ending the session detaches
+    /// and leaves the container running.
+    pub fn attach(backend: &Arc<dyn ContainerBackend>, container: &ContainerStats, stdin: bool) -> Self {
+        let output: Arc<Mutex<Scrollback>> = Arc::default();
+        let (sender, mut lines) = unbounded_channel::<String>();
+        let (backend, id, scrollback) = (backend.clone(), container.id.clone(), output.clone());
+        let task = tokio::spawn(async move {
+            let ExecStreams { output: mut stream, mut input } = match backend.attach(&id, stdin).await {
+                Ok(streams) => streams,
+                Err(e) => {
+                    scrollback.lock().unwrap().push_line(&format!("{:#}", e));
+                    return;
+                }
+            };
+            loop {
+                tokio::select! {
+                    chunk = stream.next() => match chunk {
+                        Some(Ok(chunk)) => scrollback.lock().unwrap().push(&chunk),
+                        Some(Err(e)) => {
+                            scrollback.lock().unwrap().push_line(&format!("{:#}", e));
+                            break;
+                        }
+                        None => break,
+                    },
+                    line = lines.recv(), if stdin => {
+                        // The session was closed. Dropping the connection
+                        // would close the container's stdin too, which ends
+                        // processes such as shells, so detach first.
+                        let Some(line) = line else {
+                            let _ = input.write_all(DETACH_KEYS).await;
+                            let _ = input.flush().await;
+                            return;
+                        };
+                        let sent = input.write_all(format!("{}\n", line).as_bytes()).await;
+                        if sent.is_err() || input.flush().await.is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+            scrollback.lock().unwrap().push_line("── session ended ──");
+        });
+        let kind = SessionKind::Attach { stdin };
+        Self::new(kind, container.name.clone(), output, stdin.then_some(sender), task)
+    }
+
+    fn new(
+        kind: SessionKind,
+        container: String,
+        output: Arc<Mutex<Scrollback>>,
+        stdin: Option<UnboundedSender<String>>,
+        task: JoinHandle<()>,
+    ) -> Self {
+        Self {
+            kind,
+            container,
+            input: String::new(),
+            scroll: 0,
+            json_view: JsonView::default(),
+            filter: None,
+            history: Vec::new(),
+            history_cursor: None,
+            output,
+            stdin,
+            task,
+        }
+    }
+
+    pub fn title(&self) -> String {
+        match self.kind {
+            SessionKind::Exec => format!("{}: {}", SHELL, self.container),
+            SessionKind::Logs => format!("logs: {}", self.container),
+            SessionKind::Tail => format!("tail: {}", self.container),
+            SessionKind::Attach { stdin: true } => format!("attach: {}", self.container),
+            SessionKind::Attach { stdin: false } => format!("attach (output only): {}", self.container),
+        }
+    }
+
+    pub fn is_alive(&self) -> bool {
+        !self.task.is_finished()
+    }
+
+    /// Sends the typed line to the shell or attached process, echoing it into
+    /// the scrollback.
+    pub fn submit(&mut self) {
+        let Some(stdin) = &self.stdin else {
+            return;
+        };
+        let line = std::mem::take(&mut self.input);
+        self.output.lock().unwrap().push_line(&format!("$ {}", line));
+        if !line.is_empty() && self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+        }
+        self.history_cursor = None;
+        self.scroll = 0;
+        let _ = stdin.send(line);
+    }
+
+    /// Steps back (`older`) or forward through the sent lines.
+    pub fn recall(&mut self, older: bool) {
+        let cursor = match (self.history_cursor, older) {
+            (None, true) => self.history.len().checked_sub(1),
+            (Some(i), true) => Some(i.saturating_sub(1)),
+            (Some(i), false) if i + 1 < self.history.len() => Some(i + 1),
+            (_, false) => None,
+        };
+        self.history_cursor = cursor;
+        self.input = cursor.map(|i| self.history[i].clone()).unwrap_or_default();
+    }
+
+    pub fn scroll_by(&mut self, lines: isize) {
+        let max = self.output.lock().unwrap().lines.len();
+        self.scroll = self.scroll.saturating_add_signed(lines).min(max);
+    }
+
+    /// The `count` lines ending `scroll` lines above the bottom, after
+    /// rendering and filtering JSON lines.
+    pub fn visible(&self, count: usize) -> Vec<String> {
+        let output = self.output.lock().unwrap();
+        if self.json_view == JsonView::Raw && self.filter.is_none() {
+            let end = output.lines.len().saturating_sub(self.scroll);
+            return output
+                .lines
+                .range(end.saturating_sub(count)..end)
+                .cloned()
+                .collect();
+        }
+        // Rendering the whole scrollback every frame is too slow; work
+        // backwards until the view is full.
+        let mut rendered = Vec::new();
+        for line in output.lines.iter().rev() {
+            if rendered.len() >= count + self.scroll {
+                break;
+            }
+            if let Some(lines) = json_log::render(line, self.json_view, self.filter.as_ref()) {
+                rendered.extend(lines.into_iter().rev());
+            }
+        }
+        let mut visible: Vec<String> = rendered.into_iter().skip(self.scroll).take(count).collect();
+        visible.reverse();
+        visible
+    }
+}
+
+/// Splits off the timestamp Docker puts in front of each line.
+fn split_timestamp(line: &str) -> (Option<DateTime<FixedOffset>>, &str) {
+    match line.split_once(' ') {
+        Some((time, text)) => match DateTime::parse_from_rfc3339(time) {
+            Ok(time) => (Some(time), text),
+            Err(_) => (None, line),
+        },
+        None => (None, line),
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        // An attach with stdin sees its channel close once this returns, and
+        // detaches before it ends.
+        if self.kind == (SessionKind::Attach { stdin: true }) {
+            return;
+        }
+        self.task.abort();
+    }
+}
+
+/// All open sessions, at most one of them shown at a time.
+#[derive(Default)]
+pub struct Sessions {
+    pub list: Vec<Session>,
+    /// Index of the session shown over the current screen.
+    pub active: Option<usize>,
+    /// Highlighted row while the switcher is open.
+    pub switcher: Option<usize>,
+}
+
+impl Sessions {
+    /// Adds a session and shows it.
+    pub fn open(&mut self, session: Session) {
+        self.list.push(session);
+        self.active = Some(self.list.len() - 1);
+    }
+
+    /// Ends a session; the others keep their place.
+    pub fn close(&mut self, index: usize) {
+        if index >= self.list.len() {
+            return;
+        }
+        self.list.remove(index);
+        self.active = match self.active {
+            Some(i) if i == index => None,
+            Some(i) if i > index => Some(i - 1),
+            active => active,
+        };
+        if let Some(cursor) = &mut self.switcher {
+            *cursor = (*cursor).min(self.list.len().saturating_sub(1));
+        }
+    }
+
+    /// Shows the next (or previous) session, wrapping around.
+    pub fn cycle(&mut self, forward: bool) {
+        let (Some(i), len) = (self.active, self.list.len()) else {
+            return;
+        };
+        self.active = Some(if forward { (i + 1) % len } else { (i + len - 1) % len });
+    }
+
+    pub fn active(&self) -> Option<&Session> {
+        self.list.get(self.active?)
+    }
+
+    pub fn active_mut(&mut self) -> Option<&mut Session> {
+        self.list.get_mut(self.active?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scrollback_joins_chunks_into_lines() {
+        let cases: [(&[&str], &[&str], bool); 6] = [
+            (&["one\ntwo\n"], &["one", "two"], false),
+            (&["par", "tial\n"], &["partial"], false),
+            (&["/ # "], &["/ # "], true),
+            (&["/ # ", "ls\nbin\n"], &["/ # ls", "bin"], false),
+            (&["a\n", "", "\n"], &["a", ""], false),
+            (&["x\n\ny"], &["x", "", "y"], true),
+        ];
+        for (chunks, lines, open) in cases {
+            let mut scrollback = Scrollback::default();
+            for chunk in chunks {
+                scrollback.push(chunk);
+            }
+            assert_eq!(Vec::from(scrollback.lines), lines, "{:?}", chunks);
+            assert_eq!(scrollback.open, open, "{:?}", chunks);
+        }
+    }
+
+    #[test]
+    fn echoed_lines_close_an_open_prompt() {
+        let mut scrollback = Scrollback::default();
+        scrollback.push("/ # ");
+        scrollback.push_line("$ ls");
+        scrollback.push_line("$ pwd");
+        assert_eq!(Vec::from(scrollback.lines), ["/ # ", "$ ls", "$ pwd"]);
+        assert!(!scrollback.open);
+    }
+
+    #[test]
+    fn scrollback_keeps_the_latest_lines() {
+        let mut scrollback = Scrollback::default();
+        for i in 0..SCROLLBACK + 3 {
+            scrollback.push_line(&i.to_string());
+        }
+        assert_eq!(scrollback.lines.len(), SCROLLBACK);
+        assert_eq!(scrollback.lines.front().map(String::as_str), Some("3"));
+    }
+    #[test]
+    fn tail_lines_split_off_their_timestamp() {
+        let (time, text) = split_timestamp("2024-05-01T10:00:00.123456789Z GET /health 200");
+        assert_eq!(time.map(|t| t.timestamp_millis()), Some(1714557600123));
+        assert_eq!(text, "GET /health 200");
+        for line in ["GET /health 200", "no-spaces", "", "10:00:00 started"] {
+            assert_eq!(split_timestamp(line), (None, line));
+        }
+    }
+}
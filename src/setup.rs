@@ -0,0 +1,262 @@
+//! First-run wizard: a few questions in the terminal, answered with the
+//! arrow keys, that write the config file so nobody has to start from a
+//! blank TOML file.
+
+use crate::config::Config;
+use anyhow::{Context, Result};
+use crossterm::{
+    event::{self, Event, KeyCode},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use std::{
+    fs,
+    io::{self, IsTerminal},
+    path::Path,
+};
+use tui::{
+    backend::{Backend, CrosstermBackend},
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, BorderType, Borders, Paragraph, Wrap},
+    Frame, Terminal,
+};
+
+const REFRESH_CHOICES: [u64; 4] = [1, 2, 5, 10];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Refresh,
+    Host,
+    Vim,
+    Confirm,
+}
+
+struct Wizard {
+    step: Step,
+    /// Index into [`REFRESH_CHOICES`].
+    refresh: usize,
+    host: String,
+    vim: bool,
+}
+
+impl Default for Wizard {
+    fn default() -> Self {
+        Self {
+            step: Step::Refresh,
+            refresh: 1,
+            host: String::new(),
+            vim: false,
+        }
+    }
+}
+
+enum Outcome {
+    Save,
+    /// Esc: write the defaults, so the wizard does not come back.
+    Skip,
+}
+
+impl Wizard {
+    fn handle_key(&mut self, code: KeyCode) -> Option<Outcome> {
+        match (self.step, code) {
+            (_, KeyCode::Esc) => return Some(Outcome::Skip),
+            (Step::Confirm, KeyCode::Enter) => return Some(Outcome::Save),
+            (_, KeyCode::Enter) => self.step = self.next_step(),
+            (_, KeyCode::Left) => self.step = self.previous_step(),
+            (Step::Refresh, KeyCode::Up) => self.refresh = self.refresh.saturating_sub(1),
+            (Step::Refresh, KeyCode::Down) => self.refresh = (self.refresh + 1).min(REFRESH_CHOICES.len() - 1),
+            (Step::Host, KeyCode::Char(c)) => self.host.push(c),
+            (Step::Host, KeyCode::Backspace) => {
+                self.host.pop();
+            }
+            (Step::Vim, KeyCode::Up | KeyCode::Down) => self.vim = !self.vim,
+            _ => {}
+        }
+        None
+    }
+
+    fn next_step(&self) -> Step {
+        match self.step {
+            Step::Refresh => Step::Host,
+            Step::Host => Step::Vim,
+            Step::Vim | Step::Confirm => Step::Confirm,
+        }
+    }
+
+    fn previous_step(&self) -> Step {
+        match self.step {
+            Step::Refresh | Step::Host => Step::Refresh,
+            Step::Vim => Step::Host,
+            Step::Confirm => Step::Vim,
+        }
+    }
+
+    /// The config file for the answers so far.
+    fn toml(&self) -> String {
+        let mut toml = String::from("# Written by the dockertop setup wizard; the README lists every option.\n\n");
+        toml.push_str(&format!("[list]\nrefresh_seconds = {}\n", REFRESH_CHOICES[self.refresh]));
+        let host = self.host.trim();
+        if !host.is_empty() {
+            toml.push_str(&format!("\n[api]\nhost = {:?}\n", host));
+        }
+        toml.push_str(&format!("\n[keys]\nvim = {}\n", self.vim));
+        toml
+    }
+}
+
+/// Whether to run the wizard: there is no config file yet, and a person at
+/// a terminal to answer.
+pub fn needed() -> bool {
+    Config::path().is_some_and(|p| !p.exists()) && io::stdin().is_terminal() && io::stdout().is_terminal()
+}
+
+/// Asks the questions and writes the config file.
+pub fn run() -> Result<()> {
+    let Some(path) = Config::path() else {
+        return Ok(());
+    };
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+
+    let answers = ask(&mut terminal, &path);
+
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+
+    let toml = answers?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    fs::write(&path, toml).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn ask<B: Backend>(terminal: &mut Terminal<B>, path: &Path) -> Result<String> {
+    let mut wizard = Wizard::default();
+    loop {
+        terminal.draw(|f| render(f, &wizard, path))?;
+        if let Event::Key(key) = event::read()? {
+            match wizard.handle_key(key.code) {
+                Some(Outcome::Save) => return Ok(wizard.toml()),
+                Some(Outcome::Skip) => return Ok(Wizard::default().toml()),
+                None => {}
+            }
+        }
+    }
+}
+
+fn render<B: Backend>(f: &mut Frame<B>, wizard: &Wizard, path: &Path) {
+    let selected = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD);
+    let choice = |label: String, chosen: bool| {
+        if chosen {
+            Spans::from(Span::styled(format!("> {}", label), selected))
+        } else {
+            Spans::from(format!("  {}", label))
+        }
+    };
+
+    let (number, question, mut lines) = match wizard.step {
+        Step::Refresh => (
+            1,
+            "How often should the stats refresh?",
+            REFRESH_CHOICES
+                .iter()
+                .enumerate()
+                .map(|(i, seconds)| {
+                    let label = match seconds {
+                        1 => "1 second".to_string(),
+                        2 => "2 seconds (default)".to_string(),
+                        _ => format!("{} seconds", seconds),
+                    };
+                    choice(label, i == wizard.refresh)
+                })
+                .collect(),
+        ),
+        Step::Host => (
+            2,
+            "Which Docker daemon should dockertop connect to?",
+            vec![
+                Spans::from(Span::styled(format!("> {}█", wizard.host), selected)),
+                Spans::from(""),
+                Spans::from("Leave empty for DOCKER_HOST or the local socket, or enter e.g."),
+                Spans::from("tcp://10.0.0.5:2375 or unix:///run/user/1000/docker.sock."),
+            ],
+        ),
+        Step::Vim => (
+            3,
+            "Use vim keys (j/k move the selection, besides ↑/↓)?",
+            vec![choice("No".to_string(), !wizard.vim), choice("Yes".to_string(), wizard.vim)],
+        ),
+        Step::Confirm => (4, "This will be written:", wizard.toml().lines().map(|l| Spans::from(l.to_string())).collect()),
+    };
+    let mut text = vec![
+        Spans::from(format!(
+            "There is no config file yet. A few questions to write {}; everything can be changed there later.",
+            path.display()
+        )),
+        Spans::from(""),
+        Spans::from(Span::styled(format!("{}/4  {}", number, question), Style::default().add_modifier(Modifier::BOLD))),
+        Spans::from(""),
+    ];
+    text.append(&mut lines);
+
+    let help = if wizard.step == Step::Confirm {
+        "Enter: Save  ←: Back  Esc: Save the defaults instead"
+    } else {
+        "↑/↓: Choose  Enter: Next  ←: Back  Esc: Skip and keep the defaults"
+    };
+    let area = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(2)
+        .constraints([Constraint::Min(0), Constraint::Length(1)].as_ref())
+        .split(f.size());
+    let block = Block::default()
+        .title(" Welcome to dockertop ")
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded);
+    f.render_widget(Paragraph::new(text).block(block).wrap(Wrap { trim: false }), area[0]);
+    f.render_widget(Paragraph::new(help).style(Style::default().fg(Color::DarkGray)), area[1]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn answer(keys: &[KeyCode]) -> (Wizard, Option<Outcome>) {
+        let mut wizard = Wizard::default();
+        let mut outcome = None;
+        for &key in keys {
+            outcome = wizard.handle_key(key);
+        }
+        (wizard, outcome)
+    }
+
+    #[test]
+    fn answers_become_a_config_dockertop_reads() {
+        use KeyCode::*;
+        let (wizard, outcome) = answer(&[
+            Down, Down, Down, Down, Enter,
+            Char('t'), Char('c'), Char('p'), Char(':'), Char('/'), Char('/'), Char('x'), Backspace, Char('h'), Enter,
+            Down, Enter, Enter,
+        ]);
+        assert!(matches!(outcome, Some(Outcome::Save)));
+        let config: Config = toml::from_str(&wizard.toml()).unwrap();
+        assert_eq!(config.list.refresh_seconds, 10);
+        assert_eq!(config.api.host.as_deref(), Some("tcp://h"));
+        assert!(config.keys.vim);
+    }
+
+    #[test]
+    fn the_defaults_leave_the_host_out() {
+        use KeyCode::*;
+        let (wizard, outcome) = answer(&[Left, Enter, Enter, Left, Enter, Esc]);
+        assert!(matches!(outcome, Some(Outcome::Skip)));
+        assert_eq!(wizard.step, Step::Vim);
+        let config: Config = toml::from_str(&wizard.toml()).unwrap();
+        assert_eq!((config.list.refresh_seconds, config.api.host, config.keys.vim), (2, None, false));
+    }
+}
@@ -0,0 +1,148 @@
+//! Named snapshots of every container's stats, and a report of what changed
+//! since one was taken: containers that appeared or disappeared, status
+//! changes and whose memory grew the most. Containers are matched by name,
+//! so one recreated by `docker compose up` counts as the same container.
+
+use crate::backend::ContainerStats;
+use crate::config::FormatConfig;
+use crate::units::format_bytes;
+use chrono::{DateTime, Local};
+use std::{cmp::Reverse, collections::HashMap};
+
+/// Memory changes listed in the report.
+const TOP_MEMORY: usize = 10;
+
+pub struct StatsSnapshot {
+    pub name: String,
+    pub taken: DateTime<Local>,
+    pub containers: Vec<ContainerStats>,
+}
+
+impl StatsSnapshot {
+    pub fn take(name: String, containers: Vec<ContainerStats>) -> Self {
+        Self {
+            name,
+            taken: Local::now(),
+            containers,
+        }
+    }
+
+    /// The report lines for the pane.
+    pub fn diff(&self, current: &[ContainerStats], format: &FormatConfig) -> Vec<String> {
+        let before: HashMap<&str, &ContainerStats> = self.containers.iter().map(|c| (c.name.as_str(), c)).collect();
+        let now: HashMap<&str, &ContainerStats> = current.iter().map(|c| (c.name.as_str(), c)).collect();
+        let minutes = (Local::now() - self.taken).num_minutes();
+        let mut lines = vec![
+            format!(
+                "Compared with \"{}\", taken {} ({} minutes ago)",
+                self.name,
+                self.taken.format("%Y-%m-%d %H:%M:%S"),
+                minutes
+            ),
+            String::new(),
+        ];
+
+        let appeared: Vec<&ContainerStats> = current.iter().filter(|c| !before.contains_key(c.name.as_str())).collect();
+        let disappeared: Vec<&ContainerStats> =
+            self.containers.iter().filter(|c| !now.contains_key(c.name.as_str())).collect();
+        let width = current.iter().chain(&self.containers).map(|c| c.name.len()).max().unwrap_or(0);
+        for (heading, containers) in [("Appeared", appeared), ("Disappeared", disappeared)] {
+            lines.push(format!("{} ({}):", heading, containers.len()));
+            for c in containers {
+                lines.push(format!("  {:width$}  {}  {}", c.name, c.image, c.status, width = width));
+            }
+            lines.push(String::new());
+        }
+
+        let changed: Vec<(&ContainerStats, &ContainerStats)> = current
+            .iter()
+            .filter_map(|c| Some((*before.get(c.name.as_str())?, c)))
+            .collect();
+        let status_changes: Vec<_> = changed.iter().filter(|(b, c)| b.is_running() != c.is_running()).collect();
+        lines.push(format!("Started or stopped ({}):", status_changes.len()));
+        for (before, now) in status_changes {
+            lines.push(format!("  {:width$}  {} → {}", now.name, before.status, now.status, width = width));
+        }
+        lines.push(String::new());
+
+        let mut growth: Vec<_> = changed
+            .iter()
+            .map(|(b, c)| (*c, c.memory_usage as i64 - b.memory_usage as i64, b.memory_usage))
+            .filter(|&(_, delta, _)| delta > 0)
+            .collect();
+        growth.sort_by_key(|&(_, delta, _)| Reverse(delta));
+        lines.push(format!("Memory growth (largest {} of {}):", TOP_MEMORY.min(growth.len()), growth.len()));
+        for (c, delta, before) in growth.into_iter().take(TOP_MEMORY) {
+            let percent = match before {
+                0 => String::new(),
+                before => format!(" ({:+.0}%)", delta as f64 / before as f64 * 100.0),
+            };
+            lines.push(format!(
+                "  {:width$}  {} → {}  +{}{}",
+                c.name,
+                format_bytes(format, before),
+                format_bytes(format, c.memory_usage),
+                format_bytes(format, delta as u64),
+                percent,
+                width = width
+            ));
+        }
+        let total = |containers: &[ContainerStats]| containers.iter().map(|c| c.memory_usage).sum::<u64>();
+        lines.push(String::new());
+        lines.push(format!(
+            "Total memory: {} → {}",
+            format_bytes(format, total(&self.containers)),
+            format_bytes(format, total(current))
+        ));
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::mock::container;
+
+    fn using(name: &str, memory: u64, running: bool) -> ContainerStats {
+        let mut c = container(name);
+        c.memory_usage = memory;
+        if !running {
+            c.status = "Exited (0) 1 minute ago".to_string();
+        }
+        c
+    }
+
+    #[test]
+    fn reports_compare_containers_by_name() {
+        let mut recreated = using("web", 300 << 20, true);
+        recreated.id = "other-id".to_string();
+        let snapshot = StatsSnapshot::take(
+            "before deploy".to_string(),
+            vec![using("web", 100 << 20, true), using("db", 0, false), using("old", 1 << 20, true), using("api", 50 << 20, true)],
+        );
+        let current = [recreated, using("db", 10 << 20, true), using("new", 1 << 20, true), using("api", 40 << 20, true)];
+
+        let lines = snapshot.diff(&current, &FormatConfig::DEFAULT);
+        assert!(lines[0].starts_with("Compared with \"before deploy\", taken "), "{}", lines[0]);
+        assert!(lines[0].ends_with("(0 minutes ago)"), "{}", lines[0]);
+        assert_eq!(
+            lines[2..],
+            [
+                "Appeared (1):",
+                "  new  new:latest  Up 1 minute",
+                "",
+                "Disappeared (1):",
+                "  old  old:latest  Up 1 minute",
+                "",
+                "Started or stopped (1):",
+                "  db   Exited (0) 1 minute ago → Up 1 minute",
+                "",
+                "Memory growth (largest 2 of 2):",
+                "  web  100.00 MB → 300.00 MB  +200.00 MB (+200%)",
+                "  db   0.00 B → 10.00 MB  +10.00 MB",
+                "",
+                "Total memory: 151.00 MB → 351.00 MB",
+            ]
+        );
+    }
+}
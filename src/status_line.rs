@@ -0,0 +1,86 @@
+//! A one-line summary such as `12 running, 2 unhealthy, CPU 240%`, set as
+//! the terminal title and written to a file for tmux's status bar, so the
+//! state shows while dockertop sits in a background pane.
+
+use crate::backend::ContainerStats;
+use crate::config::{FormatConfig, StatusLineConfig};
+use crate::units::format_percent;
+use anyhow::{Context, Result};
+use std::fs;
+
+pub struct StatusLine {
+    config: StatusLineConfig,
+    text: String,
+    /// The title has not been set since the summary last changed.
+    title_due: bool,
+}
+
+impl StatusLine {
+    pub fn new(config: StatusLineConfig) -> Self {
+        Self {
+            config,
+            text: String::new(),
+            title_due: false,
+        }
+    }
+
+    pub fn reconfigure(&mut self, config: StatusLineConfig) {
+        if config.file != self.config.file {
+            self.clear();
+            self.text.clear();
+        }
+        self.title_due |= config.terminal_title && !self.config.terminal_title;
+        self.config = config;
+    }
+
+    /// Writes the summary of `containers` when it changed.
+    pub fn update(&mut self, containers: &[ContainerStats], format: &FormatConfig) -> Result<()> {
+        let text = summary(containers, format);
+        if text == self.text {
+            return Ok(());
+        }
+        self.text = text;
+        self.title_due = self.config.terminal_title;
+        let Some(path) = &self.config.file else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        // Renamed into place so tmux never reads half a line.
+        let temporary = path.with_extension("tmp");
+        fs::write(&temporary, format!("{}\n", self.text))
+            .with_context(|| format!("Failed to write {}", temporary.display()))?;
+        fs::rename(&temporary, path).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// The terminal title, when it is to be set.
+    pub fn take_title(&mut self) -> Option<String> {
+        if !std::mem::take(&mut self.title_due) || self.text.is_empty() {
+            return None;
+        }
+        Some(format!("dockertop: {}", self.text))
+    }
+
+    /// Removes the file on quitting, so tmux does not go on showing a
+    /// summary nothing updates.
+    pub fn clear(&self) {
+        if let Some(path) = &self.config.file {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// E.g. `12 running, 2 unhealthy, CPU 240%`; unhealthy containers are only
+/// counted when there are some.
+pub fn summary(containers: &[ContainerStats], format: &FormatConfig) -> String {
+    let running: Vec<&ContainerStats> = containers.iter().filter(|c| c.is_running()).collect();
+    let unhealthy = running.iter().filter(|c| c.status.contains("(unhealthy)")).count();
+    let cpu = running.iter().fold(0.0, |sum, c| sum + c.cpu_usage);
+    let mut parts = vec![format!("{} running", running.len())];
+    if unhealthy > 0 {
+        parts.push(format!("{} unhealthy", unhealthy));
+    }
+    parts.push(format!("CPU {}", format_percent(format, cpu)));
+    parts.join(", ")
+}
@@ -0,0 +1,135 @@
+//! Containers started from `[[templates]]` with `a`: the Redis or Postgres
+//! one spins up the same way every time to test against.
+
+use crate::actions::{join_image, parse_image};
+use crate::backend::{ContainerBackend, PublishedPort, RunSpec};
+use crate::config::Template;
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+
+/// Label on containers run from a template, with the template's name.
+pub const TEMPLATE_LABEL: &str = "dockertop.template";
+
+/// The template picker opened by `a`.
+pub struct TemplatePicker {
+    /// Index into the configured templates.
+    pub cursor: usize,
+}
+
+/// Creates and starts a container from `template`, numbered after the
+/// containers there are now, and returns what was run.
+pub async fn run(backend: &dyn ContainerBackend, template: &Template) -> Result<RunSpec> {
+    let taken: Vec<String> = backend.containers().await?.into_iter().map(|c| c.name).collect();
+    let spec = run_spec(template, &taken)?;
+    backend.run_container(&spec).await?;
+    Ok(spec)
+}
+
+/// What to create for `template`, named after the first of its names that
+/// is not in `taken`.
+pub fn run_spec(template: &Template, taken: &[String]) -> Result<RunSpec> {
+    let (repository, tag) = parse_image(&template.image).context("Invalid image")?;
+    let ports = template
+        .ports
+        .iter()
+        .map(|port| parse_port(port).with_context(|| format!("Invalid port {:?}", port)))
+        .collect::<Result<_>>()?;
+    Ok(RunSpec {
+        name: container_name(template, taken),
+        image: join_image(&repository, &tag),
+        env: template.env.iter().map(|(key, value)| format!("{}={}", key, value)).collect(),
+        ports,
+        volumes: template.volumes.clone(),
+        labels: HashMap::from([(TEMPLATE_LABEL.to_string(), template.name.clone())]),
+    })
+}
+
+fn container_name(template: &Template, taken: &[String]) -> String {
+    let pattern = template.container_name.replace("{{template}}", &template.name);
+    if !pattern.contains("{{n}}") {
+        return pattern;
+    }
+    (1..)
+        .map(|n: usize| pattern.replace("{{n}}", &n.to_string()))
+        .find(|name| !taken.contains(name))
+        .unwrap_or(pattern)
+}
+
+/// A port as `docker run -p` takes it: `host:container`, optionally with
+/// the host address (IPv6 in brackets) in front and `/tcp` or `/udp` behind, or just the
+/// container port for any free host port.
+pub fn parse_port(port: &str) -> Result<PublishedPort> {
+    let (ports, protocol) = match port.split_once('/') {
+        Some((ports, protocol @ ("tcp" | "udp"))) => (ports, protocol),
+        Some((_, protocol)) => bail!("unknown protocol {}", protocol),
+        None => (port, "tcp"),
+    };
+    let (host_ip, host_port, container_port) = match ports.rsplitn(3, ':').collect::<Vec<_>>()[..] {
+        [container] => (None, "0", container),
+        [container, host] => (None, host, container),
+        // An IPv6 address may be bracketed, as in `[::1]:8080:80`.
+        [container, host, ip] => (Some(ip.trim_start_matches('[').trim_end_matches(']').to_string()), host, container),
+        _ => bail!("expected host:container, e.g. 5432:5432"),
+    };
+    Ok(PublishedPort {
+        host_ip,
+        host_port: host_port.parse().context("the host port is not a number")?,
+        container_port: container_port.parse().context("the container port is not a number")?,
+        protocol: protocol.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn ports_are_read_like_docker_run_reads_them() {
+        let port = |ip: Option<&str>, host, container, protocol: &str| PublishedPort {
+            host_ip: ip.map(str::to_string),
+            host_port: host,
+            container_port: container,
+            protocol: protocol.to_string(),
+        };
+        let cases = [
+            ("5432:5432", port(None, 5432, 5432, "tcp")),
+            ("80", port(None, 0, 80, "tcp")),
+            ("127.0.0.1:6379:6379/tcp", port(Some("127.0.0.1"), 6379, 6379, "tcp")),
+            ("5353:53/udp", port(None, 5353, 53, "udp")),
+            ("[::1]:8080:80", port(Some("::1"), 8080, 80, "tcp")),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(parse_port(text).unwrap(), expected, "{:?}", text);
+        }
+        for text in ["", "http", "8080:80/sctp", "99999:80", "a:b:c:d", "8080:"] {
+            assert!(parse_port(text).is_err(), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn names_take_the_lowest_free_number() {
+        let template = |container_name: &str| Template {
+            name: "pg".to_string(),
+            image: "postgres:16".to_string(),
+            env: BTreeMap::from([("POSTGRES_PASSWORD".to_string(), "dev".to_string())]),
+            ports: vec!["5432:5432".to_string()],
+            volumes: Vec::new(),
+            container_name: container_name.to_string(),
+        };
+        let taken = ["pg-1".to_string(), "pg-3".to_string()];
+        let cases = [("{{template}}-{{n}}", "pg-2"), ("{{template}}", "pg"), ("db-{{n}}", "db-1")];
+        for (pattern, name) in cases {
+            assert_eq!(container_name(&template(pattern), &taken), name);
+        }
+
+        let spec = run_spec(&template("{{template}}-{{n}}"), &taken).unwrap();
+        assert_eq!((spec.name.as_str(), spec.image.as_str()), ("pg-2", "postgres:16"));
+        assert_eq!(spec.env, ["POSTGRES_PASSWORD=dev"]);
+        assert_eq!(spec.labels.get(TEMPLATE_LABEL).map(String::as_str), Some("pg"));
+        let mut broken = template("x");
+        broken.ports = vec!["five".to_string()];
+        let error = run_spec(&broken, &[]).unwrap_err();
+        assert_eq!(format!("{:#}", error), "Invalid port \"five\": the container port is not a number: invalid digit found in string");
+    }
+}
@@ -0,0 +1,286 @@
+//! Start/stop history of a container over the last hours, merged from the
+//! daemon's event log and the inspect state, so flapping is visible at a glance.
+
+use crate::backend::ContainerEvent;
+use bollard::models::ContainerState;
+use chrono::DateTime;
+
+/// How far back the timeline reaches, in seconds.
+pub const WINDOW: i64 = 6 * 3600;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Transition {
+    Start,
+    Stop,
+    Restart,
+    Die,
+    Oom,
+}
+
+impl Transition {
+    fn from_action(action: &str) -> Option<Self> {
+        match action {
+            "start" => Some(Transition::Start),
+            "stop" => Some(Transition::Stop),
+            "restart" => Some(Transition::Restart),
+            "die" => Some(Transition::Die),
+            "oom" => Some(Transition::Oom),
+            _ => None,
+        }
+    }
+
+    pub fn symbol(self) -> char {
+        match self {
+            Transition::Start => '▲',
+            Transition::Stop => '■',
+            Transition::Restart => '↻',
+            Transition::Die => '▼',
+            Transition::Oom => '!',
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Transition::Start => "start",
+            Transition::Stop => "stop",
+            Transition::Restart => "restart",
+            Transition::Die => "die",
+            Transition::Oom => "oom",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Mark {
+    /// Unix seconds.
+    pub time: i64,
+    pub transition: Transition,
+    pub exit_code: Option<String>,
+}
+
+/// How long one start took to come up.
+#[derive(Debug, Clone)]
+pub struct Startup {
+    /// Unix seconds of the `start` event.
+    pub time: i64,
+    /// The image it started from, to tell slow starts apart by release.
+    pub image: Option<String>,
+    /// Seconds from `create` to running, for the first start after creation.
+    pub running_after: Option<i64>,
+    /// Seconds from running to the first passing health check, unless it
+    /// died before or has no healthcheck.
+    pub healthy_after: Option<i64>,
+}
+
+pub struct Timeline {
+    pub container_id: String,
+    pub marks: Vec<Mark>,
+    /// Oldest first.
+    pub startups: Vec<Startup>,
+    /// Every event in the window, lifecycle or not, oldest first.
+    pub events: Vec<ContainerEvent>,
+}
+
+/// Lifecycle marks from `events`, plus the last start and finish from the
+/// inspect state when the daemon's event backlog no longer has them.
+pub fn marks(events: &[ContainerEvent], state: Option<&ContainerState>) -> Vec<Mark> {
+    let mut marks: Vec<Mark> = events
+        .iter()
+        .filter_map(|event| {
+            Some(Mark {
+                time: event.time,
+                transition: Transition::from_action(&event.action)?,
+                exit_code: event.attributes.get("exitCode").cloned(),
+            })
+        })
+        .collect();
+
+    if let Some(state) = state {
+        let parse = |time: &Option<String>| {
+            DateTime::parse_from_rfc3339(time.as_deref()?)
+                .ok()
+                .map(|t| t.timestamp())
+                .filter(|&t| t > 0)
+        };
+        let known = |marks: &[Mark], time: i64, transitions: &[Transition]| {
+            marks
+                .iter()
+                .any(|m| transitions.contains(&m.transition) && (m.time - time).abs() <= 5)
+        };
+        if let Some(time) = parse(&state.started_at) {
+            if !known(&marks, time, &[Transition::Start, Transition::Restart]) {
+                marks.push(Mark {
+                    time,
+                    transition: Transition::Start,
+                    exit_code: None,
+                });
+            }
+        }
+        if let Some(time) = parse(&state.finished_at) {
+            if !known(&marks, time, &[Transition::Die, Transition::Stop]) {
+                marks.push(Mark {
+                    time,
+                    transition: Transition::Die,
+                    exit_code: state.exit_code.map(|code| code.to_string()),
+                });
+            }
+        }
+    }
+
+    marks.sort_by_key(|m| m.time);
+    marks
+}
+
+/// Create → running → healthy durations of each start in `events`, which
+/// must be oldest first.
+pub fn startups(events: &[ContainerEvent]) -> Vec<Startup> {
+    let mut startups: Vec<Startup> = Vec::new();
+    let mut created = None;
+    let mut waiting_for_health = false;
+    for event in events {
+        match event.action.as_str() {
+            "create" => created = Some(event.time),
+            "start" => {
+                startups.push(Startup {
+                    time: event.time,
+                    image: event.attributes.get("image").cloned(),
+                    running_after: created.take().map(|created| event.time - created),
+                    healthy_after: None,
+                });
+                waiting_for_health = true;
+            }
+            "health_status: healthy" if waiting_for_health => {
+                if let Some(startup) = startups.last_mut() {
+                    startup.healthy_after = Some(event.time - startup.time);
+                }
+                waiting_for_health = false;
+            }
+            "die" => waiting_for_health = false,
+            _ => {}
+        }
+    }
+    startups
+}
+
+/// One character per time slot over [`WINDOW`] ending at `now`; slots with
+/// several marks show the most severe one.
+pub fn strip(marks: &[Mark], now: i64, width: usize) -> String {
+    let width = width.max(1);
+    let mut slots: Vec<Option<Transition>> = vec![None; width];
+    for mark in marks.iter().filter(|m| m.time > now - WINDOW && m.time <= now) {
+        let slot = ((mark.time - (now - WINDOW)) * width as i64 / WINDOW).min(width as i64 - 1) as usize;
+        slots[slot] = slots[slot].max(Some(mark.transition));
+    }
+    slots
+        .into_iter()
+        .map(|slot| slot.map_or('·', Transition::symbol))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(time: i64, action: &str) -> ContainerEvent {
+        ContainerEvent {
+            time,
+            action: action.to_string(),
+            attributes: Default::default(),
+        }
+    }
+
+    fn mark(time: i64, transition: Transition) -> Mark {
+        Mark {
+            time,
+            transition,
+            exit_code: None,
+        }
+    }
+
+    #[test]
+    fn marks_come_from_lifecycle_events_and_fill_in_from_the_state() {
+        let mut died = event(200, "die");
+        died.attributes.insert("exitCode".to_string(), "137".to_string());
+        let events = [event(100, "start"), event(150, "exec_create: sh"), died];
+        let summary = |marks: Vec<Mark>| -> Vec<(i64, &str, Option<String>)> {
+            marks.into_iter().map(|m| (m.time, m.transition.label(), m.exit_code)).collect()
+        };
+
+        assert_eq!(
+            summary(marks(&events, None)),
+            [(100, "start", None), (200, "die", Some("137".to_string()))]
+        );
+
+        // 1970-01-01T00:01:42Z is within a few seconds of the start event.
+        let state = ContainerState {
+            started_at: Some("1970-01-01T00:01:42Z".to_string()),
+            finished_at: Some("1970-01-01T00:00:50Z".to_string()),
+            exit_code: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(
+            summary(marks(&events, Some(&state))),
+            [(50, "die", Some("1".to_string())), (100, "start", None), (200, "die", Some("137".to_string()))]
+        );
+
+        let never_finished = ContainerState {
+            started_at: Some("1970-01-01T00:05:00Z".to_string()),
+            finished_at: Some("0001-01-01T00:00:00Z".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(summary(marks(&[], Some(&never_finished))), [(300, "start", None)]);
+    }
+
+    #[test]
+    fn startups_time_each_start_from_create_to_healthy() {
+        let mut first = event(110, "start");
+        first.attributes.insert("image".to_string(), "web:1".to_string());
+        let events = [
+            event(100, "create"),
+            first,
+            event(125, "health_status: healthy"),
+            event(130, "health_status: healthy"),
+            event(200, "die"),
+            event(210, "start"),
+            event(220, "die"),
+            event(230, "health_status: healthy"),
+            event(300, "start"),
+        ];
+        let summary: Vec<_> = startups(&events)
+            .into_iter()
+            .map(|s| (s.time, s.image, s.running_after, s.healthy_after))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (110, Some("web:1".to_string()), Some(10), Some(15)),
+                (210, None, None, None),
+                (300, None, None, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn strips_place_marks_in_slots_and_keep_the_most_severe() {
+        let now = WINDOW * 2;
+        let slot = WINDOW / 6;
+        let cases = [
+            (vec![], "······"),
+            (vec![mark(now, Transition::Start)], "·····▲"),
+            (vec![mark(now - WINDOW + 1, Transition::Stop)], "■·····"),
+            (vec![mark(now - WINDOW, Transition::Stop), mark(now + 1, Transition::Stop)], "······"),
+            (
+                vec![mark(now - 3 * slot + 1, Transition::Die), mark(now - 3 * slot + 2, Transition::Start)],
+                "···▼··",
+            ),
+            (
+                vec![mark(now - 1, Transition::Oom), mark(now, Transition::Restart), mark(now - slot - 1, Transition::Restart)],
+                "····↻!",
+            ),
+        ];
+        for (marks, expected) in cases {
+            assert_eq!(strip(&marks, now, 6), expected, "{:?}", marks);
+        }
+        assert_eq!(strip(&[mark(now, Transition::Start)], now, 0), "▲");
+    }
+}
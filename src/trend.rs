@@ -0,0 +1,129 @@
+//! Rates of change from the history of refreshes: how fast a container's
+//! memory grows and how often it restarts, to put numbers on leaks and
+//! flapping.
+
+use crate::backend::ContainerStats;
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+/// Memory samples the growth is fitted over.
+const MEMORY_WINDOW: Duration = Duration::from_secs(10 * 60);
+/// Growth is only reported once the samples span this long.
+const MEMORY_MIN_SPAN: Duration = Duration::from_secs(60);
+const RESTART_WINDOW: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Default)]
+struct History {
+    /// Since the last restart, within [`MEMORY_WINDOW`].
+    memory: VecDeque<(Instant, u64)>,
+    restarts: VecDeque<Instant>,
+    running: bool,
+    uptime: Option<u64>,
+}
+
+#[derive(Default)]
+pub struct TrendMeter {
+    /// By container ID.
+    histories: HashMap<String, History>,
+}
+
+impl TrendMeter {
+    /// Adds a sample of every container and forgets removed ones, returning
+    /// the IDs of those that restarted. A restart is a container seen
+    /// running again after being seen stopped, or whose uptime went down
+    /// between refreshes.
+    pub fn update(&mut self, containers: &[ContainerStats]) -> Vec<String> {
+        let now = Instant::now();
+        let mut restarts = Vec::new();
+        self.histories.retain(|id, _| containers.iter().any(|c| &c.id == id));
+        for container in containers {
+            let seen = self.histories.contains_key(&container.id);
+            let history = self.histories.entry(container.id.clone()).or_default();
+            let running = container.is_running();
+            let uptime = uptime_seconds(&container.status);
+            let restarted = match (history.running, running) {
+                (false, true) => seen,
+                (true, true) => matches!((history.uptime, uptime), (Some(before), Some(after)) if after < before),
+                _ => false,
+            };
+            if restarted {
+                history.restarts.push_back(now);
+                history.memory.clear();
+                restarts.push(container.id.clone());
+            }
+            history.running = running;
+            history.uptime = uptime;
+            if running {
+                history.memory.push_back((now, container.memory_usage));
+            } else {
+                history.memory.clear();
+            }
+            while history.memory.front().is_some_and(|(at, _)| now.duration_since(*at) > MEMORY_WINDOW) {
+                history.memory.pop_front();
+            }
+            while history.restarts.front().is_some_and(|at| now.duration_since(*at) > RESTART_WINDOW) {
+                history.restarts.pop_front();
+            }
+        }
+        restarts
+    }
+
+    /// Memory growth in bytes per minute, fitted by least squares over the
+    /// last ten minutes since the container last started. `None` until the
+    /// samples span a minute.
+    pub fn memory_per_minute(&self, id: &str) -> Option<f64> {
+        let memory = &self.histories.get(id)?.memory;
+        let (first, _) = memory.front()?;
+        let (last, _) = memory.back()?;
+        if last.duration_since(*first) < MEMORY_MIN_SPAN {
+            return None;
+        }
+        let points: Vec<(f64, f64)> = memory
+            .iter()
+            .map(|(at, bytes)| (at.duration_since(*first).as_secs_f64() / 60.0, *bytes as f64))
+            .collect();
+        let n = points.len() as f64;
+        let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+        let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+        let covariance: f64 = points.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+        let variance: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+        (variance > 0.0).then(|| covariance / variance)
+    }
+
+    /// Restarts seen in the last hour.
+    pub fn restarts_per_hour(&self, id: &str) -> usize {
+        self.histories.get(id).map_or(0, |h| h.restarts.len())
+    }
+}
+
+/// The uptime in a running container's status, e.g. `Up 5 minutes
+/// (healthy)`, as the seconds it stands for. Docker rounds it ever more
+/// coarsely, but never down, so it only drops on a restart.
+fn uptime_seconds(status: &str) -> Option<u64> {
+    let uptime = status.strip_prefix("Up ")?;
+    let uptime = uptime.split(" (").next().unwrap_or(uptime);
+    if uptime.starts_with("Less than") {
+        return Some(0);
+    }
+    let mut words = uptime.split_whitespace();
+    let count = match words.next()? {
+        "About" | "a" | "an" => {
+            words.next();
+            1
+        }
+        count => count.parse().ok()?,
+    };
+    let unit = match words.next()?.trim_end_matches('s') {
+        "second" => 1,
+        "minute" => 60,
+        "hour" => 3600,
+        "day" => 86_400,
+        "week" => 7 * 86_400,
+        "month" => 30 * 86_400,
+        "year" => 365 * 86_400,
+        _ => return None,
+    };
+    Some(count * unit)
+}
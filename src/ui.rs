@@ -0,0 +1,2121 @@
+//! Rendering of the application state.
+
+use crate::actions::{format_age, SIGNALS};
+use crate::app::{App, DetailsTab, Grouping, InputMode, PromptAction, Screen, SortKey, StatusGroup};
+use crate::backend::{host_port, is_unspecified};
+use crate::compare::Compare;
+use crate::config::{ChartStyle, FormatConfig, HighlightColor};
+use crate::cost::CostMeter;
+use crate::files::EntryKind;
+use crate::graph;
+use crate::limits::{LimitsForm, FIELDS};
+use crate::log_search::CONTEXT_LINES;
+use crate::orphans::OrphanKind;
+use crate::pods;
+use crate::recreate::RecreateForm;
+use crate::registries;
+use crate::sessions::{SessionKind, TAIL_SEPARATOR};
+use crate::timeline;
+use crate::units::{format_bits, format_bytes, format_percent, format_rate};
+use tui::{
+    backend::Backend,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    symbols,
+    widgets::{Axis, Block, Borders, Cell, Chart, Clear, Dataset, GraphType, Paragraph, Row, Sparkline, Table, TableState, Wrap},
+    Frame,
+};
+
+/// Signed memory growth per minute, e.g. `+1.2M` or `-340K`.
+fn format_growth(format: &FormatConfig, bytes_per_minute: f64) -> String {
+    let sign = if bytes_per_minute < 0.0 { '-' } else { '+' };
+    format!("{}{}", sign, format_rate(format, bytes_per_minute.abs()))
+}
+
+/// `fraction` of `width` cells filled with eighth blocks, e.g. `███▌    `.
+fn bar(fraction: f64, width: usize) -> String {
+    const PARTIAL: [char; 8] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+    let eighths = (fraction.clamp(0.0, 1.0) * width as f64 * 8.0).round() as usize;
+    let mut bar = "█".repeat(eighths / 8);
+    if eighths / 8 < width {
+        bar.push(PARTIAL[eighths % 8]);
+    }
+    format!("{:<width$}", bar, width = width)
+}
+
+/// Published ports as `8080->80/tcp`, like `docker ps`, with the host
+/// address in front when bound to one, e.g. `[::1]:8080->80/tcp`. A port
+/// published on IPv4 and IPv6 alike is listed once.
+fn format_ports(ports: &[bollard::models::Port]) -> String {
+    let mut texts: Vec<String> = Vec::new();
+    for p in ports {
+        let typ = p.typ.map(|t| t.to_string()).unwrap_or_default();
+        let text = match (p.public_port, p.ip.as_deref().filter(|ip| !is_unspecified(ip))) {
+            (Some(public), Some(ip)) => format!("{}->{}/{}", host_port(ip, public), p.private_port, typ),
+            (Some(public), None) => format!("{}->{}/{}", public, p.private_port, typ),
+            (None, _) => format!("{}/{}", p.private_port, typ),
+        };
+        if !texts.contains(&text) {
+            texts.push(text);
+        }
+    }
+    texts.join(", ")
+}
+
+pub fn ui<B: Backend>(f: &mut Frame<B>, app: &App) {
+    // Create a vertical layout for the entire screen
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),  // Main content
+            Constraint::Length(3), // Help bar
+        ].as_ref())
+        .split(f.size());
+
+    match app.screen {
+        Screen::Containers => render_containers(f, app, chunks[0]),
+        Screen::Dependencies => render_dependencies(f, app, chunks[0]),
+        Screen::Networks => render_networks(f, app, chunks[0]),
+        Screen::Volumes => render_volumes(f, app, chunks[0]),
+        Screen::Images => render_images(f, app, chunks[0]),
+        Screen::Orphans => render_orphans(f, app, chunks[0]),
+        Screen::Disk => render_disk(f, app, chunks[0]),
+        Screen::Idle => render_idle(f, app, chunks[0]),
+        Screen::Registries => render_registries(f, app, chunks[0]),
+        Screen::Watch => render_watch(f, app, chunks[0]),
+    }
+
+    // Help bar at the bottom, replaced by update progress or the last status message
+    let key_style = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD);
+    let help_text = if let Some(update) = &app.service_update {
+        vec![
+            Span::styled(format!("{} ", update.service), key_style),
+            Span::raw(format!(
+                "-> {} [{}] {}/{} tasks updated {}",
+                update.image, update.state, update.updated_tasks, update.total_tasks, update.message
+            )),
+        ]
+    } else if let Some(deployment) = &app.deployment {
+        let style = if deployment.failed {
+            Style::default().fg(Color::Red)
+        } else if deployment.finished {
+            Style::default().fg(Color::Green)
+        } else {
+            Style::default()
+        };
+        vec![
+            Span::styled(format!("{}: ", deployment.title), key_style),
+            Span::styled(deployment.message.clone(), style),
+        ]
+    } else if let Some(error) = &app.config_error {
+        vec![Span::styled(error.clone(), Style::default().fg(Color::Red).add_modifier(Modifier::BOLD))]
+    } else if let Some(message) = &app.status_message {
+        vec![Span::raw(message.clone())]
+    } else {
+        vec![
+            Span::styled("q", key_style),
+            Span::raw(": Quit  "),
+            Span::styled("↑/↓", key_style),
+            Span::raw(": Navigate  "),
+            Span::styled("Enter", key_style),
+            Span::raw(": Select Container  "),
+            Span::styled("1-9", key_style),
+            Span::raw(": Screens  "),
+            Span::styled("w", key_style),
+            Span::raw(": Wide Rows  "),
+            Span::styled("g", key_style),
+            Span::raw(": Group  "),
+            Span::styled("E/L", key_style),
+            Span::raw(": Shell/Logs  "),
+            Span::styled("S", key_style),
+            Span::raw(": Sessions  "),
+            Span::styled("U", key_style),
+            Span::raw(": Update Service Image"),
+        ]
+    };
+
+    let help_widget = Paragraph::new(Spans::from(help_text))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(tui::widgets::BorderType::Rounded)
+        );
+
+    f.render_widget(help_widget, chunks[1]);
+
+    let mut footprint = String::new();
+    if let Some(host) = app.host_monitor.usage {
+        footprint += &format!(
+            "host: {:.0}% of {} CPUs, {}/{} · ",
+            host.cpu_percent,
+            host.cpus,
+            format_bytes(&app.format, host.memory_used),
+            format_bytes(&app.format, host.memory_total)
+        );
+    }
+    if let Some(usage) = app.self_monitor.usage {
+        footprint += &format!(
+            "dockertop: {} CPU, {} ",
+            format_percent(&app.format, usage.cpu_percent),
+            format_bytes(&app.format, usage.rss_bytes)
+        );
+    }
+    if !footprint.is_empty() {
+        let footprint = Paragraph::new(footprint)
+        .style(Style::default().fg(Color::DarkGray))
+        .alignment(Alignment::Right);
+        let inner = Rect {
+            x: chunks[1].x + 1,
+            y: chunks[1].y + 1,
+            width: chunks[1].width.saturating_sub(2),
+            height: 1,
+        };
+        f.render_widget(footprint, inner);
+    }
+
+    if let Some(pane) = &app.output {
+        let lines: Vec<Spans> = pane.lines.iter().map(|l| Spans::from(l.as_str())).collect();
+        let pane_widget = Paragraph::new(lines)
+            .scroll((pane.scroll, 0))
+            .block(
+                Block::default()
+                    .title(format!(" {} (s: Save  Esc: Close) ", pane.title))
+                    .borders(Borders::ALL)
+                    .border_type(tui::widgets::BorderType::Rounded)
+            );
+        f.render_widget(Clear, chunks[0]);
+        f.render_widget(pane_widget, chunks[0]);
+    }
+
+    if app.files.is_some() {
+        render_files(f, app, chunks[0]);
+    }
+
+    if app.log_search.is_some() {
+        render_log_search(f, app, chunks[0]);
+    }
+
+    if app.log_view.is_some() {
+        render_log_view(f, app, chunks[0]);
+    }
+
+    if app.sessions.active.is_some() {
+        render_session(f, app, chunks[0]);
+    }
+    if app.sessions.switcher.is_some() {
+        let height = app.sessions.list.len() as u16 + 2;
+        render_switcher(f, app, centered_rect(50, height, f.size()));
+    }
+
+    if app.kill_picker.is_some() {
+        render_kill_picker(f, app, centered_rect(50, SIGNALS.len() as u16 + 3, f.size()));
+    }
+    if app.template_picker.is_some() {
+        render_template_picker(f, app, centered_rect(70, app.templates.len() as u16 + 2, f.size()));
+    }
+
+    if app.show_debug {
+        render_debug_panel(f, app, centered_rect(80, 16, f.size()));
+    }
+
+    if let InputMode::Confirm(confirm) = &app.input_mode {
+        // Long questions, such as the stages of a redeploy, wrap.
+        let width = (f.size().width / 2).saturating_sub(2).max(1) as usize;
+        let lines = (confirm.question.chars().count() + 6).div_ceil(width) as u16;
+        let area = centered_rect(50, lines + 2, f.size());
+        let dialog = Paragraph::new(Spans::from(vec![
+            Span::raw(format!("{} ", confirm.question)),
+            Span::styled("[y/N]", Style::default().add_modifier(Modifier::BOLD)),
+        ]))
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true })
+        .block(
+            Block::default()
+                .title(" Confirm ")
+                .borders(Borders::ALL)
+                .border_type(tui::widgets::BorderType::Rounded)
+                .border_style(Style::default().fg(Color::Red)),
+        );
+        f.render_widget(Clear, area);
+        f.render_widget(dialog, area);
+    }
+
+    if let InputMode::Prompt(prompt) = &app.input_mode {
+        let area = centered_rect(60, 3, f.size());
+        let input = match prompt.action {
+            PromptAction::RegistryPassword { .. } => "•".repeat(prompt.input.chars().count()),
+            _ => prompt.input.clone(),
+        };
+        let prompt_widget = Paragraph::new(format!("{}█", input))
+            .block(
+                Block::default()
+                    .title(prompt.title.as_str())
+                    .borders(Borders::ALL)
+                    .border_type(tui::widgets::BorderType::Rounded)
+                    .border_style(Style::default().fg(Color::Yellow))
+            );
+        f.render_widget(Clear, area);
+        f.render_widget(prompt_widget, area);
+    }
+}
+
+/// The container table with the details of the selected container beside it.
+fn render_containers<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    // Split the main content area horizontally
+    let main_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+        .split(area);
+
+    // Container table with enhanced styling
+    // Extended rows put a second value under each of the main columns.
+    let custom_columns = app.columns.columns();
+    let row_height = if app.extended_rows { 2 } else { 1 };
+    let main_headers = if app.extended_rows {
+        ["Name\nImage", "Status\nPorts", "CPU\nNet ↓ ↑", "MEM\nDisk r w"]
+    } else {
+        ["Name", "Status", "CPU", "MEM"]
+    };
+    // The marker column only shows while something is marked.
+    let marking = !app.marked.is_empty();
+    let header = Row::new(
+        marking
+            .then_some("")
+            .into_iter()
+            .chain(main_headers)
+            .chain(app.log_meter.as_ref().map(|_| "LOG/s"))
+            .chain(app.error_column.then_some("ERR/min"))
+            .chain(app.memory_growth_column.then_some("MEM/min"))
+            .chain(app.restart_column.then_some("RST/h"))
+            .chain(app.net_io_column.then_some("NET ↓ ↑"))
+            .chain(app.block_io_column.then_some("BLK r w"))
+            .chain((!app.probes.is_empty()).then_some("APP"))
+            .chain(app.cost.as_ref().map(|_| "COST/h"))
+            .map(str::to_string)
+            .chain(custom_columns.iter().map(|c| c.name.clone()))
+            .map(|h| Cell::from(h).style(Style::default().add_modifier(Modifier::BOLD))),
+    )
+    .height(row_height);
+
+    let now_ms = chrono::Utc::now().timestamp_millis();
+    let container_rows = app
+        .containers
+        .iter()
+        .map(|c| {
+            let status_style = status_style(c);
+
+            let fading = app.fading.get(&c.id);
+            let stale = c.stale_for(now_ms).filter(|_| fading.is_none());
+            let status_style = if fading.is_some() || stale.is_some() {
+                Style::default().fg(Color::DarkGray)
+            } else {
+                status_style
+            };
+
+            // Within a pod section the pod and namespace are in the header.
+            let name = match pods::container_name(c) {
+                Some(name) if app.grouping == Grouping::Pod => format!("  {}", name),
+                _ => c.name.clone(),
+            };
+            let mut cells = vec![
+                name,
+                match (app.actions.pending(&c.id), fading, stale) {
+                    (Some(doing), _, _) => format!("{}…", doing),
+                    (_, Some(f), _) if f.removed => "Removed".to_string(),
+                    (_, _, Some(seconds)) => format!("{} (stale {}s)", c.status, seconds),
+                    _ if app.idle.idle_for(&c.id).is_some() => format!("{}, idle", c.status),
+                    _ => c.status.clone(),
+                },
+                format_percent(&app.format, c.cpu_usage),
+                if c.memory_unlimited {
+                    "no limit".to_string()
+                } else {
+                    format_percent(&app.format, c.memory_percent())
+                },
+            ];
+            if app.extended_rows {
+                let io = app.io.rate(&c.id).unwrap_or_default();
+                let second = [
+                    c.image.clone(),
+                    format_ports(&c.ports),
+                    format!("{} {}", format_rate(&app.format, io.net_rx), format_rate(&app.format, io.net_tx)),
+                    format!("{} {}", format_rate(&app.format, io.block_read), format_rate(&app.format, io.block_write)),
+                ];
+                for (cell, second) in cells.iter_mut().zip(second) {
+                    cell.push('\n');
+                    cell.push_str(&second);
+                }
+            }
+            if let Some(meter) = &app.log_meter {
+                cells.push(match meter.rate(&c.id) {
+                    Some(rate) => format!(
+                        "{:.0} l, {}",
+                        rate.lines_per_sec,
+                        format_bytes(&app.format, rate.bytes_per_sec as u64)
+                    ),
+                    None => "-".to_string(),
+                });
+            }
+            if app.error_column {
+                let rate = app.error_meter.as_ref().and_then(|m| m.per_minute(&c.id));
+                cells.push(rate.map_or("-".to_string(), |rate| format!("{:.0}", rate)));
+            }
+            if app.memory_growth_column {
+                cells.push(app.trends.memory_per_minute(&c.id).map_or("-".to_string(), |growth| format_growth(&app.format, growth)));
+            }
+            if app.restart_column {
+                cells.push(app.trends.restarts_per_hour(&c.id).to_string());
+            }
+            if app.net_io_column {
+                cells.push(app.io.rate(&c.id).map_or("-".to_string(), |io| {
+                    format!("{} {}", format_rate(&app.format, io.net_rx), format_rate(&app.format, io.net_tx))
+                }));
+            }
+            if app.block_io_column {
+                cells.push(format!("{} {}", format_rate(&app.format, c.io.block_read as f64), format_rate(&app.format, c.io.block_write as f64)));
+            }
+            if !app.probes.is_empty() {
+                cells.push(app.probes.status(&c.id).map_or("-".to_string(), |s| s.label()));
+            }
+            if let Some(cost) = &app.cost {
+                cells.push(cost.hourly(c).map_or("-".to_string(), |amount| cost.format(amount)));
+            }
+            cells.extend(
+                (0..custom_columns.len())
+                    .map(|i| app.columns.value(i, &c.id).unwrap_or("…").to_string()),
+            );
+            if marking {
+                cells.insert(0, if app.marked.contains(&c.id) { "●" } else { "" }.to_string());
+            }
+            Row::new(cells).style(status_style).height(row_height)
+        });
+
+    // Grouped lists get a header row per section; the containers are
+    // already sorted by section, so the selection only shifts by the
+    // number of headers above it.
+    let mut rows: Vec<Row> = Vec::new();
+    let mut selected_row = app.selected_index;
+    let section = |c: &crate::backend::ContainerStats| match app.grouping {
+        Grouping::Off => None,
+        Grouping::Status => Some(StatusGroup::of(c).label().to_string()),
+        Grouping::Project => Some(c.project().unwrap_or("Not in a project").to_string()),
+        Grouping::Pod => Some(pods::pod_of(c).map_or("Not in a pod".to_string(), |(ns, pod)| format!("{}/{}", ns, pod))),
+    };
+    for (i, row) in container_rows.enumerate() {
+        let group = section(&app.containers[i]);
+        if group.is_some() && (i == 0 || section(&app.containers[i - 1]) != group) {
+            let members: Vec<_> = app.containers.iter().filter(|c| section(c) == group).collect();
+            let mut cells = section_header(app, group.as_deref().unwrap_or_default(), &members);
+            if marking {
+                cells.insert(0, String::new());
+            }
+            rows.push(
+                Row::new(cells)
+                    .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+            );
+            // A selected project header is the row above its first container.
+            if i < app.selected_index || (i == app.selected_index && app.selected_project().is_none()) {
+                selected_row += 1;
+            }
+        }
+        rows.push(row);
+    }
+
+    let stat_width = if app.extended_rows { 11 } else { 7 };
+    let mut widths = vec![
+        Constraint::Percentage(30),
+        Constraint::Percentage(30),
+        Constraint::Length(stat_width),
+        // Wide enough for "no limit".
+        Constraint::Length(stat_width.max(8)),
+    ];
+    if app.log_meter.is_some() {
+        widths.push(Constraint::Length(18));
+    }
+    if app.error_column {
+        widths.push(Constraint::Length(8));
+    }
+    if app.memory_growth_column {
+        widths.push(Constraint::Length(8));
+    }
+    if app.restart_column {
+        widths.push(Constraint::Length(6));
+    }
+    if app.net_io_column {
+        widths.push(Constraint::Length(11));
+    }
+    if app.block_io_column {
+        widths.push(Constraint::Length(11));
+    }
+    if !app.probes.is_empty() {
+        widths.push(Constraint::Length(10));
+    }
+    if app.cost.is_some() {
+        widths.push(Constraint::Length(9));
+    }
+    widths.extend(custom_columns.iter().map(|c| Constraint::Length(c.name.len().max(8) as u16)));
+    if marking {
+        widths.insert(0, Constraint::Length(1));
+    }
+
+    // The footer is a second table with the same widths so its totals line
+    // up under their columns.
+    let mut title = match &app.filter {
+        Some(filter) => format!(" Containers [{}] (↑/↓ to navigate, / to filter) ", filter),
+        None => " Containers (↑/↓ to navigate, / to filter) ".to_string(),
+    };
+    if app.sort != SortKey::Off {
+        title.push_str(&format!("sorted by {} ", app.sort.label()));
+    }
+    if marking {
+        title.push_str(&format!("{} marked, Esc to clear ", app.marked.len()));
+    }
+    if let Some(project) = app.selected_project() {
+        title.push_str(&format!("{}: s stop/start, r restart, d remove ", project));
+    }
+    let exceeded = app.budgets.exceeded();
+    if !exceeded.is_empty() {
+        title.push_str(&format!("Over budget: {} ", exceeded.join(", ")));
+    }
+    let saturated = app.bandwidth.saturated();
+    if !saturated.is_empty() {
+        title.push_str(&format!("At bandwidth cap: {} ", saturated.join(", ")));
+    }
+    let mut block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_type(tui::widgets::BorderType::Rounded);
+    if app.compare.as_ref().is_some_and(|c| !c.focused) {
+        block = block.border_style(Style::default().fg(Color::Yellow));
+    }
+    let table_area = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)].as_ref())
+        .split(block.inner(main_chunks[0]));
+    f.render_widget(block, main_chunks[0]);
+
+    let containers = Table::new(rows)
+        .header(header)
+        .widths(&widths)
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+    let mut state = TableState::default();
+    state.select(Some(selected_row));
+    f.render_stateful_widget(containers, table_area[0], &mut state);
+
+    let mut totals = totals_row(app);
+    if marking {
+        totals.insert(0, String::new());
+    }
+    let footer = Table::new(vec![Row::new(totals)])
+        .widths(&widths)
+        .style(Style::default().add_modifier(Modifier::BOLD));
+    f.render_widget(footer, table_area[1]);
+
+    if let Some(form) = &app.limits_form {
+        render_limits_form(f, form, main_chunks[1]);
+        return;
+    }
+    if let Some(form) = &app.recreate_form {
+        render_recreate_form(f, form, main_chunks[1]);
+        return;
+    }
+
+    // The second host takes the place of the details.
+    if let Some(compare) = &app.compare {
+        render_compare(f, app, compare, main_chunks[1]);
+        return;
+    }
+
+    // Container details with enhanced styling
+    if let Some(container) = app.containers.get(app.selected_index) {
+        let details = match app.details_tab {
+            DetailsTab::Info => {
+                let mut details = vec![
+                    format!("Container: {}", container.name),
+                    format!("Status: {}", container.status),
+                    format!("CPU Usage: {}", format_percent(&app.format, container.cpu_usage)),
+                    if container.memory_unlimited {
+                        format!(
+                            "Memory Usage: {}, no limit ({} of {} host memory)",
+                            format_bytes(&app.format, container.memory_usage),
+                            format_percent(&app.format, container.memory_percent()),
+                            format_bytes(&app.format, container.memory_limit)
+                        )
+                    } else {
+                        format!(
+                            "Memory Usage: {} ({} of {})",
+                            format_percent(&app.format, container.memory_percent()),
+                            format_bytes(&app.format, container.memory_usage),
+                            format_bytes(&app.format, container.memory_limit)
+                        )
+                    },
+                    format!("Created: {}", container.created),
+                ];
+                // All interfaces together; each is broken down under Network.
+                if let Some(io) = app.io.rate(&container.id) {
+                    details.insert(
+                        4,
+                        format!(
+                            "Network I/O: ↓ {} ↑ {} (total ↓ {} ↑ {})",
+                            format_bits(&app.format, io.net_rx),
+                            format_bits(&app.format, io.net_tx),
+                            format_bytes(&app.format, container.io.net_rx),
+                            format_bytes(&app.format, container.io.net_tx)
+                        ),
+                    );
+                    details.insert(
+                        5,
+                        format!(
+                            "Block I/O: read {}/s, write {}/s (total read {}, written {})",
+                            format_rate(&app.format, io.block_read),
+                            format_rate(&app.format, io.block_write),
+                            format_bytes(&app.format, container.io.block_read),
+                            format_bytes(&app.format, container.io.block_write)
+                        ),
+                    );
+                }
+                // The usage includes page cache, so it is broken down below it.
+                if let Some(memory) = container.memory_breakdown {
+                    details.insert(
+                        4,
+                        format!(
+                            "Memory: RSS {}, cache {}, working set {}",
+                            format_bytes(&app.format, memory.rss),
+                            format_bytes(&app.format, memory.cache),
+                            format_bytes(&app.format, memory.working_set)
+                        ),
+                    );
+                    let swap = memory.swap.map_or_else(|| "not reported by the daemon".to_string(), |swap| format_bytes(&app.format, swap));
+                    details.insert(5, format!("Swap: {}", swap));
+                }
+                if let Some(cost) = &app.cost {
+                    details.extend(cost_lines(app, cost, container));
+                }
+                if let Some(usage) = app.budgets.of(container) {
+                    let over = if usage.excess(&app.format).is_empty() { "" } else { " (over budget)" };
+                    details.push(format!("Budget of {}: {}{}", usage.budget.project, usage.summary(&app.format), over));
+                }
+                let caps = app.bandwidth.usage(container, &app.io);
+                if !caps.is_empty() {
+                    let at_cap = if caps.iter().any(|c| c.is_saturated()) { " (at cap)" } else { "" };
+                    let caps: Vec<String> = caps.iter().map(|c| c.describe(&app.format)).collect();
+                    details.push(format!("Bandwidth: {}{}", caps.join(", "), at_cap));
+                }
+                if let (Some(sampled_at), true) = (container.sampled_at, container.is_running()) {
+                    let age = (chrono::Utc::now().timestamp_millis() - sampled_at).max(0) as f64 / 1000.0;
+                    details.push(match container.sample_latency_ms {
+                        Some(latency) => format!("Sample: {:.1}s old, fetched in {} ms", age, latency),
+                        None => format!("Sample: {:.1}s old", age),
+                    });
+                }
+                let cores = app.cores.usage(&container.id);
+                if !cores.is_empty() {
+                    details.push(String::new());
+                    details.push("CPU cores:".to_string());
+                    let width = (cores.len() - 1).to_string().len();
+                    for (core, usage) in cores.iter().enumerate() {
+                        details.push(format!("  {:>width$} {} {}", core, bar(usage / 100.0, 20), format_percent(&app.format, *usage), width = width));
+                    }
+                }
+                let interfaces = app.io.interfaces(&container.id);
+                if !interfaces.is_empty() || !container.networks.is_empty() {
+                    details.push(String::new());
+                    details.push("Network:".to_string());
+                    let width = container.networks.iter().map(|n| n.network.len()).max().unwrap_or(0);
+                    for attachment in &container.networks {
+                        let addresses: Vec<&str> = attachment.addresses().collect();
+                        details.push(format!("  {:<width$}  {}", attachment.network, addresses.join(", "), width = width));
+                    }
+                    let width = interfaces.iter().map(|i| i.name.len()).max().unwrap_or(0);
+                    for interface in interfaces {
+                        details.push(format!(
+                            "  {:<width$}  ↓ {:>10}  ↑ {:>10}  (total ↓ {} ↑ {})",
+                            interface.name,
+                            format_bits(&app.format, interface.rx),
+                            format_bits(&app.format, interface.tx),
+                            format_bytes(&app.format, interface.rx_total),
+                            format_bytes(&app.format, interface.tx_total),
+                            width = width
+                        ));
+                    }
+                }
+                if !container.mounts.is_empty() {
+                    details.push(String::new());
+                    details.push("Mounts:".to_string());
+                    for mount in &container.mounts {
+                        let source = if mount.name.is_empty() { &mount.source } else { &mount.name };
+                        details.push(format!(
+                            "  {} {} -> {} ({})",
+                            mount.kind,
+                            source,
+                            mount.destination,
+                            if mount.read_write { "rw" } else { "ro" }
+                        ));
+                    }
+                }
+
+                if let Some(history) = app.timeline.as_ref().filter(|t| t.container_id == container.id) {
+                    let width = main_chunks[1].width.saturating_sub(2) as usize;
+                    let now = chrono::Utc::now().timestamp();
+                    details.push(String::new());
+                    details.push(format!("Last {}h (▲ start ▼ die ■ stop ↻ restart ! oom):", timeline::WINDOW / 3600));
+                    details.push(timeline::strip(&history.marks, now, width));
+                    details.push(format!("-{}h{:>pad$}", timeline::WINDOW / 3600, "now", pad = width.saturating_sub(3)));
+                    for mark in history.marks.iter().rev().take(5) {
+                        let time = chrono::DateTime::from_timestamp(mark.time, 0)
+                            .map(|t| t.with_timezone(&chrono::Local).format("%H:%M").to_string())
+                            .unwrap_or_default();
+                        let exit_code = mark.exit_code.as_ref().map(|c| format!(" (exit {})", c)).unwrap_or_default();
+                        details.push(format!("  {} {}{}", time, mark.transition.label(), exit_code));
+                    }
+                    if !history.startups.is_empty() {
+                        details.push(String::new());
+                        details.push("Recent startups (create → running → healthy):".to_string());
+                        for startup in history.startups.iter().rev().take(5) {
+                            let time = chrono::DateTime::from_timestamp(startup.time, 0)
+                                .map(|t| t.with_timezone(&chrono::Local).format("%H:%M").to_string())
+                                .unwrap_or_default();
+                            let mut phases = Vec::new();
+                            if let Some(seconds) = startup.running_after {
+                                phases.push(format!("running after {}s", seconds));
+                            }
+                            if let Some(seconds) = startup.healthy_after {
+                                phases.push(format!("healthy {}s later", seconds));
+                            }
+                            if phases.is_empty() {
+                                phases.push("started".to_string());
+                            }
+                            let image = startup.image.as_ref().map(|i| format!("  ({})", i)).unwrap_or_default();
+                            details.push(format!("  {} {}{}", time, phases.join(", "), image));
+                        }
+                    }
+                }
+                details
+            }
+            DetailsTab::Events => event_lines(app, container),
+        };
+
+        let tab_style = |tab| {
+            if app.details_tab == tab {
+                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::DarkGray)
+            }
+        };
+        let title = Spans::from(vec![
+            Span::raw(" "),
+            Span::styled("Details", tab_style(DetailsTab::Info)),
+            Span::raw(" | "),
+            Span::styled("Events", tab_style(DetailsTab::Events)),
+            Span::raw(" (Tab) "),
+        ]);
+        let details_text = details.join("\n");
+        let details_widget = Paragraph::new(details_text)
+            .block(
+                Block::default()
+                    .title(title)
+                    .borders(Borders::ALL)
+                    .border_type(tui::widgets::BorderType::Rounded)
+            );
+
+        // Recent CPU goes below the details, once there are two samples to
+        // draw a line between.
+        let cpu = app.cpu_history.of(&container.id);
+        if app.details_tab == DetailsTab::Info && cpu.len() > 1 && main_chunks[1].height > 20 {
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(0), Constraint::Length(8)].as_ref())
+                .split(main_chunks[1]);
+            f.render_widget(details_widget, chunks[0]);
+            // Scaled to the peak, in tens, so the bursts of a mostly idle
+            // container stand out.
+            let peak = cpu.iter().map(|(_, v)| *v).fold(0.0, f64::max);
+            let max = (peak / 10.0).ceil().max(1.0) * 10.0;
+            let minutes = (cpu.len() as f64 * app.refresh.as_secs_f64() / 60.0).ceil();
+            let title = format!(" CPU % over {}m ", minutes);
+            render_history(f, app.chart_style, chunks[1], &title, &cpu, max, Color::Cyan);
+        } else {
+            f.render_widget(details_widget, main_chunks[1]);
+        }
+    }
+}
+
+/// The second host's list, grouped and styled like the main one.
+fn render_compare<B: Backend>(f: &mut Frame<B>, app: &App, compare: &Compare, area: Rect) {
+    let mut block = Block::default()
+        .title(format!(" {} (←/→ to switch focus) ", compare.host))
+        .borders(Borders::ALL)
+        .border_type(tui::widgets::BorderType::Rounded);
+    if compare.focused {
+        block = block.border_style(Style::default().fg(Color::Yellow));
+    }
+    if let Some(error) = &compare.error {
+        let text = Paragraph::new(error.as_str())
+            .style(Style::default().fg(Color::Red))
+            .wrap(Wrap { trim: true });
+        f.render_widget(text.block(block), area);
+        return;
+    }
+
+    let section = |c: &crate::backend::ContainerStats| match app.grouping {
+        Grouping::Off => None,
+        Grouping::Status => Some(StatusGroup::of(c).label().to_string()),
+        Grouping::Project => Some(c.project().unwrap_or("Not in a project").to_string()),
+        Grouping::Pod => Some(pods::pod_of(c).map_or("Not in a pod".to_string(), |(ns, pod)| format!("{}/{}", ns, pod))),
+    };
+    let mut rows = Vec::new();
+    let mut selected_row = compare.selected_index;
+    for (i, c) in compare.containers.iter().enumerate() {
+        let group = section(c);
+        if group.is_some() && (i == 0 || section(&compare.containers[i - 1]) != group) {
+            let members: Vec<_> = compare.containers.iter().filter(|c| section(c) == group).collect();
+            rows.push(
+                Row::new(section_header(app, group.as_deref().unwrap_or_default(), &members))
+                    .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+            );
+            if i <= compare.selected_index {
+                selected_row += 1;
+            }
+        }
+        rows.push(
+            Row::new(vec![
+                c.name.clone(),
+                c.status.clone(),
+                format_percent(&app.format, c.cpu_usage),
+                if c.memory_unlimited {
+                    "no limit".to_string()
+                } else {
+                    format_percent(&app.format, c.memory_percent())
+                },
+            ])
+            .style(status_style(c)),
+        );
+    }
+    let header = Row::new(["Name", "Status", "CPU", "MEM"])
+        .style(Style::default().add_modifier(Modifier::BOLD));
+    let table = Table::new(rows)
+        .header(header)
+        .block(block)
+        .widths(&[Constraint::Percentage(30), Constraint::Percentage(30), Constraint::Length(7), Constraint::Length(8)])
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut state = TableState::default();
+    state.select(Some(selected_row));
+    f.render_stateful_widget(table, area, &mut state);
+}
+
+fn status_style(container: &crate::backend::ContainerStats) -> Style {
+    match StatusGroup::of(container) {
+        StatusGroup::Running => Style::default().fg(Color::Green),
+        StatusGroup::Paused => Style::default().fg(Color::Cyan),
+        StatusGroup::Exited => Style::default().fg(Color::Red),
+    }
+}
+
+/// The container's estimated cost, and its compose project's.
+fn cost_lines(app: &App, cost: &CostMeter, container: &crate::backend::ContainerStats) -> Vec<String> {
+    let per = |hourly: f64| format!("{}/h, {}/day", cost.format(hourly), cost.format(hourly * 24.0));
+    let mut lines = vec![format!("Cost: {}", cost.hourly(container).map_or("not seen running".to_string(), per))];
+    if let Some(project) = container.labels.get("com.docker.compose.project") {
+        let members = app
+            .containers
+            .iter()
+            .filter(|c| c.labels.get("com.docker.compose.project") == Some(project));
+        lines.push(format!("Project {}: {}", project, per(cost.total(members))));
+    }
+    lines
+}
+
+/// Header row of a section of the grouped list. Pods get their namespace
+/// under STATUS, as their names alone fill the first column; pods and
+/// projects get their aggregate CPU and memory under those columns.
+fn section_header(app: &App, label: &str, members: &[&crate::backend::ContainerStats]) -> Vec<String> {
+    let running = members.iter().filter(|c| c.is_running()).count();
+    let (name, status) = match (app.grouping, pods::pod_of(members[0]), members[0].project()) {
+        (Grouping::Pod, Some((namespace, pod)), _) => (
+            format!("── {}", pod),
+            format!("{}, {}/{} running", namespace, running, members.len()),
+        ),
+        (Grouping::Project, _, Some(project)) => (
+            format!("── {}", project),
+            format!("{}/{} running", running, members.len()),
+        ),
+        _ => return vec![format!("── {} ({})", label, members.len())],
+    };
+    vec![
+        name,
+        status,
+        format_percent(&app.format, members.iter().fold(0.0, |sum, c| sum + c.cpu_usage)),
+        format_rate(&app.format, members.iter().map(|c| c.memory_usage).sum::<u64>() as f64),
+    ]
+}
+
+/// Sums over the listed containers, leaving out those fading out since
+/// their numbers are no longer live.
+fn totals_row(app: &App) -> Vec<String> {
+    let live: Vec<_> = app.containers.iter().filter(|c| !app.fading.contains_key(&c.id)).collect();
+    // Folded from 0.0, as summing f64s starts at -0.0.
+    let cpu = live.iter().fold(0.0, |sum, c| sum + c.cpu_usage);
+    let memory: u64 = live.iter().map(|c| c.memory_usage).sum();
+    let (rx, tx) = live
+        .iter()
+        .filter_map(|c| app.io.rate(&c.id))
+        .fold((0.0, 0.0), |(rx, tx), rate| (rx + rate.net_rx, tx + rate.net_tx));
+    let mut cells = vec![
+        format!("Total ({})", live.len()),
+        format!("Net ↓{} ↑{}", format_rate(&app.format, rx), format_rate(&app.format, tx)),
+        format_percent(&app.format, cpu),
+        format_rate(&app.format, memory as f64),
+    ];
+    if let Some(meter) = &app.log_meter {
+        let (lines, bytes) = live
+            .iter()
+            .filter_map(|c| meter.rate(&c.id))
+            .fold((0.0, 0.0), |(lines, bytes), rate| (lines + rate.lines_per_sec, bytes + rate.bytes_per_sec));
+        cells.push(format!("{:.0} l, {}", lines, format_bytes(&app.format, bytes as u64)));
+    }
+    if app.error_column {
+        let errors = live
+            .iter()
+            .filter_map(|c| app.error_meter.as_ref()?.per_minute(&c.id))
+            .fold(0.0, |sum, rate| sum + rate);
+        cells.push(format!("{:.0}", errors));
+    }
+    if app.memory_growth_column {
+        let growth = live
+            .iter()
+            .filter_map(|c| app.trends.memory_per_minute(&c.id))
+            .fold(0.0, |sum, rate| sum + rate);
+        cells.push(format_growth(&app.format, growth));
+    }
+    if app.restart_column {
+        let restarts: usize = live.iter().map(|c| app.trends.restarts_per_hour(&c.id)).sum();
+        cells.push(restarts.to_string());
+    }
+    if app.net_io_column {
+        cells.push(format!("{} {}", format_rate(&app.format, rx), format_rate(&app.format, tx)));
+    }
+    if app.block_io_column {
+        let (read, write) = live
+            .iter()
+            .fold((0, 0), |(read, write), c| (read + c.io.block_read, write + c.io.block_write));
+        cells.push(format!("{} {}", format_rate(&app.format, read as f64), format_rate(&app.format, write as f64)));
+    }
+    if let Some(cost) = &app.cost {
+        // Nothing to total under APP.
+        if !app.probes.is_empty() {
+            cells.push(String::new());
+        }
+        cells.push(cost.format(cost.total(live)));
+    }
+    cells
+}
+
+/// Recent daemon events for the container, newest first.
+fn event_lines(app: &App, container: &crate::backend::ContainerStats) -> Vec<String> {
+    let Some(history) = app.timeline.as_ref().filter(|t| t.container_id == container.id) else {
+        return vec!["Loading…".to_string()];
+    };
+    if history.events.is_empty() {
+        return vec![format!("No events in the last {}h.", timeline::WINDOW / 3600)];
+    }
+    history
+        .events
+        .iter()
+        .rev()
+        .map(|event| {
+            let time = chrono::DateTime::from_timestamp(event.time, 0)
+                .map(|t| t.with_timezone(&chrono::Local).format("%H:%M:%S").to_string())
+                .unwrap_or_default();
+            let mut line = format!("{} {}", time, event.action);
+            for key in ["exitCode", "signal", "execID"] {
+                if let Some(value) = event.attributes.get(key) {
+                    line.push_str(&format!(" {}={}", key, value));
+                }
+            }
+            line
+        })
+        .collect()
+}
+
+/// Dependency tree: each container with what has to be up before it.
+fn render_dependencies<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let dependencies = graph::dependencies(&app.containers, &app.links);
+    let (tree, independent) = graph::tree(&app.containers, &dependencies);
+
+    let mut lines: Vec<Spans> = tree
+        .iter()
+        .map(|line| {
+            let container = &app.containers[line.container];
+            let style = if line.problem {
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+            } else if container.is_running() {
+                Style::default().fg(Color::Green)
+            } else {
+                Style::default().fg(Color::DarkGray)
+            };
+            let mut spans = vec![
+                Span::raw(line.prefix.clone()),
+                Span::styled(container.name.clone(), style),
+            ];
+            if let Some(kind) = line.kind {
+                spans.push(Span::styled(format!(" ({})", kind.label()), Style::default().fg(Color::DarkGray)));
+            }
+            if line.problem {
+                spans.push(Span::styled(" not running", style));
+            }
+            if line.cycle {
+                spans.push(Span::styled(" ↻ cycle", Style::default().fg(Color::Yellow)));
+            }
+            Spans::from(spans)
+        })
+        .collect();
+
+    if tree.is_empty() {
+        lines.push(Spans::from("No dependencies between containers found."));
+    }
+    if !independent.is_empty() {
+        let names: Vec<&str> = independent.iter().map(|&i| app.containers[i].name.as_str()).collect();
+        lines.push(Spans::from(""));
+        lines.push(Spans::from(Span::styled(
+            format!("Independent: {}", names.join(", ")),
+            Style::default().fg(Color::DarkGray),
+        )));
+    }
+
+    let widget = Paragraph::new(lines).scroll((app.screen_scroll, 0)).block(
+        Block::default()
+            .title(" Dependencies (depends_on, links, network_mode) ")
+            .borders(Borders::ALL)
+            .border_type(tui::widgets::BorderType::Rounded),
+    );
+    f.render_widget(widget, area);
+}
+
+/// Network-centric tree: every network with the containers attached to it.
+fn render_networks<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let dim = Style::default().fg(Color::DarkGray);
+    let mut lines: Vec<Spans> = Vec::new();
+
+    for network in &app.networks {
+        let name = network.name.clone().unwrap_or_default();
+        let subnets: Vec<String> = network
+            .ipam
+            .iter()
+            .flat_map(|ipam| ipam.config.iter().flatten())
+            .filter_map(|config| config.subnet.clone())
+            .collect();
+        let mut description = network.driver.clone().unwrap_or_default();
+        if !subnets.is_empty() {
+            description = format!("{}, {}", description, subnets.join(", "));
+        }
+        if network.enable_ipv6 == Some(true) {
+            description.push_str(", IPv6");
+        }
+        if network.internal == Some(true) {
+            description.push_str(", internal");
+        }
+        lines.push(Spans::from(vec![
+            Span::styled(name.clone(), Style::default().add_modifier(Modifier::BOLD)),
+            Span::styled(format!(" ({})", description), dim),
+        ]));
+
+        let attached: Vec<_> = app
+            .containers
+            .iter()
+            .filter_map(|c| Some((c, c.networks.iter().find(|n| n.network == name)?)))
+            .collect();
+        if attached.is_empty() {
+            lines.push(Spans::from(Span::styled("    (no containers)", dim)));
+        }
+        for (i, (container, attachment)) in attached.iter().enumerate() {
+            let branch = if i + 1 == attached.len() { "└── " } else { "├── " };
+            let style = if container.is_running() { Style::default().fg(Color::Green) } else { dim };
+            let mut spans = vec![
+                Span::raw(branch),
+                Span::styled(container.name.clone(), style),
+                Span::styled(format!("  {}", attachment.addresses().collect::<Vec<_>>().join(", ")), dim),
+            ];
+            // Containers on several networks are the bridges between them.
+            let others: Vec<&str> = container
+                .networks
+                .iter()
+                .map(|n| n.network.as_str())
+                .filter(|n| *n != name)
+                .collect();
+            if !others.is_empty() {
+                spans.push(Span::styled(
+                    format!("  ↔ {}", others.join(", ")),
+                    Style::default().fg(Color::Cyan),
+                ));
+            }
+            lines.push(Spans::from(spans));
+        }
+        lines.push(Spans::from(""));
+    }
+
+    // Sharing another container's stack (network_mode: container:) or no
+    // network at all both leave a container out of the tree above.
+    for container in app.containers.iter().filter(|c| c.networks.is_empty()) {
+        let detail = match container.network_mode.strip_prefix("container:") {
+            Some(target) => {
+                let owner = app
+                    .containers
+                    .iter()
+                    .find(|c| c.id.starts_with(target) || c.name == target)
+                    .map_or(target, |c| c.name.as_str());
+                format!("shares the network stack of {}", owner)
+            }
+            None => "isolated, no network".to_string(),
+        };
+        lines.push(Spans::from(vec![
+            Span::styled(container.name.clone(), Style::default().fg(Color::Yellow)),
+            Span::styled(format!("  {}", detail), dim),
+        ]));
+    }
+
+    let widget = Paragraph::new(lines).scroll((app.screen_scroll, 0)).block(
+        Block::default()
+            .title(" Networks ")
+            .borders(Borders::ALL)
+            .border_type(tui::widgets::BorderType::Rounded),
+    );
+    f.render_widget(widget, area);
+}
+
+/// Every volume with the containers mounting it and where; unmounted
+/// volumes are flagged as prune candidates.
+fn render_volumes<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let dim = Style::default().fg(Color::DarkGray);
+    let mut lines: Vec<Spans> = Vec::new();
+
+    for volume in &app.volumes {
+        let users: Vec<_> = app
+            .containers
+            .iter()
+            .flat_map(|c| {
+                c.mounts
+                    .iter()
+                    .filter(|m| m.kind == "volume" && m.name == volume.name)
+                    .map(move |m| (c, m))
+            })
+            .collect();
+
+        let mut header = vec![
+            Span::styled(volume.name.clone(), Style::default().add_modifier(Modifier::BOLD)),
+            Span::styled(format!(" ({})", volume.driver), dim),
+        ];
+        if users.is_empty() {
+            header.push(Span::styled("  unused", Style::default().fg(Color::Yellow)));
+        } else if users.iter().all(|(c, _)| !c.is_running()) {
+            header.push(Span::styled("  only stopped containers", Style::default().fg(Color::Yellow)));
+        }
+        lines.push(Spans::from(header));
+
+        for (i, (container, mount)) in users.iter().enumerate() {
+            let branch = if i + 1 == users.len() { "└── " } else { "├── " };
+            let style = if container.is_running() { Style::default().fg(Color::Green) } else { dim };
+            lines.push(Spans::from(vec![
+                Span::raw(branch),
+                Span::styled(container.name.clone(), style),
+                Span::styled(
+                    format!("  {} ({})", mount.destination, if mount.read_write { "rw" } else { "ro" }),
+                    dim,
+                ),
+            ]));
+        }
+    }
+    if app.volumes.is_empty() {
+        lines.push(Spans::from("No volumes."));
+    }
+
+    let widget = Paragraph::new(lines).scroll((app.screen_scroll, 0)).block(
+        Block::default()
+            .title(" Volumes ")
+            .borders(Borders::ALL)
+            .border_type(tui::widgets::BorderType::Rounded),
+    );
+    f.render_widget(widget, area);
+}
+
+/// Every image with the containers created from it, running or not;
+/// images no container uses are flagged as safe to delete.
+fn render_images<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let dim = Style::default().fg(Color::DarkGray);
+    let mut lines: Vec<Spans> = Vec::new();
+
+    for image in &app.images {
+        let users: Vec<_> = app.containers.iter().filter(|c| c.uses_image(image)).collect();
+        let name = if image.repo_tags.is_empty() {
+            format!("<none> {}", short_id(&image.id))
+        } else {
+            image.repo_tags.join(", ")
+        };
+
+        let mut header = vec![Span::styled(name, Style::default().add_modifier(Modifier::BOLD))];
+        if image.size > 0 {
+            header.push(Span::styled(format!(" ({})", format_bytes(&app.format, image.size as u64)), dim));
+        }
+        if users.is_empty() {
+            header.push(Span::styled("  unused", Style::default().fg(Color::Yellow)));
+        } else if users.iter().all(|c| !c.is_running()) {
+            header.push(Span::styled("  only stopped containers", Style::default().fg(Color::Yellow)));
+        }
+        lines.push(Spans::from(header));
+
+        for (i, container) in users.iter().enumerate() {
+            let branch = if i + 1 == users.len() { "└── " } else { "├── " };
+            let style = if container.is_running() { Style::default().fg(Color::Green) } else { dim };
+            lines.push(Spans::from(vec![
+                Span::raw(branch),
+                Span::styled(container.name.clone(), style),
+                Span::styled(format!("  {}", container.status), dim),
+            ]));
+        }
+    }
+    if app.images.is_empty() {
+        lines.push(Spans::from("No images."));
+    }
+
+    let widget = Paragraph::new(lines).scroll((app.screen_scroll, 0)).block(
+        Block::default()
+            .title(" Images ")
+            .borders(Borders::ALL)
+            .border_type(tui::widgets::BorderType::Rounded),
+    );
+    f.render_widget(widget, area);
+}
+
+/// Orphans grouped by category, each with a checkbox for the cleanup key.
+fn render_orphans<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let dim = Style::default().fg(Color::DarkGray);
+    let mut lines: Vec<Spans> = Vec::new();
+
+    for (i, kind) in OrphanKind::ALL.into_iter().enumerate() {
+        let orphans: Vec<_> = app.orphans.iter().filter(|o| o.kind == kind).collect();
+        let checkbox = if app.orphan_selection.contains(&kind) { "[x] " } else { "[ ] " };
+        let mut style = Style::default().add_modifier(Modifier::BOLD);
+        if i == app.orphan_cursor {
+            style = style.bg(Color::DarkGray);
+        }
+        let mut title = format!("{}{} ({})", checkbox, kind.label(), orphans.len());
+        if kind == OrphanKind::StoppedContainer {
+            title.push_str(&format!(", older than {} days", app.stopped_days));
+        }
+        lines.push(Spans::from(Span::styled(title, style)));
+        for orphan in orphans {
+            lines.push(Spans::from(vec![
+                Span::raw(format!("    {}", orphan.name)),
+                Span::styled(format!("  {}", orphan.detail), dim),
+            ]));
+        }
+        lines.push(Spans::from(""));
+    }
+
+    let widget = Paragraph::new(lines).block(
+        Block::default()
+            .title(" Orphans (Space: select category, x: remove selected) ")
+            .borders(Borders::ALL)
+            .border_type(tui::widgets::BorderType::Rounded),
+    );
+    f.render_widget(widget, area);
+}
+
+/// Docker's disk usage by category over the session, with the current
+/// figures and growth since the first sample above the chart.
+/// Idle containers with the memory they hold, longest idle first.
+fn render_idle<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let config = app.idle.config();
+    let candidates = app.idle.candidates(&app.containers);
+    let dim = Style::default().fg(Color::DarkGray);
+    let mut lines = vec![
+        Spans::from(Span::styled(
+            format!(
+                "Running for {} minutes or more below {}% CPU, {}/s network and {}/s disk I/O.",
+                config.minutes,
+                config.cpu_percent,
+                format_rate(&app.format, config.net_bytes_per_second),
+                format_rate(&app.format, config.disk_bytes_per_second),
+            ),
+            dim,
+        )),
+        Spans::from(""),
+    ];
+    if candidates.is_empty() {
+        lines.push(Spans::from("No idle containers."));
+    } else {
+        let memory: u64 = candidates.iter().map(|(c, _)| c.memory_usage).sum();
+        lines.push(Spans::from(Span::styled(
+            format!("{} idle, holding {} of memory", candidates.len(), format_bytes(&app.format, memory)),
+            Style::default().add_modifier(Modifier::BOLD),
+        )));
+        let width = candidates.iter().map(|(c, _)| c.name.len()).max().unwrap_or(0);
+        for (container, idle) in candidates {
+            let minutes = idle.as_secs() / 60;
+            lines.push(Spans::from(vec![
+                Span::raw(format!(
+                    "  {:<width$}  idle {:>2}h {:02}m  {:>10}",
+                    container.name,
+                    minutes / 60,
+                    minutes % 60,
+                    format_bytes(&app.format, container.memory_usage),
+                    width = width
+                )),
+                Span::styled(format!("  {}", container.image), dim),
+            ]));
+        }
+    }
+    let widget = Paragraph::new(lines).scroll((app.screen_scroll, 0)).block(
+        Block::default()
+            .title(" Idle containers ")
+            .borders(Borders::ALL)
+            .border_type(tui::widgets::BorderType::Rounded),
+    );
+    f.render_widget(widget, area);
+}
+
+/// The stored registry logins, marking the one each registry's pulls use.
+fn render_registries<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let dim = Style::default().fg(Color::DarkGray);
+    let mut lines = vec![
+        Spans::from(Span::styled(
+            "Logins stored by the Docker client. The images dockertop pulls use the ● login of their registry.",
+            dim,
+        )),
+        Spans::from(""),
+    ];
+    if app.registries.is_empty() {
+        lines.push(Spans::from("No stored logins."));
+    }
+    let hosts: Vec<String> = app.registries.iter().map(|l| registries::registry_host(&l.server)).collect();
+    let host_width = hosts.iter().map(|h| h.len()).max().unwrap_or(0);
+    let server_width = app.registries.iter().map(|l| l.server.len()).max().unwrap_or(0);
+    let user_width = app.registries.iter().map(|l| l.username.len()).max().unwrap_or(0);
+    let chosen = app.chosen_logins();
+    for (i, (login, host)) in app.registries.iter().zip(&hosts).enumerate() {
+        let used = registries::pull_login(host, &app.registries, &chosen) == Some(login);
+        let mut style = Style::default();
+        if i == app.registry_cursor {
+            style = style.bg(Color::DarkGray);
+        }
+        lines.push(Spans::from(vec![
+            Span::styled(if used { "● " } else { "  " }, style.fg(Color::Green)),
+            Span::styled(
+                format!(
+                    "{:<host_width$}  {:<server_width$}  {:<user_width$}  ",
+                    host, login.server, login.username
+                ),
+                style,
+            ),
+            Span::styled(format!("in {}", login.store), style.fg(Color::DarkGray)),
+        ]));
+    }
+    let widget = Paragraph::new(lines).block(
+        Block::default()
+            .title(" Registries (↑/↓: Select  Enter: Use for pulls  i: Log in  o: Log out) ")
+            .borders(Borders::ALL)
+            .border_type(tui::widgets::BorderType::Rounded),
+    );
+    f.render_widget(widget, area);
+}
+
+fn render_files<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let Some(browser) = &app.files else {
+        return;
+    };
+    let height = area.height.saturating_sub(2) as usize;
+    let mut lines = Vec::new();
+    if let Some(error) = &browser.error {
+        lines.push(Spans::from(Span::styled(error.clone(), Style::default().fg(Color::Red))));
+    }
+    let title = if let Some(view) = &browser.viewing {
+        lines.extend(view.lines.iter().skip(view.scroll as usize).map(|l| Spans::from(l.as_str())));
+        format!(" {}: {} (↑/↓: Scroll  ←/Esc: Back) ", browser.container.name, view.path)
+    } else {
+        if browser.entries.is_empty() {
+            lines.push(Spans::from(Span::styled("Empty directory", Style::default().fg(Color::DarkGray))));
+        }
+        let names: Vec<String> = browser
+            .entries
+            .iter()
+            .map(|e| if e.kind == EntryKind::Directory { format!("{}/", e.name) } else { e.name.clone() })
+            .collect();
+        let width = names.iter().map(|n| n.chars().count()).max().unwrap_or(0);
+        let shown = height.saturating_sub(lines.len()).max(1);
+        let offset = browser.cursor.saturating_sub(shown - 1);
+        for (i, (entry, name)) in browser.entries.iter().zip(&names).enumerate().skip(offset).take(shown) {
+            let mut style = match entry.kind {
+                EntryKind::Directory => Style::default().fg(Color::Blue),
+                EntryKind::File => Style::default(),
+                EntryKind::Other => Style::default().fg(Color::DarkGray),
+            };
+            if i == browser.cursor {
+                style = style.bg(Color::DarkGray);
+            }
+            let modified = chrono::DateTime::from_timestamp(entry.modified, 0)
+                .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_default();
+            let size = if entry.kind == EntryKind::Directory { String::new() } else { format_bytes(&app.format, entry.size) };
+            lines.push(Spans::from(Span::styled(
+                format!("{:<width$}  {:>10}  {}", name, size, modified, width = width),
+                style,
+            )));
+        }
+        format!(
+            " Files of {}: {} (Enter: Open  ←: Up  Esc: Close) ",
+            browser.container.name, browser.path
+        )
+    };
+    let widget = Paragraph::new(lines).block(
+        Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_type(tui::widgets::BorderType::Rounded),
+    );
+    f.render_widget(Clear, area);
+    f.render_widget(widget, area);
+}
+
+fn render_disk<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(4), Constraint::Min(0)].as_ref())
+        .split(area);
+
+    let summary = match app.disk.latest() {
+        Some(usage) => {
+            let growth = app.disk.growth();
+            vec![
+                Spans::from(format!(
+                    "Total {}  (images {}, containers {}, volumes {}, build cache {})",
+                    format_bytes(&app.format, usage.total()),
+                    format_bytes(&app.format, usage.images),
+                    format_bytes(&app.format, usage.containers),
+                    format_bytes(&app.format, usage.volumes),
+                    format_bytes(&app.format, usage.build_cache),
+                )),
+                Spans::from(Span::styled(
+                    format!(
+                        "{}{} since the first sample",
+                        if growth < 0 { "-" } else { "+" },
+                        format_bytes(&app.format, growth.unsigned_abs())
+                    ),
+                    Style::default().fg(if growth > 0 { Color::Yellow } else { Color::Green }),
+                )),
+            ]
+        }
+        None => vec![Spans::from("Waiting for the first sample…")],
+    };
+    f.render_widget(
+        Paragraph::new(summary).block(
+            Block::default()
+                .title(" Disk usage ")
+                .borders(Borders::ALL)
+                .border_type(tui::widgets::BorderType::Rounded),
+        ),
+        chunks[0],
+    );
+
+    let series = [
+        ("total", Color::White),
+        ("images", Color::Cyan),
+        ("containers", Color::Magenta),
+        ("volumes", Color::Yellow),
+    ];
+    let mut points: [Vec<(f64, f64)>; 4] = Default::default();
+    for (minute, usage) in &app.disk.samples {
+        let values = [usage.total(), usage.images, usage.containers, usage.volumes];
+        for (series, value) in points.iter_mut().zip(values) {
+            series.push((*minute, value as f64));
+        }
+    }
+    let datasets = series
+        .iter()
+        .zip(&points)
+        .map(|((name, color), data)| {
+            Dataset::default()
+                .name(*name)
+                .marker(symbols::Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(*color))
+                .data(data)
+        })
+        .collect();
+
+    let max_minute = app.disk.samples.back().map_or(1.0, |(m, _)| m.max(1.0));
+    let max_bytes = app.disk.latest().map_or(1, |u| u.total()).max(1) as f64 * 1.1;
+    let chart = Chart::new(datasets)
+        .block(
+            Block::default()
+                .title(" Trend ")
+                .borders(Borders::ALL)
+                .border_type(tui::widgets::BorderType::Rounded),
+        )
+        .x_axis(
+            Axis::default()
+                .title("minutes")
+                .bounds([0.0, max_minute])
+                .labels(vec![Span::raw("0"), Span::raw(format!("{:.0}", max_minute))]),
+        )
+        .y_axis(
+            Axis::default()
+                .bounds([0.0, max_bytes])
+                .labels(vec![Span::raw("0"), Span::raw(format_bytes(&app.format, max_bytes as u64))]),
+        );
+    f.render_widget(chart, chunks[1]);
+}
+
+/// A time series of `(x, y)` points from 0 to `max`, drawn in `style`.
+fn render_history<B: Backend>(f: &mut Frame<B>, style: ChartStyle, area: Rect, title: &str, data: &[(f64, f64)], max: f64, color: Color) {
+    let first = data.first().map_or(0.0, |(x, _)| *x);
+    let last = data.last().map_or(1.0, |(x, _)| *x).max(first + 1.0);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(tui::widgets::BorderType::Rounded);
+    if style == ChartStyle::Block {
+        // Without axes, the scale goes in the title.
+        let width = area.width.saturating_sub(2) as usize;
+        let bars: Vec<u64> = resample(data, first, last, width).iter().map(|y| (y * 100.0).round() as u64).collect();
+        let sparkline = Sparkline::default()
+            .block(block.title(format!("{}(max {:.0}) ", title, max)))
+            .style(Style::default().fg(color))
+            .max((max * 100.0) as u64)
+            .data(&bars);
+        f.render_widget(sparkline, area);
+        return;
+    }
+    let filled;
+    let (points, graph_type) = if style == ChartStyle::Braille {
+        // Two dots across and four down per cell; the y-axis labels take a
+        // few columns, which only makes the fill denser.
+        let (columns, rows) = (area.width.saturating_sub(2) as usize * 2, area.height.saturating_sub(2) as usize * 4);
+        let step = max / rows.max(1) as f64;
+        filled = resample(data, first, last, columns)
+            .into_iter()
+            .enumerate()
+            .flat_map(|(i, y)| {
+                let x = first + (last - first) * i as f64 / columns.saturating_sub(1).max(1) as f64;
+                (0..=(y.min(max) / step) as usize).map(move |dot| (x, dot as f64 * step))
+            })
+            .collect::<Vec<_>>();
+        (filled.as_slice(), GraphType::Scatter)
+    } else {
+        (data, GraphType::Line)
+    };
+    let dataset = Dataset::default()
+        .marker(symbols::Marker::Braille)
+        .graph_type(graph_type)
+        .style(Style::default().fg(color))
+        .data(points);
+    let chart = Chart::new(vec![dataset])
+        .block(block.title(title.to_string()))
+        .x_axis(Axis::default().bounds([first, last]))
+        .y_axis(
+            Axis::default()
+                .bounds([0.0, max])
+                .labels(vec![Span::raw("0"), Span::raw(format!("{:.0}", max))]),
+        );
+    f.render_widget(chart, area);
+}
+
+/// `data` as `columns` values evenly spaced from `first` to `last`, each
+/// interpolated between the points either side; empty without points.
+fn resample(data: &[(f64, f64)], first: f64, last: f64, columns: usize) -> Vec<f64> {
+    if data.is_empty() {
+        return Vec::new();
+    }
+    (0..columns)
+        .map(|i| {
+            let x = first + (last - first) * i as f64 / columns.saturating_sub(1).max(1) as f64;
+            match data.iter().position(|(px, _)| *px >= x) {
+                Some(0) => data[0].1,
+                Some(after) => {
+                    let ((x0, y0), (x1, y1)) = (data[after - 1], data[after]);
+                    y0 + (y1 - y0) * (x - x0) / (x1 - x0).max(f64::EPSILON)
+                }
+                None => data[data.len() - 1].1,
+            }
+        })
+        .collect()
+}
+
+/// The `dockertop watch` dashboard: header, CPU and memory charts, then
+/// processes beside the log tail.
+fn render_watch<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let Some(watch) = &app.watch else {
+        return;
+    };
+    let rounded = |title: String| {
+        Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_type(tui::widgets::BorderType::Rounded)
+    };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Percentage(40), Constraint::Min(0)].as_ref())
+        .split(area);
+
+    let header = match &watch.container {
+        Some(c) => {
+            let mut text = format!("{}  {}  {}  {}", c.name, c.status, c.image, &c.id[..c.id.len().min(12)]);
+            if watch.recreations > 0 {
+                text.push_str(&format!("  (recreated {}×)", watch.recreations));
+            }
+            text
+        }
+        None => format!("Waiting for a container named {}…", watch.name),
+    };
+    f.render_widget(Paragraph::new(header).block(rounded(" Watch ".to_string())), chunks[0]);
+
+    let charts = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+        .split(chunks[1]);
+    let cpu: Vec<(f64, f64)> = watch.cpu.iter().copied().collect();
+    let memory: Vec<(f64, f64)> = watch.memory.iter().copied().collect();
+    // CPU can exceed 100% on multi-core hosts.
+    let cpu_max = cpu.iter().map(|(_, v)| *v).fold(100.0, f64::max);
+    let memory_title = match &watch.container {
+        Some(c) if c.memory_unlimited => " Memory % of host (no limit) ",
+        _ => " Memory % ",
+    };
+    for (area, title, data, max, color) in [
+        (charts[0], " CPU % ", &cpu, cpu_max, Color::Cyan),
+        (charts[1], memory_title, &memory, 100.0, Color::Magenta),
+    ] {
+        render_history(f, app.chart_style, area, title, data, max, color);
+    }
+
+    let bottom = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(40), Constraint::Percentage(60)].as_ref())
+        .split(chunks[2]);
+    let titles = watch.processes.titles.clone().unwrap_or_default();
+    let column = |name: &str| titles.iter().position(|t| t == name);
+    let (pid, cmd) = (column("PID"), column("CMD").or_else(|| column("COMMAND")));
+    let processes: Vec<Spans> = watch
+        .processes
+        .processes
+        .iter()
+        .flatten()
+        .map(|p| {
+            let field = |i: Option<usize>| i.and_then(|i| p.get(i)).cloned().unwrap_or_default();
+            Spans::from(format!("{:>7} {}", field(pid), field(cmd)))
+        })
+        .collect();
+    f.render_widget(Paragraph::new(processes).block(rounded(" Processes ".to_string())), bottom[0]);
+
+    let log_lines = watch.recent_logs(bottom[1].height.saturating_sub(2) as usize);
+    let logs: Vec<Spans> = log_lines
+        .iter()
+        .map(|l| Spans::from(Span::styled(l.as_str(), log_style(app, l))))
+        .collect();
+    f.render_widget(Paragraph::new(logs).block(rounded(" Logs ".to_string())), bottom[1]);
+}
+
+/// `sha256:0123abcd...` -> `0123abcd4567`.
+fn short_id(id: &str) -> &str {
+    let id = id.trim_start_matches("sha256:");
+    &id[..id.len().min(12)]
+}
+
+/// The shown exec or log session, with the line being typed at the bottom
+/// of exec sessions.
+fn render_session<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let sessions = &app.sessions;
+    let (Some(index), Some(session)) = (sessions.active, sessions.active()) else {
+        return;
+    };
+    let mut title = format!(" {} [{}/{}] ", session.title(), index + 1, sessions.list.len());
+    if !session.is_alive() {
+        title.push_str("(ended) ");
+    }
+    if session.scroll > 0 {
+        title.push_str(&format!("(scrolled {} up) ", session.scroll));
+    }
+    let interactive = matches!(session.kind, SessionKind::Exec | SessionKind::Attach { stdin: true });
+    let logs = session.kind == SessionKind::Logs;
+    if logs {
+        title.push_str(&format!("[json: {}] ", session.json_view.label()));
+        if let Some(filter) = &session.filter {
+            title.push_str(&format!("[{}] ", filter));
+        }
+    }
+    title.push_str("Esc: Back  Ctrl-N/P: Next/Prev  Ctrl-S: Sessions  PgUp/PgDn: Scroll ");
+    if logs {
+        title.push_str("j: JSON View  f: Filter ");
+    }
+    if let SessionKind::Attach { .. } = session.kind {
+        title.push_str("Ctrl-Q: Detach ");
+    }
+
+    let height = area.height.saturating_sub(if interactive { 3 } else { 2 }) as usize;
+    let mut lines: Vec<Spans> = session
+        .visible(height)
+        .into_iter()
+        .map(|line| match session.kind {
+            SessionKind::Logs => Spans::from(Span::styled(line.clone(), log_style(app, &line))),
+            SessionKind::Exec | SessionKind::Attach { .. } => Spans::from(line),
+            SessionKind::Tail => match line.split_once(TAIL_SEPARATOR) {
+                Some((name, text)) => Spans::from(vec![
+                    Span::styled(format!("{}{}", name, TAIL_SEPARATOR), Style::default().fg(container_color(name))),
+                    Span::styled(text.to_string(), log_style(app, text)),
+                ]),
+                None => Spans::from(line),
+            },
+        })
+        .collect();
+    if interactive {
+        lines.resize(height, Spans::default());
+        lines.push(Spans::from(vec![
+            Span::styled("$ ", Style::default().fg(Color::Green)),
+            Span::raw(format!("{}█", session.input)),
+        ]));
+    }
+    let widget = Paragraph::new(lines).block(
+        Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_type(tui::widgets::BorderType::Rounded)
+            .border_style(Style::default().fg(Color::Green)),
+    );
+    f.render_widget(Clear, area);
+    f.render_widget(widget, area);
+}
+
+fn render_log_view<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let Some(view) = &app.log_view else {
+        return;
+    };
+    let height = area.height.saturating_sub(2) as usize;
+    let dim = Style::default().fg(Color::DarkGray);
+    let match_style = Style::default().fg(Color::Black).bg(Color::Yellow);
+    // Merged, each line starts with its container's name in a color of its own.
+    let width = view.containers.iter().map(|c| c.name.chars().count()).max().unwrap_or(0);
+    let prefix = |i: usize| {
+        let source = view.sources[i];
+        let text = format!("{:<width$} | ", view.containers[source].name);
+        Span::styled(text, Style::default().fg(LOG_SOURCE_COLORS[source % LOG_SOURCE_COLORS.len()]))
+    };
+    let mut lines: Vec<Spans> = view
+        .visible_indices(height)
+        .into_iter()
+        .map(|i| {
+            let line = &view.lines[i];
+            let text = line.text.as_str();
+            let mut spans = Vec::new();
+            if view.merged() {
+                spans.push(prefix(i));
+            }
+            if view.current == Some(i) {
+                spans.push(Span::styled(text.to_string(), Style::default().add_modifier(Modifier::REVERSED)));
+                return Spans::from(spans);
+            }
+            // Docker's timestamp is the first word.
+            let stamp_end = if view.timestamps { text.find(' ').map_or(0, |end| end + 1) } else { 0 };
+            // Highlight rules win over the stderr color.
+            let mut style = log_style(app, &text[stamp_end..]);
+            if line.stderr && style == Style::default() {
+                style = Style::default().fg(Color::LightRed);
+            }
+            let plain = |spans: &mut Vec<Span>, start: usize, end: usize| {
+                if start < stamp_end {
+                    spans.push(Span::styled(text[start..end.min(stamp_end)].to_string(), dim));
+                }
+                if end > stamp_end {
+                    spans.push(Span::styled(text[start.max(stamp_end)..end].to_string(), style));
+                }
+            };
+            let mut end = 0;
+            for found in view.search.iter().flat_map(|search| search.find_iter(text)).filter(|m| !m.is_empty()) {
+                plain(&mut spans, end, found.start());
+                spans.push(Span::styled(found.as_str().to_string(), match_style));
+                end = found.end();
+            }
+            plain(&mut spans, end, text.len());
+            Spans::from(spans)
+        })
+        .collect();
+    if let Some(error) = &view.error {
+        lines.push(Spans::from(Span::styled(error.clone(), Style::default().fg(Color::Red))));
+    } else if view.loading && view.lines.is_empty() {
+        lines.push(Spans::from(Span::styled("Loading…", dim)));
+    } else if view.lines.is_empty() {
+        lines.push(Spans::from(Span::styled("No log output", dim)));
+    } else if lines.is_empty() {
+        lines.push(Spans::from(Span::styled("No matching lines", dim)));
+    }
+
+    let names: Vec<&str> = view.containers.iter().map(|c| c.name.as_str()).collect();
+    let mut title = format!(" Logs of {}, last {} lines ", names.join(", "), view.lines.len());
+    if view.age > 0 {
+        title.push_str(&format!("up to {} old ", format_age(view.age)));
+    }
+    let scrolled = view.scroll.min(view.shown().len().saturating_sub(height));
+    if view.following() && scrolled > 0 {
+        title.push_str(&format!("(following, paused {} up; End resumes) ", scrolled));
+    } else if view.following() {
+        title.push_str("(following) ");
+    } else if scrolled > 0 {
+        title.push_str(&format!("(scrolled {} up) ", scrolled));
+    }
+    if let Some(search) = &view.search {
+        let filter = if view.filtering { ", only these shown" } else { "" };
+        title.push_str(&format!("/{}/: {} matches{} ", search, view.match_count(), filter));
+        title.push_str("(n/N: Older/newer match  &: Only matching  Esc: Clear) ");
+    } else {
+        title.push_str("(↑/↓ PgUp/PgDn Home/End: Scroll  /: Search  f: Follow  t: Timestamps  l: Lines  r: Reload  Esc: Close) ");
+    }
+    let widget = Paragraph::new(lines).block(
+        Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_type(tui::widgets::BorderType::Rounded),
+    );
+    f.render_widget(Clear, area);
+    f.render_widget(widget, area);
+}
+
+/// The colors of the container names in a merged log view, in turn.
+const LOG_SOURCE_COLORS: [Color; 6] = [Color::Cyan, Color::Yellow, Color::Green, Color::Magenta, Color::Blue, Color::LightRed];
+
+/// The configured color of a log line, if a highlight rule matches it.
+fn log_style(app: &App, line: &str) -> Style {
+    let Some(rule) = app.highlights.iter().find(|r| r.pattern.is_match(line)) else {
+        return Style::default();
+    };
+    Style::default().fg(match rule.color {
+        HighlightColor::Red => Color::Red,
+        HighlightColor::Yellow => Color::Yellow,
+        HighlightColor::Green => Color::Green,
+        HighlightColor::Blue => Color::Blue,
+        HighlightColor::Magenta => Color::Magenta,
+        HighlightColor::Cyan => Color::Cyan,
+        HighlightColor::Gray => Color::DarkGray,
+    })
+}
+
+/// Matches of the global log search grouped by container, or the lines
+/// around the selected match.
+fn render_log_search<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let Some(search) = &app.log_search else {
+        return;
+    };
+    let height = area.height.saturating_sub(2) as usize;
+    let selected_style = Style::default().add_modifier(Modifier::REVERSED);
+    let dim = Style::default().fg(Color::DarkGray);
+    let header_style = Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD);
+
+    let (title, lines, selected_line) = match search.selected().filter(|_| search.context) {
+        Some((result, line)) => {
+            let start = line.saturating_sub(CONTEXT_LINES);
+            let end = (line + CONTEXT_LINES + 1).min(result.lines.len());
+            let lines: Vec<Spans> = (start..end)
+                .map(|i| {
+                    let style = if i == line { selected_style } else { log_style(app, &result.lines[i]) };
+                    Spans::from(vec![
+                        Span::styled(format!("{:>5} ", i + 1), dim),
+                        Span::styled(result.lines[i].clone(), style),
+                    ])
+                })
+                .collect();
+            let title = format!(
+                " {}, line {} of {} (Esc: Back to matches) ",
+                result.container,
+                line + 1,
+                result.lines.len()
+            );
+            (title, lines, line - start)
+        }
+        None => {
+            let mut lines = Vec::new();
+            let mut selected_line = 0;
+            let mut index = 0;
+            for result in &search.results {
+                let header = match &result.error {
+                    Some(error) => format!("── {}: {}", result.container, error),
+                    None => format!("── {} ({} matches)", result.container, result.matches.len()),
+                };
+                lines.push(Spans::from(Span::styled(header, header_style)));
+                for &line in &result.matches {
+                    let style = if index == search.cursor {
+                        selected_line = lines.len();
+                        selected_style
+                    } else {
+                        log_style(app, &result.lines[line])
+                    };
+                    lines.push(Spans::from(vec![
+                        Span::styled(format!("{:>5} ", line + 1), dim),
+                        Span::styled(result.lines[line].clone(), style),
+                    ]));
+                    index += 1;
+                }
+            }
+            if search.scanning {
+                lines.push(Spans::from(Span::styled("Scanning…", dim)));
+            } else if lines.is_empty() {
+                lines.push(Spans::from(Span::styled("No matches", dim)));
+            }
+            let title = format!(
+                " Log search /{}/: {} matches (↑/↓: Select  Enter: Context  Esc: Close) ",
+                search.pattern,
+                search.total()
+            );
+            (title, lines, selected_line)
+        }
+    };
+
+    // Keep the selected line in view, roughly centered once scrolled.
+    let scroll = selected_line.saturating_sub(height / 2).min(lines.len().saturating_sub(height));
+    let widget = Paragraph::new(lines).scroll((scroll as u16, 0)).block(
+        Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_type(tui::widgets::BorderType::Rounded)
+            .border_style(Style::default().fg(Color::Yellow)),
+    );
+    f.render_widget(Clear, area);
+    f.render_widget(widget, area);
+}
+
+/// A color per container name in combined tails, stable across sessions.
+fn container_color(name: &str) -> Color {
+    const PALETTE: [Color; 6] = [Color::Cyan, Color::Yellow, Color::Green, Color::Magenta, Color::Blue, Color::LightRed];
+    let hash = name.trim().bytes().fold(0usize, |hash, b| hash.wrapping_mul(31).wrapping_add(b as usize));
+    PALETTE[hash % PALETTE.len()]
+}
+
+fn render_switcher<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let sessions = &app.sessions;
+    let rows: Vec<Row> = sessions
+        .list
+        .iter()
+        .enumerate()
+        .map(|(i, session)| {
+            let state = if session.is_alive() { "running" } else { "ended" };
+            let row = Row::new(vec![format!("{}", i + 1), session.title(), state.to_string()]);
+            if sessions.switcher == Some(i) {
+                row.style(Style::default().add_modifier(Modifier::REVERSED))
+            } else {
+                row
+            }
+        })
+        .collect();
+    let table = Table::new(rows)
+        .widths(&[Constraint::Length(3), Constraint::Percentage(70), Constraint::Length(8)])
+        .block(
+            Block::default()
+                .title(" Sessions (Enter: Show  x: Close  Esc: Back) ")
+                .borders(Borders::ALL)
+                .border_type(tui::widgets::BorderType::Rounded)
+                .border_style(Style::default().fg(Color::Yellow)),
+        );
+    f.render_widget(Clear, area);
+    f.render_widget(table, area);
+}
+
+fn render_kill_picker<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let Some(picker) = &app.kill_picker else {
+        return;
+    };
+    let rows: Vec<Row> = SIGNALS
+        .iter()
+        .map(|(signal, purpose)| Row::new(vec![signal.to_string(), purpose.to_string()]))
+        .chain(std::iter::once(Row::new(vec!["Custom…".to_string(), "any other signal".to_string()])))
+        .enumerate()
+        .map(|(i, row)| {
+            if i == picker.cursor {
+                row.style(Style::default().add_modifier(Modifier::REVERSED))
+            } else {
+                row
+            }
+        })
+        .collect();
+    let table = Table::new(rows)
+        .widths(&[Constraint::Length(9), Constraint::Percentage(80)])
+        .block(
+            Block::default()
+                .title(format!(" Kill {} (Enter: Send  Esc: Cancel) ", picker.container.name))
+                .borders(Borders::ALL)
+                .border_type(tui::widgets::BorderType::Rounded)
+                .border_style(Style::default().fg(Color::Red)),
+        );
+    f.render_widget(Clear, area);
+    f.render_widget(table, area);
+}
+
+fn render_template_picker<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let Some(picker) = &app.template_picker else {
+        return;
+    };
+    let rows: Vec<Row> = app
+        .templates
+        .iter()
+        .enumerate()
+        .map(|(i, template)| {
+            let row = Row::new(vec![template.name.clone(), template.image.clone(), template.ports.join(", ")]);
+            if i == picker.cursor {
+                row.style(Style::default().add_modifier(Modifier::REVERSED))
+            } else {
+                row
+            }
+        })
+        .collect();
+    let table = Table::new(rows)
+        .widths(&[Constraint::Percentage(25), Constraint::Percentage(40), Constraint::Percentage(35)])
+        .block(
+            Block::default()
+                .title(" Run a container from (Enter: Run  Esc: Cancel) ")
+                .borders(Borders::ALL)
+                .border_type(tui::widgets::BorderType::Rounded),
+        );
+    f.render_widget(Clear, area);
+    f.render_widget(table, area);
+}
+
+fn render_limits_form<B: Backend>(f: &mut Frame<B>, form: &LimitsForm, area: Rect) {
+    let mut lines: Vec<Spans> = FIELDS
+        .iter()
+        .zip(&form.inputs)
+        .enumerate()
+        .flat_map(|(i, ((label, hint), input))| {
+            let style = if i == form.cursor {
+                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default()
+            };
+            let cursor = if i == form.cursor { "█" } else { "" };
+            [
+                Spans::from(vec![
+                    Span::styled(format!("{:<11}", label), style),
+                    Span::raw(format!("{}{}", input, cursor)),
+                ]),
+                Spans::from(Span::styled(format!("{:11}{}", "", hint), Style::default().fg(Color::DarkGray))),
+                Spans::from(""),
+            ]
+        })
+        .collect();
+    if let Some(error) = &form.error {
+        lines.push(Spans::from(Span::styled(error.clone(), Style::default().fg(Color::Red))));
+    }
+    let paragraph = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+        Block::default()
+            .title(format!(" Limits of {} (↑/↓: Field  Enter: Apply  Esc: Cancel) ", form.container.name))
+            .borders(Borders::ALL)
+            .border_type(tui::widgets::BorderType::Rounded)
+            .border_style(Style::default().fg(Color::Yellow)),
+    );
+    f.render_widget(paragraph, area);
+}
+
+fn render_recreate_form<B: Backend>(f: &mut Frame<B>, form: &RecreateForm, area: Rect) {
+    let hint = Style::default().fg(Color::DarkGray);
+    let mut lines = Vec::new();
+    let mut cursor_line = 0;
+    for (i, (section, input)) in form.fields.iter().enumerate() {
+        if i == 0 || form.fields[i - 1].0 != *section {
+            if i > 0 {
+                lines.push(Spans::from(""));
+            }
+            let (heading, example) = section.describe();
+            lines.push(Spans::from(vec![
+                Span::styled(heading, Style::default().add_modifier(Modifier::BOLD)),
+                Span::styled(format!("  {}", example), hint),
+            ]));
+        }
+        if i == form.cursor {
+            cursor_line = lines.len();
+            lines.push(Spans::from(Span::styled(
+                format!("  {}█", input),
+                Style::default().fg(Color::Yellow),
+            )));
+        } else if input.is_empty() {
+            lines.push(Spans::from(Span::styled("  +", hint)));
+        } else {
+            lines.push(Spans::from(format!("  {}", input)));
+        }
+    }
+    if let Some(error) = &form.error {
+        lines.push(Spans::from(""));
+        lines.push(Spans::from(Span::styled(error.clone(), Style::default().fg(Color::Red))));
+    }
+    // Keeps the field being edited in view.
+    let height = area.height.saturating_sub(2) as usize;
+    let scroll = (cursor_line + 2).saturating_sub(height) as u16;
+    let paragraph = Paragraph::new(lines).scroll((scroll, 0)).block(
+        Block::default()
+            .title(format!(
+                " Recreate {} (↑/↓: Field  Enter: Recreate  Esc: Cancel; volumes are kept) ",
+                form.container.name
+            ))
+            .borders(Borders::ALL)
+            .border_type(tui::widgets::BorderType::Rounded)
+            .border_style(Style::default().fg(Color::Yellow)),
+    );
+    f.render_widget(paragraph, area);
+}
+
+/// Per-endpoint API statistics plus task counts, for diagnosing slow hosts.
+fn render_debug_panel<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let tasks = tokio::runtime::Handle::current().metrics().num_alive_tasks();
+    let (rows, in_flight) = match app.backend.api_metrics() {
+        Some(metrics) => {
+            let rows: Vec<Row> = metrics
+                .snapshot()
+                .into_iter()
+                .map(|(endpoint, stats)| {
+                    Row::new(vec![
+                        endpoint.to_string(),
+                        stats.calls.to_string(),
+                        stats.errors.to_string(),
+                        format_percent(&app.format, stats.error_rate()),
+                        format!("{:.1?}", stats.average()),
+                        format!("{:.1?}", stats.max),
+                        format!("{:.1?}", stats.last),
+                    ])
+                })
+                .collect();
+            (rows, metrics.in_flight())
+        }
+        None => (vec![Row::new(vec!["(no API calls for this backend)"])], 0),
+    };
+
+    let header = Row::new(["Endpoint", "Calls", "Errors", "Err %", "Avg", "Max", "Last"])
+        .style(Style::default().add_modifier(Modifier::BOLD));
+    let table = Table::new(rows)
+        .header(header)
+        .widths(&[
+            Constraint::Percentage(28),
+            Constraint::Percentage(10),
+            Constraint::Percentage(10),
+            Constraint::Percentage(10),
+            Constraint::Percentage(14),
+            Constraint::Percentage(14),
+            Constraint::Percentage(14),
+        ])
+        .block(
+            Block::default()
+                .title(format!(
+                    " Debug: {} tasks alive, {} API calls in flight (F12 to close) ",
+                    tasks, in_flight
+                ))
+                .borders(Borders::ALL)
+                .border_type(tui::widgets::BorderType::Rounded)
+                .border_style(Style::default().fg(Color::Magenta))
+        );
+    f.render_widget(Clear, area);
+    f.render_widget(table, area);
+}
+
+/// A rectangle `percent_x` wide and `height` rows tall, centered in `r`.
+fn centered_rect(percent_x: u16, height: u16, r: Rect) -> Rect {
+    let width = r.width * percent_x / 100;
+    Rect {
+        x: r.x + (r.width - width) / 2,
+        y: r.y + r.height.saturating_sub(height) / 2,
+        width,
+        height: height.min(r.height),
+    }
+}
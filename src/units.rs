@@ -0,0 +1,134 @@
+//! Sizes, rates and percentages as text, in the units, decimal separator
+//! and precision of `[format]`, which every screen and report passes in.
+
+use crate::config::{FormatConfig, Units};
+
+/// `value` with `precision` digits after the configured separator.
+fn number(format: &FormatConfig, value: f64, precision: usize) -> String {
+    let text = format!("{:.*}", precision, value);
+    match format.decimal_separator {
+        '.' => text,
+        separator => text.replace('.', &separator.to_string()),
+    }
+}
+
+fn base(units: Units) -> f64 {
+    match units {
+        Units::Jedec | Units::Iec => 1024.0,
+        Units::Si => 1000.0,
+    }
+}
+
+/// A size such as `1.50 GB`, `1.50 GiB` or `1.61 GB` depending on the units.
+pub fn format_bytes(format: &FormatConfig, bytes: u64) -> String {
+    let units = match format.units {
+        Units::Jedec => ["B", "KB", "MB", "GB"],
+        Units::Iec => ["B", "KiB", "MiB", "GiB"],
+        Units::Si => ["B", "kB", "MB", "GB"],
+    };
+    let base = base(format.units);
+    let mut size = bytes as f64;
+    let mut unit_index = 0;
+
+    while size >= base && unit_index < units.len() - 1 {
+        size /= base;
+        unit_index += 1;
+    }
+
+    format!("{} {}", number(format, size, format.precision), units[unit_index])
+}
+
+/// Network throughput in bits per second, e.g. `12.4 Mb/s`. Always in
+/// decimal units, as link speeds are.
+pub fn format_bits(format: &FormatConfig, bytes_per_sec: f64) -> String {
+    const UNITS: [&str; 4] = ["b/s", "Kb/s", "Mb/s", "Gb/s"];
+    let mut size = bytes_per_sec * 8.0;
+    let mut unit_index = 0;
+
+    while size >= 1000.0 && unit_index < UNITS.len() - 1 {
+        size /= 1000.0;
+        unit_index += 1;
+    }
+
+    format!("{} {}", number(format, size, 1), UNITS[unit_index])
+}
+
+/// Compact rate for narrow columns, e.g. `1.2M` or `340K` (bytes per second).
+pub fn format_rate(format: &FormatConfig, bytes_per_sec: f64) -> String {
+    let units = match format.units {
+        Units::Jedec => ["B", "K", "M", "G"],
+        Units::Iec => ["B", "Ki", "Mi", "Gi"],
+        Units::Si => ["B", "k", "M", "G"],
+    };
+    let mut size = bytes_per_sec;
+    let mut unit_index = 0;
+
+    while size >= 1000.0 && unit_index < units.len() - 1 {
+        size /= base(format.units);
+        unit_index += 1;
+    }
+
+    let precision = if size < 10.0 && unit_index > 0 { 1 } else { 0 };
+    format!("{}{}", number(format, size, precision), units[unit_index])
+}
+
+/// A percentage such as `12.5%`.
+pub fn format_percent(format: &FormatConfig, percent: f64) -> String {
+    format!("{}%", number(format, percent, format.percent_precision))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JEDEC: FormatConfig = FormatConfig::DEFAULT;
+    const IEC: FormatConfig = FormatConfig {
+        units: Units::Iec,
+        ..FormatConfig::DEFAULT
+    };
+    const SI_COMMA: FormatConfig = FormatConfig {
+        units: Units::Si,
+        decimal_separator: ',',
+        precision: 1,
+        percent_precision: 0,
+    };
+
+    #[test]
+    fn sizes_in_each_unit_system() {
+        for (format, bytes, text) in [
+            (JEDEC, 0, "0.00 B"),
+            (JEDEC, 1023, "1023.00 B"),
+            (JEDEC, 1536, "1.50 KB"),
+            (JEDEC, 3 << 30, "3.00 GB"),
+            // Nothing above GB.
+            (JEDEC, 2048 << 30, "2048.00 GB"),
+            (IEC, 1536 << 20, "1.50 GiB"),
+            (SI_COMMA, 1_500_000, "1,5 MB"),
+            (SI_COMMA, 1_610_612_736, "1,6 GB"),
+        ] {
+            assert_eq!(format_bytes(&format, bytes), text, "{}", bytes);
+        }
+    }
+
+    #[test]
+    fn bits_stay_decimal_and_rates_stay_compact() {
+        for (format, bytes_per_sec, bits, rate) in [
+            (JEDEC, 0.0, "0.0 b/s", "0B"),
+            (JEDEC, 125.0, "1.0 Kb/s", "125B"),
+            (JEDEC, 1_250_000.0, "10.0 Mb/s", "1.2M"),
+            (IEC, 5_000.0, "40.0 Kb/s", "4.9Ki"),
+            (SI_COMMA, 5_000.0, "40,0 Kb/s", "5,0k"),
+            (SI_COMMA, 340_000.0, "2,7 Mb/s", "340k"),
+        ] {
+            assert_eq!(format_bits(&format, bytes_per_sec), bits, "{}", bytes_per_sec);
+            assert_eq!(format_rate(&format, bytes_per_sec), rate, "{}", bytes_per_sec);
+        }
+    }
+
+    #[test]
+    fn percentages_use_their_own_precision() {
+        assert_eq!(format_percent(&JEDEC, 12.345), "12.3%");
+        assert_eq!(format_percent(&SI_COMMA, 12.5), "12%");
+        assert_eq!(format_percent(&IEC, 250.0), "250.0%");
+    }
+}
@@ -0,0 +1,203 @@
+//! `dockertop self-update`: replaces the running binary with the latest
+//! GitHub release.
+//!
+//! Releases are expected to carry one binary per platform named
+//! `dockertop-<os>-<arch>` (as in `std::env::consts`, e.g.
+//! `dockertop-linux-x86_64`, with `.exe` on Windows) and a `SHA256SUMS` file
+//! listing their checksums.
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::{env, fs, path::Path};
+
+const LATEST_RELEASE_URL: &str = "https://api.github.com/repos/lpolish/dockertop/releases/latest";
+const CHECKSUMS_ASSET: &str = "SHA256SUMS";
+
+#[derive(Debug, Deserialize)]
+struct Release {
+    tag_name: String,
+    assets: Vec<Asset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+}
+
+impl Release {
+    fn asset(&self, name: &str) -> Result<&Asset> {
+        self.assets
+            .iter()
+            .find(|a| a.name == name)
+            .with_context(|| format!("Release {} has no {} asset", self.tag_name, name))
+    }
+}
+
+/// Parses `v1.2.3` / `1.2.3` into comparable parts, ignoring anything
+/// after a `-` (pre-release suffixes).
+fn parse_version(version: &str) -> Vec<u64> {
+    version
+        .trim_start_matches('v')
+        .split('-')
+        .next()
+        .unwrap_or_default()
+        .split('.')
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
+}
+
+fn asset_name() -> String {
+    format!("dockertop-{}-{}{}", env::consts::OS, env::consts::ARCH, env::consts::EXE_SUFFIX)
+}
+
+/// Finds the checksum for `file` in a `sha256sum`-style listing.
+fn expected_checksum(sums: &str, file: &str) -> Option<String> {
+    sums.lines().find_map(|line| {
+        let (hash, name) = line.split_once(char::is_whitespace)?;
+        (name.trim().trim_start_matches('*') == file).then(|| hash.to_lowercase())
+    })
+}
+
+async fn download(client: &reqwest::Client, url: &str) -> Result<Vec<u8>> {
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .with_context(|| format!("Failed to download {}", url))?;
+    Ok(response.bytes().await?.to_vec())
+}
+
+pub async fn self_update() -> Result<()> {
+    let client = reqwest::Client::builder()
+        .user_agent(concat!("dockertop/", env!("CARGO_PKG_VERSION")))
+        .build()?;
+
+    let release: Release = client
+        .get(LATEST_RELEASE_URL)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .context("Failed to query the latest release")?
+        .json()
+        .await
+        .context("Unexpected release metadata")?;
+
+    let current = env!("CARGO_PKG_VERSION");
+    if parse_version(&release.tag_name) <= parse_version(current) {
+        println!("dockertop {} is up to date", current);
+        return Ok(());
+    }
+
+    let name = asset_name();
+    let binary_asset = release.asset(&name)?;
+    let sums_asset = release.asset(CHECKSUMS_ASSET)?;
+
+    println!("Downloading dockertop {} ({})…", release.tag_name, name);
+    let sums = String::from_utf8(download(&client, &sums_asset.browser_download_url).await?)
+        .context("Checksum file is not valid UTF-8")?;
+    let expected = expected_checksum(&sums, &name)
+        .with_context(|| format!("{} has no entry for {}", CHECKSUMS_ASSET, name))?;
+    let binary = download(&client, &binary_asset.browser_download_url).await?;
+
+    let actual: String = Sha256::digest(&binary).iter().map(|b| format!("{:02x}", b)).collect();
+    if actual != expected {
+        bail!("Checksum mismatch for {}: expected {}, got {}", name, expected, actual);
+    }
+
+    replace_current_exe(&binary)?;
+    println!("Updated dockertop {} -> {}", current, release.tag_name);
+    Ok(())
+}
+
+/// Writes the new binary next to the current one and renames it into place,
+/// so a failure never leaves a half-written executable behind.
+fn replace_current_exe(binary: &[u8]) -> Result<()> {
+    let exe = env::current_exe()
+        .and_then(fs::canonicalize)
+        .context("Failed to locate the running executable")?;
+    let staged = exe.with_extension("new");
+
+    fs::write(&staged, binary).with_context(|| format!("Failed to write {}", staged.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&staged, fs::Permissions::from_mode(0o755))?;
+    }
+    put_in_place(&staged, &exe)
+}
+
+/// Renames `staged` over `exe`. Windows does not let a running executable
+/// be replaced, but does let it be renamed, so there it is moved aside
+/// first and put back if the new one cannot take its place. The old one is
+/// removed on the next update, once it no longer runs.
+fn put_in_place(staged: &Path, exe: &Path) -> Result<()> {
+    if !cfg!(windows) {
+        return fs::rename(staged, exe).with_context(|| format!("Failed to replace {}", exe.display()));
+    }
+    let aside = exe.with_extension("old");
+    let _ = fs::remove_file(&aside);
+    fs::rename(exe, &aside).with_context(|| format!("Failed to move {} aside", exe.display()))?;
+    if let Err(e) = fs::rename(staged, exe) {
+        let _ = fs::rename(&aside, exe);
+        return Err(e).with_context(|| format!("Failed to replace {}", exe.display()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_compare_by_their_numbers() {
+        let cases = [
+            ("v1.2.3", vec![1, 2, 3]),
+            ("1.2.3", vec![1, 2, 3]),
+            ("v1.10.0-rc.1", vec![1, 10, 0]),
+            ("2", vec![2]),
+            ("v1.x.3", vec![1, 0, 3]),
+        ];
+        for (version, parts) in cases {
+            assert_eq!(parse_version(version), parts, "{:?}", version);
+        }
+        assert!(parse_version("v1.10.0") > parse_version("v1.9.9"));
+        assert!(parse_version("v1.2.3") > parse_version("v1.2"));
+    }
+
+    #[test]
+    fn the_staged_binary_takes_the_place_of_the_old_one() {
+        let dir = env::temp_dir().join(format!("dockertop-update-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (exe, staged) = (dir.join("dockertop"), dir.join("dockertop.new"));
+        fs::write(&exe, "old").unwrap();
+        fs::write(&staged, "new").unwrap();
+
+        put_in_place(&staged, &exe).unwrap();
+        let contents = fs::read_to_string(&exe).unwrap();
+        let staged_left = staged.exists();
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(contents, "new");
+        assert!(!staged_left);
+    }
+
+    #[test]
+    fn checksums_are_looked_up_by_file_name() {
+        let sums = "ABC123  dockertop-linux-x86_64\n\
+                    def456 *dockertop-macos-aarch64\n\
+                    \n\
+                    0ff1ce  dockertop-linux-x86_64.sig\n";
+        let cases = [
+            ("dockertop-linux-x86_64", Some("abc123")),
+            ("dockertop-macos-aarch64", Some("def456")),
+            ("dockertop-linux-x86_64.sig", Some("0ff1ce")),
+            ("dockertop-windows-x86_64.exe", None),
+        ];
+        for (file, checksum) in cases {
+            assert_eq!(expected_checksum(sums, file).as_deref(), checksum, "{:?}", file);
+        }
+    }
+}
@@ -0,0 +1,202 @@
+//! `dockertop watch <name>`: a full-screen dashboard for one container that
+//! follows the name across recreations (e.g. `docker compose up` replacing it).
+
+use crate::backend::{ContainerBackend, ContainerStats};
+use crate::journal::WatchHistory;
+use bollard::models::ContainerTopResponse;
+use futures::StreamExt;
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+use tokio::task::JoinHandle;
+
+/// Five minutes of samples at the default refresh rate.
+pub const HISTORY: usize = 150;
+const LOG_LINES: usize = 500;
+
+pub struct Watch {
+    pub name: String,
+    /// The latest sample of the container, once one with the name exists.
+    pub container: Option<ContainerStats>,
+    /// Times the name turned up with a new container ID.
+    pub recreations: usize,
+    /// The ID last seen with the name, kept while no container has it so a
+    /// replacement after a gap still counts as a recreation.
+    last_id: Option<String>,
+    /// `(sample number, percent)` pairs, oldest first.
+    pub cpu: VecDeque<(f64, f64)>,
+    pub memory: VecDeque<(f64, f64)>,
+    pub processes: ContainerTopResponse,
+    logs: Arc<Mutex<VecDeque<String>>>,
+    follower: Option<JoinHandle<()>>,
+    samples: u64,
+}
+
+impl Watch {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            container: None,
+            recreations: 0,
+            last_id: None,
+            cpu: VecDeque::new(),
+            memory: VecDeque::new(),
+            processes: ContainerTopResponse::default(),
+            logs: Arc::default(),
+            follower: None,
+            samples: 0,
+        }
+    }
+
+    /// Takes the newest sample of the watched container. A new ID means it
+    /// was recreated: its history is reset and its logs followed afresh.
+    pub fn update(&mut self, backend: &Arc<dyn ContainerBackend>, containers: &[ContainerStats]) {
+        let Some(container) = containers.iter().find(|c| c.name == self.name) else {
+            self.container = None;
+            return;
+        };
+
+        let previous = self.last_id.replace(container.id.clone());
+        let recreated = previous.as_ref().is_some_and(|id| *id != container.id);
+        if recreated {
+            self.recreations += 1;
+            self.cpu.clear();
+            self.memory.clear();
+            self.push_log(format!("── {} was recreated as {} ──", self.name, short(&container.id)));
+        }
+
+        // The log stream ends whenever the container stops, so it is
+        // restarted on recreation and on every start.
+        let follower_done = self.follower.as_ref().is_none_or(|f| f.is_finished());
+        if recreated || (follower_done && container.is_running()) {
+            if let Some(follower) = self.follower.take() {
+                follower.abort();
+            }
+            let tail = if previous.is_none() { 100 } else { 0 };
+            let mut stream = backend.follow_logs(&container.id, tail, false);
+            let logs = self.logs.clone();
+            self.follower = Some(tokio::spawn(async move {
+                while let Some(Ok(chunk)) = stream.next().await {
+                    let mut logs = logs.lock().unwrap();
+                    for line in chunk.text.lines() {
+                        if logs.len() == LOG_LINES {
+                            logs.pop_front();
+                        }
+                        logs.push_back(line.to_string());
+                    }
+                }
+            }));
+        }
+
+        self.samples += 1;
+        let memory_percent = container.memory_percent();
+        for (history, value) in [(&mut self.cpu, container.cpu_usage), (&mut self.memory, memory_percent)] {
+            if history.len() == HISTORY {
+                history.pop_front();
+            }
+            history.push_back((self.samples as f64, value));
+        }
+        self.container = Some(container.clone());
+    }
+
+    pub fn history(&self) -> WatchHistory {
+        WatchHistory {
+            name: self.name.clone(),
+            recreations: self.recreations,
+            cpu: self.cpu.iter().copied().collect(),
+            memory: self.memory.iter().copied().collect(),
+        }
+    }
+
+    /// Continues the charts of an earlier run watching the same name.
+    pub fn restore(&mut self, history: WatchHistory) {
+        if history.name != self.name {
+            return;
+        }
+        self.recreations = history.recreations;
+        self.samples = history.cpu.last().map_or(0, |(sample, _)| *sample as u64);
+        self.cpu = history.cpu.into();
+        self.memory = history.memory.into();
+    }
+
+    /// The last `count` log lines, oldest first.
+    pub fn recent_logs(&self, count: usize) -> Vec<String> {
+        let logs = self.logs.lock().unwrap();
+        logs.iter().skip(logs.len().saturating_sub(count)).cloned().collect()
+    }
+
+    fn push_log(&self, line: String) {
+        self.logs.lock().unwrap().push_back(line);
+    }
+}
+
+impl Drop for Watch {
+    fn drop(&mut self) {
+        if let Some(follower) = &self.follower {
+            follower.abort();
+        }
+    }
+}
+
+fn short(id: &str) -> &str {
+    &id[..id.len().min(12)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::mock::{container, MockBackend};
+
+    async fn settle() {
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn the_watch_follows_its_name_across_recreations() {
+        let mock = MockBackend::new([]);
+        *mock.followed.lock().unwrap() = vec!["listening\n".to_string()];
+        let backend: Arc<dyn ContainerBackend> = Arc::new(mock);
+        let mut watch = Watch::new("web".to_string());
+        let mut web = container("web");
+        web.cpu_usage = 40.0;
+
+        watch.update(&backend, &[container("db"), web.clone()]);
+        watch.update(&backend, &[web.clone()]);
+        settle().await;
+        assert_eq!(watch.cpu, [(1.0, 40.0), (2.0, 40.0)]);
+        assert_eq!(watch.recent_logs(10), ["listening"]);
+
+        watch.update(&backend, &[container("db")]);
+        assert!(watch.container.is_none());
+        assert_eq!(watch.cpu.len(), 2, "a missing container keeps its charts");
+
+        let mut recreated = container("web");
+        recreated.id = "0123456789abcdef".to_string();
+        watch.update(&backend, &[recreated]);
+        settle().await;
+        assert_eq!(watch.recreations, 1);
+        assert_eq!(watch.cpu, [(3.0, 0.0)]);
+        assert_eq!(watch.recent_logs(2), ["── web was recreated as 0123456789ab ──", "listening"]);
+    }
+
+    #[test]
+    fn charts_restore_only_for_the_same_name() {
+        let history = || WatchHistory {
+            name: "web".to_string(),
+            recreations: 2,
+            cpu: vec![(7.0, 1.0), (8.0, 2.0)],
+            memory: vec![(7.0, 10.0), (8.0, 20.0)],
+        };
+        let mut other = Watch::new("db".to_string());
+        other.restore(history());
+        assert!(other.cpu.is_empty());
+
+        let mut watch = Watch::new("web".to_string());
+        watch.restore(history());
+        assert_eq!((watch.recreations, watch.samples), (2, 8));
+        assert_eq!(watch.history().memory, [(7.0, 10.0), (8.0, 20.0)]);
+    }
+}